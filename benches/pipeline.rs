@@ -0,0 +1,93 @@
+//! Benchmarks of the frame processing, from the bytes read from the port to the upscaled
+//! snapshot. Run with `cargo bench --features=bench`.
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use main::bench::{
+    self, ColorMap, EdgeStrategy, FilteringMethod, OverlaySettings, Settings, THERMAL_IMAGE_HEIGHT,
+    THERMAL_IMAGE_WIDTH,
+};
+use strum::IntoEnumIterator;
+
+/// Bytes of a frame with a warm blob on a 25 °C background.
+fn frame_bytes() -> Vec<u8> {
+    (0..THERMAL_IMAGE_HEIGHT)
+        .flat_map(|y| (0..THERMAL_IMAGE_WIDTH).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            let distance = x.abs_diff(12).pow(2) + y.abs_diff(20).pow(2);
+            let value = 250 + 200 / (1 + distance as u16);
+            value.to_le_bytes()
+        })
+        .collect()
+}
+
+fn decode(c: &mut Criterion) {
+    let bytes = frame_bytes();
+
+    c.bench_function("decode", |b| b.iter(|| bench::decode(black_box(&bytes))));
+}
+
+fn filter(c: &mut Criterion) {
+    let frame = bench::decode(&frame_bytes()).unwrap();
+    let mut group = c.benchmark_group("filter");
+
+    for filtering_method in FilteringMethod::iter() {
+        for edge_strategy in [EdgeStrategy::Extend, EdgeStrategy::Mirror] {
+            let settings = Settings {
+                filtering_method: filtering_method.clone(),
+                edge_strategy: edge_strategy.clone(),
+                ..Settings::default()
+            };
+
+            group.bench_with_input(
+                BenchmarkId::new(filtering_method.to_string(), edge_strategy.to_string()),
+                &settings,
+                |b, settings| b.iter(|| bench::filter(black_box(&frame), settings)),
+            );
+        }
+    }
+
+    group.finish();
+}
+
+fn colorize(c: &mut Criterion) {
+    let frame = bench::decode(&frame_bytes()).unwrap();
+    let color_range = Settings::default().color_range;
+    let mut group = c.benchmark_group("colorize");
+
+    for colormap in ColorMap::iter() {
+        let name = colormap.to_string();
+        let colormap = colormap.get_colormap();
+        let lut = bench::Lut::new(&*colormap, color_range);
+
+        group.bench_function(BenchmarkId::new("colormap", &name), |b| {
+            b.iter(|| bench::colorize(black_box(&frame), &*colormap, color_range));
+        });
+        group.bench_function(BenchmarkId::new("lut", &name), |b| {
+            b.iter(|| lut.colorize(black_box(&frame)));
+        });
+    }
+
+    group.finish();
+}
+
+fn upscale(c: &mut Criterion) {
+    let frame = bench::decode(&frame_bytes()).unwrap();
+    let image = bench::colorize(
+        &frame,
+        &*ColorMap::default().get_colormap(),
+        Settings::default().color_range,
+    );
+    let settings = OverlaySettings::default();
+    let mut group = c.benchmark_group("upscale");
+
+    for scale in [4, 10, 20, 40] {
+        group.bench_with_input(BenchmarkId::from_parameter(scale), &scale, |b, &scale| {
+            b.iter(|| bench::snapshot(black_box(&image), &settings, None, scale));
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decode, filter, colorize, upscale);
+criterion_main!(benches);
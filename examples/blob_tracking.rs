@@ -0,0 +1,86 @@
+//! Tracks warm blobs in the camera's frames with OpenCV, printing the centroid, the area and the
+//! peak temperature of each. Run with
+//! `cargo run --example blob_tracking --features opencv -- PORT [THRESHOLD]`, the threshold in °C
+//! defaults to 35.
+
+use anyhow::{anyhow, Context};
+use main::cv::{self, THERMAL_IMAGE_WIDTH};
+use opencv::core::{self, Mat, Point, Vector};
+use opencv::imgproc;
+use opencv::prelude::*;
+use std::time::Duration;
+
+const BAUD_RATE: u32 = 921_600;
+
+fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let port_name = args
+        .next()
+        .ok_or_else(|| anyhow!("Usage: blob_tracking PORT [THRESHOLD]"))?;
+    let threshold: f32 = match args.next() {
+        Some(threshold) => threshold.parse().context("Invalid threshold")?,
+        None => 35.0,
+    };
+
+    // the camera only starts streaming once DTR is asserted
+    let mut port = serialport::new(&port_name, BAUD_RATE)
+        .timeout(Duration::from_secs(1))
+        .dtr_on_open(true)
+        .open()
+        .with_context(|| format!("Failed to open {port_name}"))?;
+
+    for frame in 0.. {
+        let values = cv::read_frame(&mut port)?;
+        let celsius = cv::to_celsius_mat(&values)?;
+
+        let mut mask = Mat::default();
+        imgproc::threshold(
+            &celsius,
+            &mut mask,
+            f64::from(threshold),
+            255.0,
+            imgproc::THRESH_BINARY,
+        )?;
+        let mut binary = Mat::default();
+        mask.convert_to(&mut binary, core::CV_8UC1, 1.0, 0.0)?;
+
+        let mut contours = Vector::<Vector<Point>>::new();
+        imgproc::find_contours(
+            &binary,
+            &mut contours,
+            imgproc::RETR_EXTERNAL,
+            imgproc::CHAIN_APPROX_SIMPLE,
+            Point::default(),
+        )?;
+
+        for (blob, contour) in contours.iter().enumerate() {
+            let moments = imgproc::moments(&contour, false)?;
+            let rect = imgproc::bounding_rect(&contour)?;
+
+            // a single pixel or a line has no area, its bounding box still locates it
+            let (x, y) = if moments.m00 > 0.0 {
+                (moments.m10 / moments.m00, moments.m01 / moments.m00)
+            } else {
+                (
+                    f64::from(rect.x) + f64::from(rect.width - 1) / 2.0,
+                    f64::from(rect.y) + f64::from(rect.height - 1) / 2.0,
+                )
+            };
+
+            // hottest pixel of its bounding box
+            let peak = (rect.y..rect.y + rect.height)
+                .flat_map(|y| (rect.x..rect.x + rect.width).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    f32::from(values[y as usize * THERMAL_IMAGE_WIDTH + x as usize]) / 10.0
+                })
+                .fold(f32::MIN, f32::max);
+
+            println!(
+                "frame {frame}, blob {blob}: center ({x:.1}, {y:.1}), area {:.0} px, peak {peak:.1} °C",
+                moments.m00
+            );
+        }
+    }
+
+    Ok(())
+}
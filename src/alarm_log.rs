@@ -0,0 +1,128 @@
+//! Log of the triggered alarms with a snapshot of each, exported as a ZIP archive of a CSV table
+//! and the snapshots.
+
+use crate::overlay::{self, OverlaySettings};
+use crate::report;
+use crate::thermal;
+use crate::zip::ZipWriter;
+
+use eframe::egui;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+/// Oldest events are dropped beyond this, so an alarm flapping for hours doesn't eat the memory.
+const MAX_EVENTS: usize = 200;
+
+pub struct AlarmEvent {
+    pub triggered_at: SystemTime,
+    /// Maximum temperature (°C) which has tripped the alarm
+    pub max: f64,
+    pub threshold: f64,
+    /// Hottest pixel of the frame, in the shown image
+    pub pixel: Option<[usize; 2]>,
+    /// Labels of the markers and their temperatures (°C)
+    pub markers: Vec<(String, f64)>,
+    pub image: thermal::RgbImage,
+    pub thumbnail: egui::TextureHandle,
+}
+
+#[derive(Default)]
+pub struct AlarmLog {
+    pub events: VecDeque<AlarmEvent>,
+}
+
+impl AlarmLog {
+    pub fn push(&mut self, event: AlarmEvent) {
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    fn snapshot_name(index: usize) -> String {
+        format!("alarm-{:03}.png", index + 1)
+    }
+
+    /// One line per event, with a point as the decimal separator whatever the UI uses. Each
+    /// marker gets a column named after it, empty for the events logged without it.
+    pub fn to_csv(&self, precision: usize) -> String {
+        let mut labels: Vec<&str> = Vec::new();
+        for event in &self.events {
+            for (label, _) in &event.markers {
+                if !labels.contains(&label.as_str()) {
+                    labels.push(label);
+                }
+            }
+        }
+
+        let mut csv = String::from("time,max,threshold,x,y");
+        for label in &labels {
+            csv.push(',');
+            csv.push_str(&csv_field(label));
+        }
+        csv.push_str(",snapshot\n");
+
+        for (index, event) in self.events.iter().enumerate() {
+            let (x, y) = event
+                .pixel
+                .map_or((String::new(), String::new()), |[x, y]| {
+                    (x.to_string(), y.to_string())
+                });
+
+            let _ = write!(
+                csv,
+                "{},{:.precision$},{:.precision$},{x},{y}",
+                report::format_time(event.triggered_at),
+                event.max,
+                event.threshold,
+            );
+            for label in &labels {
+                csv.push(',');
+                if let Some((_, value)) = event.markers.iter().find(|(name, _)| name == label) {
+                    let _ = write!(csv, "{value:.precision$}");
+                }
+            }
+            let _ = writeln!(csv, ",{}", Self::snapshot_name(index));
+        }
+
+        csv
+    }
+
+    /// The CSV table and the upscaled snapshots.
+    pub fn to_zip(&self, precision: usize) -> anyhow::Result<Vec<u8>> {
+        let mut zip = ZipWriter::default();
+
+        zip.add(
+            "alarms.csv",
+            SystemTime::now(),
+            self.to_csv(precision).as_bytes(),
+        )?;
+
+        for (index, event) in self.events.iter().enumerate() {
+            let snapshot = overlay::snapshot(
+                &event.image,
+                &OverlaySettings::default(),
+                None,
+                overlay::SNAPSHOT_SCALE,
+            );
+
+            zip.add(
+                &Self::snapshot_name(index),
+                event.triggered_at,
+                &report::encode_png(&snapshot)?,
+            )?;
+        }
+
+        zip.finish()
+    }
+}
+
+/// Quotes a field containing a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
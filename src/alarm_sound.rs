@@ -0,0 +1,176 @@
+//! Audible alarm: a choice of tones played when the alarm trips and repeated until it's
+//! acknowledged or clears.
+
+use strum_macros::{Display, EnumIter};
+use web_time::Duration;
+
+#[cfg(not(target_os = "android"))]
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+#[cfg(not(target_os = "android"))]
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum AlarmTone {
+    #[default]
+    Beep,
+    Chime,
+    Siren,
+}
+
+impl AlarmTone {
+    pub fn duration(self) -> Duration {
+        match self {
+            AlarmTone::Beep => Duration::from_millis(650),
+            AlarmTone::Chime => Duration::from_millis(1200),
+            AlarmTone::Siren => Duration::from_millis(1500),
+        }
+    }
+
+    /// Frequency (Hz) and amplitude of the tone `t` seconds into it, `None` past its end.
+    #[cfg(not(target_os = "android"))]
+    fn envelope(self, t: f32) -> Option<(f32, f32)> {
+        if t >= self.duration().as_secs_f32() {
+            return None;
+        }
+
+        Some(match self {
+            // three short beeps
+            AlarmTone::Beep => (880.0, if t % 0.25 < 0.15 { 1.0 } else { 0.0 }),
+            // two decaying notes, a fourth apart
+            AlarmTone::Chime if t < 0.3 => (660.0, (-8.0 * t).exp()),
+            AlarmTone::Chime => (880.0, (-6.0 * (t - 0.3)).exp()),
+            // rising and falling twice
+            AlarmTone::Siren => {
+                let sweep = 1.0 - (2.0 * (t / 0.75).fract() - 1.0).abs();
+                (600.0 + 600.0 * sweep, 1.0)
+            }
+        })
+    }
+
+    /// `ToneGenerator` tone playing it.
+    #[cfg(target_os = "android")]
+    fn android_tone(self) -> i32 {
+        match self {
+            // ToneGenerator.TONE_PROP_BEEP2
+            AlarmTone::Beep => 28,
+            // ToneGenerator.TONE_PROP_ACK
+            AlarmTone::Chime => 25,
+            // ToneGenerator.TONE_CDMA_EMERGENCY_RINGBACK
+            AlarmTone::Siren => 92,
+        }
+    }
+}
+
+/// Tone requested by the UI thread, picked up by the audio thread.
+#[cfg(not(target_os = "android"))]
+#[derive(Default)]
+struct Request {
+    /// Index of the tone plus one, 0 when there's nothing new to play
+    tone: AtomicU32,
+    /// f32 bits, as there are no atomic floats
+    volume: AtomicU32,
+}
+
+#[cfg(not(target_os = "android"))]
+struct Synth {
+    sample_rate: f32,
+    /// Tone being played and seconds into it
+    playing: Option<(AlarmTone, f32)>,
+    volume: f32,
+    /// Frequency (Hz) of the tone, kept after it ends while it fades out
+    frequency: f32,
+    phase: f32,
+    /// Amplitude, following the envelope smoothly so the beeps don't pop
+    amplitude: f32,
+}
+
+#[cfg(not(target_os = "android"))]
+impl Synth {
+    fn next(&mut self, request: &Request) -> f32 {
+        let tone = request.tone.swap(0, Ordering::Relaxed);
+        if tone > 0 {
+            self.playing = AlarmTone::iter()
+                .nth(tone as usize - 1)
+                .map(|tone| (tone, 0.0));
+            self.volume = f32::from_bits(request.volume.load(Ordering::Relaxed));
+        }
+
+        let mut target = 0.0;
+
+        if let Some((tone, t)) = self.playing {
+            self.playing = tone.envelope(t).map(|(frequency, amplitude)| {
+                self.frequency = frequency;
+                target = amplitude * self.volume;
+                (tone, t + 1.0 / self.sample_rate)
+            });
+        }
+
+        self.amplitude += (target - self.amplitude) * 0.01;
+        self.phase = (self.phase + self.frequency / self.sample_rate).fract();
+
+        self.amplitude * (std::f32::consts::TAU * self.phase).sin()
+    }
+}
+
+/// Output for the alarm tones, silent between them.
+pub struct AlarmSound {
+    #[cfg(not(target_os = "android"))]
+    request: Arc<Request>,
+    #[cfg(not(target_os = "android"))]
+    _stream: cpal::Stream,
+}
+
+impl AlarmSound {
+    #[cfg(not(target_os = "android"))]
+    pub fn new() -> anyhow::Result<Self> {
+        let request = Arc::new(Request::default());
+        let stream = crate::sonification::play(|sample_rate| {
+            let request = Arc::clone(&request);
+            let mut synth = Synth {
+                sample_rate,
+                playing: None,
+                volume: 0.0,
+                frequency: 0.0,
+                phase: 0.0,
+                amplitude: 0.0,
+            };
+            move || synth.next(&request)
+        })?;
+
+        Ok(Self {
+            request,
+            _stream: stream,
+        })
+    }
+
+    #[cfg(target_os = "android")]
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {})
+    }
+
+    /// Plays `tone` once at `volume` (0 to 1), cutting off the one still playing.
+    #[cfg(not(target_os = "android"))]
+    pub fn play(&self, tone: AlarmTone, volume: f32) -> anyhow::Result<()> {
+        let index = AlarmTone::iter()
+            .position(|variant| variant == tone)
+            .unwrap_or_default();
+
+        self.request
+            .volume
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+        self.request
+            .tone
+            .store(u32::try_from(index)? + 1, Ordering::Relaxed);
+
+        Ok(())
+    }
+
+    /// Plays `tone` once at `volume` (0 to 1) of the alarm volume.
+    #[cfg(target_os = "android")]
+    pub fn play(&self, tone: AlarmTone, volume: f32) -> anyhow::Result<()> {
+        crate::android::play_alarm_tone(tone.android_tone(), tone.duration(), volume)
+    }
+}
@@ -1,13 +1,88 @@
-use std::{cell::RefCell, io, ops::Deref, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io,
+    ops::Deref,
+    os::fd::{BorrowedFd, FromRawFd, OwnedFd},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
 
-use crate::thermal::PortOpener;
+use crate::i18n::tr;
+use crate::thermal::{
+    self, AccessError, PortError, PortErrorKind, PortInfo, PortOpener, Settings, Transport,
+};
+use crate::usb::CdcAcm;
 
 use anyhow::anyhow;
+use egui_winit::winit::platform::android::activity::AndroidApp;
 use jni::{
-    objects::{JClass, JObject},
+    objects::{GlobalRef, JByteArray, JClass, JObject},
     sys::jbyte,
+    JNIEnv,
 };
 
+const VID: i32 = 0x303a;
+const PID: i32 = 0x4001;
+// only used by the direct USB transport, Java configures the port itself
+const BAUD_RATE: u32 = 921_600;
+// size of a frame, which then usually takes a single JNI call
+const READ_CHUNK_SIZE: usize = thermal::THERMAL_IMAGE_WIDTH * thermal::THERMAL_IMAGE_HEIGHT * 2;
+
+const ACTION_USB_PERMISSION: &str = "com.github.dobo90.tiop01_gui_android.USB_PERMISSION";
+// PendingIntent.FLAG_IMMUTABLE
+const FLAG_IMMUTABLE: i32 = 0x0400_0000;
+// Time for the permission dialog to take the window focus after it has been requested
+const PERMISSION_DIALOG_DELAY: Duration = Duration::from_secs(1);
+const ATTACHMENT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+pub const SNAPSHOT_DIRECTORY: &str = "Pictures/Tiop01";
+pub const REPORT_DIRECTORY: &str = "Documents/Tiop01";
+/// Folder of the shared storage the recordings go to until another one is entered
+pub const RECORDING_DIRECTORY: &str = "Documents/Tiop01";
+/// Folders of the shared storage which MediaStore takes files other than media into
+const DOCUMENT_FOLDERS: [&str; 2] = ["Documents", "Download"];
+// Intent.FLAG_GRANT_READ_URI_PERMISSION
+const FLAG_GRANT_READ_URI_PERMISSION: i32 = 0x0000_0001;
+
+const ALARM_VIBRATION: Duration = Duration::from_millis(500);
+// VibrationEffect.DEFAULT_AMPLITUDE
+const DEFAULT_AMPLITUDE: i32 = -1;
+// AudioManager.STREAM_ALARM, so the tone follows the alarm volume
+const STREAM_ALARM: i32 = 4;
+const TONE_VOLUME: i32 = 100;
+// AudioManager.STREAM_MUSIC, the sonification follows the media volume
+const STREAM_MUSIC: i32 = 3;
+// ToneGenerator.TONE_PROP_BEEP
+const TONE_PROP_BEEP: i32 = 24;
+const CLICK_LENGTH: Duration = Duration::from_millis(15);
+
+// BatteryManager.BATTERY_PROPERTY_CAPACITY
+const BATTERY_PROPERTY_CAPACITY: i32 = 4;
+// Battery percentage below which the app saves power even without battery saver
+const LOW_BATTERY: i32 = 15;
+
+// PackageManager.PERMISSION_GRANTED
+const PERMISSION_GRANTED: i32 = 0;
+
+// Intent extra set by the fullscreen shortcut
+const EXTRA_FULLSCREEN: &str = "com.github.dobo90.tiop01_gui_android.FULLSCREEN";
+// Intent.FLAG_ACTIVITY_NEW_TASK | Intent.FLAG_ACTIVITY_CLEAR_TASK
+const FLAG_ACTIVITY_RESTART: i32 = 0x1000_0000 | 0x0000_8000;
+
+// Releasing the tone generator stops the tone, so it's kept around along with its volume
+static TONE_GENERATOR: Mutex<Option<(GlobalRef, i32)>> = Mutex::new(None);
+static CLICK_GENERATOR: Mutex<Option<GlobalRef>> = Mutex::new(None);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Permission {
+    NotRequested,
+    Requested(Instant),
+    Denied,
+}
+
 pub struct Context<'a> {
     env: jni::JNIEnv<'a>,
     context: JObject<'a>,
@@ -19,26 +94,998 @@ impl<'a> Context<'a> {
     }
 }
 
+/// Keeps the display from sleeping, e.g. while the camera is streaming.
+pub fn set_keep_screen_on(keep_screen_on: bool) {
+    use egui_winit::winit::platform::android::activity::WindowManagerFlags;
+
+    let Some(app) = crate::ANDROID_APP.get() else {
+        return;
+    };
+
+    if keep_screen_on {
+        app.set_window_flags(
+            WindowManagerFlags::KEEP_SCREEN_ON,
+            WindowManagerFlags::empty(),
+        );
+    } else {
+        app.set_window_flags(
+            WindowManagerFlags::empty(),
+            WindowManagerFlags::KEEP_SCREEN_ON,
+        );
+    }
+}
+
+/// Runs `f` with the activity on the calling thread, which gets attached to the JVM if it isn't.
+fn with_activity<R>(
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> anyhow::Result<R>,
+) -> anyhow::Result<R> {
+    let app = crate::ANDROID_APP
+        .get()
+        .ok_or_else(|| anyhow!("Android app isn't initialized"))?;
+
+    with_app_activity(app, f)
+}
+
+fn with_app_activity<R>(
+    app: &AndroidApp,
+    f: impl FnOnce(&mut JNIEnv, &JObject) -> anyhow::Result<R>,
+) -> anyhow::Result<R> {
+    let jvm = unsafe { jni::JavaVM::from_raw(app.vm_as_ptr().cast()) }?;
+    let activity = unsafe { JObject::from_raw(app.activity_as_ptr().cast()) };
+    let mut env = jvm.attach_current_thread()?;
+
+    let ret = env.with_local_frame(32, |env| f(env, &activity));
+
+    // a pending exception would abort the next JNI call
+    if ret.is_err() && env.exception_check()? {
+        env.exception_clear()?;
+    }
+
+    ret
+}
+
+/// Adds a file to a shared MediaStore `collection`, `directory` being relative to the shared
+/// storage. Returns the content URI of the new file.
+fn insert_media_file<'local>(
+    env: &mut JNIEnv<'local>,
+    resolver: &JObject,
+    collection: &JObject,
+    file_name: &str,
+    mime_type: &str,
+    directory: &str,
+) -> anyhow::Result<JObject<'local>> {
+    let values = env.new_object("android/content/ContentValues", "()V", &[])?;
+
+    for (key, value) in [
+        ("_display_name", file_name),
+        ("mime_type", mime_type),
+        ("relative_path", directory),
+    ] {
+        let key = env.new_string(key)?;
+        let value = env.new_string(value)?;
+
+        env.call_method(
+            &values,
+            "put",
+            "(Ljava/lang/String;Ljava/lang/String;)V",
+            &[key.deref().into(), value.deref().into()],
+        )?;
+    }
+
+    let uri = env
+        .call_method(
+            resolver,
+            "insert",
+            "(Landroid/net/Uri;Landroid/content/ContentValues;)Landroid/net/Uri;",
+            &[collection.into(), (&values).into()],
+        )?
+        .l()?;
+
+    if uri.is_null() {
+        return Err(anyhow!("MediaStore refused to create {file_name}"));
+    }
+
+    Ok(uri)
+}
+
+fn content_resolver<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+) -> anyhow::Result<JObject<'local>> {
+    Ok(env
+        .call_method(
+            activity,
+            "getContentResolver",
+            "()Landroid/content/ContentResolver;",
+            &[],
+        )?
+        .l()?)
+}
+
+/// Creates a file like [`insert_media_file`], returns its content URI and a stream writing to it.
+fn create_media_file<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+    collection: &JObject,
+    file_name: &str,
+    mime_type: &str,
+    directory: &str,
+) -> anyhow::Result<(JObject<'local>, JObject<'local>)> {
+    let resolver = content_resolver(env, activity)?;
+    let uri = insert_media_file(env, &resolver, collection, file_name, mime_type, directory)?;
+
+    let stream = env
+        .call_method(
+            &resolver,
+            "openOutputStream",
+            "(Landroid/net/Uri;)Ljava/io/OutputStream;",
+            &[(&uri).into()],
+        )?
+        .l()?;
+
+    Ok((uri, stream))
+}
+
+fn unix_timestamp() -> anyhow::Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs())
+}
+
+/// Saves `image` as a PNG to the shared pictures collection, so it shows up in the gallery.
+/// Returns the content URI of the new file.
+fn insert_snapshot<'local>(
+    env: &mut JNIEnv<'local>,
+    activity: &JObject,
+    image: &thermal::RgbImage,
+) -> anyhow::Result<JObject<'local>> {
+    let width = i32::try_from(image.width())?;
+    let height = i32::try_from(image.height())?;
+    let pixels: Vec<i32> = image
+        .data()
+        .chunks_exact(3)
+        .map(|rgb| i32::from_be_bytes([0xff, rgb[0], rgb[1], rgb[2]]))
+        .collect();
+
+    let colors = env.new_int_array(i32::try_from(pixels.len())?)?;
+    env.set_int_array_region(&colors, 0, &pixels)?;
+
+    let config = env
+        .get_static_field(
+            "android/graphics/Bitmap$Config",
+            "ARGB_8888",
+            "Landroid/graphics/Bitmap$Config;",
+        )?
+        .l()?;
+    let bitmap = env
+        .call_static_method(
+            "android/graphics/Bitmap",
+            "createBitmap",
+            "([IIILandroid/graphics/Bitmap$Config;)Landroid/graphics/Bitmap;",
+            &[
+                (&colors).into(),
+                width.into(),
+                height.into(),
+                (&config).into(),
+            ],
+        )?
+        .l()?;
+
+    let collection = env
+        .get_static_field(
+            "android/provider/MediaStore$Images$Media",
+            "EXTERNAL_CONTENT_URI",
+            "Landroid/net/Uri;",
+        )?
+        .l()?;
+    let (uri, stream) = create_media_file(
+        env,
+        activity,
+        &collection,
+        &format!("tiop01-{}.png", unix_timestamp()?),
+        "image/png",
+        SNAPSHOT_DIRECTORY,
+    )?;
+
+    let format = env
+        .get_static_field(
+            "android/graphics/Bitmap$CompressFormat",
+            "PNG",
+            "Landroid/graphics/Bitmap$CompressFormat;",
+        )?
+        .l()?;
+
+    let compressed = env
+        .call_method(
+            &bitmap,
+            "compress",
+            "(Landroid/graphics/Bitmap$CompressFormat;ILjava/io/OutputStream;)Z",
+            &[(&format).into(), 100.into(), (&stream).into()],
+        )?
+        .z()?;
+    env.call_method(&stream, "close", "()V", &[])?;
+
+    if compressed {
+        Ok(uri)
+    } else {
+        Err(anyhow!("Failed to encode the snapshot"))
+    }
+}
+
+/// Collection of the files other than media in the shared storage.
+fn files_collection<'local>(env: &mut JNIEnv<'local>) -> anyhow::Result<JObject<'local>> {
+    let volume = env.new_string("external")?;
+
+    Ok(env
+        .call_static_method(
+            "android/provider/MediaStore$Files",
+            "getContentUri",
+            "(Ljava/lang/String;)Landroid/net/Uri;",
+            &[volume.deref().into()],
+        )?
+        .l()?)
+}
+
+/// Writes a file into the shared documents.
+fn save_document(data: &[u8], file_name: &str, mime_type: &str) -> anyhow::Result<()> {
+    with_activity(|env, activity| {
+        let collection = files_collection(env)?;
+        let (_, stream) = create_media_file(
+            env,
+            activity,
+            &collection,
+            file_name,
+            mime_type,
+            REPORT_DIRECTORY,
+        )?;
+
+        let bytes = env.byte_array_from_slice(data)?;
+        env.call_method(&stream, "write", "([B)V", &[(&bytes).into()])?;
+        env.call_method(&stream, "close", "()V", &[])?;
+
+        Ok(())
+    })
+}
+
+/// Creates a session file named after the current time in `directory` of the shared storage,
+/// under `Documents` or `Download`. Returns its path relative to the shared storage and the file,
+/// written through its descriptor so a long recording is streamed rather than held in memory.
+pub fn create_recording(directory: &str) -> anyhow::Result<(PathBuf, std::fs::File)> {
+    let directory = directory.trim().trim_matches('/');
+    let top = directory.split('/').next().unwrap_or_default();
+    if !DOCUMENT_FOLDERS.contains(&top) {
+        return Err(anyhow!(
+            "The folder has to be in {}",
+            DOCUMENT_FOLDERS.join(" or ")
+        ));
+    }
+    let file_name = format!("tiop01-{}.tiop01", unix_timestamp()?);
+
+    with_activity(|env, activity| {
+        let resolver = content_resolver(env, activity)?;
+        let collection = files_collection(env)?;
+        let uri = insert_media_file(
+            env,
+            &resolver,
+            &collection,
+            &file_name,
+            "application/octet-stream",
+            directory,
+        )?;
+
+        let mode = env.new_string("w")?;
+        let descriptor = env
+            .call_method(
+                &resolver,
+                "openFileDescriptor",
+                "(Landroid/net/Uri;Ljava/lang/String;)Landroid/os/ParcelFileDescriptor;",
+                &[(&uri).into(), mode.deref().into()],
+            )?
+            .l()?;
+        let fd = env.call_method(&descriptor, "detachFd", "()I", &[])?.i()?;
+
+        // a detached descriptor belongs to the caller, closed when the file is dropped
+        let file = unsafe { std::fs::File::from_raw_fd(fd) };
+
+        Ok((Path::new(directory).join(file_name), file))
+    })
+}
+
+pub fn save_report(html: &str) -> anyhow::Result<()> {
+    save_document(
+        html.as_bytes(),
+        &format!("tiop01-report-{}.html", unix_timestamp()?),
+        "text/html",
+    )
+}
+
+pub fn save_alarm_log(zip: &[u8]) -> anyhow::Result<()> {
+    save_document(
+        zip,
+        &format!("tiop01-alarms-{}.zip", unix_timestamp()?),
+        "application/zip",
+    )
+}
+
+pub fn save_snapshot(image: &thermal::RgbImage) -> anyhow::Result<()> {
+    with_activity(|env, activity| insert_snapshot(env, activity, image).map(|_| ()))
+}
+
+/// Saves `image` like [`save_snapshot`] and offers it to other apps through the share sheet.
+pub fn share_snapshot(image: &thermal::RgbImage) -> anyhow::Result<()> {
+    with_activity(|env, activity| {
+        let uri = insert_snapshot(env, activity, image)?;
+
+        let action = env.new_string("android.intent.action.SEND")?;
+        let intent = env.new_object(
+            "android/content/Intent",
+            "(Ljava/lang/String;)V",
+            &[action.deref().into()],
+        )?;
+
+        let mime_type = env.new_string("image/png")?;
+        env.call_method(
+            &intent,
+            "setType",
+            "(Ljava/lang/String;)Landroid/content/Intent;",
+            &[mime_type.deref().into()],
+        )?;
+
+        let extra = env.new_string("android.intent.extra.STREAM")?;
+        env.call_method(
+            &intent,
+            "putExtra",
+            "(Ljava/lang/String;Landroid/os/Parcelable;)Landroid/content/Intent;",
+            &[extra.deref().into(), (&uri).into()],
+        )?;
+        env.call_method(
+            &intent,
+            "addFlags",
+            "(I)Landroid/content/Intent;",
+            &[FLAG_GRANT_READ_URI_PERMISSION.into()],
+        )?;
+
+        let chooser = env
+            .call_static_method(
+                "android/content/Intent",
+                "createChooser",
+                "(Landroid/content/Intent;Ljava/lang/CharSequence;)Landroid/content/Intent;",
+                &[(&intent).into(), (&JObject::null()).into()],
+            )?
+            .l()?;
+
+        env.call_method(
+            activity,
+            "startActivity",
+            "(Landroid/content/Intent;)V",
+            &[(&chooser).into()],
+        )?;
+
+        Ok(())
+    })
+}
+
+fn system_service<'local>(
+    env: &mut JNIEnv<'local>,
+    context: &JObject,
+    name: &str,
+) -> anyhow::Result<JObject<'local>> {
+    let name = env.new_string(name)?;
+
+    Ok(env
+        .call_method(
+            context,
+            "getSystemService",
+            "(Ljava/lang/String;)Ljava/lang/Object;",
+            &[name.deref().into()],
+        )?
+        .l()?)
+}
+
+/// Shows the runtime permission dialog, the answer isn't delivered to native activities.
+fn request_permission(env: &mut JNIEnv, activity: &JObject, name: &str) -> anyhow::Result<()> {
+    let permission = env.new_string(name)?;
+    let permissions = env.new_object_array(1, "java/lang/String", &permission)?;
+    env.call_method(
+        activity,
+        "requestPermissions",
+        "([Ljava/lang/String;I)V",
+        &[(&permissions).into(), 0.into()],
+    )?;
+
+    Ok(())
+}
+
+fn app_icon(env: &mut JNIEnv, activity: &JObject) -> anyhow::Result<i32> {
+    let application_info = env
+        .call_method(
+            activity,
+            "getApplicationInfo",
+            "()Landroid/content/pm/ApplicationInfo;",
+            &[],
+        )?
+        .l()?;
+
+    Ok(env.get_field(&application_info, "icon", "I")?.i()?)
+}
+
+fn register_fullscreen_shortcut(env: &mut JNIEnv, activity: &JObject) -> anyhow::Result<()> {
+    let activity_class = env
+        .call_method(activity, "getClass", "()Ljava/lang/Class;", &[])?
+        .l()?;
+    let intent = env.new_object(
+        "android/content/Intent",
+        "(Landroid/content/Context;Ljava/lang/Class;)V",
+        &[activity.into(), (&activity_class).into()],
+    )?;
+
+    // shortcuts can't be added without an action
+    let action = env.new_string("android.intent.action.VIEW")?;
+    env.call_method(
+        &intent,
+        "setAction",
+        "(Ljava/lang/String;)Landroid/content/Intent;",
+        &[action.deref().into()],
+    )?;
+
+    let extra = env.new_string(EXTRA_FULLSCREEN)?;
+    env.call_method(
+        &intent,
+        "putExtra",
+        "(Ljava/lang/String;Z)Landroid/content/Intent;",
+        &[extra.deref().into(), true.into()],
+    )?;
+
+    let icon = app_icon(env, activity)?;
+    let icon = env
+        .call_static_method(
+            "android/graphics/drawable/Icon",
+            "createWithResource",
+            "(Landroid/content/Context;I)Landroid/graphics/drawable/Icon;",
+            &[activity.into(), icon.into()],
+        )?
+        .l()?;
+
+    let id = env.new_string("fullscreen")?;
+    let label = env.new_string(tr("Fullscreen view"))?;
+    let builder = env.new_object(
+        "android/content/pm/ShortcutInfo$Builder",
+        "(Landroid/content/Context;Ljava/lang/String;)V",
+        &[activity.into(), id.deref().into()],
+    )?;
+
+    for (method, signature, argument) in [
+        (
+            "setShortLabel",
+            "(Ljava/lang/CharSequence;)Landroid/content/pm/ShortcutInfo$Builder;",
+            label.deref(),
+        ),
+        (
+            "setIcon",
+            "(Landroid/graphics/drawable/Icon;)Landroid/content/pm/ShortcutInfo$Builder;",
+            &icon,
+        ),
+        (
+            "setIntent",
+            "(Landroid/content/Intent;)Landroid/content/pm/ShortcutInfo$Builder;",
+            &intent,
+        ),
+    ] {
+        env.call_method(&builder, method, signature, &[argument.into()])?;
+    }
+
+    let shortcut = env
+        .call_method(
+            &builder,
+            "build",
+            "()Landroid/content/pm/ShortcutInfo;",
+            &[],
+        )?
+        .l()?;
+    let shortcuts = env
+        .call_static_method(
+            "java/util/Collections",
+            "singletonList",
+            "(Ljava/lang/Object;)Ljava/util/List;",
+            &[(&shortcut).into()],
+        )?
+        .l()?;
+
+    let manager = system_service(env, activity, "shortcut")?;
+    env.call_method(
+        &manager,
+        "setDynamicShortcuts",
+        "(Ljava/util/List;)Z",
+        &[(&shortcuts).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Adds a launcher shortcut (long press on the app icon) that opens the image in fullscreen.
+/// Returns whether the app has been launched through it.
+/// Starts the activity of `app` again in a new task, with the intent it has been started with.
+/// The process is meant to exit right after, the new task then gets a process of its own.
+pub fn relaunch(app: &AndroidApp) -> anyhow::Result<()> {
+    with_app_activity(app, |env, activity| {
+        let intent = env
+            .call_method(activity, "getIntent", "()Landroid/content/Intent;", &[])?
+            .l()?;
+
+        if intent.is_null() {
+            return Err(anyhow!("The activity has no intent"));
+        }
+
+        env.call_method(
+            &intent,
+            "addFlags",
+            "(I)Landroid/content/Intent;",
+            &[FLAG_ACTIVITY_RESTART.into()],
+        )?;
+        env.call_method(
+            activity,
+            "startActivity",
+            "(Landroid/content/Intent;)V",
+            &[(&intent).into()],
+        )?;
+
+        Ok(())
+    })
+}
+
+pub fn fullscreen_shortcut() -> anyhow::Result<bool> {
+    with_activity(|env, activity| {
+        register_fullscreen_shortcut(env, activity)?;
+
+        let intent = env
+            .call_method(activity, "getIntent", "()Landroid/content/Intent;", &[])?
+            .l()?;
+
+        if intent.is_null() {
+            return Ok(false);
+        }
+
+        let extra = env.new_string(EXTRA_FULLSCREEN)?;
+
+        Ok(env
+            .call_method(
+                &intent,
+                "getBooleanExtra",
+                "(Ljava/lang/String;Z)Z",
+                &[extra.deref().into(), false.into()],
+            )?
+            .z()?)
+    })
+}
+
+fn vibrate(env: &mut JNIEnv, activity: &JObject) -> anyhow::Result<()> {
+    let vibrator = system_service(env, activity, "vibrator")?;
+    let duration = i64::try_from(ALARM_VIBRATION.as_millis())?;
+    let effect = env
+        .call_static_method(
+            "android/os/VibrationEffect",
+            "createOneShot",
+            "(JI)Landroid/os/VibrationEffect;",
+            &[duration.into(), DEFAULT_AMPLITUDE.into()],
+        )?
+        .l()?;
+
+    env.call_method(
+        &vibrator,
+        "vibrate",
+        "(Landroid/os/VibrationEffect;)V",
+        &[(&effect).into()],
+    )?;
+
+    Ok(())
+}
+
+/// Plays the `ToneGenerator` `tone` at `volume` (0 to 1) of the alarm volume.
+pub fn play_alarm_tone(tone: i32, duration: Duration, volume: f32) -> anyhow::Result<()> {
+    #[allow(clippy::cast_possible_truncation)]
+    let volume = (volume.clamp(0.0, 1.0) * 100.0).round() as i32;
+
+    with_activity(|env, _activity| {
+        let mut tone_generator = TONE_GENERATOR
+            .lock()
+            .map_err(|_| anyhow!("Tone generator lock is poisoned"))?;
+
+        // the volume of a tone generator is fixed, a new one is needed to change it
+        if !matches!(*tone_generator, Some((_, current)) if current == volume) {
+            let generator = env.new_object(
+                "android/media/ToneGenerator",
+                "(II)V",
+                &[STREAM_ALARM.into(), volume.into()],
+            )?;
+            *tone_generator = Some((env.new_global_ref(generator)?, volume));
+        }
+
+        if let Some((ref generator, _)) = *tone_generator {
+            let duration = i32::try_from(duration.as_millis())?;
+
+            env.call_method(
+                generator,
+                "startTone",
+                "(II)Z",
+                &[tone.into(), duration.into()],
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Plays a click of the sonification.
+pub fn play_click() -> anyhow::Result<()> {
+    with_activity(|env, _activity| {
+        let mut click_generator = CLICK_GENERATOR
+            .lock()
+            .map_err(|_| anyhow!("Click generator lock is poisoned"))?;
+
+        if click_generator.is_none() {
+            let generator = env.new_object(
+                "android/media/ToneGenerator",
+                "(II)V",
+                &[STREAM_MUSIC.into(), TONE_VOLUME.into()],
+            )?;
+            *click_generator = Some(env.new_global_ref(generator)?);
+        }
+
+        if let Some(ref generator) = *click_generator {
+            let duration = i32::try_from(CLICK_LENGTH.as_millis())?;
+
+            env.call_method(
+                generator,
+                "startTone",
+                "(II)Z",
+                &[TONE_PROP_BEEP.into(), duration.into()],
+            )?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Vibrates on a tripped alarm, so it's noticed without looking at the phone.
+pub fn alarm_vibration() -> anyhow::Result<()> {
+    with_activity(vibrate)
+}
+
+/// Whether battery saver is on or the battery is low.
+pub fn power_saving() -> anyhow::Result<bool> {
+    with_activity(|env, activity| {
+        let power_manager = system_service(env, activity, "power")?;
+
+        if env
+            .call_method(&power_manager, "isPowerSaveMode", "()Z", &[])?
+            .z()?
+        {
+            return Ok(true);
+        }
+
+        let battery_manager = system_service(env, activity, "batterymanager")?;
+        let capacity = env
+            .call_method(
+                &battery_manager,
+                "getIntProperty",
+                "(I)I",
+                &[BATTERY_PROPERTY_CAPACITY.into()],
+            )?
+            .i()?;
+
+        // the capacity is Integer.MIN_VALUE when unknown
+        Ok((0..LOW_BATTERY).contains(&capacity))
+    })
+}
+
+/// Whether the phone's camera may be used, optionally asking the user when it may not.
+pub fn camera_permission(request: bool) -> anyhow::Result<bool> {
+    const CAMERA: &str = "android.permission.CAMERA";
+
+    with_activity(|env, activity| {
+        let permission = env.new_string(CAMERA)?;
+        let granted = env
+            .call_method(
+                activity,
+                "checkSelfPermission",
+                "(Ljava/lang/String;)I",
+                &[(&permission).into()],
+            )?
+            .i()?
+            == PERMISSION_GRANTED;
+
+        if !granted && request {
+            request_permission(env, activity, CAMERA)?;
+        }
+
+        Ok(granted)
+    })
+}
+
+fn usb_manager<'local>(
+    env: &mut JNIEnv<'local>,
+    context: &JObject,
+) -> anyhow::Result<JObject<'local>> {
+    system_service(env, context, "usb")
+}
+
+fn find_camera<'local>(
+    env: &mut JNIEnv<'local>,
+    manager: &JObject,
+) -> anyhow::Result<Option<JObject<'local>>> {
+    let devices = env
+        .call_method(manager, "getDeviceList", "()Ljava/util/HashMap;", &[])?
+        .l()?;
+    let values = env
+        .call_method(&devices, "values", "()Ljava/util/Collection;", &[])?
+        .l()?;
+    let iterator = env
+        .call_method(&values, "iterator", "()Ljava/util/Iterator;", &[])?
+        .l()?;
+
+    while env.call_method(&iterator, "hasNext", "()Z", &[])?.z()? {
+        let device = env
+            .call_method(&iterator, "next", "()Ljava/lang/Object;", &[])?
+            .l()?;
+        let vid = env.call_method(&device, "getVendorId", "()I", &[])?.i()?;
+        let pid = env.call_method(&device, "getProductId", "()I", &[])?.i()?;
+
+        if vid == VID && pid == PID {
+            return Ok(Some(device));
+        }
+
+        env.delete_local_ref(device)?;
+    }
+
+    Ok(None)
+}
+
+/// Id the system has assigned to the attached camera. It changes whenever the camera is
+/// replugged.
+fn camera_id(env: &mut JNIEnv, context: &JObject) -> anyhow::Result<Option<i32>> {
+    env.with_local_frame(16, |env| {
+        let manager = usb_manager(env, context)?;
+
+        match find_camera(env, &manager)? {
+            Some(device) => Ok(Some(
+                env.call_method(&device, "getDeviceId", "()I", &[])?.i()?,
+            )),
+            None => Ok(None),
+        }
+    })
+}
+
+/// USB serial number of the attached camera. Reading it requires the permission to access the
+/// camera.
+fn camera_serial_number(env: &mut JNIEnv, context: &JObject) -> anyhow::Result<Option<String>> {
+    env.with_local_frame(16, |env| {
+        let manager = usb_manager(env, context)?;
+
+        let Some(device) = find_camera(env, &manager)? else {
+            return Ok(None);
+        };
+
+        let serial_number = env
+            .call_method(&device, "getSerialNumber", "()Ljava/lang/String;", &[])?
+            .l()?;
+
+        if serial_number.is_null() {
+            Ok(None)
+        } else {
+            Ok(Some(env.get_string(&serial_number.into())?.into()))
+        }
+    })
+}
+
 pub struct SerialPortOpener<'a> {
     actx: Rc<RefCell<Context<'a>>>,
+    permission: Permission,
+    transport: Transport,
+    serial_number: Option<String>,
 }
 
 pub struct SerialPortReadWrite<'a> {
     actx: Rc<RefCell<Context<'a>>>,
     rw: jni::objects::GlobalRef,
+    camera_id: Option<i32>,
+    attachment_checked_at: Instant,
+    /// Java array every read goes to, allocating one per read keeps the GC busy
+    read_array: GlobalRef,
+    chunk: Vec<u8>,
+    /// Bytes read from Java but not consumed yet
+    pending: VecDeque<u8>,
 }
 
 impl<'a> SerialPortOpener<'a> {
     pub fn new(actx: Rc<RefCell<Context<'a>>>) -> Self {
-        SerialPortOpener { actx }
+        SerialPortOpener {
+            actx,
+            permission: Permission::NotRequested,
+            transport: Transport::default(),
+            serial_number: None,
+        }
+    }
+
+    /// Opens the camera with the file descriptor of a UsbDeviceConnection and talks CDC-ACM to
+    /// it from Rust, so reading doesn't go through JNI at all.
+    fn open_usb(&mut self) -> anyhow::Result<CdcAcm> {
+        let actx = &mut *self.actx.borrow_mut();
+
+        let fd = actx
+            .env
+            .with_local_frame(16, |env| -> anyhow::Result<OwnedFd> {
+                let manager = usb_manager(env, &actx.context)?;
+                let device = find_camera(env, &manager)?.ok_or_else(|| {
+                    PortError::new(PortErrorKind::NotFound, "Camera isn't connected")
+                })?;
+
+                let connection = env
+                .call_method(
+                    &manager,
+                    "openDevice",
+                    "(Landroid/hardware/usb/UsbDevice;)Landroid/hardware/usb/UsbDeviceConnection;",
+                    &[(&device).into()],
+                )?
+                .l()?;
+
+                if connection.is_null() {
+                    return Err(anyhow!("Failed to open USB device"));
+                }
+
+                let fd = env
+                    .call_method(&connection, "getFileDescriptor", "()I", &[])?
+                    .i()?;
+                // the connection owns its descriptor, a duplicate outlives closing it
+                let fd = unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned();
+                env.call_method(&connection, "close", "()V", &[])?;
+
+                Ok(fd?)
+            })?;
+
+        let device =
+            nusb::Device::from_fd(fd).map_err(|e| anyhow!("Failed to open USB device: {e}"))?;
+
+        CdcAcm::from_device(&device, "Direct USB".to_owned(), BAUD_RATE, None, None)
+    }
+
+    /// Makes sure the app may access the camera, showing the system permission dialog if it
+    /// may not. When the app has been started by plugging the camera in (USB_DEVICE_ATTACHED
+    /// intent), the permission has already been granted.
+    fn ensure_permission(&mut self) -> anyhow::Result<()> {
+        let actx = &mut *self.actx.borrow_mut();
+        let permission = &mut self.permission;
+
+        actx.env.with_local_frame(16, |env| {
+            let manager = usb_manager(env, &actx.context)?;
+
+            let Some(device) = find_camera(env, &manager)? else {
+                *permission = Permission::NotRequested;
+                return Err(
+                    PortError::new(PortErrorKind::NotFound, "Camera isn't connected").into(),
+                );
+            };
+
+            let granted = env
+                .call_method(
+                    &manager,
+                    "hasPermission",
+                    "(Landroid/hardware/usb/UsbDevice;)Z",
+                    &[(&device).into()],
+                )?
+                .z()?;
+
+            if granted {
+                *permission = Permission::NotRequested;
+                return Ok(());
+            }
+
+            match *permission {
+                Permission::NotRequested => {
+                    let action = env.new_string(ACTION_USB_PERMISSION)?;
+                    let intent = env.new_object(
+                        "android/content/Intent",
+                        "(Ljava/lang/String;)V",
+                        &[action.deref().into()],
+                    )?;
+                    let pending_intent = env
+                        .call_static_method(
+                            "android/app/PendingIntent",
+                            "getBroadcast",
+                            "(Landroid/content/Context;ILandroid/content/Intent;I)\
+                             Landroid/app/PendingIntent;",
+                            &[
+                                (&actx.context).into(),
+                                0.into(),
+                                (&intent).into(),
+                                FLAG_IMMUTABLE.into(),
+                            ],
+                        )?
+                        .l()?;
+
+                    env.call_method(
+                        &manager,
+                        "requestPermission",
+                        "(Landroid/hardware/usb/UsbDevice;Landroid/app/PendingIntent;)V",
+                        &[(&device).into(), (&pending_intent).into()],
+                    )?;
+
+                    *permission = Permission::Requested(Instant::now());
+                    Err(AccessError::WaitingForPermission.into())
+                }
+                Permission::Requested(since) => {
+                    // The dialog takes the window focus while it's shown. Getting it back
+                    // without the permission means the user has denied it, which can't be
+                    // observed otherwise without a broadcast receiver.
+                    let focused = env
+                        .call_method(&actx.context, "hasWindowFocus", "()Z", &[])?
+                        .z()?;
+
+                    if focused && since.elapsed() > PERMISSION_DIALOG_DELAY {
+                        *permission = Permission::Denied;
+                        Err(AccessError::PermissionDenied.into())
+                    } else {
+                        Err(AccessError::WaitingForPermission.into())
+                    }
+                }
+                Permission::Denied => Err(AccessError::PermissionDenied.into()),
+            }
+        })
+    }
+}
+
+pub enum ThermalReadWrite<'a> {
+    Java(SerialPortReadWrite<'a>),
+    Usb(CdcAcm),
+}
+
+impl<'a> io::Read for ThermalReadWrite<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
+        match self {
+            ThermalReadWrite::Java(rw) => io::Read::read(rw, buf),
+            ThermalReadWrite::Usb(cdc) => io::Read::read(cdc, buf),
+        }
+    }
+}
+
+impl<'a> io::Write for ThermalReadWrite<'a> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
+        match self {
+            ThermalReadWrite::Java(rw) => io::Write::write(rw, buf),
+            ThermalReadWrite::Usb(cdc) => io::Write::write(cdc, buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ThermalReadWrite::Java(rw) => io::Write::flush(rw),
+            ThermalReadWrite::Usb(cdc) => io::Write::flush(cdc),
+        }
     }
 }
 
 impl<'a> PortOpener<'a> for SerialPortOpener<'a> {
-    type RW = SerialPortReadWrite<'a>;
+    type RW = ThermalReadWrite<'a>;
 
     fn open(&mut self) -> anyhow::Result<Self::RW> {
+        self.ensure_permission()?;
+
+        {
+            let actx = &mut *self.actx.borrow_mut();
+            self.serial_number = camera_serial_number(&mut actx.env, &actx.context)
+                .inspect_err(|e| log::warn!("Failed to read the camera's serial number: {e}"))
+                .ok()
+                .flatten();
+        }
+
+        if self.transport == Transport::Usb {
+            return self
+                .open_usb()
+                .map(ThermalReadWrite::Usb)
+                .inspect_err(|e| log::error!("SerialPortOpener::open_usb failed: {e}"));
+        }
+
         let actx = &mut *self.actx.borrow_mut();
+        let camera_id = camera_id(&mut actx.env, &actx.context)?;
 
         let ret = actx.env.with_local_frame(4, |env| {
             let class_loader = env
@@ -74,48 +1121,133 @@ impl<'a> PortOpener<'a> for SerialPortOpener<'a> {
             if rw.is_null() {
                 Err(anyhow!("open has returned null"))
             } else {
-                Ok(SerialPortReadWrite::new(
+                let read_array = env.new_byte_array(i32::try_from(READ_CHUNK_SIZE)?)?;
+
+                Ok(ThermalReadWrite::Java(SerialPortReadWrite::new(
                     Rc::clone(&self.actx),
                     env.new_global_ref(rw)?,
-                ))
+                    camera_id,
+                    env.new_global_ref(read_array)?,
+                )))
             }
         });
 
         ret.inspect_err(|e| log::error!("SerialPortOpener::open failed: {e}"))
     }
+
+    fn port_info(&self) -> PortInfo {
+        match self.transport {
+            Transport::Usb => PortInfo {
+                name: tr("Direct USB").into(),
+                baud_rate: Some(BAUD_RATE),
+                serial_number: self.serial_number.clone(),
+            },
+            // the baud rate is configured on the Java side
+            _ => PortInfo {
+                name: "USB".into(),
+                baud_rate: None,
+                serial_number: self.serial_number.clone(),
+            },
+        }
+    }
+
+    fn configure(&mut self, settings: &Settings) {
+        self.transport = settings.transport;
+    }
+
+    fn retry(&mut self) {
+        self.permission = Permission::NotRequested;
+    }
+
+    // the Java port is only usable from the producer's thread, attached to the JVM
+    fn writer(&self, rw: &Self::RW) -> Option<Box<dyn io::Write + Send>> {
+        match rw {
+            ThermalReadWrite::Java(_) => None,
+            ThermalReadWrite::Usb(cdc) => Some(Box::new(cdc.writer())),
+        }
+    }
 }
 
 impl<'a> SerialPortReadWrite<'a> {
-    fn new(actx: Rc<RefCell<Context<'a>>>, rw: jni::objects::GlobalRef) -> Self {
-        Self { actx, rw }
+    fn new(
+        actx: Rc<RefCell<Context<'a>>>,
+        rw: jni::objects::GlobalRef,
+        camera_id: Option<i32>,
+        read_array: GlobalRef,
+    ) -> Self {
+        Self {
+            actx,
+            rw,
+            camera_id,
+            attachment_checked_at: Instant::now(),
+            read_array,
+            chunk: vec![0; READ_CHUNK_SIZE],
+            pending: VecDeque::with_capacity(READ_CHUNK_SIZE),
+        }
     }
 
-    fn read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+    /// Fails once the camera the port has been opened for is gone. After the cable has been
+    /// replugged, the old connection may keep "working" without delivering any data, so the
+    /// producer has to drop it and open the new device. Detach broadcasts would need a Java
+    /// receiver, the device list is polled instead.
+    fn ensure_attached(&mut self) -> anyhow::Result<()> {
+        if self.attachment_checked_at.elapsed() < ATTACHMENT_CHECK_INTERVAL {
+            return Ok(());
+        }
+
+        self.attachment_checked_at = Instant::now();
+
         let actx = &mut *self.actx.borrow_mut();
 
-        let ret = actx.env.with_local_frame(4, |env| {
-            let byte_array = env.new_byte_array(i32::try_from(buf.len())?)?;
+        if camera_id(&mut actx.env, &actx.context)? == self.camera_id {
+            Ok(())
+        } else {
+            Err(anyhow!("Camera has been detached"))
+        }
+    }
 
-            let bytes_read = env
-                .call_method(&self.rw, "read", "([B)I", &[byte_array.deref().into()])?
-                .i()?;
+    /// Reads up to a whole frame from Java into `pending`.
+    fn fill_pending(&mut self) -> anyhow::Result<()> {
+        let actx = &mut *self.actx.borrow_mut();
+        let env = &mut actx.env;
+        let read_array: &JByteArray = self.read_array.as_obj().into();
 
-            if bytes_read > 0 {
-                // SAFETY: get_byte_array_region expects &mut [jbyte] that's why
-                // we have to cast from &mut [u8] to &mut [i8]
-                let buf_slice = unsafe {
-                    std::slice::from_raw_parts_mut(buf.as_mut_ptr().cast::<jbyte>(), buf.len())
-                };
+        let bytes_read = env
+            .call_method(&self.rw, "read", "([B)I", &[read_array.into()])?
+            .i()?;
 
-                env.get_byte_array_region(byte_array, 0, buf_slice)?;
+        if bytes_read <= 0 {
+            return Err(anyhow!("JNI read failed: {bytes_read}"));
+        }
 
-                Ok(usize::try_from(bytes_read)?)
-            } else {
-                Err(anyhow!("JNI read failed: {bytes_read}"))
-            }
-        });
+        let chunk = &mut self.chunk[..usize::try_from(bytes_read)?];
 
-        ret.map_err(|e| anyhow!("{e}"))
+        // SAFETY: get_byte_array_region expects &mut [jbyte] that's why
+        // we have to cast from &mut [u8] to &mut [i8]
+        let chunk_slice = unsafe {
+            std::slice::from_raw_parts_mut(chunk.as_mut_ptr().cast::<jbyte>(), chunk.len())
+        };
+
+        env.get_byte_array_region(read_array, 0, chunk_slice)?;
+        self.pending.extend(&*chunk);
+
+        Ok(())
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> anyhow::Result<usize> {
+        self.ensure_attached()?;
+
+        if self.pending.is_empty() {
+            self.fill_pending()?;
+        }
+
+        let len = buf.len().min(self.pending.len());
+
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
     }
 
     fn write(&mut self, buf: &[u8]) -> anyhow::Result<usize> {
@@ -0,0 +1,536 @@
+//! Rear camera of the phone through the NDK camera API, which unlike Camera2 in Java doesn't need
+//! callback classes the native activity can't provide.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    ptr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::anyhow;
+use eframe::egui;
+
+// ACAMERA_LENS_FACING and ACAMERA_LENS_FACING_BACK
+const LENS_FACING: u32 = 0x8_0005;
+const LENS_FACING_BACK: u8 = 1;
+// ACAMERA_SENSOR_ORIENTATION, clockwise rotation which makes the image upright
+const SENSOR_ORIENTATION: u32 = 0xe_000e;
+// ACAMERA_TYPE_BYTE and ACAMERA_TYPE_INT32
+const TYPE_BYTE: u8 = 0;
+const TYPE_INT32: u8 = 1;
+// ACameraDevice_request_template TEMPLATE_PREVIEW
+const TEMPLATE_PREVIEW: c_int = 1;
+// AIMAGE_FORMAT_YUV_420_888
+const FORMAT_YUV_420_888: i32 = 0x23;
+const MAX_IMAGES: i32 = 2;
+
+// Plenty for a layer under a 32x32 image, every other pixel is skipped on top of that
+const WIDTH: i32 = 640;
+const HEIGHT: i32 = 480;
+const SUBSAMPLING: usize = 2;
+
+enum ACameraManager {}
+enum ACameraMetadata {}
+enum ACameraDevice {}
+enum ACaptureRequest {}
+enum ACaptureSessionOutputContainer {}
+enum ACaptureSessionOutput {}
+enum ACameraOutputTarget {}
+enum ACameraCaptureSession {}
+enum ANativeWindow {}
+enum AImageReader {}
+enum AImage {}
+
+#[repr(C)]
+struct ACameraIdList {
+    num_cameras: c_int,
+    camera_ids: *const *const c_char,
+}
+
+#[repr(C)]
+struct ACameraMetadataConstEntry {
+    tag: u32,
+    kind: u8,
+    count: u32,
+    data: *const c_void,
+}
+
+#[repr(C)]
+struct ACameraDeviceStateCallbacks {
+    context: *mut c_void,
+    on_disconnected: unsafe extern "C" fn(*mut c_void, *mut ACameraDevice),
+    on_error: unsafe extern "C" fn(*mut c_void, *mut ACameraDevice, c_int),
+}
+
+#[repr(C)]
+struct ACameraCaptureSessionStateCallbacks {
+    context: *mut c_void,
+    on_closed: unsafe extern "C" fn(*mut c_void, *mut ACameraCaptureSession),
+    on_ready: unsafe extern "C" fn(*mut c_void, *mut ACameraCaptureSession),
+    on_active: unsafe extern "C" fn(*mut c_void, *mut ACameraCaptureSession),
+}
+
+#[link(name = "camera2ndk")]
+extern "C" {
+    fn ACameraManager_create() -> *mut ACameraManager;
+    fn ACameraManager_delete(manager: *mut ACameraManager);
+    fn ACameraManager_getCameraIdList(
+        manager: *mut ACameraManager,
+        list: *mut *mut ACameraIdList,
+    ) -> c_int;
+    fn ACameraManager_deleteCameraIdList(list: *mut ACameraIdList);
+    fn ACameraManager_getCameraCharacteristics(
+        manager: *mut ACameraManager,
+        camera_id: *const c_char,
+        characteristics: *mut *mut ACameraMetadata,
+    ) -> c_int;
+    fn ACameraManager_openCamera(
+        manager: *mut ACameraManager,
+        camera_id: *const c_char,
+        callbacks: *mut ACameraDeviceStateCallbacks,
+        device: *mut *mut ACameraDevice,
+    ) -> c_int;
+    fn ACameraMetadata_getConstEntry(
+        metadata: *const ACameraMetadata,
+        tag: u32,
+        entry: *mut ACameraMetadataConstEntry,
+    ) -> c_int;
+    fn ACameraMetadata_free(metadata: *mut ACameraMetadata);
+    fn ACameraDevice_close(device: *mut ACameraDevice) -> c_int;
+    fn ACameraDevice_createCaptureRequest(
+        device: *const ACameraDevice,
+        template: c_int,
+        request: *mut *mut ACaptureRequest,
+    ) -> c_int;
+    fn ACameraDevice_createCaptureSession(
+        device: *mut ACameraDevice,
+        outputs: *const ACaptureSessionOutputContainer,
+        callbacks: *const ACameraCaptureSessionStateCallbacks,
+        session: *mut *mut ACameraCaptureSession,
+    ) -> c_int;
+    fn ACaptureSessionOutputContainer_create(
+        container: *mut *mut ACaptureSessionOutputContainer,
+    ) -> c_int;
+    fn ACaptureSessionOutputContainer_free(container: *mut ACaptureSessionOutputContainer);
+    fn ACaptureSessionOutputContainer_add(
+        container: *mut ACaptureSessionOutputContainer,
+        output: *const ACaptureSessionOutput,
+    ) -> c_int;
+    fn ACaptureSessionOutput_create(
+        window: *mut ANativeWindow,
+        output: *mut *mut ACaptureSessionOutput,
+    ) -> c_int;
+    fn ACaptureSessionOutput_free(output: *mut ACaptureSessionOutput);
+    fn ACameraOutputTarget_create(
+        window: *mut ANativeWindow,
+        target: *mut *mut ACameraOutputTarget,
+    ) -> c_int;
+    fn ACameraOutputTarget_free(target: *mut ACameraOutputTarget);
+    fn ACaptureRequest_addTarget(
+        request: *mut ACaptureRequest,
+        target: *const ACameraOutputTarget,
+    ) -> c_int;
+    fn ACaptureRequest_free(request: *mut ACaptureRequest);
+    fn ACameraCaptureSession_setRepeatingRequest(
+        session: *mut ACameraCaptureSession,
+        callbacks: *mut c_void,
+        num_requests: c_int,
+        requests: *mut *mut ACaptureRequest,
+        sequence_id: *mut c_int,
+    ) -> c_int;
+    fn ACameraCaptureSession_stopRepeating(session: *mut ACameraCaptureSession) -> c_int;
+    fn ACameraCaptureSession_close(session: *mut ACameraCaptureSession);
+}
+
+#[link(name = "mediandk")]
+extern "C" {
+    fn AImageReader_new(
+        width: i32,
+        height: i32,
+        format: i32,
+        max_images: i32,
+        reader: *mut *mut AImageReader,
+    ) -> c_int;
+    fn AImageReader_delete(reader: *mut AImageReader);
+    fn AImageReader_getWindow(reader: *mut AImageReader, window: *mut *mut ANativeWindow) -> c_int;
+    fn AImageReader_acquireLatestImage(reader: *mut AImageReader, image: *mut *mut AImage)
+        -> c_int;
+    fn AImage_delete(image: *mut AImage);
+    fn AImage_getWidth(image: *const AImage, width: *mut i32) -> c_int;
+    fn AImage_getHeight(image: *const AImage, height: *mut i32) -> c_int;
+    fn AImage_getPlaneData(
+        image: *const AImage,
+        plane: c_int,
+        data: *mut *mut u8,
+        length: *mut c_int,
+    ) -> c_int;
+    fn AImage_getPlaneRowStride(image: *const AImage, plane: c_int, stride: *mut i32) -> c_int;
+    fn AImage_getPlanePixelStride(image: *const AImage, plane: c_int, stride: *mut i32) -> c_int;
+}
+
+fn check(status: c_int, function: &str) -> anyhow::Result<()> {
+    if status == 0 {
+        Ok(())
+    } else {
+        Err(anyhow!("{function} failed ({status})"))
+    }
+}
+
+unsafe extern "C" fn on_disconnected(context: *mut c_void, _: *mut ACameraDevice) {
+    log::warn!("The phone camera has been disconnected");
+    (*context.cast::<AtomicBool>()).store(true, Ordering::Relaxed);
+}
+
+unsafe extern "C" fn on_error(context: *mut c_void, _: *mut ACameraDevice, error: c_int) {
+    log::error!("The phone camera failed ({error})");
+    (*context.cast::<AtomicBool>()).store(true, Ordering::Relaxed);
+}
+
+unsafe extern "C" fn on_session_state(_: *mut c_void, _: *mut ACameraCaptureSession) {}
+
+/// Reads an integer entry of the camera characteristics.
+unsafe fn metadata_value(metadata: *const ACameraMetadata, tag: u32) -> Option<i32> {
+    let mut entry = ACameraMetadataConstEntry {
+        tag: 0,
+        kind: 0,
+        count: 0,
+        data: ptr::null(),
+    };
+
+    if ACameraMetadata_getConstEntry(metadata, tag, &mut entry) != 0 || entry.count == 0 {
+        return None;
+    }
+
+    match entry.kind {
+        TYPE_BYTE => Some(i32::from(*entry.data.cast::<u8>())),
+        TYPE_INT32 => Some(*entry.data.cast::<i32>()),
+        _ => None,
+    }
+}
+
+/// Id and sensor orientation of the first camera facing away from the screen.
+unsafe fn find_rear_camera(manager: *mut ACameraManager) -> anyhow::Result<(CString, i32)> {
+    let mut list = ptr::null_mut();
+    check(
+        ACameraManager_getCameraIdList(manager, &mut list),
+        "ACameraManager_getCameraIdList",
+    )?;
+
+    let count = usize::try_from((*list).num_cameras).unwrap_or(0);
+    let ids = if count == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts((*list).camera_ids, count)
+    };
+    let mut found = None;
+
+    for &id in ids {
+        let mut metadata = ptr::null_mut();
+        if ACameraManager_getCameraCharacteristics(manager, id, &mut metadata) != 0 {
+            continue;
+        }
+
+        let facing = metadata_value(metadata, LENS_FACING);
+        let orientation = metadata_value(metadata, SENSOR_ORIENTATION).unwrap_or(0);
+        ACameraMetadata_free(metadata);
+
+        if facing == Some(i32::from(LENS_FACING_BACK)) {
+            found = Some((CStr::from_ptr(id).to_owned(), orientation));
+            break;
+        }
+    }
+
+    ACameraManager_deleteCameraIdList(list);
+
+    found.ok_or_else(|| anyhow!("No rear camera found"))
+}
+
+struct Plane<'a> {
+    data: &'a [u8],
+    row_stride: usize,
+    pixel_stride: usize,
+}
+
+impl Plane<'_> {
+    unsafe fn new(image: *const AImage, index: c_int) -> anyhow::Result<Self> {
+        let mut data = ptr::null_mut();
+        let mut length = 0;
+        let mut row_stride = 0;
+        let mut pixel_stride = 0;
+
+        check(
+            AImage_getPlaneData(image, index, &mut data, &mut length),
+            "AImage_getPlaneData",
+        )?;
+        check(
+            AImage_getPlaneRowStride(image, index, &mut row_stride),
+            "AImage_getPlaneRowStride",
+        )?;
+        check(
+            AImage_getPlanePixelStride(image, index, &mut pixel_stride),
+            "AImage_getPlanePixelStride",
+        )?;
+
+        Ok(Self {
+            data: std::slice::from_raw_parts(data, usize::try_from(length)?),
+            row_stride: usize::try_from(row_stride)?,
+            pixel_stride: usize::try_from(pixel_stride)?,
+        })
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.data
+            .get(y * self.row_stride + x * self.pixel_stride)
+            .copied()
+            .unwrap_or(128)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn yuv_to_rgb(y: u8, u: u8, v: u8) -> egui::Color32 {
+    let y = f32::from(y);
+    let u = f32::from(u) - 128.0;
+    let v = f32::from(v) - 128.0;
+
+    let channel = |value: f32| value.clamp(0.0, 255.0) as u8;
+
+    egui::Color32::from_rgb(
+        channel(y + 1.402 * v),
+        channel(y - 0.344 * u - 0.714 * v),
+        channel(y + 1.772 * u),
+    )
+}
+
+/// Converts a YUV_420_888 image, rotating it by `orientation` degrees clockwise.
+unsafe fn convert_image(
+    image: *const AImage,
+    orientation: i32,
+) -> anyhow::Result<egui::ColorImage> {
+    let mut width = 0;
+    let mut height = 0;
+    check(AImage_getWidth(image, &mut width), "AImage_getWidth")?;
+    check(AImage_getHeight(image, &mut height), "AImage_getHeight")?;
+
+    let planes = [
+        Plane::new(image, 0)?,
+        Plane::new(image, 1)?,
+        Plane::new(image, 2)?,
+    ];
+    let width = usize::try_from(width)? / SUBSAMPLING;
+    let height = usize::try_from(height)? / SUBSAMPLING;
+    let rotated = orientation == 90 || orientation == 270;
+    let size = if rotated {
+        [height, width]
+    } else {
+        [width, height]
+    };
+
+    let pixels = (0..size[1])
+        .flat_map(|y| (0..size[0]).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let (x, y) = match orientation {
+                90 => (y, height - 1 - x),
+                180 => (width - 1 - x, height - 1 - y),
+                270 => (width - 1 - y, x),
+                _ => (x, y),
+            };
+            let (x, y) = (x * SUBSAMPLING, y * SUBSAMPLING);
+
+            yuv_to_rgb(
+                planes[0].get(x, y),
+                planes[1].get(x / 2, y / 2),
+                planes[2].get(x / 2, y / 2),
+            )
+        })
+        .collect();
+
+    Ok(egui::ColorImage { size, pixels })
+}
+
+/// Streams preview images of the rear camera. Resources are released in `Drop`, which also
+/// cleans up after a partially failed `open`.
+pub struct Camera {
+    manager: *mut ACameraManager,
+    reader: *mut AImageReader,
+    device: *mut ACameraDevice,
+    outputs: *mut ACaptureSessionOutputContainer,
+    output: *mut ACaptureSessionOutput,
+    target: *mut ACameraOutputTarget,
+    request: *mut ACaptureRequest,
+    session: *mut ACameraCaptureSession,
+    orientation: i32,
+    // set by the callbacks, e.g. when the app goes to the background
+    disconnected: Box<AtomicBool>,
+    device_callbacks: Box<ACameraDeviceStateCallbacks>,
+    session_callbacks: Box<ACameraCaptureSessionStateCallbacks>,
+}
+
+impl Camera {
+    pub fn open() -> anyhow::Result<Self> {
+        let disconnected = Box::new(AtomicBool::new(false));
+        let context = ptr::from_ref::<AtomicBool>(&disconnected).cast_mut().cast();
+
+        let mut camera = Self {
+            manager: unsafe { ACameraManager_create() },
+            reader: ptr::null_mut(),
+            device: ptr::null_mut(),
+            outputs: ptr::null_mut(),
+            output: ptr::null_mut(),
+            target: ptr::null_mut(),
+            request: ptr::null_mut(),
+            session: ptr::null_mut(),
+            orientation: 0,
+            disconnected,
+            device_callbacks: Box::new(ACameraDeviceStateCallbacks {
+                context,
+                on_disconnected,
+                on_error,
+            }),
+            session_callbacks: Box::new(ACameraCaptureSessionStateCallbacks {
+                context: ptr::null_mut(),
+                on_closed: on_session_state,
+                on_ready: on_session_state,
+                on_active: on_session_state,
+            }),
+        };
+
+        unsafe { camera.start()? };
+
+        Ok(camera)
+    }
+
+    unsafe fn start(&mut self) -> anyhow::Result<()> {
+        if self.manager.is_null() {
+            return Err(anyhow!("ACameraManager_create failed"));
+        }
+
+        let (id, orientation) = find_rear_camera(self.manager)?;
+        self.orientation = orientation;
+
+        check(
+            AImageReader_new(
+                WIDTH,
+                HEIGHT,
+                FORMAT_YUV_420_888,
+                MAX_IMAGES,
+                &mut self.reader,
+            ),
+            "AImageReader_new",
+        )?;
+        let mut window = ptr::null_mut();
+        check(
+            AImageReader_getWindow(self.reader, &mut window),
+            "AImageReader_getWindow",
+        )?;
+
+        check(
+            ACameraManager_openCamera(
+                self.manager,
+                id.as_ptr(),
+                &mut *self.device_callbacks,
+                &mut self.device,
+            ),
+            "ACameraManager_openCamera",
+        )?;
+
+        check(
+            ACaptureSessionOutputContainer_create(&mut self.outputs),
+            "ACaptureSessionOutputContainer_create",
+        )?;
+        check(
+            ACaptureSessionOutput_create(window, &mut self.output),
+            "ACaptureSessionOutput_create",
+        )?;
+        check(
+            ACaptureSessionOutputContainer_add(self.outputs, self.output),
+            "ACaptureSessionOutputContainer_add",
+        )?;
+
+        check(
+            ACameraDevice_createCaptureRequest(self.device, TEMPLATE_PREVIEW, &mut self.request),
+            "ACameraDevice_createCaptureRequest",
+        )?;
+        check(
+            ACameraOutputTarget_create(window, &mut self.target),
+            "ACameraOutputTarget_create",
+        )?;
+        check(
+            ACaptureRequest_addTarget(self.request, self.target),
+            "ACaptureRequest_addTarget",
+        )?;
+
+        check(
+            ACameraDevice_createCaptureSession(
+                self.device,
+                self.outputs,
+                &*self.session_callbacks,
+                &mut self.session,
+            ),
+            "ACameraDevice_createCaptureSession",
+        )?;
+        check(
+            ACameraCaptureSession_setRepeatingRequest(
+                self.session,
+                ptr::null_mut(),
+                1,
+                &mut self.request,
+                ptr::null_mut(),
+            ),
+            "ACameraCaptureSession_setRepeatingRequest",
+        )
+    }
+
+    /// The camera has been taken away (e.g. by another app) and has to be opened again.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected.load(Ordering::Relaxed)
+    }
+
+    /// Upright image captured since the previous call, if any.
+    pub fn latest_frame(&mut self) -> Option<egui::ColorImage> {
+        let mut image = ptr::null_mut();
+
+        // fails with AMEDIA_IMGREADER_NO_BUFFER_AVAILABLE until the next image arrives
+        if unsafe { AImageReader_acquireLatestImage(self.reader, &mut image) } != 0 {
+            return None;
+        }
+
+        let frame = unsafe { convert_image(image, self.orientation) };
+        unsafe { AImage_delete(image) };
+
+        frame
+            .map_err(|e| log::error!("Failed to convert the camera image: {e}"))
+            .ok()
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.session.is_null() {
+                ACameraCaptureSession_stopRepeating(self.session);
+                ACameraCaptureSession_close(self.session);
+            }
+            if !self.request.is_null() {
+                ACaptureRequest_free(self.request);
+            }
+            if !self.target.is_null() {
+                ACameraOutputTarget_free(self.target);
+            }
+            if !self.output.is_null() {
+                ACaptureSessionOutput_free(self.output);
+            }
+            if !self.outputs.is_null() {
+                ACaptureSessionOutputContainer_free(self.outputs);
+            }
+            if !self.device.is_null() {
+                ACameraDevice_close(self.device);
+            }
+            if !self.reader.is_null() {
+                AImageReader_delete(self.reader);
+            }
+            if !self.manager.is_null() {
+                ACameraManager_delete(self.manager);
+            }
+        }
+    }
+}
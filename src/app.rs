@@ -1,13 +1,43 @@
+#[cfg(not(target_arch = "wasm32"))]
+use crate::alarm_log::{AlarmEvent, AlarmLog};
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+use crate::alarm_sound::{AlarmSound, AlarmTone};
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+use crate::camera_grid;
+use crate::homography::{self, Homography};
+use crate::hotkeys::{Action, Hotkeys};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::http::{self, HttpServer};
+use crate::i18n::{self, format_number, tr, DecimalSeparator, Language};
 use crate::image_utils;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::measurement_layouts;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::modbus::{self, ModbusServer};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::osc::{OscSender, OscSettings};
+use crate::overlay::{self, Marker, OverlaySettings, WatermarkPosition, MARKER_COLORS};
+use crate::panorama::Panorama;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::profiles;
+use crate::protocol::Command;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::report::{self, Report, ReportEntry, Survey};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::scene_presets::{self, ScenePreset};
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+use crate::sonification::{SonificationSettings, SonificationSource, Sonifier};
 use crate::thermal::{
     self, ColorMap, ColorRange, EdgeStrategy, Emissivity, FilteringMethod, Frame, ImageProducer,
-    PortOpener, Settings, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH,
+    PortError, PortInfo, Settings, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH,
 };
 
+use std::collections::VecDeque;
 use std::fmt::Display;
 
 use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
+#[cfg(not(target_arch = "wasm32"))]
 use std::thread;
 
 use eframe::egui::load::SizedTexture;
@@ -15,20 +45,681 @@ use eframe::egui::Ui;
 use eframe::egui::{self, TextureOptions};
 use eframe::emath::Numeric;
 use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+#[cfg(target_os = "android")]
+use crate::android_camera::Camera as VisibleCamera;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+use crate::webcam::Webcam as VisibleCamera;
+
+const COLORMAP_RATIO: f32 = 10.0;
+const MAX_ZOOM: f32 = 8.0;
+// Width of the picture in picture inset relative to the thermal image
+const INSET_SIZE: f32 = 0.3;
+const FULL_UV_RECT: egui::Rect =
+    egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+// Cells per side of the mesh the visible image is warped with
+const WARP_GRID: usize = 16;
+// Side of the thumbnails of the recent files
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+const RECENT_THUMBNAIL_SIZE: f32 = 32.0;
+// How often the UI refreshes on its own while no frames arrive to drive it
+const IDLE_REPAINT_INTERVAL: web_time::Duration = web_time::Duration::from_millis(500);
+// Key of the settings in the eframe storage, stored in the format of the profiles
+#[cfg(not(target_arch = "wasm32"))]
+const SETTINGS_KEY: &str = "settings";
+// Key of the keyboard shortcuts in the eframe storage
+#[cfg(not(target_arch = "wasm32"))]
+const HOTKEYS_KEY: &str = "hotkeys";
+// Key of the UI settings in the eframe storage
+#[cfg(not(target_arch = "wasm32"))]
+const UI_SETTINGS_KEY: &str = "ui_settings";
+// Key of the folder of the recordings in the eframe storage
+#[cfg(target_os = "android")]
+const RECORDINGS_FOLDER_KEY: &str = "recordings_folder";
+// A read blocks for up to the read timeout, which goes to 10 s
+#[cfg(not(target_arch = "wasm32"))]
+const STOP_TIMEOUT: web_time::Duration = web_time::Duration::from_secs(3);
+
+// The producer of the activity, which one recreated by Android stops, see [`stop_producer`]
+#[cfg(target_os = "android")]
+static ANDROID_PRODUCER: std::sync::Mutex<Option<(Sender<UiMessage>, Receiver<()>)>> =
+    std::sync::Mutex::new(None);
+
+/// Stops the producer of the activity Android has destroyed, which finishes its recording and
+/// closes the port. Returns false if it doesn't stop in time.
+#[cfg(target_os = "android")]
+pub fn stop_producer() -> bool {
+    let Some((sender, finished)) = ANDROID_PRODUCER.lock().unwrap().take() else {
+        return true;
+    };
+
+    let _ = sender.send(UiMessage::Stop);
+    // the producer thread drops its end of the channel as it finishes
+    !matches!(
+        finished.recv_timeout(STOP_TIMEOUT),
+        Err(mpsc::RecvTimeoutError::Timeout)
+    )
+}
 
 pub enum ProducerMessage {
     Frame(Frame),
     ConnectionStatusChange(ConnectionStatus),
+    /// Bytes per second read from the port
+    DataRate(f64),
+    /// Reason why the port couldn't be opened or has been closed
+    ConnectionError(PortError),
+    /// Queues the commands of changed settings for the writer thread, sent once
+    Commands(thermal::CommandQueue),
+    /// Most recent frames from the camera, for a debug bundle
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    RawFrames(Vec<thermal::RawFrame>),
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    Recording(thermal::RecordingStatus),
 }
 
 pub enum UiMessage {
     ChangeSettings(Settings),
+    /// Asks for access to the camera again after the user has denied it
+    RetryConnection,
+    /// Frames are still sent while paused, for the alarm, but they don't wake the UI up
+    Pause(bool),
+    /// Throttles the processing while nobody sees the window
+    Suspend(bool),
+    /// Caps how many frames a second wake the UI up, every frame is still read and processed
+    MaxFrameRate(u32),
+    /// Closes the port and ends the producer's loop
+    Stop,
+    /// Asks for the most recent frames from the camera
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    CollectRawFrames,
+    /// Asks for the unfiltered temperatures with the next frame, see [`Frame::unfiltered`]
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    CollectUnfiltered,
+    /// Records the frames from the camera to a session file until stopped, the file is created
+    /// by the UI and named by the path. The offset of the system clock goes to its description.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    StartRecording(
+        std::path::PathBuf,
+        std::fs::File,
+        anyhow::Result<crate::ntp::ClockOffset>,
+    ),
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    StopRecording,
+    /// Image of the visible light camera and when it was taken, recorded with the frames
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    RecordVisible(web_time::Instant, egui::ColorImage),
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WindowSettings {
+    pub always_on_top: bool,
+    pub borderless: bool,
+    /// Fullscreen touch UI for dedicated displays, the settings are hidden until requested
+    pub kiosk: bool,
+}
+
+impl WindowSettings {
+    fn apply(&self, ctx: &egui::Context) {
+        let level = if self.always_on_top {
+            egui::WindowLevel::AlwaysOnTop
+        } else {
+            egui::WindowLevel::Normal
+        };
+
+        ctx.send_viewport_cmd(egui::ViewportCommand::WindowLevel(level));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Decorations(!self.borderless));
+    }
+}
+
+/// Where the frames come from on the desktop, switched at runtime by [`App::switch_source`]
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum Source {
+    #[default]
+    Camera,
+    #[strum(to_string = "Simulated camera")]
+    Simulated,
+    #[strum(to_string = "Playback file")]
+    Playback,
+}
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum PanelPosition {
+    #[default]
+    Auto,
+    Left,
+    Right,
+    Bottom,
+}
+
+impl PanelPosition {
+    fn resolve(self, wide_screen: bool) -> Self {
+        match self {
+            PanelPosition::Auto if wide_screen => PanelPosition::Right,
+            PanelPosition::Auto => PanelPosition::Bottom,
+            position => position,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UiSettings {
+    pub panel_position: PanelPosition,
+    pub compact: bool,
+    pub ui_scale: f32,
+    pub large_font: bool,
+    pub language: Language,
+    pub decimal_separator: DecimalSeparator,
+    pub precision: usize,
+    /// Shows the values of the sensor next to the temperatures converted from them, to check the
+    /// scaling of a firmware
+    pub raw_counts: bool,
+    /// Frames shown per second at most, whatever the refresh rate of the display
+    pub max_frame_rate: u32,
+    pub theme: egui::ThemePreference,
+    pub accent_color: Option<egui::Color32>,
+    pub background_color: Option<egui::Color32>,
+    /// Keeps the display on while the camera is connected
+    #[cfg(target_os = "android")]
+    pub keep_screen_on: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            panel_position: PanelPosition::default(),
+            compact: false,
+            ui_scale: 1.0,
+            large_font: false,
+            language: Language::default(),
+            decimal_separator: DecimalSeparator::default(),
+            precision: 2,
+            raw_counts: false,
+            max_frame_rate: 30,
+            theme: egui::ThemePreference::System,
+            accent_color: None,
+            background_color: None,
+            #[cfg(target_os = "android")]
+            keep_screen_on: true,
+        }
+    }
+}
+
+impl UiSettings {
+    const LARGE_FONT_SCALE: f32 = 1.4;
+
+    /// Parses the `key = value` lines of [`UiSettings::format`] over the defaults, unknown keys
+    /// and invalid values are skipped.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn parse(contents: &str) -> Self {
+        fn variant<T: IntoEnumIterator + Display>(value: &str) -> Option<T> {
+            T::iter().find(|variant| variant.to_string() == value)
+        }
+        fn color(value: &str) -> Option<Option<egui::Color32>> {
+            match value {
+                "none" => Some(None),
+                hex => egui::Color32::from_hex(hex).ok().map(Some),
+            }
+        }
+
+        let mut settings = Self::default();
+
+        for (key, value) in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+        {
+            let value = value.trim();
+            let bool = value.parse::<bool>().ok();
+            match key.trim() {
+                "panel_position" => settings.panel_position = variant(value).unwrap_or_default(),
+                "compact" => settings.compact = bool.unwrap_or(settings.compact),
+                "ui_scale" => {
+                    if let Ok(scale) = value.parse::<f32>() {
+                        settings.ui_scale = scale.clamp(0.5, 3.0);
+                    }
+                }
+                "large_font" => settings.large_font = bool.unwrap_or(settings.large_font),
+                "language" => settings.language = variant(value).unwrap_or_default(),
+                "decimal_separator" => {
+                    settings.decimal_separator = variant(value).unwrap_or_default();
+                }
+                "precision" => {
+                    if let Ok(precision) = value.parse::<usize>() {
+                        settings.precision = precision.min(3);
+                    }
+                }
+                "raw_counts" => settings.raw_counts = bool.unwrap_or(settings.raw_counts),
+                "max_frame_rate" => {
+                    if let Ok(rate) = value.parse::<u32>() {
+                        settings.max_frame_rate = rate.clamp(5, 120);
+                    }
+                }
+                "theme" => {
+                    settings.theme = match value {
+                        "Dark" => egui::ThemePreference::Dark,
+                        "Light" => egui::ThemePreference::Light,
+                        _ => egui::ThemePreference::System,
+                    };
+                }
+                "accent_color" => {
+                    settings.accent_color = color(value).unwrap_or(settings.accent_color);
+                }
+                "background_color" => {
+                    settings.background_color = color(value).unwrap_or(settings.background_color);
+                }
+                #[cfg(target_os = "android")]
+                "keep_screen_on" => {
+                    settings.keep_screen_on = bool.unwrap_or(settings.keep_screen_on);
+                }
+                _ => {}
+            }
+        }
+
+        settings
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn format(&self) -> anyhow::Result<String> {
+        use std::fmt::Write;
+
+        let color = |color: Option<egui::Color32>| {
+            color.map_or_else(|| "none".to_owned(), |color| color.to_hex())
+        };
+
+        let mut contents = String::from("# Tiop01 UI settings\n");
+        writeln!(contents, "panel_position = {}", self.panel_position)?;
+        writeln!(contents, "compact = {}", self.compact)?;
+        writeln!(contents, "ui_scale = {}", self.ui_scale)?;
+        writeln!(contents, "large_font = {}", self.large_font)?;
+        writeln!(contents, "language = {}", self.language)?;
+        writeln!(contents, "decimal_separator = {}", self.decimal_separator)?;
+        writeln!(contents, "precision = {}", self.precision)?;
+        writeln!(contents, "raw_counts = {}", self.raw_counts)?;
+        writeln!(contents, "max_frame_rate = {}", self.max_frame_rate)?;
+        writeln!(contents, "theme = {:?}", self.theme)?;
+        writeln!(contents, "accent_color = {}", color(self.accent_color))?;
+        writeln!(
+            contents,
+            "background_color = {}",
+            color(self.background_color)
+        )?;
+        #[cfg(target_os = "android")]
+        writeln!(contents, "keep_screen_on = {}", self.keep_screen_on)?;
+
+        Ok(contents)
+    }
+
+    fn apply(&self, ctx: &egui::Context) {
+        ctx.set_zoom_factor(self.ui_scale);
+        i18n::set_language(ctx, self.language);
+        i18n::set_decimal_separator(self.decimal_separator);
+
+        let font_scale = if self.large_font {
+            Self::LARGE_FONT_SCALE
+        } else {
+            1.0
+        };
+        let text_styles = egui::Style::default().text_styles;
+
+        ctx.set_theme(self.theme);
+        ctx.all_styles_mut(|style| {
+            style.text_styles = text_styles
+                .iter()
+                .map(|(text_style, font_id)| {
+                    let font_id =
+                        egui::FontId::new(font_id.size * font_scale, font_id.family.clone());
+                    (text_style.clone(), font_id)
+                })
+                .collect();
+
+            let defaults = if style.visuals.dark_mode {
+                egui::Visuals::dark()
+            } else {
+                egui::Visuals::light()
+            };
+            let visuals = &mut style.visuals;
+
+            visuals.selection.bg_fill = self.accent_color.unwrap_or(defaults.selection.bg_fill);
+            visuals.hyperlink_color = self.accent_color.unwrap_or(defaults.hyperlink_color);
+            visuals.panel_fill = self.background_color.unwrap_or(defaults.panel_fill);
+            visuals.window_fill = self.background_color.unwrap_or(defaults.window_fill);
+        });
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmSettings {
+    pub enabled: bool,
+    /// Maximum temperature (°C) which trips the alarm
+    pub threshold: f64,
+    #[cfg(target_os = "android")]
+    pub vibrate: bool,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    pub sound: bool,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    pub tone: AlarmTone,
+    /// 0 to 1, of the alarm volume on Android
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    pub volume: f32,
+    /// Whether the tone is played again every `repeat_interval` seconds until the alarm is
+    /// acknowledged or clears
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    pub repeat: bool,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    pub repeat_interval: f64,
+}
+
+impl Default for AlarmSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold: 60.0,
+            #[cfg(target_os = "android")]
+            vibrate: true,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            sound: false,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            tone: AlarmTone::default(),
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            volume: 0.8,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            repeat: true,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            repeat_interval: 10.0,
+        }
+    }
+}
+
+impl AlarmSettings {
+    /// The alarm is cleared only once the temperature drops this much below the threshold, so
+    /// noise around the threshold doesn't trip it over and over.
+    const HYSTERESIS: f64 = 1.0;
+}
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum FusionMode {
+    /// The thermal image is blended over the aligned visible one
+    #[default]
+    Blend,
+    /// The visible image is shown in a corner of the thermal one
+    #[strum(to_string = "Picture in picture")]
+    PictureInPicture,
+}
+
+/// Visible-light image under the thermal one, similar to FLIR's MSX.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FusionSettings {
+    pub enabled: bool,
+    pub mode: FusionMode,
+    /// Index of the webcam mounted next to the thermal camera
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    pub webcam: u32,
+    /// Opacity of the thermal image over the visible one
+    pub opacity: f32,
+    /// Shift of the visible image, in thermal image widths and heights
+    pub offset: egui::Vec2,
+    /// Height of the visible image, in thermal image heights
+    pub scale: f32,
+    /// Calibrated mapping of the visible image onto the thermal one, replaces offset and scale
+    pub homography: Option<Homography>,
+}
+
+impl Default for FusionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: FusionMode::default(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            webcam: 0,
+            opacity: 0.5,
+            offset: egui::Vec2::ZERO,
+            scale: 1.0,
+            homography: None,
+        }
+    }
+}
+
+impl FusionSettings {
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn reset_alignment(&mut self) {
+        let defaults = Self::default();
+        self.offset = defaults.offset;
+        self.scale = defaults.scale;
+        self.homography = defaults.homography;
+    }
+
+    /// Maps texture coordinates of the visible image to those of the thermal image.
+    fn transform(&self, visible_size: [usize; 2]) -> Homography {
+        if let Some(homography) = self.homography {
+            return homography;
+        }
+
+        let thermal_aspect_ratio = THERMAL_IMAGE_WIDTH as f32 / THERMAL_IMAGE_HEIGHT as f32;
+        let aspect_ratio = visible_size[0] as f32 / visible_size[1] as f32 / thermal_aspect_ratio;
+
+        Homography::from_rect(egui::Rect::from_center_size(
+            egui::pos2(0.5, 0.5) + self.offset,
+            egui::vec2(aspect_ratio, 1.0) * self.scale,
+        ))
+    }
+}
+
+/// Point pairs clicked in the visible and the thermal image to align the overlay.
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+#[derive(Debug, Default)]
+struct Calibration {
+    /// Point of the visible image waiting for its thermal counterpart
+    pending: Option<egui::Pos2>,
+    /// Texture coordinates in the visible and the thermal image
+    pairs: Vec<(egui::Pos2, egui::Pos2)>,
+}
+
+/// Corners of the perspective correction, edited on the uncorrected image.
+#[derive(Debug, Clone, PartialEq)]
+struct KeystoneEditor {
+    corners: [egui::Pos2; 4],
+    /// Correction restored when editing is cancelled
+    previous: Option<[egui::Pos2; 4]>,
+}
+
+impl KeystoneEditor {
+    const HANDLE_SIZE: f32 = 24.0;
+
+    /// Paints the corners over the image covering `rect` and lets the user drag them.
+    fn handles(&mut self, ui: &Ui, rect: egui::Rect, view: &ImageView) {
+        let color = ui.visuals().selection.bg_fill;
+        let painter = ui.painter_at(rect);
+
+        let positions = self.corners.map(|corner| view.pos_at(rect, corner));
+        painter.add(egui::Shape::closed_line(
+            positions.to_vec(),
+            egui::Stroke::new(2.0, color),
+        ));
+
+        for (index, (corner, pos)) in self.corners.iter_mut().zip(positions).enumerate() {
+            let response = ui.interact(
+                egui::Rect::from_center_size(pos, egui::Vec2::splat(Self::HANDLE_SIZE)),
+                ui.id().with(("keystone_corner", index)),
+                egui::Sense::drag(),
+            );
+
+            if response.dragged() {
+                let delta = response.drag_delta() / rect.size() / view.zoom;
+                *corner = (*corner + delta).clamp(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            }
+
+            painter.circle_filled(pos, Self::HANDLE_SIZE / 3.0, color);
+        }
+    }
+}
+
+/// Zoom, pan and spot markers of the thermal image. Works with touch (pinch, drag, tap and long
+/// press) as well as with the mouse (ctrl + scroll, drag, click and right click).
+#[derive(Debug, Clone, PartialEq)]
+struct ImageView {
+    zoom: f32,
+    /// Center of the visible part in texture coordinates
+    center: egui::Pos2,
+    markers: Vec<Marker>,
+    /// Pixel the context menu has been opened for
+    menu_pixel: Option<[usize; 2]>,
+}
+
+impl Default for ImageView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            center: egui::pos2(0.5, 0.5),
+            markers: Vec::new(),
+            menu_pixel: None,
+        }
+    }
+}
+
+impl ImageView {
+    fn uv_rect(&self) -> egui::Rect {
+        egui::Rect::from_center_size(self.center, egui::Vec2::splat(1.0 / self.zoom))
+    }
+
+    fn is_zoomed(&self) -> bool {
+        self.zoom > 1.0
+    }
+
+    fn reset_zoom(&mut self) {
+        self.zoom = 1.0;
+        self.center = egui::pos2(0.5, 0.5);
+    }
+
+    /// Maps a screen position within `rect` to texture coordinates.
+    fn uv_at(&self, rect: egui::Rect, pos: egui::Pos2) -> egui::Pos2 {
+        let uv_rect = self.uv_rect();
+        uv_rect.min + (pos - rect.min) / rect.size() * uv_rect.size()
+    }
+
+    fn pixel_at(&self, rect: egui::Rect, pos: egui::Pos2) -> Option<[usize; 2]> {
+        if !rect.contains(pos) {
+            return None;
+        }
+
+        let uv = self.uv_at(rect, pos);
+        let pixel = |coordinate: f32, size: usize| {
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let pixel = (coordinate * size as f32) as usize;
+            pixel.min(size - 1)
+        };
+
+        Some([
+            pixel(uv.x, THERMAL_IMAGE_WIDTH),
+            pixel(uv.y, THERMAL_IMAGE_HEIGHT),
+        ])
+    }
+
+    /// Maps texture coordinates to a screen position, which may lie outside of `rect`.
+    fn pos_at(&self, rect: egui::Rect, uv: egui::Pos2) -> egui::Pos2 {
+        let uv_rect = self.uv_rect();
+        rect.min + (uv - uv_rect.min) / uv_rect.size() * rect.size()
+    }
+
+    /// Screen position of the center of `pixel`, `None` when it's zoomed out of view.
+    fn pixel_pos(&self, rect: egui::Rect, pixel: [usize; 2]) -> Option<egui::Pos2> {
+        let uv = egui::pos2(
+            (pixel[0] as f32 + 0.5) / THERMAL_IMAGE_WIDTH as f32,
+            (pixel[1] as f32 + 0.5) / THERMAL_IMAGE_HEIGHT as f32,
+        );
+
+        self.uv_rect().contains(uv).then(|| self.pos_at(rect, uv))
+    }
+
+    fn clamp_center(&mut self) {
+        let half = 0.5 / self.zoom;
+        self.center = egui::pos2(
+            self.center.x.clamp(half, 1.0 - half),
+            self.center.y.clamp(half, 1.0 - half),
+        );
+    }
+
+    fn add_marker(&mut self, pixel: [usize; 2]) {
+        self.markers.push(Marker {
+            pixel,
+            name: String::new(),
+            color: MARKER_COLORS[self.markers.len() % MARKER_COLORS.len()],
+        });
+    }
+
+    fn toggle_marker(&mut self, pixel: [usize; 2]) {
+        match self.markers.iter().position(|marker| marker.pixel == pixel) {
+            Some(index) => {
+                self.markers.remove(index);
+            }
+            None => self.add_marker(pixel),
+        }
+    }
+
+    /// Labels of the markers with the temperatures (°C) of their pixels in `values`.
+    fn marker_values(&self, values: &thermal::GrayImage) -> Vec<(String, [usize; 2], f64)> {
+        self.markers
+            .iter()
+            .enumerate()
+            .map(|(index, marker)| {
+                let value = f64::from(values.get(marker.pixel).as_slice()[0]) / 10.0;
+                (marker.label(index), marker.pixel, value)
+            })
+            .collect()
+    }
+
+    fn handle_input(&mut self, ui: &Ui, rect: egui::Rect, response: &egui::Response) {
+        let (zoom_delta, zoom_center) = ui.input(|i| {
+            let center = i
+                .multi_touch()
+                .map(|touch| touch.center_pos)
+                .or(i.pointer.hover_pos());
+
+            (i.zoom_delta(), center)
+        });
+
+        if let Some(zoom_center) = zoom_center.filter(|pos| rect.contains(*pos)) {
+            if zoom_delta != 1.0 {
+                // keep the point under the fingers in place
+                let uv = self.uv_at(rect, zoom_center);
+                let offset = (zoom_center - rect.min) / rect.size() - egui::vec2(0.5, 0.5);
+
+                self.zoom = (self.zoom * zoom_delta).clamp(1.0, MAX_ZOOM);
+                self.center = uv - offset / self.zoom;
+            }
+        }
+
+        if response.dragged() && self.is_zoomed() {
+            self.center -= response.drag_delta() / rect.size() / self.zoom;
+        }
+
+        self.clamp_center();
+
+        if response.clicked() {
+            if let Some(pixel) = response
+                .interact_pointer_pos()
+                .and_then(|pos| self.pixel_at(rect, pos))
+            {
+                self.toggle_marker(pixel);
+            }
+        }
+
+        if response.secondary_clicked() {
+            self.menu_pixel = response
+                .interact_pointer_pos()
+                .and_then(|pos| self.pixel_at(rect, pos));
+        }
+    }
 }
 
 #[derive(PartialEq)]
 pub enum ConnectionStatus {
     Disconnected,
-    Connected,
+    WaitingForPermission,
+    PermissionDenied,
+    Connected(PortInfo),
 }
 
 trait ComboBoxFromIter {
@@ -44,12 +735,12 @@ impl ComboBoxFromIter for Ui {
         V: Display + PartialEq,
         I: Iterator<Item = V>,
     {
-        egui::ComboBox::from_label(label)
-            .selected_text(current_value.to_string())
+        egui::ComboBox::from_label(tr(label))
+            .selected_text(tr(&current_value.to_string()))
             .show_ui(self, |ui| {
                 for selected_value in iter {
                     let text = selected_value.to_string();
-                    ui.selectable_value(current_value, selected_value, text);
+                    ui.selectable_value(current_value, selected_value, tr(&text));
                 }
             });
     }
@@ -60,23 +751,306 @@ pub struct App {
     colormap_texture: egui::TextureHandle,
     receiver: Receiver<ProducerMessage>,
     sender: Sender<UiMessage>,
+    /// Commands queued for the producer's port
+    commands: thermal::CommandQueue,
+    /// Buffers of the frames handed back to the producer
+    frame_pool: image_utils::FramePool,
     settings: Settings,
     min: f64,
     max: f64,
     fps: f64,
-    last_frame_update: std::time::Instant,
+    data_rate: f64,
+    last_frame_update: web_time::Instant,
+    /// Recent positions of the hottest pixel, oldest first
+    hot_spot_trail: VecDeque<(web_time::Instant, [usize; 2])>,
     connection_status: ConnectionStatus,
+    connection_error: Option<PortError>,
+    image_detached: bool,
+    image_view: ImageView,
+    keystone_editor: Option<KeystoneEditor>,
+    alarm_settings: AlarmSettings,
+    overlays: OverlaySettings,
+    /// Watermark logo and its texture
+    #[cfg(not(target_arch = "wasm32"))]
+    logo: Option<(egui::ColorImage, egui::TextureHandle)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    logo_error: Option<String>,
+    /// Snapshots collected for the inspection report
+    #[cfg(not(target_arch = "wasm32"))]
+    report: Report,
+    /// Note attached to the next snapshot taken into the report
+    #[cfg(not(target_arch = "wasm32"))]
+    report_note: String,
+    /// File the report is written to, Android saves it to the shared documents instead
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    report_path: String,
+    /// Outcome of the last report generation
+    #[cfg(not(target_arch = "wasm32"))]
+    report_message: Option<String>,
+    /// Inspection points of the next survey, one per line
+    #[cfg(not(target_arch = "wasm32"))]
+    survey_points: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    survey: Option<Survey>,
+    /// Number of hot components to find and the seconds to let the board warm up before
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_component_count: usize,
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_component_warm_up: f64,
+    /// When the pending hot component scan captures the image
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_component_scan_at: Option<web_time::Instant>,
+    /// Labels, pixels and temperatures (°C) of the components found by the last scan
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_components: Vec<(String, [usize; 2], f64)>,
+    /// Name of the measurement layout to apply, save or delete
+    #[cfg(not(target_arch = "wasm32"))]
+    layout_name: String,
+    /// Saved measurement layouts, listed when first shown
+    #[cfg(not(target_arch = "wasm32"))]
+    layouts: Option<Vec<String>>,
+    /// Outcome of the last measurement layout operation
+    #[cfg(not(target_arch = "wasm32"))]
+    layout_message: Option<String>,
+    /// Scene presets, loaded when first shown
+    #[cfg(not(target_arch = "wasm32"))]
+    presets: Option<Vec<ScenePreset>>,
+    /// Name of the scene preset to save or delete
+    #[cfg(not(target_arch = "wasm32"))]
+    preset_name: String,
+    /// Outcome of the last scene preset operation
+    #[cfg(not(target_arch = "wasm32"))]
+    preset_message: Option<String>,
+    /// Serial number of the camera whose profile has been loaded last
+    #[cfg(not(target_arch = "wasm32"))]
+    profile_serial_number: Option<String>,
+    /// Outcome of the last profile load or save
+    #[cfg(not(target_arch = "wasm32"))]
+    profile_message: Option<String>,
+    alarm_active: bool,
+    /// Silences the active alarm until it clears
+    alarm_acknowledged: bool,
+    /// When the alarm tone has been played last, `None` when not since the alarm tripped
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    alarm_sounded_at: Option<web_time::Instant>,
+    /// Audio output of the alarm, `None` while its sound is disabled
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    alarm_sound: Option<AlarmSound>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    alarm_sound_error: Option<String>,
+    /// Every alarm trigger with a snapshot
+    #[cfg(not(target_arch = "wasm32"))]
+    alarm_log: AlarmLog,
+    /// File the alarm log is exported to, Android saves it to the shared documents instead
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    alarm_log_path: String,
+    /// Outcome of the last export
+    #[cfg(not(target_arch = "wasm32"))]
+    alarm_log_message: Option<String>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    sonification: SonificationSettings,
+    /// Audio output of the sonification, `None` while it's disabled
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    sonifier: Option<Sonifier>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    sonifier_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    osc: OscSettings,
+    /// Receiver address being edited, applied once the text field loses focus
+    #[cfg(not(target_arch = "wasm32"))]
+    osc_address: String,
+    /// `None` while the OSC output is disabled or has failed
+    #[cfg(not(target_arch = "wasm32"))]
+    osc_sender: Option<OscSender>,
+    #[cfg(not(target_arch = "wasm32"))]
+    osc_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    modbus_port: u16,
+    /// `None` while the Modbus server is stopped
+    #[cfg(not(target_arch = "wasm32"))]
+    modbus: Option<ModbusServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    modbus_error: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    http_port: u16,
+    /// `None` while the HTTP server is stopped
+    #[cfg(not(target_arch = "wasm32"))]
+    http: Option<HttpServer>,
+    #[cfg(not(target_arch = "wasm32"))]
+    http_error: Option<String>,
+    fusion: FusionSettings,
+    /// Visible-light image shown under the thermal one
+    visible_texture: Option<egui::TextureHandle>,
+    /// Temperatures of the shown image, for the markers
+    values: Option<thermal::GrayImage>,
+    /// Coldest and hottest pixel of `values`
+    extremes: [[usize; 2]; 2],
+    /// Positions of the shown image in the color map, recolored right away when it changes
+    positions: Option<thermal::GrayImage>,
+    /// Mosaic being stitched, `None` when its window is closed
+    panorama: Option<Panorama>,
+    panorama_texture: Option<egui::TextureHandle>,
+    window_settings: WindowSettings,
+    settings_visible: bool,
+    hotkeys: Hotkeys,
+    paused: bool,
+    /// Set while the window is hidden, the producer then only processes a frame now and then
+    suspended: bool,
+    ui_settings: UiSettings,
+    applied_ui_settings: UiSettings,
+    ui_scale_dragged: bool,
+    /// Network address being edited, applied once the text field loses focus
+    #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+    network_address: String,
+    /// Read timeout being edited, applied once the slider is released
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    serial_timeout: f32,
+    /// Ports listed in the port picker, `None` when the picker is closed
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    available_ports: Option<Vec<crate::desktop::PortDescription>>,
+    /// The browser has no threads to spare, so the producer runs as part of the UI updates
+    #[cfg(target_arch = "wasm32")]
+    producer: ImageProducer<'static, crate::web::SerialPortOpener>,
+    /// Joined on exit, `None` once it is
+    #[cfg(not(target_arch = "wasm32"))]
+    producer_thread: Option<thread::JoinHandle<()>>,
+    /// Producers of the previous sources which haven't stopped in time, still closing their ports
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    stopped_producers: Vec<thread::JoinHandle<()>>,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    source: Source,
+    /// Picked in the UI, a playback file only becomes the source once it's loaded
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    selected_source: Source,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    playback_path: String,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    playback: Option<std::sync::Arc<crate::debug_bundle::Replay>>,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    playback_error: Option<String>,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    recent: crate::recent::Recent,
+    /// Thumbnails of the recent files, `None` for the ones which can't be read
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    recent_thumbnails: std::collections::HashMap<std::path::PathBuf, Option<egui::TextureHandle>>,
+    /// Source the settings are remembered for, see [`App::source_profile_name`], empty until the
+    /// first update
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    source_profile: String,
+    #[cfg(target_os = "android")]
+    screen_kept_on: bool,
+    #[cfg(target_os = "android")]
+    power_checked_at: web_time::Instant,
+    /// Image currently shown, kept for snapshots and reports
+    #[cfg(not(target_arch = "wasm32"))]
+    shown_image: Option<thermal::RgbImage>,
+    /// Outcome of the last snapshot
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    snapshot_message: Option<String>,
+    /// Where "Capture" saves the snapshots
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    capture_directory: String,
+    /// Saves the temperatures as a 16 bit PNG next to every capture
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    capture_raw: bool,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    csv_path: String,
+    /// Outcome of the last CSV export, or that it waits for a frame
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    csv_message: Option<String>,
+    /// File the producer records to
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    recording: Option<std::path::PathBuf>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    recording_message: Option<String>,
+    /// Folder of the shared storage the recordings go to
+    #[cfg(target_os = "android")]
+    recordings_folder: String,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    video_settings: crate::video::VideoSettings,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    video: Option<crate::video::VideoRecording>,
+    /// Outcome of the last video
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    video_message: Option<String>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    visible_camera: Option<VisibleCamera>,
+    /// When the camera was last tried to be opened, `None` until the first attempt
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    visible_camera_opened_at: Option<web_time::Instant>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    visible_camera_error: Option<String>,
+    /// When the last image of the camera went to the recording
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    visible_recorded_at: Option<web_time::Instant>,
+    /// Index of the recorded visible image shown during a playback
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    shown_visible_frame: Option<usize>,
+    /// Overlay alignment in progress
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    calibration: Option<Calibration>,
+    /// Webcams listed in the overlay settings, `None` until they're shown
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    webcams: Option<Vec<crate::webcam::WebcamDescription>>,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    debug_bundle_path: String,
+    /// Outcome of the last debug bundle, or that it's being collected
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    debug_bundle_message: Option<String>,
+    /// Offset of the system clock (s) measured while the frames of a debug bundle are collected
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    ntp_offset: Option<Receiver<anyhow::Result<f64>>>,
+    /// Report of a crash of the previous run, until the user has seen it
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    crash_report: Option<std::path::PathBuf>,
+    /// Shown in a grid with the main camera while there are any
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    cameras: Vec<camera_grid::Camera>,
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    next_camera_id: usize,
+    /// Prints the statistics of every frame to stdout, see [`App::print_json_stats`]
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    json_output: bool,
+}
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+pub fn producer_main(
+    egui_ctx: egui::Context,
+    worker_sender: Sender<ProducerMessage>,
+    worker_receiver: Receiver<UiMessage>,
+    frame_pool: image_utils::FramePool,
+    source: Source,
+    playback: Option<std::sync::Arc<crate::debug_bundle::Replay>>,
+) {
+    match (source, playback) {
+        (Source::Playback, Some(replay)) => {
+            let opener = crate::debug_bundle::ReplayOpener::new(replay);
+
+            producer_main_loop(egui_ctx, worker_sender, worker_receiver, frame_pool, opener);
+        }
+        (Source::Simulated, _) => {
+            let opener = crate::simulator::SimulatedOpener;
+
+            producer_main_loop(egui_ctx, worker_sender, worker_receiver, frame_pool, opener);
+        }
+        _ => {
+            let opener = crate::desktop::SerialPortOpener::new();
+
+            producer_main_loop(egui_ctx, worker_sender, worker_receiver, frame_pool, opener);
+        }
+    }
 }
 
-#[cfg(not(target_os = "android"))]
+#[cfg(target_os = "ios")]
 fn producer_main(
     egui_ctx: egui::Context,
     worker_sender: Sender<ProducerMessage>,
     worker_receiver: Receiver<UiMessage>,
+    frame_pool: image_utils::FramePool,
 ) {
-    let opener = crate::desktop::SerialPortOpener::new();
+    let opener = crate::network::NetworkOpener::default();
 
-    producer_main_loop(egui_ctx, worker_sender, worker_receiver, opener);
+    producer_main_loop(egui_ctx, worker_sender, worker_receiver, frame_pool, opener);
 }
 
 #[cfg(target_os = "android")]
@@ -84,6 +1058,7 @@ fn producer_main(
     egui_ctx: egui::Context,
     worker_sender: Sender<ProducerMessage>,
     worker_receiver: Receiver<UiMessage>,
+    frame_pool: image_utils::FramePool,
 ) {
     use crate::android::{Context, SerialPortOpener};
     use crate::ANDROID_APP;
@@ -101,61 +1076,418 @@ fn producer_main(
     let actx = Context::new(env, context);
     let opener = SerialPortOpener::new(Rc::new(RefCell::new(actx)));
 
-    producer_main_loop(egui_ctx, worker_sender, worker_receiver, opener);
+    producer_main_loop(egui_ctx, worker_sender, worker_receiver, frame_pool, opener);
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 fn producer_main_loop<'a, T>(
     egui_ctx: egui::Context,
     worker_sender: Sender<ProducerMessage>,
     worker_receiver: Receiver<UiMessage>,
+    frame_pool: image_utils::FramePool,
     opener: T,
 ) where
-    T: PortOpener<'a>,
+    T: thermal::PortOpener<'a>,
 {
-    let mut producer = ImageProducer::new(egui_ctx, worker_sender, worker_receiver, opener);
+    let mut producer = ImageProducer::new(egui_ctx, worker_sender, worker_receiver, opener)
+        .with_frame_pool(frame_pool);
     producer.main_loop();
 }
 
+/// Waits until `deadline` for a stopped producer to finish, handing it back if it hasn't.
+#[cfg(not(target_arch = "wasm32"))]
+fn join_producer(
+    producer_thread: thread::JoinHandle<()>,
+    deadline: web_time::Instant,
+) -> Result<(), thread::JoinHandle<()>> {
+    while !producer_thread.is_finished() {
+        if web_time::Instant::now() >= deadline {
+            return Err(producer_thread);
+        }
+        thread::sleep(web_time::Duration::from_millis(10));
+    }
+
+    if producer_thread.join().is_err() {
+        log::error!("The producer has panicked");
+    }
+
+    Ok(())
+}
+
 impl App {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(cc: &eframe::CreationContext<'_>, window_settings: WindowSettings) -> Self {
         let egui_ctx = cc.egui_ctx.clone();
 
         let (ui_sender, worker_receiver): (Sender<UiMessage>, Receiver<UiMessage>) =
             mpsc::channel();
         let (worker_sender, ui_receiver): (Sender<ProducerMessage>, Receiver<ProducerMessage>) =
             mpsc::channel();
+        let frame_pool = image_utils::FramePool::default();
 
-        thread::Builder::new()
-            .name("thermal".into())
-            .spawn(move || {
-                producer_main(egui_ctx, worker_sender, worker_receiver);
-            })
-            .unwrap();
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let recent = crate::recent::Recent::load().unwrap_or_else(|e| {
+            log::error!("Failed to load the recent files: {e}");
+            crate::recent::Recent::default()
+        });
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let (source, playback) = match crate::REPLAY_BUNDLE.get() {
+            Some(replay) => (Source::Playback, Some(std::sync::Arc::clone(replay))),
+            None if recent.resume => Self::resumed_source(&recent),
+            None => (Source::Camera, None),
+        };
+        #[allow(unused_mut)]
+        let mut image_view = ImageView::default();
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if recent.resume && !recent.layout.is_empty() {
+            match measurement_layouts::load(&recent.layout) {
+                Ok(markers) => image_view.markers = markers,
+                Err(e) => log::error!("Failed to resume the layout {}: {e}", recent.layout),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let producer_thread = {
+            let frame_pool = frame_pool.clone();
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            let producer_main = {
+                let playback = playback.clone();
+                move |egui_ctx, worker_sender, worker_receiver, frame_pool| {
+                    producer_main(
+                        egui_ctx,
+                        worker_sender,
+                        worker_receiver,
+                        frame_pool,
+                        source,
+                        playback,
+                    );
+                }
+            };
+
+            #[cfg(target_os = "android")]
+            let finished_sender = {
+                let (finished_sender, finished) = mpsc::channel();
+                *ANDROID_PRODUCER.lock().unwrap() = Some((ui_sender.clone(), finished));
+                finished_sender
+            };
 
+            thread::Builder::new()
+                .name("thermal".into())
+                .spawn(move || {
+                    #[cfg(target_os = "android")]
+                    let _finished_sender = finished_sender;
+                    producer_main(egui_ctx, worker_sender, worker_receiver, frame_pool);
+                })
+                .unwrap()
+        };
+        #[cfg(target_arch = "wasm32")]
+        let producer = ImageProducer::new(
+            egui_ctx.clone(),
+            worker_sender,
+            worker_receiver,
+            crate::web::SerialPortOpener::new(egui_ctx),
+        )
+        .with_frame_pool(frame_pool.clone());
+
+        // the settings of the last run
+        #[cfg(not(target_arch = "wasm32"))]
+        let settings = {
+            let mut settings = Settings::default();
+            if let Some(contents) = cc
+                .storage
+                .and_then(|storage| storage.get_string(SETTINGS_KEY))
+            {
+                if let Err(e) = profiles::apply(&contents, SETTINGS_KEY, &mut settings) {
+                    log::error!("Failed to restore the settings: {e}");
+                }
+            }
+            settings
+        };
+        #[cfg(target_arch = "wasm32")]
         let settings = Settings::default();
+        // the replayed session is processed the way it was recorded
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let settings = {
+            let mut settings = settings;
+            if let Some(ref replay) = playback {
+                if let Err(e) = replay.apply_settings(&mut settings) {
+                    log::error!("Failed to apply the settings of {}: {e}", replay.name);
+                }
+            }
+            settings
+        };
+        let _ = ui_sender.send(UiMessage::ChangeSettings(settings.clone()));
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        crate::crash_report::set_settings(&settings);
+        #[cfg(not(target_arch = "wasm32"))]
+        let hotkeys = cc
+            .storage
+            .and_then(|storage| storage.get_string(HOTKEYS_KEY))
+            .map(|contents| Hotkeys::parse(&contents))
+            .unwrap_or_default();
+        #[cfg(target_arch = "wasm32")]
+        let hotkeys = Hotkeys::default();
+        #[cfg(not(target_arch = "wasm32"))]
+        let ui_settings = cc
+            .storage
+            .and_then(|storage| storage.get_string(UI_SETTINGS_KEY))
+            .map(|contents| UiSettings::parse(&contents))
+            .unwrap_or_default();
+        #[cfg(target_arch = "wasm32")]
+        let ui_settings = UiSettings::default();
+        ui_settings.apply(&cc.egui_ctx);
+        // in the browser the camera is read on repaints, capping them would leave frames unread
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = ui_sender.send(UiMessage::MaxFrameRate(ui_settings.max_frame_rate));
+
         let thermal_image_texture = Self::load_texture_from_black_thermal_image(&cc.egui_ctx);
         let colormap_texture = Self::load_texture_from_colormap_image(
             &cc.egui_ctx,
             &*settings.colormap.get_colormap(),
             settings.color_range,
         );
+        #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+        let network_address = settings.network_address.clone();
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let serial_timeout = settings.serial_timeout;
 
         Self {
             thermal_image_texture,
             colormap_texture,
             receiver: ui_receiver,
             sender: ui_sender,
+            commands: thermal::CommandQueue::default(),
+            frame_pool,
             settings,
             min: 0.0,
             max: 0.0,
             fps: 0.0,
-            last_frame_update: std::time::Instant::now(),
+            data_rate: 0.0,
+            last_frame_update: web_time::Instant::now(),
+            hot_spot_trail: VecDeque::new(),
             connection_status: ConnectionStatus::Disconnected,
+            connection_error: None,
+            image_detached: false,
+            image_view,
+            keystone_editor: None,
+            alarm_settings: AlarmSettings::default(),
+            overlays: OverlaySettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            logo: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            logo_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            report: Report::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            report_note: String::new(),
+            #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+            report_path: "report.html".to_owned(),
+            #[cfg(not(target_arch = "wasm32"))]
+            report_message: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            survey_points: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            survey: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_component_count: 5,
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_component_warm_up: 60.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_component_scan_at: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_components: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            layout_name: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            layouts: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            layout_message: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            presets: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            preset_name: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            preset_message: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            profile_serial_number: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            profile_message: None,
+            alarm_active: false,
+            alarm_acknowledged: false,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            alarm_sounded_at: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            alarm_sound: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            alarm_sound_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            alarm_log: AlarmLog::default(),
+            #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+            alarm_log_path: "alarms.zip".to_owned(),
+            #[cfg(not(target_arch = "wasm32"))]
+            alarm_log_message: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            sonification: SonificationSettings::default(),
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            sonifier: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            sonifier_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            osc: OscSettings::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            osc_address: OscSettings::default().address,
+            #[cfg(not(target_arch = "wasm32"))]
+            osc_sender: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            osc_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            modbus_port: modbus::DEFAULT_PORT,
+            #[cfg(not(target_arch = "wasm32"))]
+            modbus: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            modbus_error: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            http_port: http::DEFAULT_PORT,
+            #[cfg(not(target_arch = "wasm32"))]
+            http: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            http_error: None,
+            fusion: FusionSettings::default(),
+            visible_texture: None,
+            values: None,
+            extremes: [[0, 0]; 2],
+            positions: None,
+            panorama: None,
+            panorama_texture: None,
+            settings_visible: !window_settings.kiosk,
+            window_settings,
+            hotkeys,
+            paused: false,
+            suspended: false,
+            applied_ui_settings: ui_settings.clone(),
+            ui_settings,
+            ui_scale_dragged: false,
+            #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+            network_address,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            serial_timeout,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            available_ports: None,
+            #[cfg(target_arch = "wasm32")]
+            producer,
+            #[cfg(not(target_arch = "wasm32"))]
+            producer_thread: Some(producer_thread),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            stopped_producers: Vec::new(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            source,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            selected_source: source,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            playback_path: String::new(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            playback,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            playback_error: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            recent,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            recent_thumbnails: std::collections::HashMap::new(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            source_profile: String::new(),
+            #[cfg(target_os = "android")]
+            screen_kept_on: false,
+            #[cfg(target_os = "android")]
+            power_checked_at: web_time::Instant::now(),
+            #[cfg(not(target_arch = "wasm32"))]
+            shown_image: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            snapshot_message: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            capture_directory: "captures".to_owned(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            capture_raw: false,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            csv_path: "temperatures.csv".to_owned(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            csv_message: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            recording: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            recording_message: None,
+            #[cfg(target_os = "android")]
+            recordings_folder: cc
+                .storage
+                .and_then(|storage| storage.get_string(RECORDINGS_FOLDER_KEY))
+                .unwrap_or_else(|| crate::android::RECORDING_DIRECTORY.to_owned()),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            video_settings: crate::video::VideoSettings::default(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            video: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            video_message: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            visible_camera: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            visible_camera_opened_at: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            visible_camera_error: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            visible_recorded_at: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            shown_visible_frame: None,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            calibration: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            webcams: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            debug_bundle_path: "debug-bundle.zip".to_owned(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            debug_bundle_message: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            ntp_offset: None,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            crash_report: crate::crash_report::take_pending(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            cameras: Vec::new(),
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            next_camera_id: 1,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            json_output: crate::JSON_OUTPUT.load(std::sync::atomic::Ordering::Relaxed),
         }
     }
 
-    fn receive_producer_message(&mut self) -> Option<ProducerMessage> {
-        self.receiver.try_recv().ok()
+    /// Messages received since the last update, only the latest frame is kept as the older ones
+    /// would be replaced before being shown anyway.
+    fn receive_producer_messages(&mut self) -> Vec<ProducerMessage> {
+        #[allow(unused_mut)]
+        let mut messages: Vec<_> = self.receiver.try_iter().collect();
+
+        // exported from whichever frame brings them, even if it isn't shown
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        for message in &mut messages {
+            if let ProducerMessage::Frame(ref mut frame) = message {
+                if let Some(unfiltered) = frame.unfiltered.take() {
+                    self.export_csv(&unfiltered);
+                    self.frame_pool.recycle_gray_image(unfiltered);
+                }
+            }
+        }
+
+        let latest = messages
+            .iter()
+            .rposition(|message| matches!(message, ProducerMessage::Frame(_)));
+
+        messages
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, message)| match message {
+                ProducerMessage::Frame(frame) if Some(index) != latest => {
+                    self.frame_pool.recycle(frame);
+                    None
+                }
+                message => Some(message),
+            })
+            .collect()
     }
 
     fn load_texture_from_image(
@@ -163,8 +1495,11 @@ impl App {
         name: &str,
         image: &image2::Image<u8, image2::Rgb>,
     ) -> egui::TextureHandle {
-        let ci = egui::ColorImage::from_rgb(image.size().into(), image.data());
-        ctx.load_texture(name, ci, TextureOptions::default())
+        ctx.load_texture(
+            name,
+            image_utils::to_color_image(image),
+            TextureOptions::default(),
+        )
     }
 
     fn load_texture_from_black_thermal_image(ctx: &egui::Context) -> egui::TextureHandle {
@@ -189,133 +1524,4272 @@ impl App {
         );
     }
 
-    fn images(&self, ui: &mut Ui) {
-        let x = ui.available_size().x;
-
-        ui.image(SizedTexture {
-            id: self.thermal_image_texture.id(),
-            size: [x, x].into(),
-        });
+    fn show_image(&mut self, image: thermal::RgbImage, color_image: egui::ColorImage) {
+        // replacing the contents keeps the texture, instead of allocating one per frame
+        self.thermal_image_texture
+            .set(color_image, TextureOptions::default());
 
-        ui.image(SizedTexture {
-            id: self.colormap_texture.id(),
-            size: [x, x / 10.0].into(),
-        });
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(image) = self.shown_image.replace(image) {
+            self.frame_pool.recycle_rgb_image(image);
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.frame_pool.recycle_rgb_image(image);
     }
 
-    fn settings(&mut self, ui: &mut Ui) {
-        egui::widgets::global_theme_preference_buttons(ui);
-        ui.checkbox(&mut self.settings.flip_vertically, "Flip vertically");
-        ui.checkbox(&mut self.settings.flip_horizontally, "Flip horizontally");
+    /// Shows the last frame in the current color map, instead of waiting for the producer's next
+    /// frame, which takes a while at a low frame rate.
+    fn recolor_shown_image(&mut self) {
+        let Some(ref positions) = self.positions else {
+            return;
+        };
 
-        ui.combobox_from_iter(
-            FilteringMethod::iter(),
-            &mut self.settings.filtering_method,
-            "Filtering method",
-        );
-        ui.combobox_from_iter(
-            EdgeStrategy::iter(),
-            &mut self.settings.edge_strategy,
-            "Edge strategy",
-        );
-        ui.combobox_from_iter(ColorMap::iter(), &mut self.settings.colormap, "Color map");
-        ui.add(
-            egui::Slider::new(
-                &mut self.settings.emissivity,
-                Emissivity::MIN..=Emissivity::MAX,
-            )
-            .prefix("0.")
-            .text("Emissivity"),
-        );
-        ui.add(
-            egui::Slider::new(
-                &mut self.settings.color_range,
-                ColorRange::MIN..=ColorRange::MAX,
-            )
-            .suffix("%")
-            .text("Color range"),
+        let lut = image_utils::ColorLut::new(
+            &*self.settings.colormap.get_colormap(),
+            self.settings.color_range,
         );
+        let mut image = self.frame_pool.rgb_image();
+        if image.size() != positions.size() {
+            image = thermal::RgbImage::new(positions.size());
+        }
+        lut.colorize(positions, &mut image);
+
+        let color_image = image_utils::to_color_image(&image);
+        self.show_image(image, color_image);
     }
-}
 
-impl eframe::App for App {
-    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
-        let screen_size = ctx.screen_rect();
-        let use_panels = 1.5 * screen_size.width() > screen_size.height();
+    fn regenerate_panorama(&mut self, ctx: &egui::Context) {
+        self.panorama_texture = self.panorama.as_ref().and_then(|panorama| {
+            let image = panorama.image(
+                &*self.settings.colormap.get_colormap(),
+                self.settings.color_range,
+            )?;
+            Some(Self::load_texture_from_image(ctx, "panorama", &image))
+        });
+    }
 
-        let old_settings = self.settings.clone();
-        let message = self.receive_producer_message();
-        let mut image: Option<thermal::RgbImage> = None;
+    fn fit_image_size(available: egui::Vec2, aspect_ratio: f32, spacing: f32) -> egui::Vec2 {
+        // image of width w takes w / aspect_ratio and the colormap below it w / COLORMAP_RATIO
+        let height_per_width = 1.0 / aspect_ratio + 1.0 / COLORMAP_RATIO;
+        let width = available
+            .x
+            .min((available.y - spacing).max(0.0) / height_per_width)
+            .max(0.0);
 
-        if let Some(message) = message {
-            match message {
-                ProducerMessage::ConnectionStatusChange(status) => {
-                    self.connection_status = status;
+        egui::vec2(width, width / aspect_ratio)
+    }
 
-                    if self.connection_status == ConnectionStatus::Disconnected {
-                        image = Some(image_utils::generate_black_image(
-                            THERMAL_IMAGE_WIDTH,
+    fn images(&mut self, ui: &mut Ui) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if !self.cameras.is_empty() {
+            self.camera_grid(ui);
+            return;
+        }
+
+        self.main_image(ui);
+    }
+
+    fn main_image(&mut self, ui: &mut Ui) {
+        let aspect_ratio = THERMAL_IMAGE_WIDTH as f32 / THERMAL_IMAGE_HEIGHT as f32;
+        let spacing = ui.spacing().item_spacing.y;
+        let size = Self::fit_image_size(ui.available_size(), aspect_ratio, spacing);
+
+        ui.vertical_centered(|ui| {
+            let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click_and_drag());
+            self.image_view.handle_input(ui, rect, &response);
+
+            let mut tint = egui::Color32::WHITE;
+            if let (Some(texture), FusionMode::Blend) = (&self.visible_texture, self.fusion.mode) {
+                self.paint_visible(ui, rect, texture);
+                tint = tint.gamma_multiply(self.fusion.opacity);
+            }
+
+            ui.painter().image(
+                self.thermal_image_texture.id(),
+                rect,
+                self.image_view.uv_rect(),
+                tint,
+            );
+
+            if let (Some(texture), FusionMode::PictureInPicture) =
+                (&self.visible_texture, self.fusion.mode)
+            {
+                Self::paint_inset(ui, rect, texture);
+            }
+            self.paint_overlays(ui, rect);
+            self.paint_hot_spot_trail(ui, rect);
+            self.paint_markers(ui, rect);
+            self.paint_min_max_markers(ui, rect);
+            self.paint_spot_temperature(ui, rect, &response);
+            if let Some(ref mut editor) = self.keystone_editor {
+                editor.handles(ui, rect, &self.image_view);
+            }
+            response.context_menu(|ui| self.image_context_menu(ui));
+
+            ui.image(SizedTexture {
+                id: self.colormap_texture.id(),
+                size: [size.x, size.x / COLORMAP_RATIO].into(),
+            });
+        });
+    }
+
+    /// Paints the visible-light image aligned to the thermal one, which covers `rect`.
+    fn paint_visible(&self, ui: &Ui, rect: egui::Rect, texture: &egui::TextureHandle) {
+        let transform = self.fusion.transform(texture.size());
+        let mut mesh = egui::Mesh::with_texture(texture.id());
+
+        // a grid of affine triangles approximates the perspective of a calibrated transform
+        for y in 0..=WARP_GRID {
+            for x in 0..=WARP_GRID {
+                let uv = egui::pos2(x as f32, y as f32) / WARP_GRID as f32;
+                let pos = self.image_view.pos_at(rect, transform.map(uv));
+
+                mesh.vertices.push(egui::epaint::Vertex {
+                    pos,
+                    uv,
+                    color: egui::Color32::WHITE,
+                });
+            }
+        }
+
+        let row = WARP_GRID as u32 + 1;
+        for y in 0..WARP_GRID as u32 {
+            for x in 0..WARP_GRID as u32 {
+                let index = y * row + x;
+                mesh.add_triangle(index, index + 1, index + row + 1);
+                mesh.add_triangle(index, index + row + 1, index + row);
+            }
+        }
+
+        ui.painter_at(rect).add(egui::Shape::mesh(mesh));
+    }
+
+    /// Paints the visible-light image small in the bottom right corner of `rect`.
+    fn paint_inset(ui: &Ui, rect: egui::Rect, texture: &egui::TextureHandle) {
+        let [width, height] = texture.size();
+        let inset_width = rect.width() * INSET_SIZE;
+        let size = egui::vec2(inset_width, inset_width * height as f32 / width as f32);
+        let inset = egui::Rect::from_min_size(rect.max - size - ui.spacing().item_spacing, size);
+
+        let painter = ui.painter_at(rect);
+        painter.image(texture.id(), inset, FULL_UV_RECT, egui::Color32::WHITE);
+        painter.rect_stroke(inset, 0.0, egui::Stroke::new(1.0, egui::Color32::WHITE));
+    }
+
+    fn paint_overlays(&self, ui: &Ui, rect: egui::Rect) {
+        let painter = ui.painter_at(rect);
+        let view = &self.image_view;
+        let pixel_size = rect.width() / THERMAL_IMAGE_WIDTH as f32 * view.zoom;
+        let font = egui::FontId::proportional(12.0);
+        let uv_x = |column: usize| column as f32 / THERMAL_IMAGE_WIDTH as f32;
+        let uv_y = |row: usize| row as f32 / THERMAL_IMAGE_HEIGHT as f32;
+
+        // lines closer than this would cover the whole image
+        if self.overlays.grid && pixel_size >= 4.0 {
+            let stroke = egui::Stroke::new(1.0, egui::Color32::from_black_alpha(96));
+
+            for column in 1..THERMAL_IMAGE_WIDTH {
+                let x = view.pos_at(rect, egui::pos2(uv_x(column), 0.0)).x;
+                painter.vline(x, rect.y_range(), stroke);
+            }
+            for row in 1..THERMAL_IMAGE_HEIGHT {
+                let y = view.pos_at(rect, egui::pos2(0.0, uv_y(row))).y;
+                painter.hline(rect.x_range(), y, stroke);
+            }
+        }
+
+        if self.overlays.coordinates {
+            let label = |pos: egui::Pos2, anchor: egui::Align2, text: String| {
+                painter.text(pos, anchor, text, font.clone(), egui::Color32::WHITE);
+            };
+
+            for column in (0..THERMAL_IMAGE_WIDTH).step_by(overlay::LABEL_STEP) {
+                let x = view.pos_at(rect, egui::pos2(uv_x(column), 0.0)).x;
+                label(
+                    egui::pos2(x + 2.0, rect.top()),
+                    egui::Align2::LEFT_TOP,
+                    column.to_string(),
+                );
+            }
+            for row in (overlay::LABEL_STEP..THERMAL_IMAGE_HEIGHT).step_by(overlay::LABEL_STEP) {
+                let y = view.pos_at(rect, egui::pos2(0.0, uv_y(row))).y;
+                label(
+                    egui::pos2(rect.left() + 2.0, y + 2.0),
+                    egui::Align2::LEFT_TOP,
+                    row.to_string(),
+                );
+            }
+        }
+
+        if let Some((length, label)) = self.overlays.scale_bar() {
+            let margin = ui.spacing().item_spacing;
+            let bar = egui::Rect::from_min_size(
+                egui::pos2(rect.left() + margin.x, rect.bottom() - margin.y - 4.0),
+                egui::vec2(length * pixel_size, 4.0),
+            );
+
+            painter.rect_filled(
+                bar.translate(egui::vec2(1.0, 1.0)),
+                0.0,
+                egui::Color32::BLACK,
+            );
+            painter.rect_filled(bar, 0.0, egui::Color32::WHITE);
+            painter.text(
+                bar.left_top() - egui::vec2(0.0, margin.y),
+                egui::Align2::LEFT_BOTTOM,
+                label,
+                font,
+                egui::Color32::WHITE,
+            );
+        }
+
+        if self.overlays.watermark.enabled {
+            self.paint_watermark(ui, rect);
+        }
+    }
+
+    /// Paints the logo and the text next to it in the chosen corner of `rect`.
+    fn paint_watermark(&self, ui: &Ui, rect: egui::Rect) {
+        let painter = ui.painter_at(rect);
+        let watermark = &self.overlays.watermark;
+        let color = egui::Color32::WHITE.gamma_multiply(watermark.opacity);
+        let margin = ui.spacing().item_spacing.x;
+
+        let galley = painter.layout_no_wrap(
+            watermark.text.clone(),
+            egui::FontId::proportional(16.0),
+            color,
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let logo = self.logo.as_ref().map(|(_, texture)| {
+            let height = rect.height() * overlay::LOGO_SIZE;
+            let size = texture.size_vec2();
+            (texture.id(), egui::vec2(height * size.x / size.y, height))
+        });
+        #[cfg(target_arch = "wasm32")]
+        let logo: Option<(egui::TextureId, egui::Vec2)> = None;
+
+        let logo_size = logo.map_or(egui::Vec2::ZERO, |(_, size)| size);
+        let gap = if logo.is_some() { margin } else { 0.0 };
+        let block = egui::vec2(
+            logo_size.x + gap + galley.size().x,
+            logo_size.y.max(galley.size().y),
+        );
+        let block = watermark
+            .position
+            .align()
+            .align_size_within_rect(block, rect.shrink(margin));
+
+        if let Some((texture, size)) = logo {
+            let logo_rect = egui::Align2::LEFT_CENTER.align_size_within_rect(size, block);
+            painter.image(texture, logo_rect, FULL_UV_RECT, color);
+        }
+
+        let text_pos = egui::pos2(
+            block.left() + logo_size.x + gap,
+            block.center().y - galley.size().y / 2.0,
+        );
+        painter.galley_with_override_text_color(
+            text_pos + egui::vec2(1.0, 1.0),
+            galley.clone(),
+            egui::Color32::BLACK,
+        );
+        painter.galley(text_pos, galley, color);
+    }
+
+    fn update_hot_spot_trail(&mut self, now: web_time::Instant, hottest: [usize; 2]) {
+        if !self.overlays.hot_spot_trail {
+            self.hot_spot_trail.clear();
+            return;
+        }
+
+        let length = web_time::Duration::from_secs_f32(self.overlays.trail_length);
+        while self
+            .hot_spot_trail
+            .front()
+            .is_some_and(|&(at, _)| now - at > length)
+        {
+            self.hot_spot_trail.pop_front();
+        }
+
+        self.hot_spot_trail.push_back((now, hottest));
+    }
+
+    /// The trail fades with the age of its points relative to the last frame, so it stays put
+    /// while paused.
+    fn paint_hot_spot_trail(&self, ui: &Ui, rect: egui::Rect) {
+        let painter = ui.painter_at(rect);
+        let opacity = |at: web_time::Instant| {
+            let age = (self.last_frame_update - at).as_secs_f32() / self.overlays.trail_length;
+            (1.0 - age).clamp(0.0, 1.0)
+        };
+
+        let points = self.hot_spot_trail.iter();
+        for (&(_, from), &(at, to)) in points.clone().zip(points.skip(1)) {
+            let (Some(from), Some(to)) = (
+                self.image_view.pixel_pos(rect, from),
+                self.image_view.pixel_pos(rect, to),
+            ) else {
+                continue;
+            };
+            let opacity = opacity(at);
+
+            painter.line_segment(
+                [from, to],
+                egui::Stroke::new(5.0, egui::Color32::BLACK.gamma_multiply(opacity)),
+            );
+            painter.line_segment(
+                [from, to],
+                egui::Stroke::new(3.0, egui::Color32::WHITE.gamma_multiply(opacity)),
+            );
+        }
+
+        if let Some(pos) = self
+            .hot_spot_trail
+            .back()
+            .and_then(|&(_, pixel)| self.image_view.pixel_pos(rect, pixel))
+        {
+            painter.circle_filled(pos, 5.0, egui::Color32::BLACK);
+            painter.circle_filled(pos, 4.0, egui::Color32::WHITE);
+        }
+    }
+
+    fn paint_markers(&self, ui: &Ui, rect: egui::Rect) {
+        let painter = ui.painter_at(rect);
+        let radius = (rect.width() / THERMAL_IMAGE_WIDTH as f32 * self.image_view.zoom / 2.0)
+            .clamp(4.0, 16.0);
+
+        for marker in &self.image_view.markers {
+            let Some(pos) = self.image_view.pixel_pos(rect, marker.pixel) else {
+                continue;
+            };
+
+            painter.circle_stroke(pos, radius, egui::Stroke::new(4.0, egui::Color32::BLACK));
+            painter.circle_stroke(pos, radius, egui::Stroke::new(2.0, marker.color));
+
+            let value = self.values.as_ref().map(|values| {
+                let value = f64::from(values.get(marker.pixel).as_slice()[0]) / 10.0;
+                self.temperature_text(value)
+            });
+            let text = match value {
+                Some(value) if marker.name.is_empty() => value,
+                Some(value) => format!("{} {value}", marker.name),
+                None => marker.name.clone(),
+            };
+
+            painter.text(
+                pos + egui::vec2(radius + 2.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                text,
+                egui::FontId::proportional(16.0),
+                marker.color,
+            );
+        }
+    }
+
+    /// Triangles on the hottest (red, pointing up) and the coldest pixel (blue, pointing down)
+    /// labeled with their temperatures.
+    fn paint_min_max_markers(&self, ui: &Ui, rect: egui::Rect) {
+        let Some(ref values) = self.values else {
+            return;
+        };
+        if !self.overlays.min_max_markers {
+            return;
+        }
+
+        let painter = ui.painter_at(rect);
+        let [coldest, hottest] = self.extremes;
+
+        for (pixel, color, up) in [
+            (coldest, egui::Color32::from_rgb(64, 160, 255), false),
+            (hottest, egui::Color32::RED, true),
+        ] {
+            let Some(pos) = self.image_view.pixel_pos(rect, pixel) else {
+                continue;
+            };
+
+            let size = 8.0;
+            let tip = if up { -size } else { size };
+            let triangle = vec![
+                pos + egui::vec2(0.0, tip),
+                pos + egui::vec2(-size, -tip),
+                pos + egui::vec2(size, -tip),
+            ];
+            painter.add(egui::Shape::convex_polygon(
+                triangle,
+                color,
+                egui::Stroke::new(1.5, egui::Color32::BLACK),
+            ));
+
+            let value = f64::from(values.get(pixel).as_slice()[0]) / 10.0;
+            let text = format!("{} °C", self.temperature_text(value));
+            // a shadow keeps the label readable on any color
+            for (offset, color) in [(1.0, egui::Color32::BLACK), (0.0, color)] {
+                painter.text(
+                    pos + egui::vec2(size + 4.0 + offset, offset),
+                    egui::Align2::LEFT_CENTER,
+                    &text,
+                    egui::FontId::proportional(16.0),
+                    color,
+                );
+            }
+        }
+    }
+
+    /// `temperature` (°C) with the precision of the settings, followed by the raw count of the
+    /// sensor with "Show raw counts".
+    fn temperature_text(&self, temperature: f64) -> String {
+        let text = format_number(temperature, self.ui_settings.precision);
+
+        if self.ui_settings.raw_counts {
+            format!("{text} ({:.0})", self.settings.count(temperature))
+        } else {
+            text
+        }
+    }
+
+    /// Temperature of the pixel under the pointer, or under the finger while it touches the
+    /// image.
+    fn paint_spot_temperature(&self, ui: &Ui, rect: egui::Rect, response: &egui::Response) {
+        // the corner handles are dragged over the uncorrected image
+        if self.keystone_editor.is_some() {
+            return;
+        }
+        let (Some(pos), Some(values)) = (response.hover_pos(), self.values.as_ref()) else {
+            return;
+        };
+        let Some(pixel) = self.image_view.pixel_at(rect, pos) else {
+            return;
+        };
+
+        let value = f64::from(values.get(pixel).as_slice()[0]) / 10.0;
+        let painter = ui.painter_at(rect);
+        let galley = painter.layout_no_wrap(
+            format!("{} °C", self.temperature_text(value)),
+            egui::FontId::proportional(16.0),
+            egui::Color32::WHITE,
+        );
+
+        // above and right of the pointer, so a finger doesn't cover it, kept inside the image
+        let size = galley.size();
+        let min = (pos + egui::vec2(12.0, -12.0 - size.y))
+            .clamp(rect.min, (rect.max - size).max(rect.min));
+        painter.rect_filled(
+            egui::Rect::from_min_size(min, size).expand(2.0),
+            2.0,
+            egui::Color32::from_black_alpha(160),
+        );
+        painter.galley(min, galley, egui::Color32::WHITE);
+    }
+
+    /// Sensor pixel shown at `pixel`, `None` when the image is warped and pixels don't map
+    /// one to one.
+    fn sensor_pixel(&self, [x, y]: [usize; 2]) -> Option<[usize; 2]> {
+        if self.settings.keystone.is_some()
+            || self.settings.distortion != thermal::LensDistortion::default()
+            || self.settings.stabilization
+        {
+            return None;
+        }
+
+        let x = if self.settings.flip_horizontally {
+            THERMAL_IMAGE_WIDTH - 1 - x
+        } else {
+            x
+        };
+        let y = if self.settings.flip_vertically {
+            THERMAL_IMAGE_HEIGHT - 1 - y
+        } else {
+            y
+        };
+
+        Some([x, y])
+    }
+
+    fn image_context_menu(&mut self, ui: &mut Ui) {
+        if let Some(pixel) = self.image_view.menu_pixel {
+            if !self
+                .image_view
+                .markers
+                .iter()
+                .any(|marker| marker.pixel == pixel)
+                && ui.button(tr("Add marker")).clicked()
+            {
+                self.image_view.add_marker(pixel);
+                ui.close_menu();
+            }
+
+            if let Some(ref values) = self.values {
+                if ui.button(tr("Center colors here")).clicked() {
+                    self.settings.span_center = f32::from(values.get(pixel).as_slice()[0]) / 10.0;
+                    self.settings.centered_span = true;
+                    ui.close_menu();
+                }
+            }
+
+            if let Some(sensor_pixel) = self.sensor_pixel(pixel) {
+                let bad_pixels = &mut self.settings.bad_pixels;
+
+                match bad_pixels.iter().position(|bad| *bad == sensor_pixel) {
+                    Some(index) => {
+                        if ui.button(tr("Unmark bad pixel")).clicked() {
+                            bad_pixels.remove(index);
+                            ui.close_menu();
+                        }
+                    }
+                    None => {
+                        if ui.button(tr("Mark as bad pixel")).clicked() {
+                            bad_pixels.push(sensor_pixel);
+                            ui.close_menu();
+                        }
+                    }
+                }
+            }
+        }
+
+        if !self.image_view.markers.is_empty() && ui.button(tr("Clear markers")).clicked() {
+            self.image_view.markers.clear();
+            ui.close_menu();
+        }
+
+        if self.image_view.is_zoomed() && ui.button(tr("Reset zoom")).clicked() {
+            self.image_view.reset_zoom();
+            ui.close_menu();
+        }
+
+        #[cfg(target_os = "android")]
+        if self.shown_image.is_some() {
+            if ui.button(tr("Save snapshot")).clicked() {
+                self.save_snapshot(false);
+                ui.close_menu();
+            }
+            if ui.button(tr("Share")).clicked() {
+                self.save_snapshot(true);
+                ui.close_menu();
+            }
+        }
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if self.shown_image.is_some() && ui.button(tr("Capture")).clicked() {
+            self.capture();
+            ui.close_menu();
+        }
+    }
+
+    fn image_viewport(&mut self, ctx: &egui::Context) {
+        if !self.image_detached {
+            return;
+        }
+
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("image_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title(tr("Tiop01 thermal view"))
+                .with_inner_size([480.0, 540.0]),
+            |ctx, class| {
+                if class == egui::ViewportClass::Embedded {
+                    // platform without multiple native windows (e.g. Android)
+                    let mut open = true;
+
+                    egui::Window::new(tr("Thermal view"))
+                        .id(egui::Id::new("thermal_view"))
+                        .open(&mut open)
+                        .default_size([320.0, 360.0])
+                        .resizable(true)
+                        .show(ctx, |ui| self.images(ui));
+
+                    self.image_detached = open;
+                } else {
+                    egui::CentralPanel::default().show(ctx, |ui| self.images(ui));
+
+                    if ctx.input(|i| i.viewport().close_requested()) {
+                        self.image_detached = false;
+                    }
+                }
+            },
+        );
+    }
+
+    /// Stitches the frames into a mosaic while the camera is swept over the scene.
+    fn panorama_window(&mut self, ctx: &egui::Context) {
+        let Some(ref mut panorama) = self.panorama else {
+            return;
+        };
+
+        let mut open = true;
+        let mut clear = false;
+
+        egui::Window::new(tr("Panorama"))
+            .id(egui::Id::new("panorama"))
+            .open(&mut open)
+            .default_size([400.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label(tr("Sweep the camera slowly over the scene."));
+
+                ui.horizontal(|ui| {
+                    let label = if panorama.capturing { "Stop" } else { "Start" };
+                    if ui.button(tr(label)).clicked() {
+                        panorama.capturing = !panorama.capturing;
+                    }
+                    clear = ui.button(tr("Clear")).clicked();
+
+                    let [width, height] = panorama.size();
+                    ui.label(format!("{width} x {height}"));
+                });
+
+                if let Some(ref texture) = self.panorama_texture {
+                    let size = texture.size_vec2();
+                    let scale = (ui.available_width() / size.x)
+                        .min(ui.available_height() / size.y)
+                        .max(0.0);
+
+                    ui.image(SizedTexture::new(texture.id(), size * scale));
+                }
+            });
+
+        if clear {
+            panorama.clear();
+            self.panorama_texture = None;
+        }
+
+        if !open {
+            self.panorama = None;
+            self.panorama_texture = None;
+        }
+    }
+
+    fn handle_hotkeys(&mut self, ctx: &egui::Context) {
+        for action in self.hotkeys.pressed(ctx) {
+            self.perform(ctx, action);
+        }
+    }
+
+    fn perform(&mut self, ctx: &egui::Context, action: Action) {
+        match action {
+            Action::Pause => self.paused = !self.paused,
+            Action::CycleColorMap => {
+                self.settings.colormap = ColorMap::iter()
+                    .cycle()
+                    .skip_while(|colormap| *colormap != self.settings.colormap)
+                    .nth(1)
+                    .unwrap_or_default();
+            }
+            Action::FlipHorizontally => {
+                self.settings.flip_horizontally = !self.settings.flip_horizontally;
+            }
+            Action::FlipVertically => {
+                self.settings.flip_vertically = !self.settings.flip_vertically;
+            }
+            Action::ToggleFullscreen => {
+                let fullscreen = ctx.input(|i| i.viewport().fullscreen.unwrap_or(false));
+                ctx.send_viewport_cmd(egui::ViewportCommand::Fullscreen(!fullscreen));
+            }
+            Action::AcknowledgeAlarm => self.alarm_acknowledged = true,
+            Action::LockSpan => self.settings.span_locked = !self.settings.span_locked,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            Action::Snapshot => self.capture(),
+            #[cfg(target_os = "android")]
+            Action::Snapshot => self.save_snapshot(false),
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            Action::ToggleRecording => {
+                if self.recording.is_some() {
+                    let _ = self.sender.send(UiMessage::StopRecording);
+                } else if matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+                    self.start_recording();
+                }
+            }
+        }
+    }
+
+    /// Large buttons floating over the image for kiosk mode, where there's no keyboard.
+    fn touch_controls(&mut self, ctx: &egui::Context) {
+        const BUTTON_SIZE: egui::Vec2 = egui::vec2(120.0, 64.0);
+
+        let status_bar_height = ctx.style().spacing.interact_size.y * 2.0;
+
+        egui::Area::new(egui::Id::new("touch_controls"))
+            .anchor(
+                egui::Align2::RIGHT_BOTTOM,
+                egui::vec2(-16.0, -16.0 - status_bar_height),
+            )
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let button = |text: &str| {
+                        egui::Button::new(egui::RichText::new(tr(text)).size(24.0))
+                            .min_size(BUTTON_SIZE)
+                    };
+
+                    if ui.add(button("Color map")).clicked() {
+                        self.perform(ctx, Action::CycleColorMap);
+                    }
+
+                    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+                    if ui
+                        .add_enabled(self.shown_image.is_some(), button("Snapshot"))
+                        .clicked()
+                    {
+                        self.perform(ctx, Action::Snapshot);
+                    }
+
+                    let pause = if self.paused { "Resume" } else { "Pause" };
+
+                    if ui.add(button(pause)).clicked() {
+                        self.perform(ctx, Action::Pause);
+                    }
+
+                    if ui
+                        .add(button("Settings").selected(self.settings_visible))
+                        .clicked()
+                    {
+                        self.settings_visible = !self.settings_visible;
+                    }
+                });
+            });
+    }
+
+    fn optional_color(ui: &mut Ui, color: &mut Option<egui::Color32>, label: &str) {
+        ui.horizontal(|ui| {
+            let mut enabled = color.is_some();
+            ui.checkbox(&mut enabled, tr(label));
+
+            match (enabled, color.as_mut()) {
+                (true, Some(color)) => {
+                    ui.color_edit_button_srgba(color);
+                }
+                (true, None) => *color = Some(ui.visuals().selection.bg_fill),
+                (false, _) => *color = None,
+            }
+        });
+    }
+
+    fn keystone_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| match self.keystone_editor.take() {
+            None => {
+                if ui.button(tr("Perspective correction")).clicked() {
+                    // the corners are picked on the uncorrected image
+                    let previous = self.settings.keystone.take();
+                    self.keystone_editor = Some(KeystoneEditor {
+                        corners: previous.unwrap_or(homography::UNIT_SQUARE),
+                        previous,
+                    });
+                }
+
+                if self.settings.keystone.is_some() && ui.button(tr("Reset")).clicked() {
+                    self.settings.keystone = None;
+                }
+            }
+            Some(editor) => {
+                ui.label(tr("Drag the corners"));
+
+                if ui.button(tr("Done")).clicked() {
+                    self.settings.keystone =
+                        (editor.corners != homography::UNIT_SQUARE).then_some(editor.corners);
+                } else if ui.button(tr("Cancel")).clicked() {
+                    self.settings.keystone = editor.previous;
+                } else {
+                    self.keystone_editor = Some(editor);
+                }
+            }
+        });
+    }
+
+    fn display_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let theme = &mut self.ui_settings.theme;
+            ui.selectable_value(theme, egui::ThemePreference::System, tr("System"));
+            ui.selectable_value(theme, egui::ThemePreference::Dark, tr("Dark"));
+            ui.selectable_value(theme, egui::ThemePreference::Light, tr("Light"));
+        });
+        Self::optional_color(ui, &mut self.ui_settings.accent_color, "Accent color");
+        Self::optional_color(
+            ui,
+            &mut self.ui_settings.background_color,
+            "Background color",
+        );
+        ui.checkbox(&mut self.image_detached, tr("Detach image"));
+        ui.checkbox(&mut self.settings.flip_vertically, tr("Flip vertically"));
+        ui.checkbox(
+            &mut self.settings.flip_horizontally,
+            tr("Flip horizontally"),
+        );
+        self.keystone_settings(ui);
+        if ui
+            .add_enabled(self.panorama.is_none(), egui::Button::new(tr("Panorama")))
+            .clicked()
+        {
+            self.panorama = Some(Panorama::default());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.scene_presets(ui);
+        ui.combobox_from_iter(ColorMap::iter(), &mut self.settings.colormap, "Color map");
+        ui.add(
+            egui::Slider::new(
+                &mut self.settings.color_range,
+                ColorRange::MIN..=ColorRange::MAX,
+            )
+            .suffix("%")
+            .text(tr("Color range")),
+        );
+        ui.add_enabled(
+            !self.settings.centered_span,
+            egui::Checkbox::new(&mut self.settings.span_locked, tr("Lock color span")),
+        )
+        .on_hover_text(tr(
+            "Keeps the colors of the current temperatures while the camera moves across a scene",
+        ));
+        ui.add_enabled(
+            !self.settings.centered_span,
+            egui::Slider::new(
+                &mut self.settings.range_smoothing,
+                0.0..=thermal::MAX_RANGE_SMOOTHING,
+            )
+            .suffix(" s")
+            .text(tr("Range smoothing")),
+        )
+        .on_hover_text(tr(
+            "How slowly the colors follow the coldest and the hottest pixel, 0 follows every frame",
+        ));
+        ui.add_enabled(
+            !self.settings.centered_span,
+            egui::Slider::new(
+                &mut self.settings.range_outliers,
+                0..=thermal::MAX_RANGE_OUTLIERS,
+            )
+            .text(tr("Ignored extreme pixels")),
+        )
+        .on_hover_text(tr(
+            "Leaves this many of the hottest and of the coldest pixels out of the color span",
+        ));
+        ui.checkbox(
+            &mut self.settings.centered_span,
+            tr("Center on temperature"),
+        );
+        ui.add_enabled_ui(self.settings.centered_span, |ui| {
+            ui.add(
+                egui::Slider::new(&mut self.settings.span_center, -20.0..=300.0)
+                    .suffix(" °C")
+                    .text(tr("Center")),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.settings.span_half_width, 0.5..=50.0)
+                    .logarithmic(true)
+                    .prefix("± ")
+                    .suffix(" °C")
+                    .text(tr("Span")),
+            );
+        });
+        ui.checkbox(&mut self.paused, tr("Pause"));
+
+        #[cfg(target_os = "android")]
+        {
+            self.snapshot_buttons(ui);
+            ui.horizontal(|ui| {
+                ui.label(tr("Recordings folder"));
+                ui.text_edit_singleline(&mut self.recordings_folder);
+            });
+            self.recording_buttons(ui);
+        }
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.capture_buttons(ui);
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn capture_buttons(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Folder"));
+            ui.text_edit_singleline(&mut self.capture_directory);
+        });
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.shown_image.is_some(), egui::Button::new(tr("Capture")))
+                .on_hover_text(tr("Saves the shown image as a PNG"))
+                .clicked()
+            {
+                self.capture();
+            }
+            ui.checkbox(&mut self.capture_raw, tr("Raw 16-bit data"));
+        });
+
+        if let Some(ref message) = self.snapshot_message {
+            ui.label(message);
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(tr("File"));
+            ui.text_edit_singleline(&mut self.csv_path);
+        });
+        let connected = matches!(self.connection_status, ConnectionStatus::Connected(_));
+        if ui
+            .add_enabled(connected, egui::Button::new(tr("Export CSV")))
+            .on_hover_text(tr("Temperatures of the next frame in °C, before filtering"))
+            .clicked()
+        {
+            let _ = self.sender.send(UiMessage::CollectUnfiltered);
+            self.csv_message = Some(tr("Collecting…").to_owned());
+        }
+
+        if let Some(ref message) = self.csv_message {
+            ui.label(message);
+        }
+
+        self.recording_buttons(ui);
+        self.video_buttons(ui);
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn recording_buttons(&mut self, ui: &mut Ui) {
+        let connected = matches!(self.connection_status, ConnectionStatus::Connected(_));
+
+        if self.recording.is_some() {
+            if ui.button(tr("Stop recording")).clicked() {
+                let _ = self.sender.send(UiMessage::StopRecording);
+            }
+        } else if ui
+            .add_enabled(connected, egui::Button::new(tr("Record")))
+            .on_hover_text(tr("Records the temperatures for replaying them later"))
+            .clicked()
+        {
+            self.start_recording();
+        }
+
+        if let Some(ref message) = self.recording_message {
+            ui.label(message);
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn video_buttons(&mut self, ui: &mut Ui) {
+        // a video keeps the size it has been started with
+        ui.add_enabled_ui(self.video.is_none(), |ui| {
+            ui.add(
+                egui::Slider::new(
+                    &mut self.video_settings.frame_rate,
+                    crate::video::MIN_FRAME_RATE..=crate::video::MAX_FRAME_RATE,
+                )
+                .text(tr("Video frame rate")),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.video_settings.scale, 1..=crate::video::MAX_SCALE)
+                    .text(tr("Video scale")),
+            );
+        });
+
+        if self.video.is_some() {
+            if ui.button(tr("Stop video")).clicked() {
+                self.stop_video();
+            }
+        } else if ui
+            .add_enabled(
+                self.shown_image.is_some(),
+                egui::Button::new(tr("Record video")),
+            )
+            .on_hover_text(tr("Saves the shown images as a Motion JPEG video"))
+            .clicked()
+        {
+            self.start_video();
+        }
+
+        if let Some(ref message) = self.video_message {
+            ui.label(message);
+        }
+    }
+
+    /// Starts a video named after the current time in the capture directory.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn start_video(&mut self) {
+        let Some(image) = self.rendered_image(self.video_settings.scale) else {
+            return;
+        };
+
+        let result = (|| {
+            let directory = std::path::Path::new(&self.capture_directory);
+            std::fs::create_dir_all(directory)?;
+
+            let stem = Self::capture_stem(directory, "avi");
+            let path = directory.join(format!("{stem}.avi"));
+            crate::video::VideoRecording::start(path, &self.video_settings, &image)
+        })();
+
+        self.video_message = match result {
+            Ok(video) => {
+                let message = format!("{} {}", tr("Recording to"), video.path().display());
+                self.video = Some(video);
+                Some(message)
+            }
+            Err(e) => {
+                log::error!("Failed to start the video: {e}");
+                Some(format!("{}: {e}", tr("Failed to save the video")))
+            }
+        };
+    }
+
+    /// Adds the frames due since the last update, each the image shown now.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn update_video(&mut self) {
+        let due = match self.video {
+            Some(ref video) => video.due_frames(web_time::Instant::now()),
+            None => return,
+        };
+        if due == 0 {
+            return;
+        }
+
+        let Some(image) = self.rendered_image(self.video_settings.scale) else {
+            return;
+        };
+        let result = self
+            .video
+            .as_mut()
+            .map_or(Ok(()), |video| video.write_frames(&image, due));
+
+        if let Err(e) = result {
+            log::error!("Failed to write the video: {e}");
+            self.video = None;
+            self.video_message = Some(format!("{}: {e}", tr("Failed to save the video")));
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn stop_video(&mut self) {
+        let Some(video) = self.video.take() else {
+            return;
+        };
+
+        self.video_message = Some(match video.finish() {
+            Ok((path, frames)) => {
+                format!("{} {frames}: {}", tr("Frames recorded"), path.display())
+            }
+            Err(e) => {
+                log::error!("Failed to finish the video: {e}");
+                format!("{}: {e}", tr("Failed to save the video"))
+            }
+        });
+    }
+
+    /// Records to a file named after the current time in the capture directory.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn start_recording(&mut self) {
+        let directory = std::path::Path::new(&self.capture_directory);
+
+        let file = std::fs::create_dir_all(directory).and_then(|()| {
+            let stem = Self::capture_stem(directory, "tiop01");
+            let path = directory.join(format!("{stem}.tiop01"));
+            std::fs::File::create(&path).map(|file| (path, file))
+        });
+
+        match file {
+            Ok((path, file)) => self.send_start_recording(path, file),
+            Err(e) => {
+                log::error!(
+                    "Failed to create a recording in {}: {e}",
+                    directory.display()
+                );
+                self.recording_message = Some(format!("{}: {e}", tr("Failed to record")));
+            }
+        }
+    }
+
+    /// Records to a file named after the current time in the recordings folder of the shared
+    /// storage.
+    #[cfg(target_os = "android")]
+    fn start_recording(&mut self) {
+        match crate::android::create_recording(&self.recordings_folder) {
+            Ok((path, file)) => self.send_start_recording(path, file),
+            Err(e) => {
+                log::error!("Failed to create a recording: {e}");
+                self.recording_message = Some(format!("{}: {e}", tr("Failed to record")));
+            }
+        }
+    }
+
+    /// Starts recording to `file` once the offset of the system clock has been measured, which
+    /// takes up to the NTP timeout, so that recordings of several machines can be lined up.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn send_start_recording(&self, path: std::path::PathBuf, file: std::fs::File) {
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let ntp_offset = crate::ntp::ClockOffset::measure(crate::ntp::DEFAULT_SERVER);
+            let _ = sender.send(UiMessage::StartRecording(path, file, ntp_offset));
+        });
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn update_recording(&mut self, status: thermal::RecordingStatus) {
+        self.recording_message = Some(match status {
+            thermal::RecordingStatus::Started(path) => {
+                let message = format!("{} {}", tr("Recording to"), path.display());
+                self.recording = Some(path);
+                message
+            }
+            thermal::RecordingStatus::Finished { path, frames } => {
+                self.recording = None;
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                {
+                    self.recent.add_file(&path);
+                    self.save_recent();
+                }
+                format!("{} {frames}: {}", tr("Frames recorded"), path.display())
+            }
+            thermal::RecordingStatus::Failed(e) => {
+                self.recording = None;
+                format!("{}: {e}", tr("Failed to record"))
+            }
+        });
+    }
+
+    /// Name of a new file in `directory`, after the current time, numbered when there's already
+    /// one with the `extension` from the same second.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn capture_stem(directory: &std::path::Path, extension: &str) -> String {
+        let [year, month, day, hour, minute, second] =
+            report::civil_time(std::time::SystemTime::now());
+        let stem = format!("tiop01-{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}");
+
+        (1..)
+            .map(|index| match index {
+                1 => stem.clone(),
+                index => format!("{stem}-{index}"),
+            })
+            .find(|stem| !directory.join(format!("{stem}.{extension}")).exists())
+            .unwrap_or(stem)
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn export_csv(&mut self, values: &thermal::GrayImage) {
+        let result = std::fs::write(&self.csv_path, report::temperature_csv(values));
+
+        self.csv_message = Some(match result {
+            Ok(()) => format!("{} {}", tr("Saved to"), self.csv_path),
+            Err(e) => {
+                log::error!("Failed to export the temperatures: {e}");
+                format!("{}: {e}", tr("Failed to export the temperatures"))
+            }
+        });
+    }
+
+    /// Saves the snapshot of the shown image, and the temperatures if wanted, to files named
+    /// after the current time in the capture directory.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn capture(&mut self) {
+        let Some(image) = self.snapshot_image() else {
+            return;
+        };
+
+        let result = (|| {
+            let directory = std::path::Path::new(&self.capture_directory);
+            std::fs::create_dir_all(directory)?;
+
+            let stem = Self::capture_stem(directory, "png");
+            let path = directory.join(format!("{stem}.png"));
+            std::fs::write(&path, report::encode_png(&image)?)?;
+
+            if let (true, Some(values)) = (self.capture_raw, self.values.as_ref()) {
+                let raw_path = directory.join(format!("{stem}-raw.png"));
+                std::fs::write(raw_path, report::encode_gray16_png(values)?)?;
+            }
+
+            anyhow::Ok(path)
+        })();
+
+        self.snapshot_message = Some(match result {
+            Ok(path) => format!("{} {}", tr("Saved to"), path.display()),
+            Err(e) => {
+                log::error!("Failed to save snapshot: {e}");
+                format!("{}: {e}", tr("Failed to save snapshot"))
+            }
+        });
+    }
+
+    #[cfg(target_os = "android")]
+    fn snapshot_buttons(&mut self, ui: &mut Ui) {
+        if self.shown_image.is_none() {
+            return;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button(tr("Save snapshot")).clicked() {
+                self.save_snapshot(false);
+            }
+            if ui.button(tr("Share")).clicked() {
+                self.save_snapshot(true);
+            }
+
+            if let Some(ref message) = self.snapshot_message {
+                ui.label(message);
+            }
+        });
+    }
+
+    /// Upscaled shown image with the watermark, and the other overlays if they're wanted.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn snapshot_image(&self) -> Option<thermal::RgbImage> {
+        self.rendered_image(overlay::SNAPSHOT_SCALE)
+    }
+
+    /// Shown image upscaled `scale` times with the overlays of [`App::snapshot_image`].
+    #[cfg(not(target_arch = "wasm32"))]
+    fn rendered_image(&self, scale: usize) -> Option<thermal::RgbImage> {
+        let image = self.shown_image.as_ref()?;
+        let overlays = if self.overlays.in_snapshots {
+            self.overlays.clone()
+        } else {
+            OverlaySettings {
+                watermark: self.overlays.watermark.clone(),
+                ..OverlaySettings::default()
+            }
+        };
+        let logo = self.logo.as_ref().map(|(image, _)| image);
+
+        Some(overlay::snapshot(image, &overlays, logo, scale))
+    }
+
+    #[cfg(target_os = "android")]
+    fn save_snapshot(&mut self, share: bool) {
+        let Some(image) = self.snapshot_image() else {
+            return;
+        };
+
+        let result = if share {
+            crate::android::share_snapshot(&image).map(|()| String::new())
+        } else {
+            crate::android::save_snapshot(&image)
+                .map(|()| format!("{} {}", tr("Saved to"), crate::android::SNAPSHOT_DIRECTORY))
+        };
+
+        self.snapshot_message = Some(result.unwrap_or_else(|e| {
+            log::error!("Failed to save snapshot: {e}");
+            format!("{}: {e}", tr("Failed to save snapshot"))
+        }));
+    }
+
+    fn processing_settings(&mut self, ui: &mut Ui) {
+        ui.combobox_from_iter(
+            FilteringMethod::iter(),
+            &mut self.settings.filtering_method,
+            "Filtering method",
+        );
+        ui.combobox_from_iter(
+            EdgeStrategy::iter(),
+            &mut self.settings.edge_strategy,
+            "Edge strategy",
+        );
+        ui.checkbox(
+            &mut self.settings.measure_filtered,
+            tr("Measure on the filtered image"),
+        );
+
+        ui.add(
+            egui::Slider::new(
+                &mut self.settings.frames_to_average,
+                1..=thermal::MAX_FRAMES_TO_AVERAGE,
+            )
+            .text(tr("Frames to average")),
+        );
+
+        ui.checkbox(
+            &mut self.settings.baseline_difference,
+            tr("Difference from baseline"),
+        );
+        if self.settings.baseline_difference {
+            ui.add(
+                egui::Slider::new(&mut self.settings.baseline_period, 5.0..=600.0)
+                    .logarithmic(true)
+                    .suffix(" s")
+                    .text(tr("Baseline period")),
+            );
+        }
+
+        ui.checkbox(&mut self.settings.stabilization, tr("Stabilization"));
+
+        let distortion = &mut self.settings.distortion;
+        ui.add(
+            egui::Slider::new(&mut distortion.k1, thermal::LensDistortion::RANGE)
+                .text(tr("Lens distortion (k1)")),
+        );
+        ui.add(
+            egui::Slider::new(&mut distortion.k2, thermal::LensDistortion::RANGE)
+                .text(tr("Lens distortion (k2)")),
+        );
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn toggle_port_picker(&mut self) {
+        self.available_ports = match self.available_ports {
+            Some(_) => None,
+            None => Some(crate::desktop::available_ports()),
+        };
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn port_picker(&mut self, ctx: &egui::Context) {
+        let Some(ref ports) = self.available_ports else {
+            return;
+        };
+
+        let mut open = true;
+        let mut refresh = false;
+
+        egui::Window::new(tr("Serial port"))
+            .id(egui::Id::new("port_picker"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.selectable_value(&mut self.settings.port, None, tr("Auto-detect"));
+
+                for port in ports {
+                    let mut label = egui::RichText::new(&port.label);
+
+                    if port.camera {
+                        label = label.strong();
+                    }
+
+                    ui.selectable_value(&mut self.settings.port, Some(port.name.clone()), label);
+                }
+
+                ui.separator();
+                refresh = ui.button(tr("Refresh")).clicked();
+            });
+
+        if !open {
+            self.available_ports = None;
+        } else if refresh {
+            self.available_ports = Some(crate::desktop::available_ports());
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn crash_report_prompt(&mut self, ctx: &egui::Context) {
+        let Some(ref path) = self.crash_report else {
+            return;
+        };
+
+        let mut open = true;
+        let mut dismissed = false;
+
+        egui::Window::new(tr("Crash report"))
+            .id(egui::Id::new("crash_report"))
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(tr(
+                    "The app crashed the last time it ran. Please attach the crash report to a bug report.",
+                ));
+                ui.label(path.display().to_string());
+
+                ui.horizontal(|ui| {
+                    if ui.button(tr("Open")).clicked() {
+                        if let Err(e) = crate::crash_report::open(path) {
+                            log::error!("Failed to open {}: {e}", path.display());
+                        }
+                        dismissed = true;
+                    }
+                    dismissed |= ui.button(tr("Dismiss")).clicked();
+                });
+            });
+
+        if !open || dismissed {
+            self.crash_report = None;
+        }
+    }
+
+    fn status_bar(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let port_text = match self.connection_status {
+                ConnectionStatus::Disconnected => tr("Disconnected").to_owned(),
+                ConnectionStatus::WaitingForPermission => tr("Waiting for permission…").to_owned(),
+                ConnectionStatus::PermissionDenied => {
+                    tr("Permission denied — tap to retry").to_owned()
+                }
+                ConnectionStatus::Connected(ref port) => match port.baud_rate {
+                    Some(baud_rate) => format!("{} @ {baud_rate} bd", port.name),
+                    None => port.name.clone(),
+                },
+            };
+
+            if self.connection_status == ConnectionStatus::PermissionDenied {
+                if ui.link(port_text).clicked() {
+                    let _ = self.sender.send(UiMessage::RetryConnection);
+                }
+            } else {
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                {
+                    let response = ui.link(port_text).on_hover_text(tr("Select serial port"));
+
+                    if response.clicked() {
+                        self.toggle_port_picker();
+                    }
+                }
+                #[cfg(target_arch = "wasm32")]
+                {
+                    let response = ui.link(port_text).on_hover_text(tr("Select serial port"));
+
+                    if response.clicked() {
+                        crate::web::request_port(ui.ctx().clone());
+                    }
+                }
+                #[cfg(any(target_os = "android", target_os = "ios"))]
+                {
+                    ui.label(port_text);
+                }
+            }
+
+            if let (ConnectionStatus::Disconnected, Some(error)) =
+                (&self.connection_status, &self.connection_error)
+            {
+                ui.separator();
+                let text = format!("{}: {}", tr(error.kind.title()), error.message);
+                let response = ui.add(
+                    egui::Label::new(egui::RichText::new(text).color(ui.visuals().warn_fg_color))
+                        .truncate(),
+                );
+
+                if let Some(hint) = error.kind.hint() {
+                    response.on_hover_text(tr(hint));
+                }
+            }
+
+            if let ConnectionStatus::Connected(_) = self.connection_status {
+                ui.separator();
+                ui.label(format!(
+                    "{}: {}, {}: {}",
+                    tr("Min"),
+                    self.temperature_text(self.min),
+                    tr("max"),
+                    self.temperature_text(self.max),
+                ));
+                ui.separator();
+                ui.label(format!("{}: {}", tr("FPS"), format_number(self.fps, 2)));
+                ui.separator();
+                ui.label(format!(
+                    "{} kB/s",
+                    format_number(self.data_rate / 1000.0, 1)
+                ));
+
+                if self.paused {
+                    ui.separator();
+                    ui.label(tr("paused"));
+                }
+
+                if self.settings.power_saving {
+                    ui.separator();
+                    ui.label(tr("power saving"));
+                }
+
+                if self.settings.baseline_difference {
+                    ui.separator();
+                    ui.label(tr("difference"));
+                }
+
+                if self.alarm_active {
+                    ui.separator();
+                    ui.label(egui::RichText::new(tr("Alarm")).color(ui.visuals().error_fg_color));
+
+                    if !self.alarm_acknowledged && ui.small_button(tr("Acknowledge")).clicked() {
+                        self.perform(ui.ctx(), Action::AcknowledgeAlarm);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Statistics of `frame` and the state of the alarm as a JSON object on a single line.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn json_stats(&self, frame: &Frame) -> String {
+        let now = std::time::SystemTime::now();
+        let [year, month, day, hour, minute, second] = report::civil_time(now);
+        let milliseconds = now
+            .duration_since(std::time::SystemTime::UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_millis());
+
+        let values = frame.values.data();
+        let mean = values.iter().map(|&value| f64::from(value)).sum::<f64>()
+            / values.len().max(1) as f64
+            / 10.0;
+        let alarm = &self.alarm_settings;
+
+        format!(
+            "{{\"timestamp\":\"{year:04}-{month:02}-{day:02}T{hour:02}:{minute:02}:{second:02}.\
+             {milliseconds:03}Z\",\"min\":{:.1},\"max\":{:.1},\"mean\":{mean:.2},\
+             \"alarm\":{{\"enabled\":{},\"threshold\":{},\"active\":{},\"acknowledged\":{}}}}}",
+            frame.min,
+            frame.max,
+            alarm.enabled,
+            alarm.threshold,
+            self.alarm_active,
+            self.alarm_active && self.alarm_acknowledged,
+        )
+    }
+
+    /// Prints the statistics of `frame` as a line of JSON, for piping `--json-output` into `jq`
+    /// or a log collector.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn print_json_stats(&self, frame: &Frame) {
+        use std::io::Write;
+
+        // a closed pipe only loses the statistics, not the window
+        let _ = writeln!(std::io::stdout().lock(), "{}", self.json_stats(frame));
+    }
+
+    /// Returns whether the alarm has just tripped.
+    fn check_alarm(&mut self, max: f64) -> bool {
+        let alarm = &self.alarm_settings;
+
+        if !alarm.enabled {
+            self.alarm_active = false;
+            return false;
+        }
+
+        if self.alarm_active {
+            self.alarm_active = max > alarm.threshold - AlarmSettings::HYSTERESIS;
+            return false;
+        }
+        if max < alarm.threshold {
+            return false;
+        }
+
+        self.alarm_active = true;
+        self.alarm_acknowledged = false;
+
+        #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+        {
+            self.alarm_sounded_at = None;
+        }
+
+        #[cfg(target_os = "android")]
+        if alarm.vibrate {
+            if let Err(e) = crate::android::alarm_vibration() {
+                log::error!("Failed to signal the alarm: {e}");
+            }
+        }
+
+        true
+    }
+
+    /// Plays the alarm tone when the alarm trips and then repeatedly until it's acknowledged or
+    /// clears.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn update_alarm_sound(&mut self, ctx: &egui::Context) {
+        let alarm = &self.alarm_settings;
+
+        if !alarm.sound {
+            self.alarm_sound = None;
+            self.alarm_sound_error = None;
+            return;
+        }
+
+        if !self.alarm_active || self.alarm_acknowledged {
+            return;
+        }
+
+        let now = web_time::Instant::now();
+        let repeat = alarm.repeat;
+        let interval = web_time::Duration::from_secs_f64(alarm.repeat_interval);
+        let due = match self.alarm_sounded_at {
+            None => true,
+            Some(at) => repeat && now >= at + interval,
+        };
+
+        if due {
+            self.play_alarm_tone();
+            self.alarm_sounded_at = Some(now);
+        }
+
+        if repeat {
+            let next_at = self.alarm_sounded_at.map_or(now, |at| at + interval);
+            ctx.request_repaint_after(next_at.saturating_duration_since(now));
+        }
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn play_alarm_tone(&mut self) {
+        if self.alarm_sound.is_none() && self.alarm_sound_error.is_none() {
+            match AlarmSound::new() {
+                Ok(sound) => self.alarm_sound = Some(sound),
+                Err(e) => {
+                    log::error!("Failed to start the alarm sound: {e}");
+                    self.alarm_sound_error = Some(e.to_string());
+                }
+            }
+        }
+
+        if let Some(ref sound) = self.alarm_sound {
+            let alarm = &self.alarm_settings;
+
+            if let Err(e) = sound.play(alarm.tone, alarm.volume) {
+                log::error!("Failed to play the alarm tone: {e}");
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn log_alarm(&mut self, ctx: &egui::Context, frame: &Frame) {
+        let markers = self
+            .image_view
+            .marker_values(&frame.values)
+            .into_iter()
+            .map(|(label, _, value)| (label, value))
+            .collect();
+
+        self.alarm_log.push(AlarmEvent {
+            triggered_at: std::time::SystemTime::now(),
+            max: frame.max,
+            threshold: self.alarm_settings.threshold,
+            pixel: Some(frame.hottest),
+            markers,
+            image: frame.image.clone(),
+            thumbnail: ctx.load_texture(
+                "alarm_thumbnail",
+                frame.color_image.clone(),
+                TextureOptions::default(),
+            ),
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_alarm_log(&mut self) {
+        let result = self
+            .alarm_log
+            .to_zip(self.ui_settings.precision)
+            .and_then(|zip| {
+                #[cfg(target_os = "android")]
+                {
+                    crate::android::save_alarm_log(&zip)?;
+                    Ok(crate::android::REPORT_DIRECTORY.to_owned())
+                }
+                #[cfg(not(target_os = "android"))]
+                {
+                    std::fs::write(&self.alarm_log_path, zip)?;
+                    Ok(self.alarm_log_path.clone())
+                }
+            });
+
+        self.alarm_log_message = Some(match result {
+            Ok(path) => format!("{} {path}", tr("Saved to")),
+            Err(e) => {
+                log::error!("Failed to export the alarm log: {e}");
+                format!("{}: {e}", tr("Failed to export the alarm log"))
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn alarm_log_settings(&mut self, ui: &mut Ui) {
+        ui.separator();
+        ui.label(format!(
+            "{}: {}",
+            tr("Alarm events"),
+            self.alarm_log.events.len()
+        ));
+
+        egui::ScrollArea::vertical()
+            .id_salt("alarm_log")
+            .max_height(160.0)
+            .show(ui, |ui| {
+                for event in self.alarm_log.events.iter().rev() {
+                    ui.horizontal(|ui| {
+                        ui.image(SizedTexture::new(event.thumbnail.id(), [32.0, 32.0]));
+
+                        let mut text = format!(
+                            "{}\n{} °C",
+                            report::format_time(event.triggered_at),
+                            format_number(event.max, self.ui_settings.precision)
+                        );
+                        if let Some([x, y]) = event.pixel {
+                            text.push_str(&format!(" ({x}, {y})"));
+                        }
+                        ui.label(text);
+                    });
+                }
+            });
+
+        #[cfg(not(target_os = "android"))]
+        ui.horizontal(|ui| {
+            ui.label(tr("File"));
+            ui.text_edit_singleline(&mut self.alarm_log_path);
+        });
+        ui.horizontal(|ui| {
+            let has_events = !self.alarm_log.events.is_empty();
+
+            if ui
+                .add_enabled(has_events, egui::Button::new(tr("Export")))
+                .clicked()
+            {
+                self.export_alarm_log();
+            }
+            if ui
+                .add_enabled(has_events, egui::Button::new(tr("Clear")))
+                .clicked()
+            {
+                self.alarm_log.events.clear();
+            }
+        });
+
+        if let Some(ref message) = self.alarm_log_message {
+            ui.label(message);
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn save_debug_bundle(&mut self, frames: &[thermal::RawFrame]) {
+        let connection = match self.connection_status {
+            ConnectionStatus::Connected(ref port) => format!("{port:?}"),
+            ConnectionStatus::Disconnected => "Disconnected".to_owned(),
+            ConnectionStatus::WaitingForPermission => "Waiting for permission".to_owned(),
+            ConnectionStatus::PermissionDenied => "Permission denied".to_owned(),
+        };
+        // the query has had the time the frames took, it rarely has to be waited for
+        let ntp_offset = match self.ntp_offset.take().map(|receiver| {
+            receiver
+                .recv_timeout(web_time::Duration::from_secs(2))
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Timed out")))
+        }) {
+            Some(Ok(offset)) => format!("{offset:+.3} s from {}", crate::ntp::DEFAULT_SERVER),
+            Some(Err(e)) => format!("Unavailable: {e:#}"),
+            None => "Unavailable".to_owned(),
+        };
+        let system_info = [
+            (
+                "Generated",
+                report::format_time(std::time::SystemTime::now()),
+            ),
+            (
+                "Application",
+                format!("tiop01-gui {}", env!("CARGO_PKG_VERSION")),
+            ),
+            (
+                "System",
+                format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+            ),
+            ("Transport", self.settings.transport.to_string()),
+            ("Connection", connection),
+            (
+                "Last error",
+                self.connection_error
+                    .as_ref()
+                    .map_or_else(String::new, |error| format!("{:?}: {error}", error.kind)),
+            ),
+            ("Data rate", format!("{:.0} B/s", self.data_rate)),
+            ("Frame rate", format!("{:.1} fps", self.fps)),
+            ("Frames", frames.len().to_string()),
+            ("NTP offset", ntp_offset),
+        ];
+
+        let result =
+            crate::debug_bundle::write(frames, &self.settings, &system_info).and_then(|zip| {
+                std::fs::write(&self.debug_bundle_path, zip)?;
+                Ok(self.debug_bundle_path.clone())
+            });
+
+        self.debug_bundle_message = Some(match result {
+            Ok(path) => format!("{} {path}", tr("Saved to")),
+            Err(e) => {
+                log::error!("Failed to save the debug bundle: {e}");
+                format!("{}: {e}", tr("Failed to save the debug bundle"))
+            }
+        });
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn troubleshooting_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("File"));
+            ui.text_edit_singleline(&mut self.debug_bundle_path);
+        });
+
+        if ui
+            .button(tr("Save debug bundle"))
+            .on_hover_text(tr(
+                "Recent frames from the camera, the log and the settings, to attach to bug reports",
+            ))
+            .clicked()
+        {
+            // saved once the producer sends the frames
+            let _ = self.sender.send(UiMessage::CollectRawFrames);
+
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(crate::ntp::measure_offset(crate::ntp::DEFAULT_SERVER));
+            });
+            self.ntp_offset = Some(receiver);
+            self.debug_bundle_message = Some(tr("Collecting…").to_owned());
+        }
+
+        if let Some(ref message) = self.debug_bundle_message {
+            ui.label(message);
+        }
+    }
+
+    fn marker_settings(&mut self, ui: &mut Ui) {
+        let markers = &mut self.image_view.markers;
+        let mut removed = None;
+
+        if markers.is_empty() {
+            ui.label(tr("Click or tap the image to add a marker"));
+        }
+
+        for (index, marker) in markers.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgba(&mut marker.color);
+                ui.add(
+                    egui::TextEdit::singleline(&mut marker.name)
+                        .hint_text(format!("M{}", index + 1))
+                        .desired_width(80.0),
+                );
+                ui.label(format!("{}, {}", marker.pixel[0], marker.pixel[1]));
+
+                if ui.button(tr("Remove")).clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+
+        if let Some(index) = removed {
+            markers.remove(index);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            ui.separator();
+            self.measurement_layout_settings(ui);
+        }
+    }
+
+    /// Buttons applying the scene presets, and saving the current span, color map and alarm as
+    /// one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn scene_presets(&mut self, ui: &mut Ui) {
+        let presets = self
+            .presets
+            .get_or_insert_with(|| {
+                scene_presets::load().unwrap_or_else(|e| {
+                    log::error!("Failed to load the scene presets: {e}");
+                    scene_presets::defaults()
+                })
+            })
+            .clone();
+
+        ui.horizontal_wrapped(|ui| {
+            for preset in &presets {
+                if ui.button(&preset.name).clicked() {
+                    self.apply_preset(preset);
+                }
+            }
+        });
+
+        ui.collapsing(tr("Edit presets"), |ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.preset_name)
+                    .hint_text(tr("Preset name"))
+                    .desired_width(160.0),
+            );
+
+            let name = self.preset_name.trim().to_owned();
+            let saved = presets.iter().any(|preset| preset.name == name);
+            let mut changed = None;
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new(tr("Save")))
+                    .on_hover_text(tr("Saves the span, the color map and the alarm shown now"))
+                    .clicked()
+                {
+                    let preset = self.current_preset(&name);
+                    let mut presets = presets.clone();
+                    match presets.iter_mut().find(|preset| preset.name == name) {
+                        Some(saved) => *saved = preset,
+                        None => presets.push(preset),
+                    }
+                    changed = Some(presets);
+                }
+
+                if ui
+                    .add_enabled(saved, egui::Button::new(tr("Delete")))
+                    .clicked()
+                {
+                    let mut presets = presets.clone();
+                    presets.retain(|preset| preset.name != name);
+                    changed = Some(presets);
+                }
+            });
+
+            if let Some(presets) = changed {
+                self.preset_message = match scene_presets::save(&presets) {
+                    Ok(path) => Some(format!("{} {}", tr("Saved to"), path.display())),
+                    Err(e) => {
+                        log::error!("Failed to save the scene presets: {e}");
+                        Some(format!("{}: {e}", tr("Failed to save the presets")))
+                    }
+                };
+                self.presets = Some(presets);
+            }
+
+            if let Some(ref message) = self.preset_message {
+                ui.label(message);
+            }
+        });
+    }
+
+    /// Preset of the current span, color map and alarm.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn current_preset(&self, name: &str) -> ScenePreset {
+        let (center, half_width) = (self.settings.span_center, self.settings.span_half_width);
+
+        ScenePreset {
+            name: name.to_owned(),
+            low: center - half_width,
+            high: center + half_width,
+            colormap: self.settings.colormap.clone(),
+            alarm: self
+                .alarm_settings
+                .enabled
+                .then_some(self.alarm_settings.threshold),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn apply_preset(&mut self, preset: &ScenePreset) {
+        let (center, half_width) = preset.span();
+
+        self.settings.centered_span = true;
+        self.settings.span_center = center;
+        // the widest span the slider offers
+        self.settings.span_half_width = half_width.clamp(0.5, 50.0);
+        self.settings.colormap = preset.colormap.clone();
+
+        self.alarm_settings.enabled = preset.alarm.is_some();
+        if let Some(threshold) = preset.alarm {
+            self.alarm_settings.threshold = threshold;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn measurement_layout_settings(&mut self, ui: &mut Ui) {
+        let layouts = self
+            .layouts
+            .get_or_insert_with(|| {
+                measurement_layouts::list().unwrap_or_else(|e| {
+                    log::error!("Failed to list the measurement layouts: {e}");
+                    Vec::new()
+                })
+            })
+            .clone();
+        let saved = layouts.contains(&self.layout_name.trim().to_owned());
+
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("measurement_layouts")
+                .selected_text(tr("Saved layouts"))
+                .show_ui(ui, |ui| {
+                    for name in layouts {
+                        let selected = self.layout_name == name;
+                        if ui.selectable_label(selected, &name).clicked() {
+                            self.layout_name = name;
+                        }
+                    }
+                });
+            ui.add(
+                egui::TextEdit::singleline(&mut self.layout_name)
+                    .hint_text(tr("Layout name"))
+                    .desired_width(120.0),
+            );
+        });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(saved, egui::Button::new(tr("Apply")))
+                .clicked()
+            {
+                self.layout_message = match measurement_layouts::load(&self.layout_name) {
+                    Ok(markers) => {
+                        self.image_view.markers = markers;
+                        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+                        {
+                            self.recent.layout = self.layout_name.trim().to_owned();
+                            self.save_recent();
+                        }
+                        None
+                    }
+                    Err(e) => {
+                        log::error!("Failed to load the measurement layout: {e}");
+                        Some(format!("{}: {e}", tr("Failed to load the layout")))
+                    }
+                };
+            }
+
+            let can_save =
+                !self.layout_name.trim().is_empty() && !self.image_view.markers.is_empty();
+            if ui
+                .add_enabled(can_save, egui::Button::new(tr("Save")))
+                .clicked()
+            {
+                self.layout_message =
+                    match measurement_layouts::save(&self.layout_name, &self.image_view.markers) {
+                        Ok(path) => Some(format!("{} {}", tr("Saved to"), path.display())),
+                        Err(e) => {
+                            log::error!("Failed to save the measurement layout: {e}");
+                            Some(format!("{}: {e}", tr("Failed to save the layout")))
+                        }
+                    };
+                self.layouts = None;
+            }
+
+            if ui
+                .add_enabled(saved, egui::Button::new(tr("Delete")))
+                .clicked()
+            {
+                if let Err(e) = measurement_layouts::delete(&self.layout_name) {
+                    log::error!("Failed to delete the measurement layout: {e}");
+                }
+                self.layouts = None;
+            }
+        });
+
+        if let Some(ref message) = self.layout_message {
+            ui.label(message);
+        }
+    }
+
+    fn overlay_settings(&mut self, ui: &mut Ui) {
+        let overlays = &mut self.overlays;
+
+        ui.checkbox(&mut overlays.grid, tr("Pixel grid"));
+        ui.checkbox(&mut overlays.coordinates, tr("Coordinates"));
+        ui.checkbox(&mut overlays.scale_bar, tr("Scale bar"));
+        ui.add_enabled(
+            overlays.scale_bar,
+            egui::Slider::new(&mut overlays.distance, 0.1..=20.0)
+                .logarithmic(true)
+                .suffix(" m")
+                .text(tr("Distance")),
+        );
+        ui.add_enabled(
+            overlays.scale_bar,
+            egui::Slider::new(&mut overlays.field_of_view, 10.0..=120.0)
+                .suffix("°")
+                .text(tr("Field of view")),
+        );
+
+        ui.checkbox(&mut overlays.min_max_markers, tr("Min/max markers"));
+        ui.checkbox(&mut overlays.hot_spot_trail, tr("Hot spot trail"));
+        ui.add_enabled(
+            overlays.hot_spot_trail,
+            egui::Slider::new(&mut overlays.trail_length, 1.0..=60.0)
+                .suffix(" s")
+                .text(tr("Trail length")),
+        );
+
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.checkbox(&mut overlays.in_snapshots, tr("Include in snapshots"));
+
+        ui.separator();
+        self.watermark_settings(ui);
+    }
+
+    fn watermark_settings(&mut self, ui: &mut Ui) {
+        let watermark = &mut self.overlays.watermark;
+
+        ui.checkbox(&mut watermark.enabled, tr("Watermark"));
+        ui.add_enabled_ui(watermark.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(tr("Text"));
+                ui.text_edit_singleline(&mut watermark.text);
+            });
+
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui| {
+                ui.label(tr("Logo"));
+                ui.add(egui::TextEdit::singleline(&mut watermark.logo_path).hint_text("logo.png"));
+
+                if ui.button(tr("Load")).clicked() {
+                    match overlay::load_logo(&watermark.logo_path) {
+                        Ok(image) => {
+                            let texture = ui.ctx().load_texture(
+                                "logo",
+                                image.clone(),
+                                TextureOptions::LINEAR,
+                            );
+                            self.logo = Some((image, texture));
+                            self.logo_error = None;
+                        }
+                        Err(e) => {
+                            log::error!("Failed to load the logo: {e}");
+                            self.logo_error =
+                                Some(format!("{}: {e}", tr("Failed to load the logo")));
+                        }
+                    }
+                }
+                if self.logo.is_some() && ui.button(tr("Remove")).clicked() {
+                    self.logo = None;
+                }
+            });
+            #[cfg(not(target_arch = "wasm32"))]
+            if let Some(ref error) = self.logo_error {
+                ui.colored_label(ui.visuals().error_fg_color, error);
+            }
+
+            ui.combobox_from_iter(
+                WatermarkPosition::iter(),
+                &mut watermark.position,
+                "Position",
+            );
+            ui.add(egui::Slider::new(&mut watermark.opacity, 0.1..=1.0).text(tr("Opacity")));
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn marker_values(&self) -> Vec<(String, [usize; 2], f64)> {
+        self.values
+            .as_ref()
+            .map_or_else(Vec::new, |values| self.image_view.marker_values(values))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn add_to_report(&mut self, label: String, markers: Vec<(String, [usize; 2], f64)>) {
+        let Some(image) = self.snapshot_image() else {
+            return;
+        };
+
+        let entry = ReportEntry {
+            label,
+            taken_at: std::time::SystemTime::now(),
+            image,
+            min: self.min,
+            max: self.max,
+            markers,
+            note: std::mem::take(&mut self.report_note),
+        };
+
+        // a point captured again during a survey replaces its earlier snapshot
+        match self
+            .report
+            .entries
+            .iter_mut()
+            .find(|existing| !entry.label.is_empty() && existing.label == entry.label)
+        {
+            Some(existing) => *existing = entry,
+            None => self.report.entries.push(entry),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_report(&mut self) {
+        let camera = match self.connection_status {
+            ConnectionStatus::Connected(ref port) => port.name.clone(),
+            _ => tr("Disconnected").to_owned(),
+        };
+        let metadata = [
+            (
+                "Generated",
+                report::format_time(std::time::SystemTime::now()),
+            ),
+            (
+                "Application",
+                format!("tiop01-gui {}", env!("CARGO_PKG_VERSION")),
+            ),
+            ("Camera", camera),
+            (
+                "Emissivity",
+                format_number(self.settings.emissivity.to_f64() / 100.0, 2),
+            ),
+            (
+                "Color map",
+                tr(&self.settings.colormap.to_string()).to_owned(),
+            ),
+            (
+                "Filtering method",
+                tr(&self.settings.filtering_method.to_string()).to_owned(),
+            ),
+        ];
+
+        let result = self
+            .report
+            .to_html(&metadata, self.ui_settings.precision)
+            .and_then(|html| {
+                #[cfg(target_os = "android")]
+                {
+                    crate::android::save_report(&html)?;
+                    Ok(crate::android::REPORT_DIRECTORY.to_owned())
+                }
+                #[cfg(not(target_os = "android"))]
+                {
+                    std::fs::write(&self.report_path, html)?;
+                    Ok(self.report_path.clone())
+                }
+            });
+
+        self.report_message = Some(match result {
+            Ok(path) => format!("{} {path}", tr("Saved to")),
+            Err(e) => {
+                log::error!("Failed to save the report: {e}");
+                format!("{}: {e}", tr("Failed to save the report"))
+            }
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn report_settings(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label(tr("Title"));
+            ui.text_edit_singleline(&mut self.report.title);
+        });
+        ui.label(tr("Notes"));
+        ui.text_edit_multiline(&mut self.report.notes);
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(tr("Note"));
+            ui.text_edit_singleline(&mut self.report_note);
+        });
+        if ui
+            .add_enabled(
+                self.shown_image.is_some(),
+                egui::Button::new(tr("Add snapshot")),
+            )
+            .clicked()
+        {
+            self.add_to_report(String::new(), self.marker_values());
+        }
+
+        let mut removed = None;
+        for (index, entry) in self.report.entries.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{}. {}",
+                    index + 1,
+                    report::format_time(entry.taken_at)
+                ));
+                if !entry.label.is_empty() {
+                    ui.strong(&entry.label);
+                }
+                if !entry.note.is_empty() {
+                    ui.label(&entry.note);
+                }
+                if ui.small_button(tr("Remove")).clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.report.entries.remove(index);
+        }
+
+        ui.separator();
+        self.hot_component_settings(ui);
+
+        ui.separator();
+        ui.label(tr("Inspection points (one per line)"));
+        ui.text_edit_multiline(&mut self.survey_points);
+        if ui
+            .add_enabled(self.survey.is_none(), egui::Button::new(tr("Start survey")))
+            .clicked()
+        {
+            self.survey = Survey::new(&self.survey_points);
+        }
+
+        ui.separator();
+        #[cfg(not(target_os = "android"))]
+        ui.horizontal(|ui| {
+            ui.label(tr("File"));
+            ui.text_edit_singleline(&mut self.report_path);
+        });
+        ui.horizontal(|ui| {
+            let has_entries = !self.report.entries.is_empty();
+
+            if ui
+                .add_enabled(has_entries, egui::Button::new(tr("Generate report")))
+                .clicked()
+            {
+                self.save_report();
+            }
+            if ui
+                .add_enabled(has_entries, egui::Button::new(tr("Clear")))
+                .clicked()
+            {
+                self.report.entries.clear();
+            }
+        });
+
+        if let Some(ref message) = self.report_message {
+            ui.label(message);
+        }
+    }
+
+    /// Walks through the inspection points, capturing each into the report.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn hot_component_settings(&mut self, ui: &mut Ui) {
+        ui.strong(tr("Hot components"));
+        ui.add(egui::Slider::new(&mut self.hot_component_count, 1..=10).text(tr("Components")));
+        ui.add(
+            egui::Slider::new(&mut self.hot_component_warm_up, 0.0..=600.0)
+                .suffix(" s")
+                .text(tr("Warm-up")),
+        );
+
+        match self.hot_component_scan_at {
+            Some(at) => {
+                ui.horizontal(|ui| {
+                    let left = at.saturating_duration_since(web_time::Instant::now());
+                    ui.label(format!("{} {} s", tr("Capturing in"), left.as_secs()));
+
+                    if ui.button(tr("Cancel")).clicked() {
+                        self.hot_component_scan_at = None;
+                    }
+                });
+            }
+            None => {
+                let connected = matches!(self.connection_status, ConnectionStatus::Connected(_));
+
+                if ui
+                    .add_enabled(connected, egui::Button::new(tr("Find hot components")))
+                    .clicked()
+                {
+                    self.hot_component_scan_at = Some(
+                        web_time::Instant::now()
+                            + web_time::Duration::from_secs_f64(self.hot_component_warm_up),
+                    );
+                }
+            }
+        }
+
+        if !self.hot_components.is_empty() {
+            egui::Grid::new("hot_components")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong(tr("Marker"));
+                    ui.strong(tr("Pixel"));
+                    ui.strong(tr("Temperature"));
+                    ui.end_row();
+
+                    for (label, [x, y], value) in &self.hot_components {
+                        ui.label(label);
+                        ui.label(format!("{x}, {y}"));
+                        ui.label(format!(
+                            "{} °C",
+                            format_number(*value, self.ui_settings.precision)
+                        ));
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+
+    /// Once the warm-up is over, finds the hottest distinct spots of the image and takes it with
+    /// their temperatures into the report.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_hot_component_scan(&mut self, ctx: &egui::Context) {
+        /// Spots closer than this (in pixels) are taken for the same component
+        const MIN_DISTANCE: usize = 3;
+
+        let Some(at) = self.hot_component_scan_at else {
+            return;
+        };
+
+        let now = web_time::Instant::now();
+        if now < at {
+            ctx.request_repaint_after((at - now).min(web_time::Duration::from_secs(1)));
+            return;
+        }
+
+        let Some(ref values) = self.values else {
+            return;
+        };
+
+        self.hot_component_scan_at = None;
+        self.hot_components =
+            image_utils::hot_spots(values, self.hot_component_count, MIN_DISTANCE)
+                .into_iter()
+                .enumerate()
+                .map(|(index, pixel)| {
+                    let value = f64::from(values.get(pixel).as_slice()[0]) / 10.0;
+                    (format!("H{}", index + 1), pixel, value)
+                })
+                .collect();
+
+        self.add_to_report(tr("Hot components").to_owned(), self.hot_components.clone());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn survey_window(&mut self, ctx: &egui::Context) {
+        let Some(ref mut survey) = self.survey else {
+            return;
+        };
+
+        let mut open = true;
+        let mut capture = None;
+        let mut generate = false;
+
+        egui::Window::new(tr("Survey"))
+            .id(egui::Id::new("survey"))
+            .open(&mut open)
+            .show(ctx, |ui| {
+                ui.label(format!(
+                    "{} / {}",
+                    survey.current.min(survey.points.len() - 1) + 1,
+                    survey.points.len()
+                ));
+
+                match survey.current_point() {
+                    Some(point) => {
+                        ui.heading(point);
+                        ui.horizontal(|ui| {
+                            ui.label(tr("Note"));
+                            ui.text_edit_singleline(&mut self.report_note);
+                        });
+                    }
+                    None => {
+                        ui.label(tr("All points have been inspected."));
+                    }
+                }
+
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(survey.current > 0, egui::Button::new(tr("Back")))
+                        .clicked()
+                    {
+                        survey.previous();
+                    }
+
+                    if let Some(point) = survey.current_point() {
+                        if ui
+                            .add_enabled(
+                                self.shown_image.is_some(),
+                                egui::Button::new(tr("Capture")),
+                            )
+                            .clicked()
+                        {
+                            capture = Some(point.to_owned());
+                            survey.next();
+                        }
+                        if ui.button(tr("Skip")).clicked() {
+                            survey.next();
+                        }
+                    } else {
+                        generate = ui.button(tr("Generate report")).clicked();
+                    }
+                });
+            });
+
+        if let Some(point) = capture {
+            self.add_to_report(point, self.marker_values());
+        }
+        if generate {
+            self.save_report();
+            self.survey = None;
+        } else if !open {
+            self.survey = None;
+        }
+    }
+
+    fn alarm_settings(&mut self, ui: &mut Ui) {
+        let alarm = &mut self.alarm_settings;
+
+        ui.checkbox(&mut alarm.enabled, tr("Enabled"));
+        ui.add(
+            egui::Slider::new(&mut alarm.threshold, -20.0..=300.0)
+                .suffix(" °C")
+                .text(tr("Threshold")),
+        );
+
+        #[cfg(target_os = "android")]
+        ui.checkbox(&mut alarm.vibrate, tr("Vibrate"));
+
+        #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+        {
+            ui.checkbox(&mut alarm.sound, tr("Sound"));
+
+            let mut test = false;
+
+            ui.add_enabled_ui(alarm.sound, |ui| {
+                ui.horizontal(|ui| {
+                    ui.combobox_from_iter(AlarmTone::iter(), &mut alarm.tone, "Tone");
+                    test = ui.button(tr("Test")).clicked();
+                });
+                ui.add(egui::Slider::new(&mut alarm.volume, 0.0..=1.0).text(tr("Volume")));
+                ui.checkbox(&mut alarm.repeat, tr("Repeat until acknowledged"));
+                ui.add_enabled(
+                    alarm.repeat,
+                    egui::Slider::new(&mut alarm.repeat_interval, 2.0..=120.0)
+                        .suffix(" s")
+                        .text(tr("Repeat every")),
+                );
+            });
+
+            if test {
+                self.play_alarm_tone();
+            }
+            if let Some(ref error) = self.alarm_sound_error {
+                ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.alarm_log_settings(ui);
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn sonification_settings(&mut self, ui: &mut Ui) {
+        let sonification = &mut self.sonification;
+
+        ui.checkbox(&mut sonification.enabled, tr("Enabled"));
+        ui.combobox_from_iter(
+            SonificationSource::iter(),
+            &mut sonification.source,
+            "Follow",
+        );
+        #[cfg(not(target_os = "android"))]
+        ui.combobox_from_iter(
+            crate::sonification::SonificationMode::iter(),
+            &mut sonification.mode,
+            "Mode",
+        );
+        ui.add(
+            egui::Slider::new(&mut sonification.low, -20.0..=300.0)
+                .suffix(" °C")
+                .text(tr("Silent below")),
+        );
+        ui.add(
+            egui::Slider::new(&mut sonification.high, -20.0..=300.0)
+                .suffix(" °C")
+                .text(tr("Maximum at")),
+        );
+
+        if let Some(ref error) = self.sonifier_error {
+            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn osc_settings(&mut self, ui: &mut Ui) {
+        let mut reconnect = ui.checkbox(&mut self.osc.enabled, tr("Enabled")).changed();
+
+        ui.horizontal(|ui| {
+            ui.label(tr("Address"));
+            if ui.text_edit_singleline(&mut self.osc_address).lost_focus() {
+                self.osc_address = self.osc_address.trim().to_owned();
+                reconnect |= self.osc_address != self.osc.address;
+                self.osc.address = self.osc_address.clone();
+            }
+        });
+        ui.add(
+            egui::Slider::new(&mut self.osc.grid_size, 1..=THERMAL_IMAGE_WIDTH)
+                .text(tr("Grid size")),
+        );
+
+        if reconnect {
+            self.connect_osc();
+        }
+
+        if let Some(ref error) = self.osc_error {
+            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+    }
+
+    /// Opens the socket of the OSC output while it's enabled, closes it otherwise.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn connect_osc(&mut self) {
+        self.osc_sender = None;
+        self.osc_error = None;
+
+        if self.osc.enabled {
+            match OscSender::new(&self.osc.address) {
+                Ok(sender) => self.osc_sender = Some(sender),
+                Err(e) => {
+                    log::error!("Failed to start the OSC output: {e:#}");
+                    self.osc_error = Some(format!("{e:#}"));
+                }
+            }
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send_osc(&mut self, frame: &Frame) {
+        let Some(ref sender) = self.osc_sender else {
+            return;
+        };
+
+        if let Err(e) = sender.send(&frame.values, frame.min, frame.max, self.osc.grid_size) {
+            log::error!("Failed to send OSC: {e}");
+            self.osc_error = Some(e.to_string());
+            self.osc_sender = None;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn modbus_settings(&mut self, ui: &mut Ui) {
+        let mut enabled = self.modbus.is_some();
+
+        ui.horizontal(|ui| {
+            let toggled = ui.checkbox(&mut enabled, tr("Enabled")).changed();
+            ui.add_enabled_ui(!enabled, |ui| {
+                ui.label(tr("Port"));
+                ui.add(egui::DragValue::new(&mut self.modbus_port));
+            });
+
+            if toggled {
+                self.modbus = None;
+                self.modbus_error = None;
+
+                if enabled {
+                    match ModbusServer::start(self.modbus_port) {
+                        Ok(server) => self.modbus = Some(server),
+                        Err(e) => {
+                            log::error!("Failed to start the Modbus server: {e:#}");
+                            self.modbus_error = Some(format!("{e:#}"));
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(ref error) = self.modbus_error {
+            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+    }
+
+    /// Writes the values of `frame` to the Modbus registers, and the flags once an update when
+    /// there's no frame.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_modbus(&self, frame: Option<&Frame>) {
+        let Some(ref server) = self.modbus else {
+            return;
+        };
+        let connected = matches!(self.connection_status, ConnectionStatus::Connected(_));
+
+        server.update(|registers| {
+            if let Some(frame) = frame {
+                let markers = self.image_view.marker_values(&frame.values);
+
+                registers[modbus::MAX] = modbus::temperature(frame.max);
+                registers[modbus::MIN] = modbus::temperature(frame.min);
+                registers[modbus::FRAME_COUNTER] = registers[modbus::FRAME_COUNTER].wrapping_add(1);
+                registers[modbus::MARKER_COUNT] = markers.len() as u16;
+                for (index, register) in registers[modbus::MARKERS..].iter_mut().enumerate() {
+                    *register = markers
+                        .get(index)
+                        .map_or(modbus::NO_VALUE, |&(_, _, value)| {
+                            modbus::temperature(value)
+                        });
+                }
+            } else if !connected {
+                registers[modbus::MAX] = modbus::NO_VALUE;
+                registers[modbus::MIN] = modbus::NO_VALUE;
+                registers[modbus::MARKERS..].fill(modbus::NO_VALUE);
+            }
+
+            let flags = [
+                (self.alarm_settings.enabled, modbus::ALARM_ENABLED),
+                (self.alarm_active, modbus::ALARM_ACTIVE),
+                (
+                    self.alarm_active && self.alarm_acknowledged,
+                    modbus::ALARM_ACKNOWLEDGED,
+                ),
+                (connected, modbus::CONNECTED),
+            ];
+            registers[modbus::FLAGS] = flags
+                .into_iter()
+                .filter(|&(set, _)| set)
+                .fold(0, |flags, (_, flag)| flags | flag);
+        });
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn http_settings(&mut self, ui: &mut Ui) {
+        let mut enabled = self.http.is_some();
+
+        ui.horizontal(|ui| {
+            let toggled = ui.checkbox(&mut enabled, tr("Enabled")).changed();
+            ui.add_enabled_ui(!enabled, |ui| {
+                ui.label(tr("Port"));
+                ui.add(egui::DragValue::new(&mut self.http_port));
+            });
+
+            if toggled {
+                self.http = None;
+                self.http_error = None;
+
+                if enabled {
+                    match HttpServer::start(self.http_port) {
+                        Ok(server) => self.http = Some(server),
+                        Err(e) => {
+                            log::error!("Failed to start the HTTP server: {e:#}");
+                            self.http_error = Some(format!("{e:#}"));
+                        }
+                    }
+                }
+            }
+        });
+
+        if let Some(ref error) = self.http_error {
+            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+    }
+
+    /// Hands `frame` to the HTTP server, and clears what it serves once the camera disconnects.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_http(&self, frame: Option<&Frame>) {
+        let Some(ref server) = self.http else {
+            return;
+        };
+
+        match frame {
+            Some(frame) => {
+                let stats = self.json_stats(frame);
+                server.update(|status| {
+                    status.stats = Some(stats);
+                    status.image = Some(frame.image.clone());
+                });
+            }
+            None if !matches!(self.connection_status, ConnectionStatus::Connected(_)) => {
+                server.update(|status| *status = http::Status::default());
+            }
+            None => {}
+        }
+    }
+
+    /// Feeds the followed temperature to the sonification, starting and stopping its audio
+    /// output as it's enabled and disabled.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn update_sonification(&mut self, ctx: &egui::Context) {
+        if !self.sonification.enabled {
+            self.sonifier = None;
+            self.sonifier_error = None;
+            return;
+        }
+
+        if self.sonifier.is_none() && self.sonifier_error.is_none() {
+            match Sonifier::new() {
+                Ok(sonifier) => self.sonifier = Some(sonifier),
+                Err(e) => {
+                    log::error!("Failed to start the sonification: {e}");
+                    self.sonifier_error = Some(e.to_string());
+                }
+            }
+        }
+
+        let connected = matches!(self.connection_status, ConnectionStatus::Connected(_));
+        let temperature = match self.sonification.source {
+            _ if !connected || self.paused => None,
+            SonificationSource::Max => Some(self.max),
+            SonificationSource::Center => self.values.as_ref().map(|values| {
+                let center = [THERMAL_IMAGE_WIDTH / 2, THERMAL_IMAGE_HEIGHT / 2];
+                f64::from(values.get(center).as_slice()[0]) / 10.0
+            }),
+        };
+        let level = temperature.and_then(|temperature| self.sonification.level(temperature));
+
+        if let Some(ref mut sonifier) = self.sonifier {
+            sonifier.update(ctx, &self.sonification, level);
+        }
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn webcam_picker(&mut self, ui: &mut Ui) {
+        let webcams = self
+            .webcams
+            .get_or_insert_with(crate::webcam::available_webcams);
+        let previous = self.fusion.webcam;
+        let mut refresh = false;
+
+        ui.horizontal(|ui| {
+            if webcams.is_empty() {
+                ui.label(tr("No webcam found"));
+            } else {
+                let selected = webcams
+                    .iter()
+                    .find(|webcam| webcam.index == self.fusion.webcam)
+                    .map_or_else(
+                        || self.fusion.webcam.to_string(),
+                        |webcam| webcam.name.clone(),
+                    );
+
+                egui::ComboBox::from_label(tr("Webcam"))
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        for webcam in webcams.iter() {
+                            ui.selectable_value(
+                                &mut self.fusion.webcam,
+                                webcam.index,
+                                &webcam.name,
+                            );
+                        }
+                    });
+            }
+
+            refresh = ui.button(tr("Refresh")).clicked();
+        });
+
+        if refresh {
+            self.webcams = None;
+        }
+
+        if previous != self.fusion.webcam {
+            self.visible_camera = None;
+            self.visible_camera_opened_at = None;
+        }
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn fusion_settings(&mut self, ui: &mut Ui) {
+        #[cfg(not(target_os = "android"))]
+        self.webcam_picker(ui);
+
+        let fusion = &mut self.fusion;
+        let blend = fusion.mode == FusionMode::Blend;
+        // offset and scale are part of a calibrated transform
+        let manual = blend && fusion.homography.is_none();
+
+        ui.checkbox(&mut fusion.enabled, tr("Camera overlay"));
+        ui.combobox_from_iter(FusionMode::iter(), &mut fusion.mode, "Mode");
+        ui.add_enabled(
+            blend,
+            egui::Slider::new(&mut fusion.opacity, 0.0..=1.0).text(tr("Opacity")),
+        );
+        ui.add_enabled(
+            manual,
+            egui::Slider::new(&mut fusion.offset.x, -0.5..=0.5).text(tr("Horizontal offset")),
+        );
+        ui.add_enabled(
+            manual,
+            egui::Slider::new(&mut fusion.offset.y, -0.5..=0.5).text(tr("Vertical offset")),
+        );
+        ui.add_enabled(
+            manual,
+            egui::Slider::new(&mut fusion.scale, 0.5..=3.0).text(tr("Scale")),
+        );
+
+        ui.horizontal(|ui| {
+            let calibrate = blend && fusion.enabled && self.calibration.is_none();
+            if ui
+                .add_enabled(calibrate, egui::Button::new(tr("Calibrate alignment")))
+                .clicked()
+            {
+                self.calibration = Some(Calibration::default());
+            }
+
+            if ui
+                .add_enabled(blend, egui::Button::new(tr("Reset alignment")))
+                .clicked()
+            {
+                fusion.reset_alignment();
+            }
+        });
+
+        if let Some(ref error) = self.visible_camera_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    }
+
+    /// Shows an image for picking calibration points, returns the clicked texture coordinates.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn calibration_image(
+        ui: &mut Ui,
+        texture: &egui::TextureHandle,
+        points: &[egui::Pos2],
+        pending: Option<egui::Pos2>,
+    ) -> Option<egui::Pos2> {
+        let [width, height] = texture.size();
+        let aspect_ratio = width as f32 / height as f32;
+        let size = Self::fit_image_size(ui.available_size(), aspect_ratio, 0.0);
+
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        painter.image(texture.id(), rect, FULL_UV_RECT, egui::Color32::WHITE);
+
+        let to_screen = |uv: egui::Pos2| rect.min + uv.to_vec2() * rect.size();
+        for (number, &point) in points.iter().enumerate() {
+            let pos = to_screen(point);
+            painter.circle_stroke(pos, 6.0, egui::Stroke::new(2.0, egui::Color32::WHITE));
+            painter.text(
+                pos + egui::vec2(8.0, 0.0),
+                egui::Align2::LEFT_CENTER,
+                (number + 1).to_string(),
+                egui::FontId::proportional(14.0),
+                egui::Color32::WHITE,
+            );
+        }
+        if let Some(point) = pending {
+            painter.circle_filled(to_screen(point), 4.0, ui.visuals().selection.bg_fill);
+        }
+
+        response
+            .clicked()
+            .then(|| response.interact_pointer_pos())
+            .flatten()
+            .map(|pos| ((pos - rect.min) / rect.size()).to_pos2())
+    }
+
+    /// Lets the user click the same points in both images and fits the overlay transform to them.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn calibration_window(&mut self, ctx: &egui::Context) {
+        let Some(ref mut calibration) = self.calibration else {
+            return;
+        };
+
+        let mut open = true;
+        let mut apply = false;
+
+        egui::Window::new(tr("Alignment calibration"))
+            .id(egui::Id::new("alignment_calibration"))
+            .open(&mut open)
+            .default_size([640.0, 400.0])
+            .show(ctx, |ui| {
+                ui.label(tr(
+                    "Click a point in the camera image, then the same point in the thermal image. \
+                     At least four pairs are needed.",
+                ));
+
+                ui.columns(2, |columns| {
+                    let (visible, thermal): (Vec<_>, Vec<_>) =
+                        calibration.pairs.iter().copied().unzip();
+
+                    match self.visible_texture {
+                        Some(ref texture) => {
+                            if let Some(point) = Self::calibration_image(
+                                &mut columns[0],
+                                texture,
+                                &visible,
+                                calibration.pending,
+                            ) {
+                                calibration.pending = Some(point);
+                            }
+                        }
+                        None => {
+                            columns[0].label(tr("Waiting for the camera"));
+                        }
+                    }
+
+                    if let Some(point) = Self::calibration_image(
+                        &mut columns[1],
+                        &self.thermal_image_texture,
+                        &thermal,
+                        None,
+                    ) {
+                        if let Some(visible) = calibration.pending.take() {
+                            calibration.pairs.push((visible, point));
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let undo = calibration.pending.is_some() || !calibration.pairs.is_empty();
+                    if ui
+                        .add_enabled(undo, egui::Button::new(tr("Undo")))
+                        .clicked()
+                        && calibration.pending.take().is_none()
+                    {
+                        calibration.pairs.pop();
+                    }
+
+                    apply = ui
+                        .add_enabled(calibration.pairs.len() >= 4, egui::Button::new(tr("Apply")))
+                        .clicked();
+                });
+            });
+
+        if apply {
+            match Homography::from_points(&calibration.pairs) {
+                Some(homography) => {
+                    self.fusion.homography = Some(homography);
+                    self.calibration = None;
+                }
+                None => self.visible_camera_error = Some(tr("Points can't be aligned").to_owned()),
+            }
+        } else if !open {
+            self.calibration = None;
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    fn open_visible_camera(&self, first_attempt: bool) -> anyhow::Result<VisibleCamera> {
+        // ask for the permission only once, the dialog would keep popping up otherwise
+        if !crate::android::camera_permission(first_attempt)? {
+            anyhow::bail!("{}", tr("Camera permission required"));
+        }
+
+        VisibleCamera::open()
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn open_visible_camera(&self, _first_attempt: bool) -> anyhow::Result<VisibleCamera> {
+        VisibleCamera::open(self.fusion.webcam)
+    }
+
+    /// Opens the camera while the overlay is enabled and uploads its latest image, which also
+    /// goes to the recording. A recording with images of the camera plays them back instead.
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn update_visible_camera(&mut self, ctx: &egui::Context) {
+        const RETRY_INTERVAL: web_time::Duration = web_time::Duration::from_secs(2);
+        const FRAME_INTERVAL: web_time::Duration = web_time::Duration::from_millis(33);
+        // every image is encoded as a PNG, a few a second are enough to follow the frames
+        const RECORDING_INTERVAL: web_time::Duration = web_time::Duration::from_millis(200);
+
+        if !self.fusion.enabled {
+            self.visible_camera = None;
+            self.visible_camera_opened_at = None;
+            self.visible_camera_error = None;
+            self.visible_texture = None;
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            {
+                self.shown_visible_frame = None;
+            }
+            return;
+        }
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if let (Source::Playback, Some(replay)) = (self.source, self.playback.clone()) {
+            if replay.has_visible_frames() {
+                self.visible_camera = None;
+                self.visible_camera_error = None;
+                self.show_recorded_visible_frame(ctx, &replay);
+                ctx.request_repaint_after(FRAME_INTERVAL);
+                return;
+            }
+        }
+
+        if self
+            .visible_camera
+            .as_ref()
+            .is_some_and(VisibleCamera::is_disconnected)
+        {
+            self.visible_camera = None;
+        }
+
+        let retry = self
+            .visible_camera_opened_at
+            .is_none_or(|opened_at| opened_at.elapsed() >= RETRY_INTERVAL);
+
+        if self.visible_camera.is_none() && retry {
+            let first_attempt = self.visible_camera_opened_at.is_none();
+            self.visible_camera_opened_at = Some(web_time::Instant::now());
+
+            match self.open_visible_camera(first_attempt) {
+                Ok(camera) => {
+                    self.visible_camera = Some(camera);
+                    self.visible_camera_error = None;
+                }
+                Err(e) => {
+                    log::error!("Failed to open the camera: {e}");
+                    self.visible_camera_error = Some(e.to_string());
+                }
+            }
+        }
+
+        let Some(ref mut camera) = self.visible_camera else {
+            ctx.request_repaint_after(RETRY_INTERVAL);
+            return;
+        };
+
+        if let Some(image) = camera.latest_frame() {
+            let record = self.recording.is_some()
+                && self
+                    .visible_recorded_at
+                    .is_none_or(|recorded_at| recorded_at.elapsed() >= RECORDING_INTERVAL);
+            if record {
+                let now = web_time::Instant::now();
+                self.visible_recorded_at = Some(now);
+                let _ = self
+                    .sender
+                    .send(UiMessage::RecordVisible(now, image.clone()));
+            }
+
+            self.set_visible_image(ctx, image);
+        }
+
+        ctx.request_repaint_after(FRAME_INTERVAL);
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn set_visible_image(&mut self, ctx: &egui::Context, image: egui::ColorImage) {
+        match self.visible_texture {
+            Some(ref mut texture) => texture.set(image, TextureOptions::LINEAR),
+            None => {
+                self.visible_texture =
+                    Some(ctx.load_texture("visible_image", image, TextureOptions::LINEAR));
+            }
+        }
+    }
+
+    /// Uploads the visible image recorded up to the frame being played back, when it changes.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn show_recorded_visible_frame(
+        &mut self,
+        ctx: &egui::Context,
+        replay: &crate::debug_bundle::Replay,
+    ) {
+        let Some((index, frame)) = replay.visible_frame() else {
+            return;
+        };
+        if self.shown_visible_frame == Some(index) {
+            return;
+        }
+
+        self.shown_visible_frame = Some(index);
+        match frame.image() {
+            Ok(image) => self.set_visible_image(ctx, image),
+            Err(e) => log::error!("Failed to decode a recorded visible image: {e}"),
+        }
+    }
+
+    /// The main camera and the additional ones side by side, with the range over all of them
+    /// below.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn camera_grid(&mut self, ui: &mut Ui) {
+        let count = self.cameras.len() + 1;
+        let columns = (count as f32).sqrt().ceil() as usize;
+        let rows = count.div_ceil(columns);
+        let spacing = ui.spacing().item_spacing;
+        let stats_height = ui.text_style_height(&egui::TextStyle::Body) + spacing.y;
+        let available = ui.available_size() - egui::vec2(0.0, stats_height);
+        let cell_size = egui::vec2(
+            (available.x - spacing.x * (columns - 1) as f32) / columns as f32,
+            (available.y - spacing.y * (rows - 1) as f32) / rows as f32,
+        )
+        .max(egui::Vec2::ZERO);
+
+        for row in 0..rows {
+            ui.horizontal(|ui| {
+                for index in row * columns..count.min((row + 1) * columns) {
+                    ui.allocate_ui_with_layout(
+                        cell_size,
+                        egui::Layout::top_down(egui::Align::Center),
+                        |ui| {
+                            ui.set_min_size(cell_size);
+                            self.grid_cell(ui, index);
+                        },
+                    );
+                }
+            });
+        }
+
+        self.combined_stats(ui);
+    }
+
+    /// Camera number `index + 1` of the grid, the main one first.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn grid_cell(&mut self, ui: &mut Ui, index: usize) {
+        let (status, error, range) = match index.checked_sub(1) {
+            None => (
+                &self.connection_status,
+                &self.connection_error,
+                matches!(self.connection_status, ConnectionStatus::Connected(_))
+                    .then_some((self.min, self.max)),
+            ),
+            Some(index) => {
+                let camera = &self.cameras[index];
+                (
+                    &camera.connection_status,
+                    &camera.connection_error,
+                    camera.range,
+                )
+            }
+        };
+
+        let mut caption = format!("{} {}", tr("Camera"), index + 1);
+        match (status, error, range) {
+            (ConnectionStatus::Connected(port), _, Some((min, max))) => {
+                caption += &format!(
+                    " ({}), {}: {}, {}: {}",
+                    port.name,
+                    tr("Min"),
+                    format_number(min, self.ui_settings.precision),
+                    tr("max"),
+                    format_number(max, self.ui_settings.precision),
+                );
+            }
+            (ConnectionStatus::Connected(port), _, None) => caption += &format!(" ({})", port.name),
+            (_, Some(error), _) => caption += &format!(": {}", tr(error.kind.title())),
+            _ => caption += &format!(": {}", tr("Disconnected")),
+        }
+        ui.add(egui::Label::new(caption).truncate());
+
+        match index.checked_sub(1) {
+            None => self.main_image(ui),
+            Some(index) => {
+                let aspect_ratio = THERMAL_IMAGE_WIDTH as f32 / THERMAL_IMAGE_HEIGHT as f32;
+                let spacing = ui.spacing().item_spacing.y;
+                let size = Self::fit_image_size(ui.available_size(), aspect_ratio, spacing);
+
+                ui.image(SizedTexture::new(self.cameras[index].texture.id(), size));
+            }
+        }
+    }
+
+    /// Lowest and highest temperature over all the connected cameras, and which camera sees it.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn combined_stats(&self, ui: &mut Ui) {
+        let main = matches!(self.connection_status, ConnectionStatus::Connected(_))
+            .then_some((self.min, self.max));
+        let ranges: Vec<(usize, (f64, f64))> = std::iter::once(main)
+            .chain(self.cameras.iter().map(|camera| camera.range))
+            .enumerate()
+            .filter_map(|(index, range)| Some((index + 1, range?)))
+            .collect();
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: {}/{}",
+                tr("Connected"),
+                ranges.len(),
+                self.cameras.len() + 1
+            ));
+
+            let lowest = ranges.iter().min_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+            let highest = ranges.iter().max_by(|a, b| a.1 .1.total_cmp(&b.1 .1));
+            if let (Some(&(lowest, (min, _))), Some(&(highest, (_, max)))) = (lowest, highest) {
+                ui.separator();
+                ui.label(format!(
+                    "{}: {} ({} {lowest}), {}: {} ({} {highest})",
+                    tr("Min"),
+                    format_number(min, self.ui_settings.precision),
+                    tr("Camera"),
+                    tr("max"),
+                    format_number(max, self.ui_settings.precision),
+                    tr("Camera"),
+                ));
+            }
+        });
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn camera_settings(&mut self, ui: &mut Ui) {
+        let mut removed = None;
+
+        for (index, camera) in self.cameras.iter_mut().enumerate() {
+            ui.push_id(index, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(format!("{} {}", tr("Camera"), index + 2));
+                    if ui.small_button(tr("Remove")).clicked() {
+                        removed = Some(index);
+                    }
+                });
+
+                // direct USB only finds the first camera
+                let transport = camera.settings.transport;
+                ui.combobox_from_iter(
+                    thermal::Transport::iter()
+                        .filter(|&transport| transport != thermal::Transport::Usb),
+                    &mut camera.settings.transport,
+                    "Transport",
+                );
+
+                let network = camera.settings.transport == thermal::Transport::Network;
+                let hint = if network {
+                    "host:4001"
+                } else if cfg!(target_os = "windows") {
+                    "COM4"
+                } else {
+                    "/dev/ttyACM1"
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label(tr(if network {
+                        "Network address"
+                    } else {
+                        "Serial port"
+                    }));
+
+                    let response =
+                        ui.add(egui::TextEdit::singleline(&mut camera.address).hint_text(hint));
+                    if response.lost_focus() || transport != camera.settings.transport {
+                        camera.apply_address();
+                    }
+                });
+
+                ui.combobox_from_iter(ColorMap::iter(), &mut camera.settings.colormap, "Color map");
+            });
+            ui.separator();
+        }
+
+        if let Some(index) = removed {
+            // dropping the camera stops its producer
+            self.cameras.remove(index);
+        }
+
+        if ui.button(tr("Add camera")).clicked() {
+            self.cameras.push(camera_grid::Camera::new(
+                ui.ctx(),
+                self.next_camera_id,
+                self.ui_settings.max_frame_rate,
+            ));
+            self.next_camera_id += 1;
+        }
+    }
+
+    /// Stops the producer and starts one reading `source` with the current settings. The old
+    /// producer closes its port after its current read, which is waited for so that the new
+    /// source can open the same device, but not for longer than [`STOP_TIMEOUT`].
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn switch_source(&mut self, ctx: &egui::Context, source: Source) {
+        let _ = self.sender.send(UiMessage::Stop);
+        self.stopped_producers
+            .retain(|thread| !thread.is_finished());
+        if let Some(producer_thread) = self.producer_thread.take() {
+            let deadline = web_time::Instant::now() + STOP_TIMEOUT;
+            if let Err(producer_thread) = join_producer(producer_thread, deadline) {
+                log::warn!("The producer hasn't stopped in time, opening {source} anyway");
+                self.stopped_producers.push(producer_thread);
+            }
+        }
+
+        let (ui_sender, worker_receiver) = mpsc::channel();
+        let (worker_sender, ui_receiver) = mpsc::channel();
+        let _ = ui_sender.send(UiMessage::ChangeSettings(self.settings.clone()));
+        let _ = ui_sender.send(UiMessage::MaxFrameRate(self.ui_settings.max_frame_rate));
+        let _ = ui_sender.send(UiMessage::Pause(self.paused));
+
+        let egui_ctx = ctx.clone();
+        let frame_pool = self.frame_pool.clone();
+        let playback = self.playback.clone();
+        let producer_thread = thread::Builder::new()
+            .name("thermal".into())
+            .spawn(move || {
+                producer_main(
+                    egui_ctx,
+                    worker_sender,
+                    worker_receiver,
+                    frame_pool,
+                    source,
+                    playback,
+                );
+            });
+
+        match producer_thread {
+            Ok(producer_thread) => {
+                self.producer_thread = Some(producer_thread);
+                self.sender = ui_sender;
+                self.receiver = ui_receiver;
+                // replaced by the new producer's
+                self.commands = thermal::CommandQueue::default();
+                self.recent.source = source.to_string();
+                self.save_recent();
+                self.shown_visible_frame = None;
+                // the old producer finishes its recording
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                if let Some(path) = self.recording.take() {
+                    self.recording_message = Some(format!("{} {}", tr("Saved to"), path.display()));
+                }
+                self.source = source;
+            }
+            Err(e) => {
+                log::error!("Failed to start the producer: {e}");
+                self.selected_source = self.source;
+                return;
+            }
+        }
+
+        // the old producer won't report its disconnection anymore
+        self.connection_status = ConnectionStatus::Disconnected;
+        self.connection_error = None;
+        self.data_rate = 0.0;
+        self.alarm_active = false;
+        if let Some(values) = self.values.take() {
+            self.frame_pool.recycle_gray_image(values);
+        }
+        self.hot_spot_trail.clear();
+        self.thermal_image_texture = Self::load_texture_from_black_thermal_image(ctx);
+
+        if let (Source::Playback, Some(replay)) = (source, &self.playback) {
+            // the replayed session is processed the way it was recorded
+            if let Err(e) = replay.apply_settings(&mut self.settings) {
+                log::error!("Failed to apply the settings of {}: {e}", replay.name);
+            }
+        }
+        self.switch_source_profile();
+        self.sync_edited_settings();
+    }
+
+    /// Names the source for its profile: the camera by its transport and port or address, a
+    /// playback by its file.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn source_profile_name(&self) -> String {
+        match self.source {
+            Source::Camera => match self.settings.transport {
+                thermal::Transport::Serial => {
+                    format!("serial-{}", self.settings.port.as_deref().unwrap_or("auto"))
+                }
+                thermal::Transport::Usb => "usb".to_owned(),
+                thermal::Transport::Network => {
+                    format!("network-{}", self.settings.network_address)
+                }
+            },
+            Source::Simulated => "simulated".to_owned(),
+            Source::Playback => format!(
+                "playback-{}",
+                self.playback
+                    .as_ref()
+                    .map_or("", |replay| replay.name.as_str())
+            ),
+        }
+    }
+
+    /// Remembers the settings of the previous source and applies the ones of the current source,
+    /// once the source, its port or its address has changed.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn switch_source_profile(&mut self) {
+        let name = self.source_profile_name();
+        if name == self.source_profile {
+            return;
+        }
+
+        // nothing to remember before the first source
+        if !self.source_profile.is_empty() {
+            if let Err(e) = profiles::save_source(&self.source_profile, &self.settings) {
+                log::error!("Failed to save the profile of {}: {e}", self.source_profile);
+            }
+        }
+        if let Err(e) = profiles::load_source(&name, &mut self.settings) {
+            log::error!("Failed to load the profile of {name}: {e}");
+        }
+        self.source_profile = name;
+    }
+
+    /// Picks the source of the frames, see [`App::switch_source`].
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn source_settings(&mut self, ui: &mut Ui) {
+        ui.combobox_from_iter(Source::iter(), &mut self.selected_source, "Source");
+
+        match self.selected_source {
+            Source::Playback => {
+                ui.horizontal(|ui| {
+                    ui.label(tr("File"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.playback_path)
+                            .hint_text("debug-bundle.zip, recording.tiop01"),
+                    );
+                });
+
+                if ui.button(tr("Play")).clicked() {
+                    let path = std::path::PathBuf::from(self.playback_path.trim());
+                    self.play(ui.ctx(), &path);
+                }
+
+                if let Some(ref error) = self.playback_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                self.recent_files(ui);
+            }
+            source if source != self.source => self.switch_source(ui.ctx(), source),
+            _ => {}
+        }
+
+        if ui
+            .checkbox(
+                &mut self.recent.resume,
+                tr("Resume the last source at startup"),
+            )
+            .on_hover_text(tr(
+                "Plays the most recent file again and applies the last measurement layout",
+            ))
+            .changed()
+        {
+            self.save_recent();
+        }
+    }
+
+    /// Plays the recording or debug bundle at `path` in place of the camera.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn play(&mut self, ctx: &egui::Context, path: &std::path::Path) {
+        match crate::debug_bundle::Replay::load(path) {
+            Ok(replay) => {
+                self.playback = Some(std::sync::Arc::new(replay));
+                self.playback_error = None;
+                self.recent.add_file(path);
+                self.switch_source(ctx, Source::Playback);
+            }
+            Err(e) => self.playback_error = Some(e.to_string()),
+        }
+    }
+
+    /// Lists the recently played files with a thumbnail of their first frame, a click plays one
+    /// again.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn recent_files(&mut self, ui: &mut Ui) {
+        if self.recent.files.is_empty() {
+            return;
+        }
+
+        ui.label(tr("Recent files"));
+        let mut reopened = None;
+
+        for path in self.recent.files.clone() {
+            ui.horizontal(|ui| {
+                if let Some(texture) = self.recent_thumbnail(ui.ctx(), &path) {
+                    ui.image(SizedTexture::new(
+                        texture.id(),
+                        egui::Vec2::splat(RECENT_THUMBNAIL_SIZE),
+                    ));
+                }
+
+                let name = path
+                    .file_name()
+                    .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+                if ui
+                    .button(name)
+                    .on_hover_text(path.display().to_string())
+                    .clicked()
+                {
+                    reopened = Some(path.clone());
+                }
+            });
+        }
+
+        if let Some(path) = reopened {
+            self.playback_path = path.display().to_string();
+            self.play(ui.ctx(), &path);
+        }
+    }
+
+    /// Colored with the color map of the moment the thumbnail is first shown.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn recent_thumbnail(
+        &mut self,
+        ctx: &egui::Context,
+        path: &std::path::Path,
+    ) -> Option<egui::TextureHandle> {
+        let colormap = self.settings.colormap.get_colormap();
+
+        self.recent_thumbnails
+            .entry(path.to_owned())
+            .or_insert_with(|| {
+                let image = crate::recent::first_frame(path)
+                    .and_then(|values| crate::recent::thumbnail(&values, &*colormap))
+                    .map_err(|e| log::warn!("No thumbnail of {}: {e}", path.display()))
+                    .ok()?;
+
+                Some(ctx.load_texture(
+                    format!("recent {}", path.display()),
+                    image,
+                    TextureOptions::NEAREST,
+                ))
+            })
+            .clone()
+    }
+
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn save_recent(&self) {
+        if let Err(e) = self.recent.save() {
+            log::error!("Failed to save the recent files: {e}");
+        }
+    }
+
+    /// The source [`crate::recent::Recent`] remembers, a playback of its most recent file. The
+    /// camera when that file can't be played anymore.
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    fn resumed_source(
+        recent: &crate::recent::Recent,
+    ) -> (Source, Option<std::sync::Arc<crate::debug_bundle::Replay>>) {
+        let source = Source::iter()
+            .find(|source| source.to_string() == recent.source)
+            .unwrap_or_default();
+        if source != Source::Playback {
+            return (source, None);
+        }
+
+        let Some(path) = recent.files.first() else {
+            return (Source::Camera, None);
+        };
+        match crate::debug_bundle::Replay::load(path) {
+            Ok(replay) => (Source::Playback, Some(std::sync::Arc::new(replay))),
+            Err(e) => {
+                log::error!("Failed to resume the playback of {}: {e}", path.display());
+                (Source::Camera, None)
+            }
+        }
+    }
+
+    fn device_settings(&mut self, ui: &mut Ui) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.source_settings(ui);
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        if self.source == Source::Camera {
+            ui.combobox_from_iter(
+                thermal::Transport::iter(),
+                &mut self.settings.transport,
+                "Transport",
+            );
+
+            if self.settings.transport == thermal::Transport::Serial {
+                let port = self.settings.port.as_deref().unwrap_or(tr("Auto-detect"));
+
+                if ui
+                    .button(format!("{}: {port}", tr("Serial port")))
+                    .clicked()
+                {
+                    self.toggle_port_picker();
+                }
+            }
+
+            ui.combobox_from_iter(
+                thermal::ControlLine::iter(),
+                &mut self.settings.dtr,
+                "DTR on open",
+            );
+            ui.combobox_from_iter(
+                thermal::ControlLine::iter(),
+                &mut self.settings.rts,
+                "RTS on open",
+            );
+
+            if self.settings.transport != thermal::Transport::Network {
+                // the port is reopened with the new timeout, so not on every step of a drag
+                let response = ui.add(
+                    egui::Slider::new(&mut self.serial_timeout, 0.1..=10.0)
+                        .logarithmic(true)
+                        .suffix(" s")
+                        .text(tr("Read timeout")),
+                );
+                if !response.dragged() {
+                    self.settings.serial_timeout = self.serial_timeout;
+                }
+            }
+        }
+
+        ui.combobox_from_iter(
+            thermal::ReadChunkSize::iter(),
+            &mut self.settings.read_chunk_size,
+            "Read size",
+        );
+
+        #[cfg(target_os = "ios")]
+        let network = true;
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        let network =
+            self.source == Source::Camera && self.settings.transport == thermal::Transport::Network;
+        #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+        if network {
+            ui.horizontal(|ui| {
+                ui.label(tr("Network address"));
+
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.network_address).hint_text("host:4001"),
+                );
+
+                if response.lost_focus() {
+                    self.settings
+                        .network_address
+                        .clone_from(&self.network_address);
+                }
+            });
+        }
+
+        #[cfg(target_os = "android")]
+        {
+            let mut direct_usb = self.settings.transport == thermal::Transport::Usb;
+
+            if ui.checkbox(&mut direct_usb, tr("Direct USB")).changed() {
+                self.settings.transport = if direct_usb {
+                    thermal::Transport::Usb
+                } else {
+                    thermal::Transport::Serial
+                };
+            }
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        if !crate::web::is_supported() {
+            ui.label(tr("This browser supports neither Web Serial nor WebUSB"));
+        } else if ui.button(tr("Select serial port")).clicked() {
+            crate::web::request_port(ui.ctx().clone());
+        }
+
+        ui.add(
+            egui::Slider::new(
+                &mut self.settings.emissivity,
+                Emissivity::MIN..=Emissivity::MAX,
+            )
+            .prefix("0.")
+            .text(tr("Emissivity")),
+        );
+
+        ui.add(
+            egui::Slider::new(&mut self.settings.temperature_offset, -10.0..=10.0)
+                .step_by(0.1)
+                .suffix(" °C")
+                .text(tr("Temperature offset")),
+        );
+
+        ui.combobox_from_iter(
+            thermal::CountFormat::iter(),
+            &mut self.settings.count_format,
+            "Camera values",
+        );
+        if self.settings.count_format == thermal::CountFormat::Custom {
+            ui.add(
+                egui::Slider::new(&mut self.settings.count_scale, 0.001..=1.0)
+                    .logarithmic(true)
+                    .suffix(" °C")
+                    .text(tr("Per count")),
+            );
+            ui.add(
+                egui::Slider::new(&mut self.settings.count_offset, -300.0..=300.0)
+                    .suffix(" °C")
+                    .text(tr("At count 0")),
+            );
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(format!(
+                "{}: {}",
+                tr("Bad pixels"),
+                self.settings.bad_pixels.len()
+            ));
+
+            if !self.settings.bad_pixels.is_empty() && ui.button(tr("Clear")).clicked() {
+                self.settings.bad_pixels.clear();
+            }
+        });
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.profile_settings(ui);
+    }
+
+    /// Serial number of the connected camera, if the transport tells it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn serial_number(&self) -> Option<&str> {
+        match self.connection_status {
+            ConnectionStatus::Connected(ref port) => port.serial_number.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Applies the profile of a newly connected camera. Reconnecting the same camera keeps the
+    /// settings as they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_profile(&mut self) {
+        let Some(serial_number) = self.serial_number().map(str::to_owned) else {
+            return;
+        };
+
+        if self.profile_serial_number.as_ref() == Some(&serial_number) {
+            return;
+        }
+
+        self.profile_message = match profiles::load(&serial_number, &mut self.settings) {
+            Ok(true) => Some(tr("Device profile loaded").to_owned()),
+            Ok(false) => None,
+            Err(e) => {
+                log::error!("Failed to load the profile of {serial_number}: {e}");
+                Some(format!("{}: {e}", tr("Failed to load device profile")))
+            }
+        };
+        self.profile_serial_number = Some(serial_number);
+        self.sync_edited_settings();
+    }
+
+    /// Shows the settings which are edited before they're applied as they are now, once the
+    /// settings have been replaced by a profile.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn sync_edited_settings(&mut self) {
+        #[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+        self.network_address
+            .clone_from(&self.settings.network_address);
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        {
+            self.serial_timeout = self.settings.serial_timeout;
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn profile_settings(&mut self, ui: &mut Ui) {
+        let Some(serial_number) = self.serial_number().map(str::to_owned) else {
+            return;
+        };
+
+        ui.label(format!("{}: {serial_number}", tr("Serial number")));
+
+        if ui.button(tr("Save device profile")).clicked() {
+            self.profile_message = match profiles::save(&serial_number, &self.settings) {
+                Ok(path) => Some(format!("{} {}", tr("Saved to"), path.display())),
+                Err(e) => {
+                    log::error!("Failed to save the profile of {serial_number}: {e}");
+                    Some(format!("{}: {e}", tr("Failed to save device profile")))
+                }
+            };
+        }
+
+        if let Some(ref message) = self.profile_message {
+            ui.label(message);
+        }
+    }
+
+    fn layout_settings(&mut self, ui: &mut Ui) {
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        {
+            ui.checkbox(&mut self.window_settings.always_on_top, tr("Always on top"));
+            ui.checkbox(&mut self.window_settings.borderless, tr("Borderless"));
+        }
+
+        ui.combobox_from_iter(
+            PanelPosition::iter(),
+            &mut self.ui_settings.panel_position,
+            "Settings position",
+        );
+        ui.checkbox(&mut self.ui_settings.compact, tr("Compact"));
+        self.ui_scale_dragged = ui
+            .add(
+                egui::Slider::new(&mut self.ui_settings.ui_scale, 0.5..=3.0)
+                    .step_by(0.1)
+                    .text(tr("UI scale")),
+            )
+            .dragged();
+        ui.checkbox(&mut self.ui_settings.large_font, tr("Large font"));
+        ui.combobox_from_iter(Language::iter(), &mut self.ui_settings.language, "Language");
+        ui.combobox_from_iter(
+            DecimalSeparator::iter(),
+            &mut self.ui_settings.decimal_separator,
+            "Decimal separator",
+        );
+        ui.add(egui::Slider::new(&mut self.ui_settings.precision, 0..=3).text(tr("Precision")));
+        ui.checkbox(&mut self.ui_settings.raw_counts, tr("Show raw counts"))
+            .on_hover_text(tr(
+                "Adds the values sent by the camera to the temperatures shown",
+            ));
+        #[cfg(not(target_arch = "wasm32"))]
+        ui.add(
+            egui::Slider::new(&mut self.ui_settings.max_frame_rate, 5..=120)
+                .suffix(" fps")
+                .text(tr("Maximum frame rate")),
+        );
+
+        #[cfg(target_os = "android")]
+        ui.checkbox(
+            &mut self.ui_settings.keep_screen_on,
+            tr("Keep screen on while connected"),
+        );
+    }
+
+    /// Suspends the processing while the window is minimized or, on mobile, in the background.
+    /// The browser stops calling the producer on its own.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_suspension(&mut self, ctx: &egui::Context) {
+        // mobile platforms don't report the app going to the background, only losing the focus
+        #[cfg(target_os = "android")]
+        let hidden = ctx.input(|i| i.viewport().focused == Some(false));
+        #[cfg(target_os = "ios")]
+        let hidden = ctx.input(|i| i.viewport().focused == Some(false));
+        #[cfg(not(any(target_os = "android", target_os = "ios")))]
+        let hidden = ctx.input(|i| i.viewport().minimized == Some(true));
+
+        if hidden != self.suspended {
+            self.suspended = hidden;
+            let _ = self.sender.send(UiMessage::Suspend(hidden));
+            #[cfg(not(any(target_os = "android", target_os = "ios")))]
+            for camera in &self.cameras {
+                camera.send(UiMessage::Suspend(hidden));
+            }
+        }
+    }
+
+    /// Throttles the frame rate while battery saver is on or the battery is low.
+    #[cfg(target_os = "android")]
+    fn update_power_saving(&mut self) {
+        const POWER_CHECK_INTERVAL: web_time::Duration = web_time::Duration::from_secs(5);
+
+        if self.power_checked_at.elapsed() < POWER_CHECK_INTERVAL {
+            return;
+        }
+
+        self.power_checked_at = web_time::Instant::now();
+
+        match crate::android::power_saving() {
+            Ok(power_saving) => self.settings.power_saving = power_saving,
+            Err(e) => log::error!("Failed to check the power state: {e}"),
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    fn update_keep_screen_on(&mut self) {
+        let keep_screen_on = self.ui_settings.keep_screen_on
+            && matches!(self.connection_status, ConnectionStatus::Connected(_));
+
+        if keep_screen_on != self.screen_kept_on {
+            crate::android::set_keep_screen_on(keep_screen_on);
+            self.screen_kept_on = keep_screen_on;
+        }
+    }
+
+    fn settings(&mut self, ui: &mut Ui) {
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if self.ui_settings.compact {
+                ui.spacing_mut().item_spacing = egui::vec2(4.0, 2.0);
+                ui.spacing_mut().slider_width = 100.0;
+            }
+
+            egui::CollapsingHeader::new(tr("Display"))
+                .id_salt("display")
+                .default_open(true)
+                .show(ui, |ui| self.display_settings(ui));
+            egui::CollapsingHeader::new(tr("Processing"))
+                .id_salt("processing")
+                .default_open(!self.ui_settings.compact)
+                .show(ui, |ui| self.processing_settings(ui));
+            egui::CollapsingHeader::new(tr("Device"))
+                .id_salt("device")
+                .default_open(!self.ui_settings.compact)
+                .show(ui, |ui| self.device_settings(ui));
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            egui::CollapsingHeader::new(tr("Additional cameras"))
+                .id_salt("cameras")
+                .default_open(!self.cameras.is_empty())
+                .show(ui, |ui| self.camera_settings(ui));
+            egui::CollapsingHeader::new(tr("Markers"))
+                .id_salt("markers")
+                .default_open(false)
+                .show(ui, |ui| self.marker_settings(ui));
+            egui::CollapsingHeader::new(tr("Overlays"))
+                .id_salt("overlays")
+                .default_open(false)
+                .show(ui, |ui| self.overlay_settings(ui));
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::CollapsingHeader::new(tr("Report"))
+                .id_salt("report")
+                .default_open(false)
+                .show(ui, |ui| self.report_settings(ui));
+            egui::CollapsingHeader::new(tr("Alarm"))
+                .id_salt("alarm")
+                .default_open(false)
+                .show(ui, |ui| self.alarm_settings(ui));
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            egui::CollapsingHeader::new(tr("Sonification"))
+                .id_salt("sonification")
+                .default_open(false)
+                .show(ui, |ui| self.sonification_settings(ui));
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::CollapsingHeader::new(tr("OSC output"))
+                .id_salt("osc")
+                .default_open(false)
+                .show(ui, |ui| self.osc_settings(ui));
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::CollapsingHeader::new(tr("Modbus TCP"))
+                .id_salt("modbus")
+                .default_open(false)
+                .show(ui, |ui| self.modbus_settings(ui));
+            #[cfg(not(target_arch = "wasm32"))]
+            egui::CollapsingHeader::new(tr("HTTP server"))
+                .id_salt("http")
+                .default_open(false)
+                .show(ui, |ui| self.http_settings(ui));
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            {
+                let camera = if cfg!(target_os = "android") {
+                    "Phone camera"
+                } else {
+                    "Webcam"
+                };
+                egui::CollapsingHeader::new(tr(camera))
+                    .id_salt("fusion")
+                    .default_open(false)
+                    .show(ui, |ui| self.fusion_settings(ui));
+            }
+            egui::CollapsingHeader::new(tr("Layout"))
+                .id_salt("layout")
+                .default_open(false)
+                .show(ui, |ui| self.layout_settings(ui));
+            egui::CollapsingHeader::new(tr("Keyboard shortcuts"))
+                .id_salt("keyboard_shortcuts")
+                .default_open(false)
+                .show(ui, |ui| self.hotkeys.ui(ui));
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            egui::CollapsingHeader::new(tr("Troubleshooting"))
+                .id_salt("troubleshooting")
+                .default_open(false)
+                .show(ui, |ui| self.troubleshooting_settings(ui));
+        });
+    }
+}
+
+impl eframe::App for App {
+    /// Stores the settings, the UI settings and the keyboard shortcuts (and the recordings folder
+    /// on Android), restored by [`App::new`] at the next start.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        match profiles::format("Tiop01 settings", &self.settings) {
+            Ok(contents) => storage.set_string(SETTINGS_KEY, contents),
+            Err(e) => log::error!("Failed to store the settings: {e}"),
+        }
+        match self.hotkeys.format() {
+            Ok(contents) => storage.set_string(HOTKEYS_KEY, contents),
+            Err(e) => log::error!("Failed to store the keyboard shortcuts: {e}"),
+        }
+        match self.ui_settings.format() {
+            Ok(contents) => storage.set_string(UI_SETTINGS_KEY, contents),
+            Err(e) => log::error!("Failed to store the UI settings: {e}"),
+        }
+        #[cfg(target_os = "android")]
+        storage.set_string(RECORDINGS_FOLDER_KEY, self.recordings_folder.clone());
+    }
+
+    /// Stops the producer so the port is closed properly, a device left half-open could block
+    /// the next program opening it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn on_exit(&mut self) {
+        let _ = self.sender.send(UiMessage::Stop);
+        // a video without its index can't be played
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.stop_video();
+
+        #[allow(unused_mut)]
+        let mut producer_threads: Vec<_> = self.producer_thread.take().into_iter().collect();
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        producer_threads.extend(
+            self.cameras
+                .iter_mut()
+                .filter_map(camera_grid::Camera::stop),
+        );
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        producer_threads.append(&mut self.stopped_producers);
+
+        let deadline = web_time::Instant::now() + STOP_TIMEOUT;
+        for producer_thread in producer_threads {
+            if join_producer(producer_thread, deadline).is_err() {
+                log::warn!("The producer hasn't stopped in time, exiting anyway");
+                return;
+            }
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _: &mut eframe::Frame) {
+        let screen_size = ctx.screen_rect();
+        let use_panels = 1.5 * screen_size.width() > screen_size.height();
+
+        let old_settings = self.settings.clone();
+        let old_window_settings = self.window_settings.clone();
+        let old_paused = self.paused;
+        self.handle_hotkeys(ctx);
+
+        #[cfg(target_arch = "wasm32")]
+        self.producer.step();
+
+        let mut image: Option<(thermal::RgbImage, egui::ColorImage)> = None;
+
+        for message in self.receive_producer_messages() {
+            if let ProducerMessage::Frame(ref frame) = message {
+                if self.check_alarm(frame.max) {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.log_alarm(ctx, frame);
+                }
+                #[cfg(not(target_arch = "wasm32"))]
+                {
+                    self.send_osc(frame);
+                    self.update_modbus(Some(frame));
+                    self.update_http(Some(frame));
+                }
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                if self.json_output {
+                    self.print_json_stats(frame);
+                }
+            }
+
+            match message {
+                ProducerMessage::ConnectionStatusChange(status) => {
+                    self.connection_status = status;
+                    self.connection_error = None;
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    self.load_profile();
+
+                    if !matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+                        self.data_rate = 0.0;
+                        self.alarm_active = false;
+                        self.values = None;
+                        self.positions = None;
+                        self.hot_spot_trail.clear();
+                        let black = image_utils::generate_black_image(
+                            THERMAL_IMAGE_WIDTH,
                             THERMAL_IMAGE_HEIGHT,
-                        ));
+                        );
+                        let color_image = image_utils::to_color_image(&black);
+                        image = Some((black, color_image));
                     }
                 }
+                ProducerMessage::DataRate(data_rate) => self.data_rate = data_rate,
+                ProducerMessage::ConnectionError(error) => self.connection_error = Some(error),
+                ProducerMessage::Commands(commands) => self.commands = commands,
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                ProducerMessage::RawFrames(frames) => self.save_debug_bundle(&frames),
+                #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+                ProducerMessage::Recording(status) => self.update_recording(status),
+                ProducerMessage::Frame(frame) if self.paused => self.frame_pool.recycle(frame),
                 ProducerMessage::Frame(frame) => {
-                    let now = std::time::Instant::now();
+                    let now = web_time::Instant::now();
                     self.min = frame.min;
                     self.max = frame.max;
                     self.fps = 1.0 / (now - self.last_frame_update).as_secs_f64();
                     self.last_frame_update = now;
-                    image = Some(frame.image);
+                    self.extremes = [frame.coldest, frame.hottest];
+                    self.update_hot_spot_trail(now, frame.hottest);
+
+                    if self
+                        .panorama
+                        .as_mut()
+                        .is_some_and(|panorama| panorama.add(&frame.values))
+                    {
+                        self.regenerate_panorama(ctx);
+                    }
+
+                    if let Some(values) = self.values.replace(frame.values) {
+                        self.frame_pool.recycle_gray_image(values);
+                    }
+                    if let Some(positions) = self.positions.replace(frame.positions) {
+                        self.frame_pool.recycle_gray_image(positions);
+                    }
+                    image = Some((frame.image, frame.color_image));
                 }
             }
         }
 
-        if let Some(image) = image {
-            self.thermal_image_texture =
-                Self::load_texture_from_image(ctx, "thermal_image", &image);
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        for camera in &mut self.cameras {
+            camera.update(ctx, self.paused);
         }
 
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.vertical_centered(|ui| {
-                ui.heading("Tiop01 thermal camera GUI");
-            });
-        });
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.update_modbus(None);
+            self.update_http(None);
+        }
 
-        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
-            let text: String = match self.connection_status {
-                ConnectionStatus::Disconnected => "Disconnected".into(),
-                ConnectionStatus::Connected => format!(
-                    "Min: {:.02}, max: {:.02}, FPS: {:.02}",
-                    self.min, self.max, self.fps
-                ),
-            };
+        if let Some((image, color_image)) = image {
+            self.show_image(image, color_image);
+        }
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.update_video();
 
-            ui.vertical_centered(|ui| {
-                ui.label(text);
+        if !self.window_settings.kiosk {
+            egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+                ui.vertical_centered(|ui| {
+                    ui.heading(tr("Tiop01 thermal camera GUI"));
+                });
             });
+        }
+
+        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
+            self.status_bar(ui);
         });
 
         egui::CentralPanel::default().show(ctx, |ui| {
-            if use_panels {
-                ui.columns(2, |columns| {
+            if self.image_detached {
+                self.settings(ui);
+                return;
+            }
+
+            if !self.settings_visible {
+                self.images(ui);
+                return;
+            }
+
+            match self.ui_settings.panel_position.resolve(use_panels) {
+                PanelPosition::Left => ui.columns(2, |columns| {
+                    self.settings(&mut columns[0]);
+                    self.images(&mut columns[1]);
+                }),
+                PanelPosition::Right => ui.columns(2, |columns| {
                     self.images(&mut columns[0]);
                     self.settings(&mut columns[1]);
-                });
-            } else {
-                self.images(ui);
-                self.settings(ui);
+                }),
+                PanelPosition::Auto | PanelPosition::Bottom => {
+                    // leave at least a third of the height for the settings
+                    let max_size = ui.available_size() * egui::vec2(1.0, 2.0 / 3.0);
+                    ui.allocate_ui(max_size, |ui| self.images(ui));
+                    self.settings(ui);
+                }
             }
         });
 
+        self.image_viewport(ctx);
+        self.panorama_window(ctx);
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.survey_window(ctx);
+            self.update_hot_component_scan(ctx);
+        }
+
+        if self.window_settings.kiosk {
+            self.touch_controls(ctx);
+            ctx.set_cursor_icon(egui::CursorIcon::None);
+        }
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.port_picker(ctx);
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.crash_report_prompt(ctx);
+
+        #[cfg(target_os = "android")]
+        {
+            self.update_keep_screen_on();
+            self.update_power_saving();
+        }
+
+        #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+        {
+            self.update_visible_camera(ctx);
+            self.calibration_window(ctx);
+            self.update_sonification(ctx);
+            self.update_alarm_sound(ctx);
+        }
+
+        if old_window_settings != self.window_settings {
+            self.window_settings.apply(ctx);
+        }
+
+        // applying the scale while its slider is dragged would move the slider under the cursor
+        if !self.ui_scale_dragged && self.applied_ui_settings != self.ui_settings {
+            self.ui_settings.apply(ctx);
+            if self.applied_ui_settings.max_frame_rate != self.ui_settings.max_frame_rate {
+                let _ = self
+                    .sender
+                    .send(UiMessage::MaxFrameRate(self.ui_settings.max_frame_rate));
+                #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+                for camera in &mut self.cameras {
+                    camera.set_max_frame_rate(self.ui_settings.max_frame_rate);
+                }
+            }
+            self.applied_ui_settings = self.ui_settings.clone();
+        }
+
+        if old_paused != self.paused {
+            let _ = self.sender.send(UiMessage::Pause(self.paused));
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            for camera in &self.cameras {
+                camera.send(UiMessage::Pause(self.paused));
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_suspension(ctx);
+
+        // new frames request a repaint, without them the UI only needs to catch up now and then
+        if self.paused
+            || self.suspended
+            || !matches!(self.connection_status, ConnectionStatus::Connected(_))
+        {
+            ctx.request_repaint_after(IDLE_REPAINT_INTERVAL);
+        }
+
+        #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+        self.switch_source_profile();
+
         if old_settings != self.settings {
+            // queued for the writer thread, as the producer may be blocked in a read for a while
+            if old_settings.emissivity != self.settings.emissivity {
+                self.commands
+                    .send(Command::SetEmissivity(self.settings.emissivity.into()));
+            }
             let _ = self
                 .sender
                 .send(UiMessage::ChangeSettings(self.settings.clone()));
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            crate::crash_report::set_settings(&self.settings);
 
             if old_settings.colormap != self.settings.colormap
                 || old_settings.color_range != self.settings.color_range
             {
                 self.regenerate_colormap(ctx, self.settings.color_range);
+                self.regenerate_panorama(ctx);
+                self.recolor_shown_image();
             }
         }
     }
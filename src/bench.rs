@@ -0,0 +1,88 @@
+//! Steps of the producer's processing, exposed to the benchmarks in `benches/`. The crate has no
+//! public API otherwise.
+
+pub use crate::overlay::{snapshot, OverlaySettings};
+pub use crate::thermal::{
+    ColorMap, ColorRange, EdgeStrategy, FilteringMethod, GrayImage, RgbImage, Settings,
+    THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_SIZE, THERMAL_IMAGE_WIDTH,
+};
+
+use crate::image_utils;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+
+type Colormap = dyn scarlet::colormap::ColorMap<scarlet::color::RGBColor> + Sync;
+
+/// Decodes a frame as read from the port, tenths of °C as little-endian words.
+pub fn decode(mut bytes: &[u8]) -> anyhow::Result<GrayImage> {
+    let mut image = GrayImage::new(THERMAL_IMAGE_SIZE);
+    bytes.read_u16_into::<LittleEndian>(image.data_mut())?;
+
+    Ok(image)
+}
+
+/// Smooths `image` with the kernel of `settings`, `None` when filtering is off.
+pub fn filter(image: &GrayImage, settings: &Settings) -> Option<GrayImage> {
+    settings.get_kernel().map(|kernel| image.run(kernel, None))
+}
+
+fn min_max(image: &GrayImage) -> (u16, u16) {
+    let min = image.data().iter().copied().min().unwrap_or_default();
+    let max = image.data().iter().copied().max().unwrap_or_default();
+
+    (min, max.max(min.saturating_add(1)))
+}
+
+/// Colorizes `image` over its range, evaluating the colormap for every pixel like the producer.
+pub fn colorize(image: &GrayImage, colormap: &Colormap, color_range: ColorRange) -> RgbImage {
+    let (min, max) = min_max(image);
+    let mut colorized = RgbImage::new(image.size());
+
+    colorized.each_pixel_mut(|pt, pixel| {
+        let value = image.get([pt.x, pt.y]).as_slice()[0];
+        let color = colormap.transform_single(image_utils::map_to_scaled_value(
+            value,
+            min,
+            max,
+            color_range,
+        ));
+        pixel.copy_from_slice([color.int_r(), color.int_g(), color.int_b()]);
+    });
+
+    colorized
+}
+
+/// Colormap sampled at 256 points, trading precision for a table lookup per pixel.
+pub struct Lut([[u8; 3]; 256]);
+
+impl Lut {
+    pub fn new(colormap: &Colormap, color_range: ColorRange) -> Self {
+        let mut lut = [[0; 3]; 256];
+
+        for (index, entry) in (0_u16..).zip(lut.iter_mut()) {
+            let color = colormap.transform_single(image_utils::map_to_scaled_value(
+                index,
+                0,
+                255,
+                color_range,
+            ));
+            *entry = [color.int_r(), color.int_g(), color.int_b()];
+        }
+
+        Self(lut)
+    }
+
+    /// Same as [`colorize`] with the colors looked up.
+    pub fn colorize(&self, image: &GrayImage) -> RgbImage {
+        let (min, max) = min_max(image);
+        let mut colorized = RgbImage::new(image.size());
+
+        colorized.each_pixel_mut(|pt, pixel| {
+            let value = image.get([pt.x, pt.y]).as_slice()[0];
+            let index = u32::from(value - min) * 255 / u32::from(max - min);
+            pixel.copy_from_slice(self.0[usize::try_from(index).unwrap_or(255)]);
+        });
+
+        colorized
+    }
+}
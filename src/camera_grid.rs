@@ -0,0 +1,204 @@
+//! Additional cameras shown in a grid next to the main one, e.g. to monitor several test fixtures
+//! at once. Each camera has a producer and settings of its own, so it can be on another port or
+//! a network stream and use another color map.
+
+use crate::app::{ConnectionStatus, ProducerMessage, UiMessage};
+use crate::desktop::SerialPortOpener;
+use crate::image_utils::{self, FramePool};
+use crate::protocol::Command;
+use crate::thermal::{
+    CommandQueue, ImageProducer, PortError, Settings, Transport, THERMAL_IMAGE_HEIGHT,
+    THERMAL_IMAGE_WIDTH,
+};
+
+use eframe::egui::{self, TextureOptions};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+struct Producer {
+    sender: Sender<UiMessage>,
+    receiver: Receiver<ProducerMessage>,
+    thread: Option<JoinHandle<()>>,
+    sent_settings: Settings,
+    /// Writer half of the producer's port
+    commands: CommandQueue,
+}
+
+pub struct Camera {
+    id: usize,
+    /// Edited in the UI, sent to the producer whenever it changes
+    pub settings: Settings,
+    /// Port or network address being edited, applied once the field loses the focus
+    pub address: String,
+    max_frame_rate: u32,
+    /// Started once the camera has a port or an address, auto-detection would only find the
+    /// main camera
+    producer: Option<Producer>,
+    frame_pool: FramePool,
+    pub texture: egui::TextureHandle,
+    pub connection_status: ConnectionStatus,
+    pub connection_error: Option<PortError>,
+    /// Lowest and highest temperature of the last frame, while connected
+    pub range: Option<(f64, f64)>,
+}
+
+fn black_image() -> egui::ColorImage {
+    image_utils::to_color_image(&image_utils::generate_black_image(
+        THERMAL_IMAGE_WIDTH,
+        THERMAL_IMAGE_HEIGHT,
+    ))
+}
+
+impl Camera {
+    /// Camera without a port yet, `id` tells the cameras apart.
+    pub fn new(ctx: &egui::Context, id: usize, max_frame_rate: u32) -> Self {
+        Self {
+            id,
+            settings: Settings::default(),
+            address: String::new(),
+            max_frame_rate,
+            producer: None,
+            frame_pool: FramePool::default(),
+            texture: ctx.load_texture(
+                format!("camera_{id}"),
+                black_image(),
+                TextureOptions::default(),
+            ),
+            connection_status: ConnectionStatus::Disconnected,
+            connection_error: None,
+            range: None,
+        }
+    }
+
+    fn has_address(&self) -> bool {
+        match self.settings.transport {
+            Transport::Network => !self.settings.network_address.is_empty(),
+            _ => self.settings.port.is_some(),
+        }
+    }
+
+    fn start(&self, ctx: &egui::Context) -> anyhow::Result<Producer> {
+        let (sender, producer_receiver) = mpsc::channel();
+        let (producer_sender, receiver) = mpsc::channel();
+
+        let _ = sender.send(UiMessage::ChangeSettings(self.settings.clone()));
+        let _ = sender.send(UiMessage::MaxFrameRate(self.max_frame_rate));
+
+        let ctx = ctx.clone();
+        let frame_pool = self.frame_pool.clone();
+        let thread = thread::Builder::new()
+            .name(format!("thermal-{}", self.id))
+            .spawn(move || {
+                let mut producer = ImageProducer::new(
+                    ctx,
+                    producer_sender,
+                    producer_receiver,
+                    SerialPortOpener::new(),
+                )
+                .with_frame_pool(frame_pool);
+                producer.main_loop();
+            })?;
+
+        Ok(Producer {
+            sender,
+            receiver,
+            thread: Some(thread),
+            sent_settings: self.settings.clone(),
+            commands: CommandQueue::default(),
+        })
+    }
+
+    /// Applies the edited address to the port or the network address, depending on the
+    /// transport.
+    pub fn apply_address(&mut self) {
+        let address = self.address.trim();
+
+        if self.settings.transport == Transport::Network {
+            self.settings.network_address = address.to_owned();
+        } else {
+            self.settings.port = (!address.is_empty()).then(|| address.to_owned());
+        }
+    }
+
+    pub fn send(&self, message: UiMessage) {
+        if let Some(ref producer) = self.producer {
+            let _ = producer.sender.send(message);
+        }
+    }
+
+    pub fn set_max_frame_rate(&mut self, max_frame_rate: u32) {
+        self.max_frame_rate = max_frame_rate;
+        self.send(UiMessage::MaxFrameRate(max_frame_rate));
+    }
+
+    /// Starts or reconfigures the producer and shows the latest frame, frames are dropped while
+    /// `paused`.
+    pub fn update(&mut self, ctx: &egui::Context, paused: bool) {
+        if self.producer.is_none() && self.has_address() {
+            match self.start(ctx) {
+                Ok(producer) => self.producer = Some(producer),
+                Err(e) => log::error!("Failed to start camera {}: {e}", self.id),
+            }
+        }
+
+        let Some(ref mut producer) = self.producer else {
+            return;
+        };
+
+        if self.settings != producer.sent_settings {
+            // queued for the writer thread, as the producer may be blocked in a read for a while
+            if self.settings.emissivity != producer.sent_settings.emissivity {
+                producer
+                    .commands
+                    .send(Command::SetEmissivity(self.settings.emissivity.into()));
+            }
+            let _ = producer
+                .sender
+                .send(UiMessage::ChangeSettings(self.settings.clone()));
+            producer.sent_settings = self.settings.clone();
+        }
+
+        let mut latest = None;
+
+        for message in producer.receiver.try_iter() {
+            match message {
+                ProducerMessage::ConnectionStatusChange(status) => {
+                    self.connection_status = status;
+                    self.connection_error = None;
+
+                    if !matches!(self.connection_status, ConnectionStatus::Connected(_)) {
+                        self.range = None;
+                        self.texture.set(black_image(), TextureOptions::default());
+                    }
+                }
+                ProducerMessage::ConnectionError(error) => self.connection_error = Some(error),
+                ProducerMessage::Frame(frame) if paused => self.frame_pool.recycle(frame),
+                ProducerMessage::Frame(frame) => {
+                    self.range = Some((frame.min, frame.max));
+                    if let Some(older) = latest.replace(frame) {
+                        self.frame_pool.recycle(older);
+                    }
+                }
+                ProducerMessage::Commands(commands) => producer.commands = commands,
+                ProducerMessage::DataRate(_)
+                | ProducerMessage::RawFrames(_)
+                | ProducerMessage::Recording(_) => {}
+            }
+        }
+
+        if let Some(frame) = latest {
+            self.texture
+                .set(frame.color_image, TextureOptions::default());
+            self.frame_pool.recycle_rgb_image(frame.image);
+            self.frame_pool.recycle_gray_image(frame.values);
+            self.frame_pool.recycle_gray_image(frame.positions);
+        }
+    }
+
+    /// Asks the producer to close the port, returns its thread to wait for. Dropping the camera
+    /// stops it too.
+    pub fn stop(&mut self) -> Option<JoinHandle<()>> {
+        self.send(UiMessage::Stop);
+        self.producer.as_mut()?.thread.take()
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{anyhow, Context};
+use std::path::PathBuf;
+
+/// Runs instead of the GUI
+#[derive(Debug)]
+pub enum Command {
+    /// Checks a recorded session
+    Verify(PathBuf),
+    /// Rewrites a recorded session in the current version
+    Migrate(PathBuf, PathBuf),
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    pub command: Option<Command>,
+    pub always_on_top: bool,
+    pub borderless: bool,
+    pub size: Option<[f32; 2]>,
+    pub position: Option<[f32; 2]>,
+    pub kiosk: bool,
+    /// Debug bundle replayed in place of the camera
+    pub replay_bundle: Option<PathBuf>,
+    /// Prints the statistics of every frame to stdout as JSON, a line each
+    pub json_output: bool,
+    /// Publishes the frames as a ROS 2 node instead of showing the window
+    #[cfg(feature = "ros2")]
+    pub ros2: bool,
+}
+
+const USAGE: &str = "Usage: tiop01-gui [--always-on-top] [--borderless] \
+                     [--size WIDTHxHEIGHT] [--position X,Y] [--kiosk] \
+                     [--replay-bundle FILE] [--json-output] [--ros2 [--ros-args ... [--]]]\n       \
+                     tiop01-gui verify FILE\n       \
+                     tiop01-gui migrate FILE OUTPUT";
+
+fn parse_pair(value: &str, separator: char) -> anyhow::Result<[f32; 2]> {
+    let (first, second) = value
+        .split_once(separator)
+        .ok_or_else(|| anyhow!("Expected two values separated by '{separator}': {value}"))?;
+
+    Ok([
+        first.trim().parse().context("Invalid number")?,
+        second.trim().parse().context("Invalid number")?,
+    ])
+}
+
+impl Args {
+    pub fn parse() -> anyhow::Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from<I>(args: I) -> anyhow::Result<Self>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| {
+                args.next()
+                    .ok_or_else(|| anyhow!("Missing value for {name}\n{USAGE}"))
+            };
+
+            match arg.as_str() {
+                "--always-on-top" => parsed.always_on_top = true,
+                "--borderless" => parsed.borderless = true,
+                "--size" => parsed.size = Some(parse_pair(&value("--size")?, 'x')?),
+                "--position" => parsed.position = Some(parse_pair(&value("--position")?, ',')?),
+                "--kiosk" => parsed.kiosk = true,
+                "--replay-bundle" => {
+                    parsed.replay_bundle = Some(value("--replay-bundle")?.into());
+                }
+                "--json-output" => parsed.json_output = true,
+                "verify" if parsed.command.is_none() => {
+                    parsed.command = Some(Command::Verify(value("verify")?.into()));
+                }
+                "migrate" if parsed.command.is_none() => {
+                    let file = value("migrate")?.into();
+                    parsed.command = Some(Command::Migrate(file, value("migrate")?.into()));
+                }
+                #[cfg(feature = "ros2")]
+                "--ros2" => parsed.ros2 = true,
+                // read by the ROS 2 client library, up to the closing `--` if there's one
+                #[cfg(feature = "ros2")]
+                "--ros-args" => {
+                    for arg in args.by_ref() {
+                        if arg == "--" {
+                            break;
+                        }
+                    }
+                }
+                _ => return Err(anyhow!("Unknown argument: {arg}\n{USAGE}")),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    pub fn native_options(&self) -> eframe::NativeOptions {
+        let mut native_options = eframe::NativeOptions {
+            viewport: self.viewport(),
+            // the restored window would override the geometry given on the command line
+            persist_window: self.size.is_none() && self.position.is_none() && !self.kiosk,
+            ..eframe::NativeOptions::default()
+        };
+
+        // Single board computers render through slow GPUs, often straight to DRM/KMS under a
+        // kiosk compositor. Keep a single frame in flight and prefer the low power adapter.
+        if self.kiosk {
+            let wgpu_options = &mut native_options.wgpu_options;
+            wgpu_options.desired_maximum_frame_latency = Some(1);
+
+            if let eframe::egui_wgpu::WgpuSetup::CreateNew {
+                ref mut power_preference,
+                ..
+            } = wgpu_options.wgpu_setup
+            {
+                *power_preference = eframe::wgpu::PowerPreference::LowPower;
+            }
+        }
+
+        native_options
+    }
+
+    fn viewport(&self) -> eframe::egui::ViewportBuilder {
+        let mut viewport = eframe::egui::ViewportBuilder::default()
+            .with_decorations(!self.borderless && !self.kiosk)
+            .with_fullscreen(self.kiosk)
+            .with_window_level(if self.always_on_top {
+                eframe::egui::WindowLevel::AlwaysOnTop
+            } else {
+                eframe::egui::WindowLevel::Normal
+            });
+
+        if let Some(size) = self.size {
+            viewport = viewport.with_inner_size(size);
+        }
+        if let Some(position) = self.position {
+            viewport = viewport.with_position(position);
+        }
+
+        viewport
+    }
+}
@@ -0,0 +1,129 @@
+//! Crash reports: a panic writes the backtrace, the recent log and the settings to the
+//! configuration directory, and the next start offers to open the report. Users in the field
+//! can't reproduce a crash under a debugger, but they can attach the report to an issue.
+
+use crate::debug_bundle;
+use crate::profiles;
+use crate::report;
+use crate::thermal::Settings;
+
+use anyhow::anyhow;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, TryLockError};
+use std::time::SystemTime;
+
+/// Names the report of a crash which hasn't been offered to the user yet
+const PENDING_FILE: &str = "pending";
+
+/// Settings at the time of a crash, formatted whenever they change so the panic hook only has to
+/// copy them
+static SETTINGS: Mutex<String> = Mutex::new(String::new());
+
+fn directory() -> anyhow::Result<PathBuf> {
+    Ok(profiles::config_directory()
+        .ok_or_else(|| anyhow!("No directory for crash reports"))?
+        .join("crashes"))
+}
+
+/// Keeps the settings written to a crash report up to date.
+pub fn set_settings(settings: &Settings) {
+    if let Ok(mut formatted) = SETTINGS.lock() {
+        *formatted = format!("{settings:#?}");
+    }
+}
+
+/// Writes a crash report on panic, after the default hook has printed the message.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        match write(info) {
+            Ok(path) => eprintln!("Crash report saved to {}", path.display()),
+            Err(e) => eprintln!("Failed to save the crash report: {e}"),
+        }
+    }));
+}
+
+/// Writes the report of the panic described by `info`, returns its path.
+fn write(info: &dyn std::fmt::Display) -> anyhow::Result<PathBuf> {
+    let now = SystemTime::now();
+    let settings = match SETTINGS.try_lock() {
+        Ok(settings) => settings.clone(),
+        Err(TryLockError::Poisoned(e)) => e.into_inner().clone(),
+        Err(TryLockError::WouldBlock) => String::new(),
+    };
+
+    let mut contents = String::new();
+    writeln!(
+        contents,
+        "tiop01-gui {} crashed at {}",
+        env!("CARGO_PKG_VERSION"),
+        report::format_time(now)
+    )?;
+    writeln!(
+        contents,
+        "System: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )?;
+    writeln!(
+        contents,
+        "Thread: {}",
+        std::thread::current().name().unwrap_or("unnamed")
+    )?;
+    writeln!(contents, "{info}")?;
+    writeln!(
+        contents,
+        "\nBacktrace:\n{}",
+        std::backtrace::Backtrace::force_capture()
+    )?;
+    writeln!(contents, "\nSettings:\n{settings}")?;
+    writeln!(contents, "\nLog:\n{}", debug_bundle::recent_log())?;
+
+    let [year, month, day, hour, minute, second] = report::civil_time(now);
+    let file_name =
+        format!("crash-{year:04}{month:02}{day:02}-{hour:02}{minute:02}{second:02}.txt");
+    let directory = directory()?;
+
+    std::fs::create_dir_all(&directory)?;
+    std::fs::write(directory.join(&file_name), contents)?;
+    std::fs::write(directory.join(PENDING_FILE), &file_name)?;
+
+    Ok(directory.join(file_name))
+}
+
+/// Report of the crash of the previous run, if it hasn't been offered yet. It's only offered
+/// once.
+pub fn take_pending() -> Option<PathBuf> {
+    let directory = directory().ok()?;
+    let pending = directory.join(PENDING_FILE);
+
+    let file_name = std::fs::read_to_string(&pending).ok()?;
+    if let Err(e) = std::fs::remove_file(&pending) {
+        log::warn!("Failed to remove {}: {e}", pending.display());
+    }
+
+    let path = directory.join(file_name.trim());
+    path.is_file().then_some(path)
+}
+
+/// Opens the report in the default text editor.
+pub fn open(path: &Path) -> anyhow::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = std::process::Command::new("notepad");
+    #[cfg(target_os = "macos")]
+    let mut command = {
+        let mut command = std::process::Command::new("open");
+        command.arg("-t");
+        command
+    };
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command.arg(path).spawn()?;
+
+    Ok(())
+}
@@ -0,0 +1,48 @@
+//! Frames as OpenCV matrices, for vision processing beyond what the app does. See
+//! `examples/blob_tracking.rs`, run with `cargo run --example blob_tracking --features opencv`.
+
+pub use crate::thermal::{THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use opencv::core::{Mat, Scalar, CV_16UC1, CV_32FC1};
+use opencv::prelude::*;
+use std::io;
+
+/// Bytes of a frame as the camera sends it
+pub const FRAME_SIZE: usize = THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT * 2;
+
+/// Reads the next frame from `reader`, e.g. the camera's serial port or `frames.bin` of a debug
+/// bundle. The values are tenths of °C, row by row.
+pub fn read_frame(reader: &mut impl io::Read) -> io::Result<Vec<u16>> {
+    let mut values = vec![0; THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT];
+    reader.read_u16_into::<LittleEndian>(&mut values)?;
+
+    Ok(values)
+}
+
+fn new_mat(typ: i32) -> opencv::Result<Mat> {
+    Mat::new_rows_cols_with_default(
+        THERMAL_IMAGE_HEIGHT as i32,
+        THERMAL_IMAGE_WIDTH as i32,
+        typ,
+        Scalar::all(0.0),
+    )
+}
+
+/// Values of a frame as they are, a `CV_16UC1` matrix of tenths of °C.
+pub fn to_mat(values: &[u16]) -> opencv::Result<Mat> {
+    let mut mat = new_mat(CV_16UC1)?;
+    mat.data_typed_mut::<u16>()?.copy_from_slice(values);
+
+    Ok(mat)
+}
+
+/// Temperatures of a frame, a `CV_32FC1` matrix of °C.
+pub fn to_celsius_mat(values: &[u16]) -> opencv::Result<Mat> {
+    let mut mat = new_mat(CV_32FC1)?;
+    for (temperature, &value) in mat.data_typed_mut::<f32>()?.iter_mut().zip(values) {
+        *temperature = f32::from(value) / 10.0;
+    }
+
+    Ok(mat)
+}
@@ -0,0 +1,385 @@
+//! Debug bundles: the most recent frames straight from the camera, the log, the settings and a
+//! description of the system, saved as a ZIP archive to attach to bug reports. A bundle can be
+//! replayed in place of the camera with `--replay-bundle`, reproducing the session, and so can a
+//! recording, see [`crate::session`].
+
+use crate::profiles;
+use crate::report;
+use crate::session;
+use crate::thermal::{
+    PortError, PortErrorKind, PortInfo, PortOpener, RawFrame, Settings, THERMAL_IMAGE_HEIGHT,
+    THERMAL_IMAGE_WIDTH,
+};
+use crate::zip::{self, ZipWriter};
+
+use anyhow::anyhow;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use web_time::{Duration, Instant};
+
+/// Oldest lines are dropped beyond this
+const MAX_LOG_LINES: usize = 1000;
+
+const FRAMES_FILE: &str = "frames.bin";
+const TIMESTAMPS_FILE: &str = "frames.csv";
+const PROFILE_FILE: &str = "settings.conf";
+
+static LOG_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Logs through `env_logger` as configured by `RUST_LOG`, and keeps the recent messages of info
+/// level and above for the bundles whatever it's configured to.
+struct Logger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.level() <= log::Level::Info {
+            if let Ok(mut lines) = LOG_LINES.lock() {
+                if lines.len() == MAX_LOG_LINES {
+                    lines.pop_front();
+                }
+                lines.push_back(format!(
+                    "{} {} {}: {}",
+                    report::format_time(SystemTime::now()),
+                    record.level(),
+                    record.target(),
+                    record.args()
+                ));
+            }
+        }
+
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Replaces `env_logger::init`.
+pub fn init_logging() {
+    let inner = env_logger::Builder::from_default_env().build();
+    let max_level = inner.filter().max(log::LevelFilter::Info);
+
+    if log::set_boxed_logger(Box::new(Logger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
+
+/// The recent log lines. Doesn't wait for the log, so a panic while logging can't block it.
+pub fn recent_log() -> String {
+    let lines = match LOG_LINES.try_lock() {
+        Ok(lines) => lines,
+        Err(TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(TryLockError::WouldBlock) => return String::new(),
+    };
+
+    lines
+        .iter()
+        .fold(String::new(), |log, line| log + line + "\n")
+}
+
+/// Archives `frames` with the log, the profile of `settings` and `system_info`, pairs of a name
+/// and a value describing the session.
+pub fn write(
+    frames: &[RawFrame],
+    settings: &Settings,
+    system_info: &[(&str, String)],
+) -> anyhow::Result<Vec<u8>> {
+    let now = SystemTime::now();
+    let mut zip = ZipWriter::default();
+
+    let mut system = String::new();
+    for (name, value) in system_info {
+        writeln!(system, "{name}: {value}")?;
+    }
+    zip.add("system.txt", now, system.as_bytes())?;
+
+    zip.add("log.txt", now, recent_log().as_bytes())?;
+
+    zip.add(
+        PROFILE_FILE,
+        now,
+        profiles::format("Tiop01 debug bundle", settings)?.as_bytes(),
+    )?;
+    // everything else the replay can't restore, for reading
+    zip.add("settings.txt", now, format!("{settings:#?}\n").as_bytes())?;
+
+    // the bytes as they came from the port, in the order they came
+    let stream: Vec<u8> = frames
+        .iter()
+        .flat_map(|frame| frame.values.iter().flat_map(|value| value.to_le_bytes()))
+        .collect();
+    zip.add(FRAMES_FILE, now, &stream)?;
+
+    // the monotonic offsets pace a replay, the system clock ones correlate the frames with
+    // other recordings, see the NTP offset in system.txt
+    let mut timestamps = String::from("frame,milliseconds,unix_milliseconds\n");
+    if let Some(first) = frames.first() {
+        for (index, frame) in frames.iter().enumerate() {
+            let offset = frame.received_at.duration_since(first.received_at);
+            let wall_clock = frame
+                .wall_clock
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            writeln!(
+                timestamps,
+                "{index},{},{}",
+                offset.as_millis(),
+                wall_clock.as_millis()
+            )?;
+        }
+    }
+    zip.add(TIMESTAMPS_FILE, now, timestamps.as_bytes())?;
+
+    zip.finish()
+}
+
+/// Session recorded in a debug bundle or a session file.
+pub struct Replay {
+    /// File name of the bundle or the recording
+    pub name: String,
+    /// Settings of the session in the profile format, see [`profiles::apply`]
+    pub profile: String,
+    /// Frames as bytes read from the port and when they were read since the first one
+    frames: Vec<(Duration, Vec<u8>)>,
+    /// Images of the visible light camera of a recording, by their offsets
+    visible_frames: Vec<session::VisibleFrame>,
+    /// Offset of the frame being replayed (µs), for showing the visible image taken with it
+    position: AtomicU64,
+}
+
+impl Replay {
+    /// Loads a debug bundle, or a recording told by its header.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        if session::is_session(&bytes) {
+            return Self::load_recording(path);
+        }
+
+        let files = zip::read(&bytes)?;
+        let file = |name: &str| {
+            files
+                .iter()
+                .find(|(file_name, _)| file_name == name)
+                .map(|(_, contents)| contents.as_slice())
+                .ok_or_else(|| anyhow!("{} has no {name}", path.display()))
+        };
+
+        let profile = String::from_utf8_lossy(file(PROFILE_FILE)?).into_owned();
+        // fails early on an invalid profile, instead of when the replay starts
+        profiles::apply(&profile, PROFILE_FILE, &mut Settings::default())?;
+
+        let offsets = String::from_utf8_lossy(file(TIMESTAMPS_FILE)?)
+            .lines()
+            .skip(1)
+            .map(|line| {
+                // bundles of older versions have no system clock column
+                let milliseconds = line
+                    .split(',')
+                    .nth(1)
+                    .ok_or_else(|| anyhow!("{TIMESTAMPS_FILE}: invalid line {line}"))?;
+                Ok(Duration::from_millis(milliseconds.trim().parse()?))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let stream = file(FRAMES_FILE)?;
+        let frame_size = stream.len().checked_div(offsets.len()).unwrap_or(0);
+        if offsets.is_empty() != stream.is_empty() || frame_size * offsets.len() != stream.len() {
+            return Err(anyhow!(
+                "{FRAMES_FILE} doesn't match the {} frames of {TIMESTAMPS_FILE}",
+                offsets.len()
+            ));
+        }
+
+        let frames = if offsets.is_empty() {
+            Vec::new()
+        } else {
+            offsets
+                .into_iter()
+                .zip(stream.chunks(frame_size).map(<[u8]>::to_vec))
+                .collect()
+        };
+
+        Ok(Self {
+            name: path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            profile,
+            frames,
+            visible_frames: Vec::new(),
+            position: AtomicU64::new(0),
+        })
+    }
+
+    fn load_recording(path: &Path) -> anyhow::Result<Self> {
+        let session = session::read(path)?;
+
+        // older recordings without the settings are replayed with the current ones
+        let profile = session.profile.unwrap_or_default();
+        profiles::apply(&profile, PROFILE_FILE, &mut Settings::default())?;
+
+        let frames = session
+            .frames
+            .into_iter()
+            .map(|frame| {
+                if (frame.width, frame.height) != (THERMAL_IMAGE_WIDTH, THERMAL_IMAGE_HEIGHT) {
+                    return Err(anyhow!(
+                        "Frame of {}×{} pixels, the camera sends {THERMAL_IMAGE_WIDTH}×{THERMAL_IMAGE_HEIGHT}",
+                        frame.width,
+                        frame.height
+                    ));
+                }
+
+                let bytes = frame.values.iter().flat_map(|value| value.to_le_bytes());
+                Ok((frame.offset, bytes.collect()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            name: path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+            profile,
+            frames,
+            visible_frames: session.visible_frames,
+            position: AtomicU64::new(0),
+        })
+    }
+
+    pub fn has_visible_frames(&self) -> bool {
+        !self.visible_frames.is_empty()
+    }
+
+    /// The latest visible image taken up to the frame being replayed, with its index.
+    pub fn visible_frame(&self) -> Option<(usize, &session::VisibleFrame)> {
+        let position = Duration::from_micros(self.position.load(Ordering::Relaxed));
+        let index = self
+            .visible_frames
+            .partition_point(|frame| frame.offset <= position)
+            .checked_sub(1)?;
+
+        Some((index, &self.visible_frames[index]))
+    }
+
+    /// Values of the first frame, as the camera sent them.
+    pub fn first_frame(&self) -> Option<Vec<u16>> {
+        let (_, bytes) = self.frames.first()?;
+
+        Some(
+            bytes
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+        )
+    }
+
+    /// Applies the settings of the session to `settings`, the rest of them stay as they are.
+    pub fn apply_settings(&self, settings: &mut Settings) -> anyhow::Result<()> {
+        profiles::apply(&self.profile, PROFILE_FILE, settings)
+    }
+}
+
+/// Port streaming the frames of a bundle at the pace they were recorded, failing like a
+/// disconnected camera after the last one.
+pub struct ReplayPort {
+    replay: Arc<Replay>,
+    started_at: Instant,
+    /// Index of the frame being read
+    frame: usize,
+    /// Bytes of that frame already read
+    position: usize,
+}
+
+impl io::Read for ReplayPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some((offset, bytes)) = self.replay.frames.get(self.frame) else {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "End of the replayed session",
+            ));
+        };
+
+        if self.position == 0 {
+            let due = self.started_at + *offset;
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+            self.replay.position.store(
+                u64::try_from(offset.as_micros()).unwrap_or(u64::MAX),
+                Ordering::Relaxed,
+            );
+        }
+
+        let len = buf.len().min(bytes.len() - self.position);
+        buf[..len].copy_from_slice(&bytes[self.position..self.position + len]);
+        self.position += len;
+
+        if self.position == bytes.len() {
+            self.frame += 1;
+            self.position = 0;
+        }
+
+        Ok(len)
+    }
+}
+
+// commands to the camera have no effect on a recording
+impl io::Write for ReplayPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Opens the replayed session in place of a camera, from the start on every reconnection.
+pub struct ReplayOpener {
+    replay: Arc<Replay>,
+}
+
+impl ReplayOpener {
+    pub fn new(replay: Arc<Replay>) -> Self {
+        Self { replay }
+    }
+}
+
+impl PortOpener<'static> for ReplayOpener {
+    type RW = ReplayPort;
+
+    fn open(&mut self) -> anyhow::Result<Self::RW> {
+        if self.replay.frames.is_empty() {
+            return Err(
+                PortError::new(PortErrorKind::NotFound, "The session has no frames").into(),
+            );
+        }
+
+        Ok(ReplayPort {
+            replay: Arc::clone(&self.replay),
+            started_at: Instant::now(),
+            frame: 0,
+            position: 0,
+        })
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: format!("Replay of {}", self.replay.name),
+            baud_rate: None,
+            serial_number: None,
+        }
+    }
+}
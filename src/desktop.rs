@@ -1,36 +1,317 @@
-use crate::thermal::PortOpener;
+use crate::network;
+use crate::thermal::{
+    ControlLine, PortError, PortErrorKind, PortInfo, PortOpener, Settings, Transport,
+};
+use crate::usb::CdcAcm;
 
-use anyhow::anyhow;
 use serialport::SerialPort;
-use std::{io, marker::PhantomData, time::Duration};
+use std::{io, marker::PhantomData, net::TcpStream, path::Path, time::Duration};
+
+const BAUD_RATE: u32 = 921_600;
+const VID: u16 = 0x303a;
+const PID: u16 = 0x4001;
+
+fn is_camera(port_info: &serialport::UsbPortInfo) -> bool {
+    port_info.vid == VID && port_info.pid == PID
+}
+
+/// macOS lists every device twice, as /dev/tty.* and /dev/cu.*. Opening the tty variant
+/// blocks until carrier detect is asserted, which the camera never does, so always use the
+/// cu variant.
+fn preferred_port_name(port_name: &str) -> String {
+    match port_name.strip_prefix("/dev/tty.") {
+        Some(device) if cfg!(target_os = "macos") => format!("/dev/cu.{device}"),
+        _ => port_name.to_owned(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Sandbox {
+    Flatpak,
+    Snap,
+}
+
+impl Sandbox {
+    fn detect() -> Option<Self> {
+        if std::env::var_os("FLATPAK_ID").is_some() || Path::new("/.flatpak-info").exists() {
+            Some(Sandbox::Flatpak)
+        } else if std::env::var_os("SNAP").is_some() {
+            Some(Sandbox::Snap)
+        } else {
+            None
+        }
+    }
+
+    fn guidance(self) -> &'static str {
+        match self {
+            Sandbox::Flatpak => {
+                "The Flatpak sandbox may be blocking access to serial devices. Grant it with: \
+                 flatpak override --user --device=all com.github.dobo90.Tiop01"
+            }
+            Sandbox::Snap => {
+                "The Snap sandbox may be blocking access to serial devices. Grant it with: \
+                 snap connect tiop01-gui:raw-usb && snap connect tiop01-gui:serial-port"
+            }
+        }
+    }
+
+    /// Appends the guidance to an error if running inside a sandbox.
+    fn explain(error: anyhow::Error) -> anyhow::Error {
+        match Self::detect() {
+            Some(sandbox) => {
+                let error = PortError::classify(&error);
+                PortError::new(
+                    error.kind,
+                    format!("{}. {}", error.message, sandbox.guidance()),
+                )
+                .into()
+            }
+            None => error,
+        }
+    }
+}
+
+fn open_error(error: &serialport::Error) -> anyhow::Error {
+    let kind = match error.kind {
+        serialport::ErrorKind::Io(io::ErrorKind::NotFound) => PortErrorKind::NotFound,
+        serialport::ErrorKind::Io(io::ErrorKind::PermissionDenied) => {
+            PortErrorKind::PermissionDenied
+        }
+        // Windows denies access to a port open elsewhere, Unix systems report EBUSY which
+        // serialport doesn't have a kind for
+        serialport::ErrorKind::NoDevice => PortErrorKind::Busy,
+        _ if error.description.to_lowercase().contains("busy") => PortErrorKind::Busy,
+        _ => PortErrorKind::Other,
+    };
+
+    PortError::new(kind, format!("Failed to open port: {error}")).into()
+}
+
+/// udev isn't reachable from inside Flatpak, so serialport can't enumerate anything. sysfs
+/// still is, which is enough to find the camera by its USB ids.
+#[cfg(target_os = "linux")]
+fn sysfs_camera_ports() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/sys/class/tty") else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+
+            if !name.starts_with("ttyACM") {
+                return None;
+            }
+
+            // device points at the USB interface, the ids live on its parent
+            let usb_device = entry.path().join("device/..");
+            let read_id = |attribute: &str| {
+                std::fs::read_to_string(usb_device.join(attribute))
+                    .ok()
+                    .and_then(|id| u16::from_str_radix(id.trim(), 16).ok())
+            };
+
+            (read_id("idVendor")? == VID && read_id("idProduct")? == PID)
+                .then(|| format!("/dev/{name}"))
+        })
+        .collect()
+}
 
 pub struct SerialPortOpener<'a> {
+    port: Option<String>,
+    dtr: ControlLine,
+    rts: ControlLine,
+    transport: Transport,
+    network_address: String,
+    timeout: Duration,
+    opened_port: Option<String>,
+    serial_number: Option<String>,
     phantom: PhantomData<&'a ()>,
 }
 
 impl<'a> SerialPortOpener<'a> {
     pub fn new() -> Self {
         Self {
+            port: None,
+            dtr: ControlLine::default(),
+            rts: ControlLine::default(),
+            transport: Transport::default(),
+            network_address: String::new(),
+            timeout: Duration::from_secs(1),
+            opened_port: None,
+            serial_number: None,
             phantom: PhantomData,
         }
     }
+
+    /// Returns the DTR and RTS states to set on open, `None` leaves the line untouched.
+    fn control_lines(&self) -> (Option<bool>, Option<bool>) {
+        // The ESP32-S2 CDC implementation only starts streaming once DTR is asserted, which
+        // macOS doesn't do on open. RTS stays deasserted so the chip isn't put into the
+        // bootloader.
+        let resolve = |line: ControlLine, macos_default: bool| match line {
+            ControlLine::Default => cfg!(target_os = "macos").then_some(macos_default),
+            ControlLine::Asserted => Some(true),
+            ControlLine::Deasserted => Some(false),
+        };
+
+        (resolve(self.dtr, true), resolve(self.rts, false))
+    }
+
+    fn find_port(&self) -> anyhow::Result<String> {
+        if let Some(ref port) = self.port {
+            return Ok(preferred_port_name(port));
+        }
+
+        let ports = match serialport::available_ports() {
+            Ok(ports) => ports,
+            Err(e) if Sandbox::detect().is_none() => return Err(e.into()),
+            Err(e) => {
+                log::warn!("Failed to enumerate serial ports inside a sandbox: {e}");
+                Vec::new()
+            }
+        };
+
+        let mut candidates: Vec<(Option<u8>, String)> = ports
+            .into_iter()
+            .filter_map(|port| match port.port_type {
+                serialport::SerialPortType::UsbPort(ref port_info) if is_camera(port_info) => {
+                    Some((port_info.interface, preferred_port_name(&port.port_name)))
+                }
+                _ => None,
+            })
+            .collect();
+
+        // A composite device exposes one port per CDC interface. The stream is served on the
+        // first one, so prefer the lowest interface number when it's known.
+        candidates.sort_by_key(|(interface, _)| interface.unwrap_or(u8::MAX));
+        candidates.dedup_by(|a, b| a.1 == b.1);
+
+        #[cfg(target_os = "linux")]
+        if candidates.is_empty() && Sandbox::detect().is_some() {
+            candidates = sysfs_camera_ports()
+                .into_iter()
+                .map(|port_name| (None, port_name))
+                .collect();
+        }
+
+        if candidates.len() > 1 {
+            log::info!("Found multiple camera ports: {candidates:?}, using the first one");
+        }
+
+        candidates
+            .into_iter()
+            .next()
+            .map(|(_, port_name)| port_name)
+            .ok_or_else(|| {
+                Sandbox::explain(
+                    PortError::new(PortErrorKind::NotFound, "Failed to find serial port").into(),
+                )
+            })
+    }
+}
+
+/// USB serial number of the device behind a serial port, if the platform reports it.
+fn serial_number(port_name: &str) -> Option<String> {
+    serialport::available_ports()
+        .ok()?
+        .into_iter()
+        .find(|port| preferred_port_name(&port.port_name) == port_name)
+        .and_then(|port| match port.port_type {
+            serialport::SerialPortType::UsbPort(port_info) => port_info.serial_number,
+            _ => None,
+        })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortDescription {
+    pub name: String,
+    pub label: String,
+    /// Whether the port belongs to a TIOP01 camera
+    pub camera: bool,
 }
 
-pub struct ThermalReadWrite(Box<dyn SerialPort>);
+fn describe_usb_port(port_name: &str, port_info: &serialport::UsbPortInfo) -> String {
+    // On Windows the product is the friendly name, e.g. "USB Serial Device (COM7)", which
+    // already contains the port name
+    let mut label = match port_info.product {
+        Some(ref product) if product.contains(port_name) => product.clone(),
+        Some(ref product) => format!("{product} ({port_name})"),
+        None => format!("{port_name} ({:04x}:{:04x})", port_info.vid, port_info.pid),
+    };
+
+    if let Some(ref manufacturer) = port_info.manufacturer {
+        label.push_str(&format!(" – {manufacturer}"));
+    }
+    if let Some(interface) = port_info.interface {
+        label.push_str(&format!(" [if {interface}]"));
+    }
+
+    label
+}
+
+pub fn available_ports() -> Vec<PortDescription> {
+    let ports = serialport::available_ports()
+        .inspect_err(|e| log::error!("Failed to enumerate serial ports: {e}"))
+        .unwrap_or_default();
+
+    let mut descriptions: Vec<PortDescription> = ports
+        .into_iter()
+        .map(|port| {
+            let (label, camera) = match port.port_type {
+                serialport::SerialPortType::UsbPort(ref port_info) => (
+                    describe_usb_port(&port.port_name, port_info),
+                    is_camera(port_info),
+                ),
+                _ => (port.port_name.clone(), false),
+            };
+
+            PortDescription {
+                name: preferred_port_name(&port.port_name),
+                label,
+                camera,
+            }
+        })
+        .collect();
+
+    let mut seen = std::collections::HashSet::new();
+    descriptions.retain(|description| seen.insert(description.name.clone()));
+
+    descriptions
+}
+
+pub enum ThermalReadWrite {
+    Serial(Box<dyn SerialPort>),
+    Usb(CdcAcm),
+    Network(TcpStream),
+}
 
 impl io::Read for ThermalReadWrite {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize, io::Error> {
-        self.0.read(buf)
+        match self {
+            ThermalReadWrite::Serial(port) => port.read(buf),
+            ThermalReadWrite::Usb(cdc) => cdc.read(buf),
+            ThermalReadWrite::Network(stream) => stream.read(buf),
+        }
     }
 }
 
 impl io::Write for ThermalReadWrite {
     fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
-        self.0.write(buf)
+        match self {
+            ThermalReadWrite::Serial(port) => port.write(buf),
+            ThermalReadWrite::Usb(cdc) => cdc.write(buf),
+            ThermalReadWrite::Network(stream) => stream.write(buf),
+        }
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
+        match self {
+            ThermalReadWrite::Serial(port) => port.flush(),
+            ThermalReadWrite::Usb(cdc) => cdc.flush(),
+            ThermalReadWrite::Network(stream) => stream.flush(),
+        }
     }
 }
 
@@ -38,26 +319,82 @@ impl<'a> PortOpener<'a> for SerialPortOpener<'a> {
     type RW = ThermalReadWrite;
 
     fn open(&mut self) -> anyhow::Result<Self::RW> {
-        let mut port_path: Option<String> = None;
+        let (dtr, rts) = self.control_lines();
+        self.serial_number = None;
 
-        for port in serialport::available_ports()? {
-            if let serialport::SerialPortType::UsbPort(port_info) = port.port_type {
-                if port_info.vid == 0x303a && port_info.pid == 0x4001 {
-                    port_path = Some(port.port_name);
-                }
+        match self.transport {
+            Transport::Serial => {}
+            Transport::Usb => {
+                let mut cdc =
+                    CdcAcm::open(VID, PID, BAUD_RATE, dtr, rts).map_err(Sandbox::explain)?;
+                cdc.set_timeout(self.timeout);
+                self.opened_port = Some(cdc.name().to_owned());
+                self.serial_number = cdc.serial_number().map(str::to_owned);
+
+                return Ok(ThermalReadWrite::Usb(cdc));
+            }
+            Transport::Network => {
+                let stream = network::connect(&self.network_address)?;
+                self.opened_port = Some(self.network_address.clone());
+
+                return Ok(ThermalReadWrite::Network(stream));
             }
         }
 
-        match port_path {
-            Some(port_path) => {
-                let port = serialport::new(port_path, 921_600)
-                    .timeout(Duration::from_secs(1))
-                    .open();
+        let port_path = self.find_port()?;
 
-                port.map(ThermalReadWrite)
-                    .map_err(|e| anyhow!("Failed to open port: {e}"))
-            }
-            None => Err(anyhow!("Failed to find serial port")),
+        let mut builder = serialport::new(&port_path, BAUD_RATE).timeout(self.timeout);
+
+        // setting DTR as part of open avoids toggling it afterwards, which may reset the chip
+        if let Some(dtr) = dtr {
+            builder = builder.dtr_on_open(dtr);
+        }
+
+        let port = builder.open();
+
+        self.opened_port = Some(port_path);
+
+        let mut port = port.map_err(|e| Sandbox::explain(open_error(&e)))?;
+        self.serial_number = self.opened_port.as_deref().and_then(serial_number);
+
+        if let Some(rts) = rts {
+            port.write_request_to_send(rts)?;
+        }
+
+        Ok(ThermalReadWrite::Serial(port))
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: self.opened_port.clone().unwrap_or_default(),
+            baud_rate: (self.transport != Transport::Network).then_some(BAUD_RATE),
+            serial_number: self.serial_number.clone(),
         }
     }
+
+    fn writer(&self, rw: &Self::RW) -> Option<Box<dyn io::Write + Send>> {
+        let writer: io::Result<Box<dyn io::Write + Send>> = match rw {
+            ThermalReadWrite::Serial(port) => port
+                .try_clone()
+                .map(|port| Box::new(port) as _)
+                .map_err(io::Error::from),
+            ThermalReadWrite::Usb(cdc) => Ok(Box::new(cdc.writer())),
+            ThermalReadWrite::Network(stream) => {
+                stream.try_clone().map(|stream| Box::new(stream) as _)
+            }
+        };
+
+        writer
+            .inspect_err(|e| log::warn!("Failed to split the port: {e}"))
+            .ok()
+    }
+
+    fn configure(&mut self, settings: &Settings) {
+        self.port.clone_from(&settings.port);
+        self.dtr = settings.dtr;
+        self.rts = settings.rts;
+        self.transport = settings.transport;
+        self.network_address.clone_from(&settings.network_address);
+        self.timeout = Duration::from_secs_f32(settings.serial_timeout);
+    }
 }
@@ -0,0 +1,74 @@
+//! Entry points of the fuzz targets in `fuzz/`.
+
+use crate::app::{ConnectionStatus, ProducerMessage, UiMessage};
+use crate::mock::MockOpener;
+use crate::thermal::{
+    FilteringMethod, ImageProducer, Settings, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH,
+};
+
+use eframe::egui;
+use std::sync::mpsc;
+
+const FRAME_BYTES: usize = 2 * THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT;
+
+/// Settings picked by the bits of `flags`, so the processing steps get fuzzed too.
+fn settings(flags: u8) -> Settings {
+    let flag = |bit: u8| flags & (1 << bit) != 0;
+
+    Settings {
+        filtering_method: match flags & 0b11 {
+            0 => FilteringMethod::None,
+            1 => FilteringMethod::Box3x3,
+            _ => FilteringMethod::Gaussian3x3,
+        },
+        flip_horizontally: flag(2),
+        flip_vertically: flag(3),
+        frames_to_average: if flag(4) { 4 } else { 1 },
+        stabilization: flag(5),
+        baseline_difference: flag(6),
+        centered_span: flag(7),
+        ..Settings::default()
+    }
+}
+
+/// Runs the producer over `data` as the stream of a camera, its first byte choosing the
+/// settings. Panics when the producer does, or when it gets stuck instead of consuming a frame
+/// or disconnecting at every step.
+pub fn producer(data: &[u8]) {
+    let Some((&flags, stream)) = data.split_first() else {
+        return;
+    };
+
+    let (ui_sender, producer_receiver) = mpsc::channel();
+    let (producer_sender, ui_receiver) = mpsc::channel();
+    let _ = ui_sender.send(UiMessage::ChangeSettings(settings(flags)));
+
+    let mut producer = ImageProducer::new(
+        egui::Context::default(),
+        producer_sender,
+        producer_receiver,
+        MockOpener::default().connect_raw(stream.to_vec()),
+    );
+
+    let max_steps = stream.len() / FRAME_BYTES + 2;
+    for _ in 0..max_steps {
+        producer.step();
+
+        for message in ui_receiver.try_iter() {
+            match message {
+                ProducerMessage::Frame(frame) => {
+                    assert!(frame.min <= frame.max);
+                    assert_eq!(
+                        frame.values.data().len(),
+                        THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT
+                    );
+                }
+                // reopening would wait for a second, the stream is over anyway
+                ProducerMessage::ConnectionStatusChange(ConnectionStatus::Disconnected) => return,
+                _ => {}
+            }
+        }
+    }
+
+    panic!("The producer hasn't finished the stream in {max_steps} steps");
+}
@@ -0,0 +1,111 @@
+use eframe::egui;
+
+/// Corners of the unit square, clockwise from the top left one.
+pub const UNIT_SQUARE: [egui::Pos2; 4] = [
+    egui::pos2(0.0, 0.0),
+    egui::pos2(1.0, 0.0),
+    egui::pos2(1.0, 1.0),
+    egui::pos2(0.0, 1.0),
+];
+
+/// Perspective transform between two images, in texture coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Homography([[f32; 3]; 3]);
+
+impl Homography {
+    /// Maps the unit square onto `rect`.
+    pub fn from_rect(rect: egui::Rect) -> Self {
+        Self([
+            [rect.width(), 0.0, rect.min.x],
+            [0.0, rect.height(), rect.min.y],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Maps the unit square onto a quadrilateral, `None` when it's degenerate.
+    pub fn from_quad(corners: [egui::Pos2; 4]) -> Option<Self> {
+        let pairs: Vec<_> = UNIT_SQUARE.into_iter().zip(corners).collect();
+        Self::from_points(&pairs)
+    }
+
+    /// Least squares fit of the transform mapping the first point of each pair onto the second
+    /// one. Needs at least four pairs, no three of them on a line.
+    pub fn from_points(pairs: &[(egui::Pos2, egui::Pos2)]) -> Option<Self> {
+        if pairs.len() < 4 {
+            return None;
+        }
+
+        // normal equations of the direct linear transform with the last element fixed to 1
+        let mut matrix = [[0.0_f64; 9]; 8];
+
+        for &(from, to) in pairs {
+            let (x, y) = (f64::from(from.x), f64::from(from.y));
+            let (u, v) = (f64::from(to.x), f64::from(to.y));
+            let rows = [
+                [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u],
+                [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v],
+            ];
+
+            for row in &rows {
+                for i in 0..8 {
+                    for j in 0..9 {
+                        matrix[i][j] += row[i] * row[j];
+                    }
+                }
+            }
+        }
+
+        let h = solve(matrix)?;
+
+        #[allow(clippy::cast_possible_truncation)]
+        Some(Self([
+            [h[0] as f32, h[1] as f32, h[2] as f32],
+            [h[3] as f32, h[4] as f32, h[5] as f32],
+            [h[6] as f32, h[7] as f32, 1.0],
+        ]))
+    }
+
+    pub fn map(&self, pos: egui::Pos2) -> egui::Pos2 {
+        let [a, b, c] = self.0;
+        let w = c[0] * pos.x + c[1] * pos.y + c[2];
+
+        egui::pos2(
+            (a[0] * pos.x + a[1] * pos.y + a[2]) / w,
+            (b[0] * pos.x + b[1] * pos.y + b[2]) / w,
+        )
+    }
+}
+
+/// Gaussian elimination with partial pivoting of an augmented 8x8 system.
+fn solve(mut matrix: [[f64; 9]; 8]) -> Option<[f64; 8]> {
+    const EPSILON: f64 = 1e-12;
+
+    for column in 0..8 {
+        let pivot = (column..8)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+
+        if matrix[pivot][column].abs() < EPSILON {
+            return None;
+        }
+
+        matrix.swap(column, pivot);
+
+        let pivot_row = matrix[column];
+        for (index, row) in matrix.iter_mut().enumerate() {
+            if index != column {
+                let factor = row[column] / pivot_row[column];
+
+                for (value, pivot_value) in row.iter_mut().zip(pivot_row).skip(column) {
+                    *value -= factor * pivot_value;
+                }
+            }
+        }
+    }
+
+    let mut solution = [0.0; 8];
+    for (i, value) in solution.iter_mut().enumerate() {
+        *value = matrix[i][8] / matrix[i][i];
+    }
+
+    Some(solution)
+}
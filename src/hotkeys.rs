@@ -0,0 +1,163 @@
+use crate::i18n::tr;
+
+use eframe::egui::{self, Key, Modifiers};
+use std::fmt::Write;
+use strum::IntoEnumIterator;
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, EnumIter)]
+pub enum Action {
+    Pause,
+    #[strum(to_string = "Cycle color map")]
+    CycleColorMap,
+    #[strum(to_string = "Flip horizontally")]
+    FlipHorizontally,
+    #[strum(to_string = "Flip vertically")]
+    FlipVertically,
+    #[strum(to_string = "Toggle fullscreen")]
+    ToggleFullscreen,
+    #[strum(to_string = "Acknowledge alarm")]
+    AcknowledgeAlarm,
+    #[strum(to_string = "Lock color span")]
+    LockSpan,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    #[strum(to_string = "Save snapshot")]
+    Snapshot,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    #[strum(to_string = "Start/stop recording")]
+    ToggleRecording,
+}
+
+impl Action {
+    fn default_key(self) -> Key {
+        match self {
+            Action::Pause => Key::Space,
+            Action::CycleColorMap => Key::C,
+            Action::FlipHorizontally => Key::H,
+            Action::FlipVertically => Key::V,
+            Action::ToggleFullscreen => Key::F11,
+            Action::AcknowledgeAlarm => Key::A,
+            Action::LockSpan => Key::L,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            Action::Snapshot => Key::S,
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            Action::ToggleRecording => Key::R,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hotkeys {
+    bindings: Vec<(Action, Key)>,
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        Self {
+            bindings: Action::iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+        }
+    }
+}
+
+impl Hotkeys {
+    pub fn key(&self, action: Action) -> Option<Key> {
+        self.bindings
+            .iter()
+            .find(|(a, _)| *a == action)
+            .map(|(_, key)| *key)
+    }
+
+    pub fn set(&mut self, action: Action, key: Key) {
+        match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+            Some(binding) => binding.1 = key,
+            None => self.bindings.push((action, key)),
+        }
+    }
+
+    /// Parses the `action = key` lines of [`Hotkeys::format`] over the defaults, unknown actions
+    /// and keys are skipped.
+    pub fn parse(contents: &str) -> Self {
+        let mut hotkeys = Self::default();
+
+        for (action, key) in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+        {
+            let action = Action::iter().find(|a| a.to_string() == action.trim());
+            if let (Some(action), Some(key)) = (action, Key::from_name(key.trim())) {
+                hotkeys.set(action, key);
+            }
+        }
+
+        hotkeys
+    }
+
+    pub fn format(&self) -> anyhow::Result<String> {
+        let mut contents = String::from("# Tiop01 keyboard shortcuts\n");
+        for (action, key) in &self.bindings {
+            writeln!(contents, "{action} = {}", key.name())?;
+        }
+
+        Ok(contents)
+    }
+
+    /// Returns the actions whose keys were pressed this frame. Keys are ignored while a
+    /// widget (e.g. a text field) has keyboard focus.
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<Action> {
+        if ctx.wants_keyboard_input() {
+            return Vec::new();
+        }
+
+        ctx.input_mut(|i| {
+            self.bindings
+                .iter()
+                .filter(|(_, key)| i.consume_key(Modifiers::NONE, *key))
+                .map(|(action, _)| *action)
+                .collect()
+        })
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("hotkeys").num_columns(2).show(ui, |ui| {
+            for action in Action::iter() {
+                let mut current = self.key(action);
+
+                ui.label(tr(&action.to_string()));
+                egui::ComboBox::from_id_salt(("hotkey", action as usize))
+                    .selected_text(current.map_or(tr("None"), Key::name))
+                    .show_ui(ui, |ui| {
+                        for key in Key::ALL {
+                            ui.selectable_value(&mut current, Some(*key), key.name());
+                        }
+                    });
+                ui.end_row();
+
+                if let Some(key) = current {
+                    self.set(action, key);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_bindings() {
+        let mut hotkeys = Hotkeys::default();
+        hotkeys.set(Action::Pause, Key::P);
+        hotkeys.set(Action::LockSpan, Key::F5);
+
+        assert_eq!(Hotkeys::parse(&hotkeys.format().unwrap()), hotkeys);
+        assert_eq!(
+            Hotkeys::parse("Pause = Nonsense\nSneeze = X"),
+            Hotkeys::default()
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! HTTP server for tools which poll, e.g. Node-RED, IFTTT or a dashboard in a browser.
+//! `GET /status.json` returns whether the camera is connected with the statistics of the last
+//! frame, in the format of `--json-output`, and `GET /frame.png` the last image. CORS is allowed
+//! from any origin, so pages served elsewhere can fetch them too.
+
+use crate::report;
+use crate::tcp_server;
+use crate::thermal;
+
+use anyhow::Context;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+pub const DEFAULT_PORT: u16 = 8080;
+
+/// Longest request accepted, the requests of the endpoints are far shorter
+const MAX_REQUEST_SIZE: usize = 8192;
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What the endpoints serve, `None` while the camera is disconnected
+#[derive(Default)]
+pub struct Status {
+    /// Statistics of the last frame as a JSON object
+    pub stats: Option<String>,
+    pub image: Option<thermal::RgbImage>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: &'static str, content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn text(status: &'static str) -> Self {
+        Self::new(status, "text/plain; charset=utf-8", format!("{status}\n"))
+    }
+}
+
+/// Reads the request head, the endpoints take no body.
+fn read_request(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request too large",
+            ));
+        }
+
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+
+    Ok(request)
+}
+
+fn respond(request: &[u8], status: &Mutex<Status>) -> Response {
+    let request = String::from_utf8_lossy(request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (method, target) = (request_line.next(), request_line.next().unwrap_or_default());
+    // the query string, e.g. a cache buster, doesn't matter
+    let path = target.split('?').next().unwrap_or_default();
+
+    match method {
+        Some("GET") => {}
+        // preflight of a cross-origin request, the headers are added to every response
+        Some("OPTIONS") => return Response::new("204 No Content", "text/plain", ""),
+        _ => return Response::text("405 Method Not Allowed"),
+    }
+
+    let status = status.lock().unwrap_or_else(|e| e.into_inner());
+
+    match path {
+        "/status.json" => {
+            let body = format!(
+                "{{\"connected\":{},\"frame\":{}}}\n",
+                status.stats.is_some(),
+                status.stats.as_deref().unwrap_or("null")
+            );
+            Response::new("200 OK", "application/json", body)
+        }
+        "/frame.png" => match status.image {
+            Some(ref image) => match report::encode_png(image) {
+                Ok(png) => Response::new("200 OK", "image/png", png),
+                Err(e) => {
+                    log::error!("Failed to encode the frame: {e}");
+                    Response::text("500 Internal Server Error")
+                }
+            },
+            None => Response::text("503 Service Unavailable"),
+        },
+        _ => Response::text("404 Not Found"),
+    }
+}
+
+fn serve(mut stream: TcpStream, status: &Mutex<Status>) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    let response = respond(&read_request(&mut stream)?, status);
+    let head = format!(
+        "HTTP/1.1 {}\r\n\
+         Content-Type: {}\r\n\
+         Content-Length: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, OPTIONS\r\n\
+         Access-Control-Allow-Headers: *\r\n\
+         Connection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(&response.body)
+}
+
+pub struct HttpServer {
+    status: Arc<Mutex<Status>>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Listens on `port` of every interface, a request is served by a thread of its own.
+    pub fn start(port: u16) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .with_context(|| format!("Failed to listen on port {port}"))?;
+        listener.set_nonblocking(true)?;
+
+        let status = Arc::new(Mutex::new(Status::default()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let status = Arc::clone(&status);
+            let stopped = Arc::clone(&stopped);
+
+            thread::Builder::new()
+                .name("http".to_owned())
+                .spawn(move || {
+                    tcp_server::serve_nonblocking("HTTP", &listener, &stopped, move |stream| {
+                        serve(stream, &status)
+                    });
+                })?
+        };
+
+        Ok(Self {
+            status,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn update(&self, update: impl FnOnce(&mut Status)) {
+        let mut status = self.status.lock().unwrap_or_else(|e| e.into_inner());
+        update(&mut status);
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(path: &str, status: Status) -> Response {
+        let request = format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        respond(request.as_bytes(), &Mutex::new(status))
+    }
+
+    #[test]
+    fn reports_a_disconnected_camera() {
+        let response = get("/status.json", Status::default());
+
+        assert_eq!(response.status, "200 OK");
+        assert_eq!(response.body, b"{\"connected\":false,\"frame\":null}\n");
+        assert_eq!(
+            get("/frame.png", Status::default()).status,
+            "503 Service Unavailable"
+        );
+    }
+
+    #[test]
+    fn serves_the_stats_whatever_the_query() {
+        let status = Status {
+            stats: Some("{\"max\":64.8}".to_owned()),
+            image: None,
+        };
+        let response = get("/status.json?t=1", status);
+
+        assert_eq!(response.content_type, "application/json");
+        assert_eq!(
+            response.body,
+            b"{\"connected\":true,\"frame\":{\"max\":64.8}}\n"
+        );
+    }
+
+    #[test]
+    fn answers_preflights_and_rejects_other_methods() {
+        let status = Mutex::new(Status::default());
+
+        assert_eq!(
+            respond(b"OPTIONS /status.json HTTP/1.1\r\n\r\n", &status).status,
+            "204 No Content"
+        );
+        assert_eq!(
+            respond(b"POST /status.json HTTP/1.1\r\n\r\n", &status).status,
+            "405 Method Not Allowed"
+        );
+        assert_eq!(get("/", Status::default()).status, "404 Not Found");
+    }
+}
@@ -0,0 +1,466 @@
+use eframe::egui;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use strum_macros::{Display, EnumIter};
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum Language {
+    #[default]
+    Auto,
+    English,
+    #[strum(to_string = "Deutsch")]
+    German,
+    #[strum(to_string = "Polski")]
+    Polish,
+    #[strum(to_string = "中文")]
+    Chinese,
+}
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum DecimalSeparator {
+    #[default]
+    Auto,
+    #[strum(to_string = "Point (1.5)")]
+    Point,
+    #[strum(to_string = "Comma (1,5)")]
+    Comma,
+}
+
+// Languages (ISO 639-1) which use a decimal comma
+const DECIMAL_COMMA_LANGUAGES: &[&str] = &[
+    "bg", "cs", "da", "de", "el", "es", "fi", "fr", "hr", "hu", "id", "it", "lt", "lv", "nb", "nl",
+    "no", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk", "vi",
+];
+
+// Each row holds the English text (used as the key) followed by the German, Polish and
+// Chinese translations.
+#[rustfmt::skip]
+static TRANSLATIONS: &[[&str; 4]] = &[
+    ["Tiop01 thermal camera GUI", "Tiop01 Wärmebildkamera", "Tiop01 – kamera termowizyjna", "Tiop01 热像仪"],
+    ["Tiop01 thermal view", "Tiop01 Wärmebild", "Tiop01 – obraz termowizyjny", "Tiop01 热成像视图"],
+    ["Thermal view", "Wärmebild", "Obraz termowizyjny", "热成像视图"],
+    ["Disconnected", "Getrennt", "Rozłączono", "未连接"],
+    ["Waiting for permission…", "Warte auf Berechtigung…", "Oczekiwanie na uprawnienia…", "正在等待授权…"],
+    ["Permission denied — tap to retry", "Berechtigung verweigert – zum Wiederholen tippen", "Odmowa dostępu – dotknij, aby ponowić", "权限被拒绝 — 点击重试"],
+    ["Min", "Min", "Min", "最低"],
+    ["max", "max", "maks", "最高"],
+    ["Max", "Max", "Maks", "最高"],
+    ["FPS", "FPS", "FPS", "帧率"],
+    ["paused", "pausiert", "wstrzymano", "已暂停"],
+    ["power saving", "Energiesparen", "oszczędzanie energii", "省电"],
+    ["Display", "Anzeige", "Wyświetlanie", "显示"],
+    ["Processing", "Verarbeitung", "Przetwarzanie", "处理"],
+    ["Device", "Gerät", "Urządzenie", "设备"],
+    ["Layout", "Layout", "Układ", "布局"],
+    ["Keyboard shortcuts", "Tastenkürzel", "Skróty klawiszowe", "键盘快捷键"],
+    ["Detach image", "Bild abtrennen", "Odłącz obraz", "分离图像"],
+    ["Flip vertically", "Vertikal spiegeln", "Odbij w pionie", "垂直翻转"],
+    ["Flip horizontally", "Horizontal spiegeln", "Odbij w poziomie", "水平翻转"],
+    ["Color map", "Farbpalette", "Paleta kolorów", "色表"],
+    ["Color range", "Farbbereich", "Zakres kolorów", "色彩范围"],
+    ["Pause", "Pause", "Pauza", "暂停"],
+    ["Save snapshot", "Schnappschuss speichern", "Zapisz zrzut", "保存快照"],
+    ["Share", "Teilen", "Udostępnij", "分享"],
+    ["Add marker", "Markierung hinzufügen", "Dodaj znacznik", "添加标记"],
+    ["Clear markers", "Markierungen entfernen", "Usuń znaczniki", "清除标记"],
+    ["Reset zoom", "Zoom zurücksetzen", "Resetuj powiększenie", "重置缩放"],
+    ["Saved to", "Gespeichert in", "Zapisano w", "已保存到"],
+    ["Failed to save snapshot", "Schnappschuss konnte nicht gespeichert werden", "Nie udało się zapisać zrzutu", "保存快照失败"],
+    ["Resume", "Fortsetzen", "Wznów", "继续"],
+    ["Settings", "Einstellungen", "Ustawienia", "设置"],
+    ["Filtering method", "Filtermethode", "Metoda filtrowania", "滤波方法"],
+    ["Edge strategy", "Randbehandlung", "Obsługa krawędzi", "边缘策略"],
+    ["Emissivity", "Emissionsgrad", "Emisyjność", "发射率"],
+    ["Always on top", "Immer im Vordergrund", "Zawsze na wierzchu", "窗口置顶"],
+    ["Borderless", "Randlos", "Bez ramki", "无边框"],
+    ["Settings position", "Position der Einstellungen", "Położenie ustawień", "设置位置"],
+    ["Compact", "Kompakt", "Kompaktowy", "紧凑"],
+    ["UI scale", "UI-Skalierung", "Skala interfejsu", "界面缩放"],
+    ["Large font", "Große Schrift", "Duża czcionka", "大字体"],
+    ["Language", "Sprache", "Język", "语言"],
+    ["Cycle color map", "Farbpalette wechseln", "Zmień paletę", "切换色表"],
+    ["Toggle fullscreen", "Vollbild umschalten", "Przełącz pełny ekran", "切换全屏"],
+    ["None", "Keine", "Brak", "无"],
+    ["Gaussian 3x3", "Gauß 3x3", "Gaussa 3x3", "高斯 3x3"],
+    ["Constant", "Konstant", "Stała", "常数"],
+    ["Extend", "Erweitern", "Rozszerzenie", "延伸"],
+    ["Wrap", "Umbrechen", "Zawijanie", "环绕"],
+    ["Mirror", "Spiegeln", "Odbicie", "镜像"],
+    ["Auto", "Automatisch", "Automatycznie", "自动"],
+    ["Left", "Links", "Po lewej", "左"],
+    ["Right", "Rechts", "Po prawej", "右"],
+    ["Bottom", "Unten", "Na dole", "下"],
+    ["Decimal separator", "Dezimaltrennzeichen", "Separator dziesiętny", "小数点"],
+    ["Precision", "Genauigkeit", "Dokładność", "精度"],
+    ["Maximum frame rate", "Maximale Bildrate", "Maksymalna liczba klatek", "最大帧率"],
+    ["Fullscreen view", "Vollbildansicht", "Widok pełnoekranowy", "全屏视图"],
+    ["Alarm", "Alarm", "Alarm", "报警"],
+    ["Enabled", "Aktiviert", "Włączony", "启用"],
+    ["Threshold", "Schwellwert", "Próg", "阈值"],
+    ["Vibrate", "Vibrieren", "Wibracje", "振动"],
+    ["Sound", "Ton", "Dźwięk", "声音"],
+    ["Phone camera", "Handykamera", "Aparat telefonu", "手机相机"],
+    ["Camera overlay", "Kamera-Überlagerung", "Nakładka z aparatu", "相机叠加"],
+    ["Opacity", "Deckkraft", "Krycie", "不透明度"],
+    ["Horizontal offset", "Horizontaler Versatz", "Przesunięcie poziome", "水平偏移"],
+    ["Vertical offset", "Vertikaler Versatz", "Przesunięcie pionowe", "垂直偏移"],
+    ["Scale", "Skalierung", "Skala", "缩放比例"],
+    ["Reset alignment", "Ausrichtung zurücksetzen", "Resetuj wyrównanie", "重置对齐"],
+    ["Camera permission required", "Kameraberechtigung erforderlich", "Wymagane uprawnienie do aparatu", "需要相机权限"],
+    ["Webcam", "Webcam", "Kamera internetowa", "网络摄像头"],
+    ["No webcam found", "Keine Webcam gefunden", "Nie znaleziono kamery internetowej", "未找到网络摄像头"],
+    ["Mode", "Modus", "Tryb", "模式"],
+    ["Blend", "Überblenden", "Nakładanie", "混合"],
+    ["Picture in picture", "Bild im Bild", "Obraz w obrazie", "画中画"],
+    ["Calibrate alignment", "Ausrichtung kalibrieren", "Kalibruj wyrównanie", "校准对齐"],
+    ["Alignment calibration", "Ausrichtungskalibrierung", "Kalibracja wyrównania", "对齐校准"],
+    ["Click a point in the camera image, then the same point in the thermal image. At least four pairs are needed.", "Klicken Sie auf einen Punkt im Kamerabild und dann auf denselben Punkt im Wärmebild. Es werden mindestens vier Paare benötigt.", "Kliknij punkt na obrazie z aparatu, a potem ten sam punkt na obrazie termowizyjnym. Potrzebne są co najmniej cztery pary.", "先点击相机图像中的一个点，再点击热成像中的同一点。至少需要四对点。"],
+    ["Waiting for the camera", "Warten auf die Kamera", "Oczekiwanie na aparat", "正在等待相机"],
+    ["Undo", "Rückgängig", "Cofnij", "撤销"],
+    ["Apply", "Anwenden", "Zastosuj", "应用"],
+    ["Points can't be aligned", "Punkte können nicht ausgerichtet werden", "Nie można wyrównać punktów", "无法对齐这些点"],
+    ["Perspective correction", "Perspektivkorrektur", "Korekcja perspektywy", "透视校正"],
+    ["Drag the corners", "Ecken ziehen", "Przeciągnij narożniki", "拖动角点"],
+    ["Done", "Fertig", "Gotowe", "完成"],
+    ["Cancel", "Abbrechen", "Anuluj", "取消"],
+    ["Reset", "Zurücksetzen", "Resetuj", "重置"],
+    ["Lens distortion (k1)", "Objektivverzeichnung (k1)", "Dystorsja obiektywu (k1)", "镜头畸变 (k1)"],
+    ["Lens distortion (k2)", "Objektivverzeichnung (k2)", "Dystorsja obiektywu (k2)", "镜头畸变 (k2)"],
+    ["Stabilization", "Stabilisierung", "Stabilizacja", "防抖"],
+    ["Panorama", "Panorama", "Panorama", "全景"],
+    ["Sweep the camera slowly over the scene.", "Schwenken Sie die Kamera langsam über die Szene.", "Powoli przesuwaj kamerę nad sceną.", "将相机缓慢扫过场景。"],
+    ["Start", "Starten", "Rozpocznij", "开始"],
+    ["Stop", "Stoppen", "Zatrzymaj", "停止"],
+    ["Clear", "Leeren", "Wyczyść", "清除"],
+    ["Overlays", "Überlagerungen", "Nakładki", "叠加层"],
+    ["Pixel grid", "Pixelraster", "Siatka pikseli", "像素网格"],
+    ["Coordinates", "Koordinaten", "Współrzędne", "坐标"],
+    ["Scale bar", "Maßstabsleiste", "Podziałka", "比例尺"],
+    ["Distance", "Entfernung", "Odległość", "距离"],
+    ["Field of view", "Sichtfeld", "Pole widzenia", "视场角"],
+    ["Include in snapshots", "In Schnappschüsse aufnehmen", "Dołącz do zrzutów", "包含在快照中"],
+    ["Watermark", "Wasserzeichen", "Znak wodny", "水印"],
+    ["Text", "Text", "Tekst", "文字"],
+    ["Logo", "Logo", "Logo", "标志"],
+    ["Load", "Laden", "Wczytaj", "加载"],
+    ["Remove", "Entfernen", "Usuń", "移除"],
+    ["Failed to load the logo", "Logo konnte nicht geladen werden", "Nie udało się wczytać logo", "无法加载标志"],
+    ["Position", "Position", "Położenie", "位置"],
+    ["Top left", "Oben links", "Lewy górny róg", "左上"],
+    ["Top right", "Oben rechts", "Prawy górny róg", "右上"],
+    ["Bottom left", "Unten links", "Lewy dolny róg", "左下"],
+    ["Bottom right", "Unten rechts", "Prawy dolny róg", "右下"],
+    ["Report", "Bericht", "Raport", "报告"],
+    ["Title", "Titel", "Tytuł", "标题"],
+    ["Notes", "Notizen", "Notatki", "备注"],
+    ["Note", "Notiz", "Notatka", "注释"],
+    ["Add snapshot", "Schnappschuss hinzufügen", "Dodaj zrzut", "添加快照"],
+    ["File", "Datei", "Plik", "文件"],
+    ["Generate report", "Bericht erstellen", "Generuj raport", "生成报告"],
+    ["Failed to save the report", "Bericht konnte nicht gespeichert werden", "Nie udało się zapisać raportu", "无法保存报告"],
+    ["Inspection report", "Inspektionsbericht", "Raport z inspekcji", "检测报告"],
+    ["Generated", "Erstellt", "Wygenerowano", "生成时间"],
+    ["Application", "Anwendung", "Aplikacja", "应用程序"],
+    ["Camera", "Kamera", "Kamera", "相机"],
+    ["Snapshot", "Schnappschuss", "Zrzut", "快照"],
+    ["Marker", "Markierung", "Znacznik", "标记"],
+    ["Pixel", "Pixel", "Piksel", "像素"],
+    ["Temperature", "Temperatur", "Temperatura", "温度"],
+    ["Inspection points (one per line)", "Inspektionspunkte (einer pro Zeile)", "Punkty inspekcji (jeden w wierszu)", "检测点（每行一个）"],
+    ["Start survey", "Begehung starten", "Rozpocznij przegląd", "开始巡检"],
+    ["Survey", "Begehung", "Przegląd", "巡检"],
+    ["All points have been inspected.", "Alle Punkte wurden geprüft.", "Wszystkie punkty zostały sprawdzone.", "所有检测点均已完成。"],
+    ["Back", "Zurück", "Wstecz", "上一个"],
+    ["Capture", "Aufnehmen", "Zarejestruj", "拍摄"],
+    ["Skip", "Überspringen", "Pomiń", "跳过"],
+    ["Temperature offset", "Temperaturversatz", "Przesunięcie temperatury", "温度偏移"],
+    ["Bad pixels", "Defekte Pixel", "Uszkodzone piksele", "坏点"],
+    ["Mark as bad pixel", "Als defektes Pixel markieren", "Oznacz jako uszkodzony piksel", "标记为坏点"],
+    ["Unmark bad pixel", "Defekt-Markierung entfernen", "Usuń oznaczenie uszkodzonego piksela", "取消坏点标记"],
+    ["Serial number", "Seriennummer", "Numer seryjny", "序列号"],
+    ["Save device profile", "Geräteprofil speichern", "Zapisz profil urządzenia", "保存设备配置"],
+    ["Device profile loaded", "Geräteprofil geladen", "Wczytano profil urządzenia", "已加载设备配置"],
+    ["Failed to load device profile", "Geräteprofil konnte nicht geladen werden", "Nie udało się wczytać profilu urządzenia", "加载设备配置失败"],
+    ["Failed to save device profile", "Geräteprofil konnte nicht gespeichert werden", "Nie udało się zapisać profilu urządzenia", "保存设备配置失败"],
+    ["Sonification", "Sonifikation", "Sonifikacja", "声音提示"],
+    ["Follow", "Folgen", "Śledź", "跟随"],
+    ["Center spot", "Mittelpunkt", "Punkt centralny", "中心点"],
+    ["Clicks", "Klicks", "Kliknięcia", "咔嗒声"],
+    ["Pitch", "Tonhöhe", "Wysokość tonu", "音高"],
+    ["Silent below", "Still unter", "Cisza poniżej", "静音低于"],
+    ["Maximum at", "Maximum bei", "Maksimum przy", "最大值于"],
+    ["OSC output", "OSC-Ausgabe", "Wyjście OSC", "OSC 输出"],
+    ["Address", "Adresse", "Adres", "地址"],
+    ["Grid size", "Rastergröße", "Rozmiar siatki", "网格大小"],
+    ["Port", "Port", "Port", "端口"],
+    ["HTTP server", "HTTP-Server", "Serwer HTTP", "HTTP 服务器"],
+    ["Frames to average", "Zu mittelnde Bilder", "Uśrednianych klatek", "平均帧数"],
+    ["Difference from baseline", "Abweichung von der Grundlinie", "Różnica względem linii bazowej", "与基线的差异"],
+    ["Baseline period", "Grundlinienzeitraum", "Okres linii bazowej", "基线周期"],
+    ["difference", "Abweichung", "różnica", "差异"],
+    ["Alarm events", "Alarmereignisse", "Zdarzenia alarmowe", "报警事件"],
+    ["Export", "Exportieren", "Eksportuj", "导出"],
+    ["Failed to export the alarm log", "Alarmprotokoll konnte nicht exportiert werden", "Nie udało się wyeksportować dziennika alarmów", "导出报警日志失败"],
+    ["Acknowledge", "Quittieren", "Potwierdź", "确认"],
+    ["Acknowledge alarm", "Alarm quittieren", "Potwierdź alarm", "确认报警"],
+    ["Tone", "Signalton", "Sygnał", "提示音"],
+    ["Beep", "Piepton", "Pisk", "蜂鸣"],
+    ["Chime", "Gong", "Gong", "钟声"],
+    ["Siren", "Sirene", "Syrena", "警笛"],
+    ["Test", "Testen", "Testuj", "试听"],
+    ["Volume", "Lautstärke", "Głośność", "音量"],
+    ["Repeat until acknowledged", "Bis zur Quittierung wiederholen", "Powtarzaj do potwierdzenia", "重复直到确认"],
+    ["Repeat every", "Wiederholen alle", "Powtarzaj co", "重复间隔"],
+    ["Hot spot trail", "Hotspot-Spur", "Ślad gorącego punktu", "热点轨迹"],
+    ["Trail length", "Spurlänge", "Długość śladu", "轨迹长度"],
+    ["Markers", "Markierungen", "Znaczniki", "标记"],
+    ["Click or tap the image to add a marker", "Zum Hinzufügen einer Markierung auf das Bild klicken oder tippen", "Kliknij lub dotknij obraz, aby dodać znacznik", "点击图像以添加标记"],
+    ["Saved layouts", "Gespeicherte Layouts", "Zapisane układy", "已保存的布局"],
+    ["Layout name", "Layoutname", "Nazwa układu", "布局名称"],
+    ["Save", "Speichern", "Zapisz", "保存"],
+    ["Delete", "Löschen", "Usuń", "删除"],
+    ["Failed to load the layout", "Layout konnte nicht geladen werden", "Nie udało się wczytać układu", "加载布局失败"],
+    ["Failed to save the layout", "Layout konnte nicht gespeichert werden", "Nie udało się zapisać układu", "保存布局失败"],
+    ["Hot components", "Heiße Bauteile", "Gorące elementy", "高温元件"],
+    ["Components", "Bauteile", "Elementy", "元件数"],
+    ["Warm-up", "Aufwärmen", "Rozgrzewanie", "预热"],
+    ["Capturing in", "Aufnahme in", "Zapis za", "拍摄倒计时"],
+    ["Find hot components", "Heiße Bauteile finden", "Znajdź gorące elementy", "查找高温元件"],
+    ["Center on temperature", "Auf Temperatur zentrieren", "Wyśrodkuj na temperaturze", "以温度为中心"],
+    ["Center", "Mitte", "Środek", "中心"],
+    ["Span", "Spanne", "Zakres", "跨度"],
+    ["Center colors here", "Farben hier zentrieren", "Wyśrodkuj kolory tutaj", "以此处为颜色中心"],
+    ["Measure on the filtered image", "Am gefilterten Bild messen", "Mierz na przefiltrowanym obrazie", "在滤波后的图像上测量"],
+    ["Keep screen on while connected", "Bildschirm eingeschaltet lassen, solange verbunden", "Nie wygaszaj ekranu podczas połączenia", "连接时保持屏幕常亮"],
+    ["System", "System", "Systemowy", "跟随系统"],
+    ["Dark", "Dunkel", "Ciemny", "深色"],
+    ["Light", "Hell", "Jasny", "浅色"],
+    ["Accent color", "Akzentfarbe", "Kolor akcentu", "强调色"],
+    ["Background color", "Hintergrundfarbe", "Kolor tła", "背景色"],
+    ["Serial port", "Serielle Schnittstelle", "Port szeregowy", "串口"],
+    ["Select serial port", "Serielle Schnittstelle auswählen", "Wybierz port szeregowy", "选择串口"],
+    ["Auto-detect", "Automatisch erkennen", "Wykryj automatycznie", "自动检测"],
+    ["Refresh", "Aktualisieren", "Odśwież", "刷新"],
+    ["DTR on open", "DTR beim Öffnen", "DTR przy otwarciu", "打开时 DTR"],
+    ["RTS on open", "RTS beim Öffnen", "RTS przy otwarciu", "打开时 RTS"],
+    ["Source", "Quelle", "Źródło", "来源"],
+    ["Simulated camera", "Simulierte Kamera", "Symulowana kamera", "模拟相机"],
+    ["Playback file", "Wiedergabedatei", "Plik do odtworzenia", "回放文件"],
+    ["Play", "Abspielen", "Odtwórz", "播放"],
+    ["Transport", "Übertragung", "Transmisja", "传输方式"],
+    ["Network", "Netzwerk", "Sieć", "网络"],
+    ["Network address", "Netzwerkadresse", "Adres sieciowy", "网络地址"],
+    ["Direct USB", "Direktes USB", "Bezpośrednio przez USB", "直接 USB"],
+    ["Additional cameras", "Weitere Kameras", "Dodatkowe kamery", "其他相机"],
+    ["Add camera", "Kamera hinzufügen", "Dodaj kamerę", "添加相机"],
+    ["Connected", "Verbunden", "Połączono", "已连接"],
+    ["Platform default", "Plattformstandard", "Domyślne dla platformy", "平台默认"],
+    ["Asserted", "Gesetzt", "Ustawiony", "置位"],
+    ["Deasserted", "Nicht gesetzt", "Wyzerowany", "复位"],
+    ["Point (1.5)", "Punkt (1.5)", "Kropka (1.5)", "点 (1.5)"],
+    ["This browser supports neither Web Serial nor WebUSB", "Dieser Browser unterstützt weder Web Serial noch WebUSB", "Ta przeglądarka nie obsługuje ani Web Serial, ani WebUSB", "此浏览器既不支持 Web Serial 也不支持 WebUSB"],
+    ["Comma (1,5)", "Komma (1,5)", "Przecinek (1,5)", "逗号 (1,5)"],
+    ["Camera not found", "Kamera nicht gefunden", "Nie znaleziono kamery", "未找到相机"],
+    ["Access denied", "Zugriff verweigert", "Odmowa dostępu", "访问被拒绝"],
+    ["Port busy", "Schnittstelle belegt", "Port zajęty", "端口被占用"],
+    ["Unexpected data", "Unerwartete Daten", "Nieoczekiwane dane", "意外的数据"],
+    ["Camera not responding", "Kamera antwortet nicht", "Kamera nie odpowiada", "相机无响应"],
+    ["Connection failed", "Verbindung fehlgeschlagen", "Połączenie nie powiodło się", "连接失败"],
+    ["Check that the camera is plugged in, or pick its port", "Prüfen Sie, ob die Kamera angeschlossen ist, oder wählen Sie ihre Schnittstelle", "Sprawdź, czy kamera jest podłączona, lub wybierz jej port", "请检查相机是否已连接，或选择其端口"],
+    ["Allow access to the port, on Linux by joining the dialout group", "Erlauben Sie den Zugriff auf die Schnittstelle, unter Linux über die Gruppe dialout", "Zezwól na dostęp do portu, w Linuksie przez dołączenie do grupy dialout", "请允许访问该端口，在 Linux 上可加入 dialout 组"],
+    ["Close the other program using the camera", "Schließen Sie das andere Programm, das die Kamera verwendet", "Zamknij inny program używający kamery", "请关闭正在使用相机的其他程序"],
+    ["Make sure the port belongs to the camera", "Stellen Sie sicher, dass die Schnittstelle zur Kamera gehört", "Upewnij się, że port należy do kamery", "请确认该端口属于相机"],
+    ["Replug the camera if it doesn't recover", "Stecken Sie die Kamera neu ein, falls sie sich nicht erholt", "Podłącz kamerę ponownie, jeśli nie wróci do działania", "如果无法恢复，请重新插拔相机"],
+    ["Troubleshooting", "Fehlerbehebung", "Rozwiązywanie problemów", "故障排除"],
+    ["Save debug bundle", "Debug-Paket speichern", "Zapisz pakiet diagnostyczny", "保存调试包"],
+    ["Recent frames from the camera, the log and the settings, to attach to bug reports", "Letzte Bilder der Kamera, das Protokoll und die Einstellungen, zum Anhängen an Fehlerberichte", "Ostatnie klatki z kamery, dziennik i ustawienia do dołączenia do zgłoszeń błędów", "相机的最近帧、日志和设置，可附加到错误报告中"],
+    ["Collecting…", "Wird gesammelt…", "Zbieranie…", "正在收集…"],
+    ["Failed to save the debug bundle", "Debug-Paket konnte nicht gespeichert werden", "Nie udało się zapisać pakietu diagnostycznego", "保存调试包失败"],
+    ["Crash report", "Absturzbericht", "Raport awarii", "崩溃报告"],
+    ["The app crashed the last time it ran. Please attach the crash report to a bug report.", "Die App ist beim letzten Start abgestürzt. Bitte hängen Sie den Absturzbericht an einen Fehlerbericht an.", "Aplikacja uległa awarii podczas ostatniego uruchomienia. Dołącz raport awarii do zgłoszenia błędu.", "应用上次运行时崩溃了。请将崩溃报告附加到错误报告中。"],
+    ["Open", "Öffnen", "Otwórz", "打开"],
+    ["Dismiss", "Verwerfen", "Odrzuć", "忽略"],
+    ["Read timeout", "Lese-Timeout", "Limit czasu odczytu", "读取超时"],
+    ["Read size", "Lesegröße", "Rozmiar odczytu", "读取大小"],
+    ["Whole frame", "Ganzes Bild", "Cała klatka", "整帧"],
+    ["64 bytes", "64 Byte", "64 bajty", "64 字节"],
+    ["512 bytes", "512 Byte", "512 bajtów", "512 字节"],
+    ["Folder", "Ordner", "Folder", "文件夹"],
+    ["Saves the shown image as a PNG", "Speichert das angezeigte Bild als PNG", "Zapisuje wyświetlany obraz jako PNG", "将显示的图像保存为 PNG"],
+    ["Raw 16-bit data", "16-Bit-Rohdaten", "Surowe dane 16-bitowe", "16 位原始数据"],
+    ["Export CSV", "CSV exportieren", "Eksportuj CSV", "导出 CSV"],
+    ["Temperatures of the next frame in °C, before filtering", "Temperaturen des nächsten Bildes in °C, vor der Filterung", "Temperatury następnej klatki w °C, przed filtrowaniem", "下一帧的温度（°C），滤波之前"],
+    ["Failed to export the temperatures", "Temperaturen konnten nicht exportiert werden", "Nie udało się wyeksportować temperatur", "导出温度失败"],
+    ["Record", "Aufnehmen", "Nagrywaj", "录制"],
+    ["Stop recording", "Aufnahme beenden", "Zatrzymaj nagrywanie", "停止录制"],
+    ["Records the temperatures for replaying them later", "Nimmt die Temperaturen auf, um sie später wiederzugeben", "Nagrywa temperatury do późniejszego odtworzenia", "录制温度以便稍后回放"],
+    ["Recording to", "Aufnahme in", "Nagrywanie do", "正在录制到"],
+    ["Frames recorded", "Aufgenommene Bilder", "Nagrane klatki", "已录制帧数"],
+    ["Failed to record", "Aufnahme fehlgeschlagen", "Nie udało się nagrać", "录制失败"],
+    ["Video frame rate", "Video-Bildrate", "Liczba klatek wideo", "视频帧率"],
+    ["Video scale", "Video-Skalierung", "Skala wideo", "视频缩放"],
+    ["Record video", "Video aufnehmen", "Nagraj wideo", "录制视频"],
+    ["Stop video", "Video beenden", "Zatrzymaj wideo", "停止视频"],
+    ["Saves the shown images as a Motion JPEG video", "Speichert die angezeigten Bilder als Motion-JPEG-Video", "Zapisuje wyświetlane obrazy jako wideo Motion JPEG", "将显示的图像保存为 Motion JPEG 视频"],
+    ["Failed to save the video", "Video konnte nicht gespeichert werden", "Nie udało się zapisać wideo", "保存视频失败"],
+    ["Recent files", "Zuletzt geöffnet", "Ostatnie pliki", "最近的文件"],
+    ["Resume the last source at startup", "Letzte Quelle beim Start fortsetzen", "Wznów ostatnie źródło przy starcie", "启动时恢复上次的来源"],
+    ["Plays the most recent file again and applies the last measurement layout", "Spielt die zuletzt geöffnete Datei erneut ab und wendet das letzte Messlayout an", "Odtwarza ponownie ostatni plik i stosuje ostatni układ pomiarowy", "再次播放最近的文件并应用上次的测量布局"],
+    ["Range smoothing", "Bereichsglättung", "Wygładzanie zakresu", "范围平滑"],
+    ["How slowly the colors follow the coldest and the hottest pixel, 0 follows every frame", "Wie langsam die Farben dem kältesten und dem heißesten Pixel folgen, 0 folgt jedem Bild", "Jak wolno kolory podążają za najzimniejszym i najgorętszym pikselem, 0 podąża za każdą klatką", "颜色跟随最冷和最热像素的快慢，0 表示跟随每一帧"],
+    ["Ignored extreme pixels", "Ignorierte Extrempixel", "Pomijane skrajne piksele", "忽略的极端像素"],
+    ["Leaves this many of the hottest and of the coldest pixels out of the color span", "Lässt so viele der heißesten und der kältesten Pixel aus dem Farbbereich heraus", "Pomija tyle najgorętszych i najzimniejszych pikseli w zakresie kolorów", "将这么多最热和最冷的像素排除在色彩范围之外"],
+    ["Edit presets", "Voreinstellungen bearbeiten", "Edytuj ustawienia wstępne", "编辑预设"],
+    ["Preset name", "Name der Voreinstellung", "Nazwa ustawienia wstępnego", "预设名称"],
+    ["Saves the span, the color map and the alarm shown now", "Speichert den Bereich, die Farbpalette und den Alarm, die gerade eingestellt sind", "Zapisuje obecny zakres, mapę kolorów i alarm", "保存当前的范围、色彩映射和警报"],
+    ["Failed to save the presets", "Voreinstellungen konnten nicht gespeichert werden", "Nie udało się zapisać ustawień wstępnych", "保存预设失败"],
+    ["Lock color span", "Farbbereich sperren", "Zablokuj zakres kolorów", "锁定色彩范围"],
+    ["Keeps the colors of the current temperatures while the camera moves across a scene", "Behält die Farben der aktuellen Temperaturen bei, während die Kamera über eine Szene bewegt wird", "Zachowuje kolory obecnych temperatur podczas przesuwania kamery po scenie", "在相机移动扫过场景时保持当前温度的颜色"],
+    ["Show raw counts", "Rohwerte anzeigen", "Pokaż surowe wartości", "显示原始计数"],
+    ["Adds the values sent by the camera to the temperatures shown", "Ergänzt die angezeigten Temperaturen um die von der Kamera gesendeten Werte", "Dodaje do wyświetlanych temperatur wartości wysyłane przez kamerę", "在显示的温度旁附加相机发送的值"],
+    ["Camera values", "Kamerawerte", "Wartości kamery", "相机数值"],
+    ["Tenths of °C", "Zehntel °C", "Dziesiąte części °C", "0.1 °C"],
+    ["Hundredths of °C", "Hundertstel °C", "Setne części °C", "0.01 °C"],
+    ["Tenths of K", "Zehntel K", "Dziesiąte części K", "0.1 K"],
+    ["Custom", "Benutzerdefiniert", "Własne", "自定义"],
+    ["Per count", "Pro Zählwert", "Na jednostkę", "每计数"],
+    ["At count 0", "Bei Zählwert 0", "Przy wartości 0", "计数为 0 时"],
+    ["Min/max markers", "Min/Max-Markierungen", "Znaczniki min/maks", "最低/最高温度标记"],
+    ["Start/stop recording", "Aufnahme starten/beenden", "Rozpocznij/zatrzymaj nagrywanie", "开始/停止录制"],
+    ["Recordings folder", "Aufnahmeordner", "Folder nagrań", "录制文件夹"],
+];
+
+// Fonts with CJK glyphs which are commonly present on the supported platforms. egui's
+// default fonts don't cover Chinese.
+const CJK_FONT_PATHS: &[&str] = &[
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc",
+    "/system/fonts/NotoSansCJK-Regular.ttc",
+    "/System/Library/Fonts/PingFang.ttc",
+    "C:\\Windows\\Fonts\\msyh.ttc",
+    "C:\\Windows\\Fonts\\simsun.ttc",
+];
+
+static CURRENT_LANGUAGE: AtomicU8 = AtomicU8::new(0);
+static CJK_FONT_INSTALLED: AtomicBool = AtomicBool::new(false);
+static DECIMAL_COMMA: AtomicBool = AtomicBool::new(false);
+
+impl Language {
+    fn column(self) -> usize {
+        match self {
+            Language::Auto | Language::English => 0,
+            Language::German => 1,
+            Language::Polish => 2,
+            Language::Chinese => 3,
+        }
+    }
+
+    fn from_locale(locale: &str) -> Self {
+        match locale.get(..2) {
+            Some("de") => Language::German,
+            Some("pl") => Language::Polish,
+            Some("zh") => Language::Chinese,
+            _ => Language::English,
+        }
+    }
+
+    /// Resolves `Auto` to the language of the system locale.
+    pub fn resolve(self) -> Self {
+        match self {
+            Language::Auto => sys_locale::get_locale()
+                .map_or(Language::English, |locale| Self::from_locale(&locale)),
+            language => language,
+        }
+    }
+}
+
+impl DecimalSeparator {
+    /// Resolves `Auto` to the separator used by the system locale.
+    pub fn resolve(self) -> Self {
+        match self {
+            DecimalSeparator::Auto => {
+                let comma = sys_locale::get_locale().is_some_and(|locale| {
+                    locale
+                        .get(..2)
+                        .is_some_and(|language| DECIMAL_COMMA_LANGUAGES.contains(&language))
+                });
+
+                if comma {
+                    DecimalSeparator::Comma
+                } else {
+                    DecimalSeparator::Point
+                }
+            }
+            separator => separator,
+        }
+    }
+}
+
+fn install_cjk_font(ctx: &egui::Context) {
+    let Some(font) = CJK_FONT_PATHS
+        .iter()
+        .find_map(|path| std::fs::read(path).ok())
+    else {
+        log::warn!("No CJK font found, Chinese text won't be rendered correctly");
+        return;
+    };
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts
+        .font_data
+        .insert("cjk".into(), egui::FontData::from_owned(font).into());
+
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().push("cjk".into());
+    }
+
+    ctx.set_fonts(fonts);
+}
+
+pub fn set_language(ctx: &egui::Context, language: Language) {
+    let language = language.resolve();
+
+    if language == Language::Chinese && !CJK_FONT_INSTALLED.swap(true, Ordering::Relaxed) {
+        install_cjk_font(ctx);
+    }
+
+    CURRENT_LANGUAGE.store(
+        u8::try_from(language.column()).unwrap_or(0),
+        Ordering::Relaxed,
+    );
+}
+
+pub fn set_decimal_separator(separator: DecimalSeparator) {
+    DECIMAL_COMMA.store(
+        separator.resolve() == DecimalSeparator::Comma,
+        Ordering::Relaxed,
+    );
+}
+
+/// Formats a number with the given number of decimal places and the configured decimal
+/// separator.
+pub fn format_number(value: f64, precision: usize) -> String {
+    let formatted = format!("{value:.precision$}");
+
+    if DECIMAL_COMMA.load(Ordering::Relaxed) {
+        formatted.replace('.', ",")
+    } else {
+        formatted
+    }
+}
+
+/// Translates an English UI text to the current language. Texts without a translation are
+/// returned unchanged.
+pub fn tr(text: &str) -> &str {
+    let column = usize::from(CURRENT_LANGUAGE.load(Ordering::Relaxed));
+
+    if column == 0 {
+        return text;
+    }
+
+    TRANSLATIONS
+        .iter()
+        .find(|row| row[0] == text)
+        .map_or(text, |row| row[column])
+}
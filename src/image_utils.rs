@@ -1,6 +1,9 @@
+use eframe::egui;
 use eframe::emath::Numeric;
+use std::sync::{Arc, Mutex};
 
-use crate::thermal::{self, ColorRange};
+use crate::homography::Homography;
+use crate::thermal::{self, ColorRange, LensDistortion};
 
 #[derive(Debug)]
 pub enum Flip {
@@ -29,6 +32,80 @@ where
     }
 }
 
+/// Samples the image through a perspective transform of its texture coordinates, which maps
+/// each output pixel to the input pixel (nearest one) it shows.
+#[derive(Debug, Clone, Copy)]
+pub struct Warp(pub Homography);
+
+impl<T, C, U, D> image2::Filter<T, C, U, D> for Warp
+where
+    T: image2::Type,
+    C: image2::Color,
+    U: image2::Type,
+    D: image2::Color,
+{
+    fn compute_at(
+        &self,
+        pt: image2::Point,
+        input: &image2::Input<T, C>,
+        dest: &mut image2::DataMut<U, D>,
+    ) {
+        let width = input.images[0].width() as f32;
+        let height = input.images[0].height() as f32;
+        let uv = self.0.map(egui::pos2(
+            (pt.x as f32 + 0.5) / width,
+            (pt.y as f32 + 0.5) / height,
+        ));
+
+        let px = input.get_pixel(
+            [pixel(uv.x * width, width), pixel(uv.y * height, height)],
+            None,
+        );
+        px.copy_to_slice(dest);
+    }
+}
+
+/// Straightens the optics' distortion, each output pixel shows the input pixel (nearest one) its
+/// light ends up at.
+#[derive(Debug, Clone, Copy)]
+pub struct Undistort(pub LensDistortion);
+
+impl<T, C, U, D> image2::Filter<T, C, U, D> for Undistort
+where
+    T: image2::Type,
+    C: image2::Color,
+    U: image2::Type,
+    D: image2::Color,
+{
+    fn compute_at(
+        &self,
+        pt: image2::Point,
+        input: &image2::Input<T, C>,
+        dest: &mut image2::DataMut<U, D>,
+    ) {
+        let size = egui::vec2(
+            input.images[0].width() as f32,
+            input.images[0].height() as f32,
+        );
+        let pos =
+            egui::vec2(pt.x as f32 + 0.5, pt.y as f32 + 0.5) / size * 2.0 - egui::Vec2::splat(1.0);
+        let source = (self.0.distort(pos) + egui::Vec2::splat(1.0)) / 2.0 * size;
+
+        let px = input.get_pixel([pixel(source.x, size.x), pixel(source.y, size.y)], None);
+        px.copy_to_slice(dest);
+    }
+}
+
+/// Pixel at a coordinate in pixels, clamped to the image.
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn pixel(coordinate: f32, size: f32) -> usize {
+    coordinate.clamp(0.0, size - 1.0) as usize
+}
+
+pub fn to_color_image(image: &thermal::RgbImage) -> egui::ColorImage {
+    egui::ColorImage::from_rgb(image.size().into(), image.data())
+}
+
 pub fn generate_black_image(width: usize, height: usize) -> thermal::RgbImage {
     let mut imgbuf = thermal::RgbImage::new([width, height]);
     let black = [0, 0, 0];
@@ -40,11 +117,152 @@ pub fn generate_black_image(width: usize, height: usize) -> thermal::RgbImage {
     imgbuf
 }
 
-pub fn map_to_scaled_value(input: u16, min: u16, max: u16, color_range: ColorRange) -> f64 {
+/// Values of the pixels which aren't masked, see [`thermal::Frame::values`].
+pub fn unmasked<'a>(values: &'a [u16], mask: &'a [bool]) -> impl Iterator<Item = u16> + 'a {
+    values
+        .iter()
+        .zip(mask)
+        .filter(|(_, &masked)| !masked)
+        .map(|(&value, _)| value)
+}
+
+/// Values and indices of the pixels which aren't at [`INVALID_POSITION`] in `positions`.
+fn valid_pixels<'a>(
+    values: &'a thermal::GrayImage,
+    positions: &'a thermal::GrayImage,
+) -> impl Iterator<Item = (usize, u16)> + 'a {
+    values
+        .data()
+        .iter()
+        .zip(positions.data())
+        .enumerate()
+        .filter(|(_, (_, &position))| position != INVALID_POSITION)
+        .map(|(index, (&value, _))| (index, value))
+}
+
+/// Position of the hottest pixel of a valid count, `None` if there's none.
+pub fn hottest_pixel(
+    values: &thermal::GrayImage,
+    positions: &thermal::GrayImage,
+) -> Option<[usize; 2]> {
+    let width = values.width();
+
+    valid_pixels(values, positions)
+        .max_by_key(|&(_, value)| value)
+        .map(|(index, _)| [index % width, index / width])
+}
+
+/// Position of the coldest pixel of a valid count, `None` if there's none.
+pub fn coldest_pixel(
+    values: &thermal::GrayImage,
+    positions: &thermal::GrayImage,
+) -> Option<[usize; 2]> {
+    let width = values.width();
+
+    valid_pixels(values, positions)
+        .min_by_key(|&(_, value)| value)
+        .map(|(index, _)| [index % width, index / width])
+}
+
+/// Up to `count` hottest pixels which are at least `min_distance` pixels apart horizontally or
+/// vertically, hottest first, so that each lies on a distinct hot region.
+pub fn hot_spots(
+    values: &thermal::GrayImage,
+    count: usize,
+    min_distance: usize,
+) -> Vec<[usize; 2]> {
+    let width = values.width();
+    let mut pixels: Vec<_> = values.data().iter().copied().enumerate().collect();
+    pixels.sort_unstable_by(|(_, a), (_, b)| b.cmp(a));
+
+    let mut spots: Vec<[usize; 2]> = Vec::with_capacity(count);
+    for (index, _) in pixels {
+        if spots.len() == count {
+            break;
+        }
+
+        let pixel = [index % width, index / width];
+        if spots
+            .iter()
+            .all(|spot| spot[0].abs_diff(pixel[0]).max(spot[1].abs_diff(pixel[1])) >= min_distance)
+        {
+            spots.push(pixel);
+        }
+    }
+
+    spots
+}
+
+/// Maps the position of a value in its span (0 to 1) to the part of the color map `color_range`
+/// selects, centered.
+pub fn apply_color_range(position: f64, color_range: ColorRange) -> f64 {
     let color_range = color_range.to_f64() / 100.0;
-    let value = f64::from(input - min) / f64::from(max - min);
 
-    ((1.0 - color_range) / 2.0) + value * color_range
+    ((1.0 - color_range) / 2.0) + position * color_range
+}
+
+/// Position of `input` in the span from `min` to `max`, 0 to 1.
+pub fn position(input: u16, min: u16, max: u16) -> f64 {
+    f64::from(input - min) / f64::from(max - min)
+}
+
+pub fn map_to_scaled_value(input: u16, min: u16, max: u16, color_range: ColorRange) -> f64 {
+    apply_color_range(position(input, min, max), color_range)
+}
+
+/// Like [`position`] for a fixed span from `low` to `high` (raw values), values outside of it are
+/// at its ends.
+pub fn span_position(input: u16, low: f64, high: f64) -> f64 {
+    ((f64::from(input) - low) / (high - low)).clamp(0.0, 1.0)
+}
+
+/// Color of the pixels with invalid counts, in none of the color maps
+pub const INVALID_COLOR: [u8; 3] = [255, 0, 255];
+/// Position stored for a pixel with an invalid count, beyond those of [`encode_position`]
+pub const INVALID_POSITION: u16 = u16::MAX;
+
+/// Position (0 to 1) stored in a 16 bit image, see [`thermal::Frame::positions`].
+pub fn encode_position(position: f64) -> u16 {
+    (position.clamp(0.0, 1.0) * f64::from(INVALID_POSITION - 1)).round() as u16
+}
+
+/// Colors of a color map sampled for the positions of [`encode_position`], so an image is
+/// recolored with a lookup per pixel.
+pub struct ColorLut(Vec<[u8; 3]>);
+
+impl ColorLut {
+    const SIZE: usize = 1024;
+
+    pub fn new(
+        cmap: &(dyn scarlet::colormap::ColorMap<scarlet::color::RGBColor> + Sync),
+        color_range: ColorRange,
+    ) -> Self {
+        Self(
+            (0..Self::SIZE)
+                .map(|index| {
+                    let position = index as f64 / (Self::SIZE - 1) as f64;
+                    let color = cmap.transform_single(apply_color_range(position, color_range));
+                    [color.int_r(), color.int_g(), color.int_b()]
+                })
+                .collect(),
+        )
+    }
+
+    /// Colors `image` after the encoded `positions` of its pixels, [`INVALID_POSITION`] in
+    /// [`INVALID_COLOR`].
+    pub fn colorize(&self, positions: &thermal::GrayImage, image: &mut thermal::RgbImage) {
+        let scale = (Self::SIZE - 1) as f64 / f64::from(INVALID_POSITION - 1);
+
+        image.each_pixel_mut(|pt, pixel| {
+            let position = positions.get([pt.x, pt.y]).as_slice()[0];
+            if position == INVALID_POSITION {
+                pixel.copy_from_slice(INVALID_COLOR);
+                return;
+            }
+            let index = (f64::from(position) * scale).round() as usize;
+            pixel.copy_from_slice(self.0[index]);
+        });
+    }
 }
 
 pub fn generate_colormap_image(
@@ -69,3 +287,212 @@ pub fn generate_colormap_image(
 
     imgbuf
 }
+
+/// Mean of the most recent frames, updated incrementally: each new frame is added to the sums
+/// and the one falling out of the window subtracted.
+#[derive(Default)]
+pub struct FrameAverager {
+    frames: std::collections::VecDeque<thermal::GrayImage>,
+    sums: Vec<u32>,
+}
+
+impl FrameAverager {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Adds a frame and returns the mean of the last `count` ones (fewer until that many have
+    /// been seen).
+    pub fn push(&mut self, frame: &thermal::GrayImage, count: usize) -> thermal::GrayImage {
+        if self.sums.len() != frame.data().len() {
+            self.reset();
+            self.sums = vec![0; frame.data().len()];
+        }
+
+        for (sum, &value) in self.sums.iter_mut().zip(frame.data()) {
+            *sum += u32::from(value);
+        }
+        self.frames.push_back(frame.clone());
+
+        while self.frames.len() > count.max(1) {
+            if let Some(oldest) = self.frames.pop_front() {
+                for (sum, &value) in self.sums.iter_mut().zip(oldest.data()) {
+                    *sum -= u32::from(value);
+                }
+            }
+        }
+
+        let count = u32::try_from(self.frames.len()).unwrap_or(u32::MAX);
+        let mut mean = frame.clone();
+
+        for (value, &sum) in mean.data_mut().iter_mut().zip(&self.sums) {
+            // rounded, a mean of u16 values always fits a u16
+            *value = u16::try_from((sum + count / 2) / count).unwrap_or(u16::MAX);
+        }
+
+        mean
+    }
+}
+
+/// Slowly following mean of the frames, an exponential moving average with a time constant of
+/// the period given to [`RollingBaseline::update`].
+#[derive(Default)]
+pub struct RollingBaseline {
+    mean: Vec<f32>,
+    updated_at: Option<web_time::Instant>,
+}
+
+impl RollingBaseline {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Deviation of each pixel of `frame` from the baseline, which is then updated with the
+    /// frame. The first frame becomes the baseline.
+    pub fn update(&mut self, frame: &thermal::GrayImage, period: web_time::Duration) -> Vec<f32> {
+        let now = web_time::Instant::now();
+
+        if self.mean.len() != frame.data().len() {
+            self.mean = frame.data().iter().map(|&value| f32::from(value)).collect();
+            self.updated_at = Some(now);
+        }
+
+        let elapsed = self
+            .updated_at
+            .map_or(0.0, |updated_at| (now - updated_at).as_secs_f32());
+        let weight = 1.0 - (-elapsed / period.as_secs_f32().max(f32::EPSILON)).exp();
+        self.updated_at = Some(now);
+
+        frame
+            .data()
+            .iter()
+            .zip(&mut self.mean)
+            .map(|(&value, mean)| {
+                let value = f32::from(value);
+                let deviation = value - *mean;
+                *mean += (value - *mean) * weight;
+                deviation
+            })
+            .collect()
+    }
+}
+
+/// Minimum and maximum of `values` once the `outliers` lowest and highest ones are left out,
+/// `None` when none are.
+pub fn trimmed_range(values: &[u16], outliers: usize) -> Option<(u16, u16)> {
+    if outliers == 0 || values.is_empty() {
+        return None;
+    }
+
+    // at least the median is kept
+    let outliers = outliers.min((values.len() - 1) / 2);
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable();
+
+    Some((sorted[outliers], sorted[sorted.len() - 1 - outliers]))
+}
+
+/// Minimum and maximum of the frames, each an exponential moving average with a time constant of
+/// the period given to [`SmoothedRange::update`].
+#[derive(Default)]
+pub struct SmoothedRange {
+    range: Option<(f64, f64)>,
+    updated_at: Option<web_time::Instant>,
+}
+
+impl SmoothedRange {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Updates the range with the extremes of a frame (raw values) and returns it as a span for
+    /// [`span_position`]. The first frame sets the range.
+    pub fn update(&mut self, min: u16, max: u16, period: web_time::Duration) -> (f64, f64) {
+        let now = web_time::Instant::now();
+        let (min, max) = (f64::from(min), f64::from(max));
+
+        let elapsed = self
+            .updated_at
+            .map_or(0.0, |updated_at| (now - updated_at).as_secs_f64());
+        let weight = 1.0 - (-elapsed / period.as_secs_f64().max(f64::EPSILON)).exp();
+        self.updated_at = Some(now);
+
+        let (low, high) = self.range.map_or((min, max), |(low, high)| {
+            (low + (min - low) * weight, high + (max - high) * weight)
+        });
+        self.range = Some((low, high));
+
+        // a uniform frame is at the start of the color map, like with the range of the frame
+        (low, high.max(low + 1.0))
+    }
+}
+
+/// Position of a deviation from the baseline, no deviation being the middle of the color map and
+/// `span` either of its ends.
+pub fn deviation_position(deviation: f32, span: f32) -> f64 {
+    f64::from((0.5 + deviation / (2.0 * span)).clamp(0.0, 1.0))
+}
+
+/// Buffers kept for reuse, of each kind
+const MAX_POOLED_FRAMES: usize = 4;
+
+#[derive(Default)]
+struct PooledFrames {
+    gray: Vec<thermal::GrayImage>,
+    rgb: Vec<thermal::RgbImage>,
+}
+
+/// Buffers of the thermal frames, recycled by the producer and handed back by the UI once it's
+/// done with them, instead of allocating new ones for every frame. Clones share the buffers.
+#[derive(Clone, Default)]
+pub struct FramePool(Arc<Mutex<PooledFrames>>);
+
+impl FramePool {
+    /// Gray image of the thermal image size, still holding a previous frame.
+    pub fn gray_image(&self) -> thermal::GrayImage {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut pooled| pooled.gray.pop())
+            .unwrap_or_else(|| thermal::GrayImage::new(thermal::THERMAL_IMAGE_SIZE))
+    }
+
+    /// RGB image of the thermal image size, still holding a previous frame.
+    pub fn rgb_image(&self) -> thermal::RgbImage {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|mut pooled| pooled.rgb.pop())
+            .unwrap_or_else(|| thermal::RgbImage::new(thermal::THERMAL_IMAGE_SIZE))
+    }
+
+    pub fn recycle_gray_image(&self, image: thermal::GrayImage) {
+        if let Ok(mut pooled) = self.0.lock() {
+            if Self::reusable(image.width(), image.height(), pooled.gray.len()) {
+                pooled.gray.push(image);
+            }
+        }
+    }
+
+    pub fn recycle_rgb_image(&self, image: thermal::RgbImage) {
+        if let Ok(mut pooled) = self.0.lock() {
+            if Self::reusable(image.width(), image.height(), pooled.rgb.len()) {
+                pooled.rgb.push(image);
+            }
+        }
+    }
+
+    /// Recycles the buffers of a frame which won't be shown.
+    pub fn recycle(&self, frame: thermal::Frame) {
+        self.recycle_rgb_image(frame.image);
+        self.recycle_gray_image(frame.values);
+        self.recycle_gray_image(frame.positions);
+        if let Some(unfiltered) = frame.unfiltered {
+            self.recycle_gray_image(unfiltered);
+        }
+    }
+
+    fn reusable(width: usize, height: usize, pooled: usize) -> bool {
+        [width, height] == thermal::THERMAL_IMAGE_SIZE && pooled < MAX_POOLED_FRAMES
+    }
+}
@@ -2,22 +2,103 @@
 
 #[cfg(target_os = "android")]
 mod android;
-#[cfg(not(target_os = "android"))]
+#[cfg(target_os = "android")]
+mod android_camera;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod camera_grid;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod cli;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod crash_report;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod debug_bundle;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 mod desktop;
+#[cfg(not(any(target_os = "android", target_arch = "wasm32")))]
+mod network;
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+mod usb;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod video;
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_arch = "wasm32")]
+mod web_usb;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod webcam;
 
 #[cfg(target_os = "android")]
 use egui_winit::winit::platform::android::activity::AndroidApp;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod alarm_log;
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+mod alarm_sound;
 mod app;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "opencv")]
+pub mod cv;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+mod homography;
+mod hotkeys;
+#[cfg(not(target_arch = "wasm32"))]
+mod http;
+mod i18n;
 mod image_utils;
+#[cfg(not(target_arch = "wasm32"))]
+mod measurement_layouts;
+#[cfg(any(test, feature = "fuzz"))]
+#[cfg_attr(not(test), allow(dead_code))]
+mod mock;
+#[cfg(not(target_arch = "wasm32"))]
+mod modbus;
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+mod ntp;
+#[cfg(not(target_arch = "wasm32"))]
+mod osc;
+mod overlay;
+mod panorama;
+#[cfg(not(target_arch = "wasm32"))]
+mod profiles;
+mod protocol;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod recent;
+#[cfg(not(target_arch = "wasm32"))]
+mod report;
+#[cfg(all(
+    feature = "ros2",
+    not(any(target_os = "android", target_os = "ios", target_arch = "wasm32"))
+))]
+mod ros;
+#[cfg(not(target_arch = "wasm32"))]
+mod scene_presets;
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+#[cfg_attr(target_os = "android", allow(dead_code))]
+mod session;
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+mod simulator;
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+mod sonification;
+mod stabilization;
+#[cfg(not(target_arch = "wasm32"))]
+mod tcp_server;
 mod thermal;
+#[cfg(not(target_arch = "wasm32"))]
+mod zip;
 
+#[cfg(not(target_arch = "wasm32"))]
 use eframe::NativeOptions;
 
 #[cfg(feature = "profiling")]
 static PUFFIN_SERVER: std::sync::OnceLock<puffin_http::Server> = std::sync::OnceLock::new();
 
-fn _main(native_options: NativeOptions) -> eframe::Result<()> {
+#[cfg(not(target_arch = "wasm32"))]
+fn _main(
+    native_options: NativeOptions,
+    window_settings: app::WindowSettings,
+) -> eframe::Result<()> {
     #[cfg(feature = "profiling")]
     {
         puffin::set_scopes_on(true);
@@ -29,20 +110,83 @@ fn _main(native_options: NativeOptions) -> eframe::Result<()> {
     eframe::run_native(
         "Tiop01",
         native_options,
-        Box::new(|cc| Ok(Box::new(app::App::new(cc)))),
+        Box::new(|cc| Ok(Box::new(app::App::new(cc, window_settings)))),
     )
 }
 
-#[cfg(not(target_os = "android"))]
+/// Set by `--json-output`
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+static JSON_OUTPUT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Session replayed in place of the camera
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+static REPLAY_BUNDLE: std::sync::OnceLock<std::sync::Arc<debug_bundle::Replay>> =
+    std::sync::OnceLock::new();
+
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
 #[allow(dead_code)]
 fn main() -> Result<(), eframe::Error> {
-    env_logger::init();
+    debug_bundle::init_logging();
+    crash_report::install_panic_hook();
 
-    let native_options = NativeOptions {
-        ..eframe::NativeOptions::default()
+    let args = cli::Args::parse().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
+
+    if let Some(ref command) = args.command {
+        let result = match command {
+            cli::Command::Verify(path) => session::verify(path),
+            cli::Command::Migrate(path, output) => session::migrate(path, output),
+        };
+
+        match result {
+            Ok(verification) => {
+                println!("{verification}");
+                std::process::exit(if verification.is_valid() { 0 } else { 1 });
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(ref path) = args.replay_bundle {
+        let replay = debug_bundle::Replay::load(path).unwrap_or_else(|e| {
+            eprintln!("Failed to load {}: {e}", path.display());
+            std::process::exit(1);
+        });
+        let _ = REPLAY_BUNDLE.set(std::sync::Arc::new(replay));
+    }
+
+    JSON_OUTPUT.store(args.json_output, std::sync::atomic::Ordering::Relaxed);
+
+    #[cfg(feature = "ros2")]
+    if args.ros2 {
+        if let Err(e) = ros::run(REPLAY_BUNDLE.get().cloned()) {
+            eprintln!("ROS 2 node failed: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    let native_options = args.native_options();
+    let window_settings = app::WindowSettings {
+        always_on_top: args.always_on_top,
+        borderless: args.borderless || args.kiosk,
+        kiosk: args.kiosk,
     };
 
-    _main(native_options)
+    _main(native_options, window_settings)
+}
+
+#[cfg(target_os = "ios")]
+#[allow(dead_code)]
+fn main() -> Result<(), eframe::Error> {
+    env_logger::init();
+
+    _main(NativeOptions::default(), app::WindowSettings::default())
 }
 
 #[cfg(target_os = "android")]
@@ -57,14 +201,68 @@ extern "Rust" fn android_main(app: AndroidApp) {
         android_logger::Config::default().with_max_level(log::LevelFilter::Info),
     );
 
-    let _ = ANDROID_APP.set(app.clone());
+    // winit can't create a second event loop in a process, so an activity recreated while the
+    // process lives on (e.g. reopened after Back) is started again in a new one. The old producer
+    // is stopped first, so its recording gets finished. Rotating doesn't recreate the activity as
+    // long as it handles the configuration changes itself (android:configChanges in the manifest).
+    if ANDROID_APP.set(app.clone()).is_err() {
+        log::warn!("The activity has been recreated, restarting the app");
+        if !app::stop_producer() {
+            log::warn!("The producer hasn't stopped in time, restarting anyway");
+        }
+        if let Err(e) = android::relaunch(&app) {
+            log::error!("Failed to relaunch the app: {e}");
+        }
+        std::process::exit(0);
+    }
 
     let native_options = NativeOptions {
+        // eframe has no storage directory of its own on Android
+        persistence_path: app.internal_data_path().map(|path| path.join("app.ron")),
         event_loop_builder: Some(Box::new(move |builder| {
             builder.with_android_app(app);
         })),
         ..eframe::NativeOptions::default()
     };
 
-    let _ = _main(native_options);
+    let fullscreen = android::fullscreen_shortcut().unwrap_or_else(|e| {
+        log::error!("Failed to register the fullscreen shortcut: {e}");
+        false
+    });
+
+    // the fullscreen view is the kiosk UI, with the settings behind a button
+    let window_settings = app::WindowSettings {
+        kiosk: fullscreen,
+        ..app::WindowSettings::default()
+    };
+
+    let _ = _main(native_options, window_settings);
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen(start)]
+pub fn wasm_main() {
+    use wasm_bindgen::JsCast;
+
+    let _ = eframe::WebLogger::init(log::LevelFilter::Info);
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("tiop01_canvas"))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("Canvas tiop01_canvas not found");
+
+        let result = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(app::App::new(cc, app::WindowSettings::default())))),
+            )
+            .await;
+
+        if let Err(e) = result {
+            log::error!("Failed to start the app: {e:?}");
+        }
+    });
 }
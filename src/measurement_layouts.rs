@@ -0,0 +1,131 @@
+//! Measurement layouts: named sets of markers saved to files, so repeated inspections of the same
+//! board measure at identical points.
+
+use crate::overlay::Marker;
+use crate::profiles;
+use crate::thermal::{THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use anyhow::anyhow;
+use eframe::egui;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+fn directory() -> anyhow::Result<PathBuf> {
+    Ok(profiles::config_directory()
+        .ok_or_else(|| anyhow!("No directory for measurement layouts"))?
+        .join("layouts"))
+}
+
+fn path(name: &str) -> anyhow::Result<PathBuf> {
+    let file_name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if file_name.is_empty() {
+        return Err(anyhow!("The layout needs a name"));
+    }
+
+    Ok(directory()?.join(format!("{file_name}.layout")))
+}
+
+/// Names of the saved layouts, sorted.
+pub fn list() -> anyhow::Result<Vec<String>> {
+    let entries = match std::fs::read_dir(directory()?) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut names = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "layout")
+        {
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names.sort();
+
+    Ok(names)
+}
+
+/// Parses a marker stored as `x,y #rrggbb name`, the name being optional.
+fn parse_marker(line: &str) -> anyhow::Result<Marker> {
+    let mut parts = line.splitn(3, ' ');
+    let pixel = parts.next().unwrap_or_default();
+    let color = parts.next().ok_or_else(|| anyhow!("Missing color"))?;
+    let name = parts.next().unwrap_or_default().trim();
+
+    let (x, y) = pixel
+        .split_once(',')
+        .ok_or_else(|| anyhow!("Invalid pixel {pixel}"))?;
+    let pixel = [x.parse()?, y.parse()?];
+    if pixel[0] >= THERMAL_IMAGE_WIDTH || pixel[1] >= THERMAL_IMAGE_HEIGHT {
+        return Err(anyhow!("Pixel {x},{y} out of the image"));
+    }
+
+    let color = egui::Color32::from_hex(color).map_err(|_| anyhow!("Invalid color {color}"))?;
+
+    Ok(Marker {
+        pixel,
+        name: name.to_owned(),
+        color,
+    })
+}
+
+pub fn load(name: &str) -> anyhow::Result<Vec<Marker>> {
+    let path = path(name)?;
+    let contents = std::fs::read_to_string(&path)?;
+
+    contents
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| {
+            parse_marker(line).map_err(|e| anyhow!("{}:{}: {e}", path.display(), number + 1))
+        })
+        .collect()
+}
+
+/// Stores `markers` as the layout `name`, replacing one of the same name, returns its path.
+pub fn save(name: &str, markers: &[Marker]) -> anyhow::Result<PathBuf> {
+    let path = path(name)?;
+
+    let mut contents = format!("# Tiop01 measurement layout {}\n", name.trim());
+    for marker in markers {
+        let [x, y] = marker.pixel;
+        let [r, g, b, _] = marker.color.to_array();
+        writeln!(
+            contents,
+            "{x},{y} #{r:02x}{g:02x}{b:02x} {}",
+            marker.name.trim()
+        )?;
+    }
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(&path, contents)?;
+
+    Ok(path)
+}
+
+pub fn delete(name: &str) -> anyhow::Result<()> {
+    std::fs::remove_file(path(name)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+//! Scripted in-memory transport, so the producer can be tested without a camera.
+
+use crate::thermal::{PortError, PortErrorKind, PortInfo, PortOpener, THERMAL_IMAGE_SIZE};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::rc::Rc;
+
+/// Encodes a frame the way the camera sends it, tenths of °C as little-endian words.
+pub fn encode_frame(values: &[u16]) -> Vec<u8> {
+    assert_eq!(values.len(), THERMAL_IMAGE_SIZE[0] * THERMAL_IMAGE_SIZE[1]);
+
+    values
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+/// Port of the mock camera, streaming the scripted bytes and then timing out like a serial port
+/// does once the camera goes silent.
+pub struct MockPort {
+    input: VecDeque<u8>,
+    written: Rc<RefCell<Vec<u8>>>,
+}
+
+impl io::Read for MockPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.input.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Operation timed out",
+            ));
+        }
+
+        let len = buf.len().min(self.input.len());
+        for (dst, src) in buf.iter_mut().zip(self.input.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
+    }
+}
+
+impl io::Write for MockPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.borrow_mut().extend_from_slice(buf);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Opener going through a script of connections, each either failing or streaming its bytes.
+/// Once the script runs out, the camera isn't found anymore.
+#[derive(Default)]
+pub struct MockOpener {
+    script: VecDeque<anyhow::Result<Vec<u8>>>,
+    /// Everything written to the camera, over all the connections
+    pub written: Rc<RefCell<Vec<u8>>>,
+}
+
+impl MockOpener {
+    /// Adds a connection streaming `frames`.
+    pub fn connect(mut self, frames: &[Vec<u16>]) -> Self {
+        let input = frames
+            .iter()
+            .flat_map(|frame| encode_frame(frame))
+            .collect();
+        self.script.push_back(Ok(input));
+        self
+    }
+
+    /// Adds a connection streaming `bytes` as they are, e.g. a truncated frame.
+    pub fn connect_raw(mut self, bytes: Vec<u8>) -> Self {
+        self.script.push_back(Ok(bytes));
+        self
+    }
+
+    /// Adds an attempt to open the port which fails with `error`.
+    pub fn fail(mut self, error: anyhow::Error) -> Self {
+        self.script.push_back(Err(error));
+        self
+    }
+}
+
+impl PortOpener<'static> for MockOpener {
+    type RW = MockPort;
+
+    fn open(&mut self) -> anyhow::Result<Self::RW> {
+        let input = self.script.pop_front().unwrap_or_else(|| {
+            Err(PortError::new(PortErrorKind::NotFound, "The script has ended").into())
+        })?;
+
+        Ok(MockPort {
+            input: input.into(),
+            written: Rc::clone(&self.written),
+        })
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: "Mock".to_owned(),
+            baud_rate: None,
+            serial_number: None,
+        }
+    }
+}
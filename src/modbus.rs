@@ -0,0 +1,253 @@
+//! Modbus TCP server exposing the key values as registers, so PLCs and SCADA systems can poll
+//! the camera directly. The same registers are read as holding (function 3) or input registers
+//! (function 4), temperatures in tenths of °C as signed 16 bit integers:
+//!
+//! | Register | Value                                                                      |
+//! |----------|----------------------------------------------------------------------------|
+//! | 0        | Maximum temperature                                                        |
+//! | 1        | Minimum temperature                                                        |
+//! | 2        | Flags: alarm enabled (bit 0), active (1), acknowledged (2), connected (3)  |
+//! | 3        | Frame counter, wrapping, for noticing a stalled camera                     |
+//! | 4        | Number of markers                                                          |
+//! | 10–25    | Temperatures of the first 16 markers, `0x8000` without a marker             |
+
+use crate::tcp_server::{self, POLL_INTERVAL};
+
+use anyhow::Context;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+pub const MAX: usize = 0;
+pub const MIN: usize = 1;
+pub const FLAGS: usize = 2;
+pub const FRAME_COUNTER: usize = 3;
+pub const MARKER_COUNT: usize = 4;
+pub const MARKERS: usize = 10;
+pub const MAX_MARKERS: usize = 16;
+const REGISTER_COUNT: usize = MARKERS + MAX_MARKERS;
+
+pub const ALARM_ENABLED: u16 = 1 << 0;
+pub const ALARM_ACTIVE: u16 = 1 << 1;
+pub const ALARM_ACKNOWLEDGED: u16 = 1 << 2;
+pub const CONNECTED: u16 = 1 << 3;
+
+/// Register of a missing value
+pub const NO_VALUE: u16 = 0x8000;
+
+pub const DEFAULT_PORT: u16 = 1502;
+
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+const READ_INPUT_REGISTERS: u8 = 0x04;
+/// Largest number of registers a single request may read
+const MAX_QUANTITY: usize = 125;
+
+const ILLEGAL_FUNCTION: u8 = 0x01;
+const ILLEGAL_DATA_ADDRESS: u8 = 0x02;
+const ILLEGAL_DATA_VALUE: u8 = 0x03;
+
+/// Transaction, protocol, length and unit identifier
+const HEADER_SIZE: usize = 7;
+
+type Registers = Arc<Mutex<[u16; REGISTER_COUNT]>>;
+
+/// Temperature (°C) as a register.
+pub fn temperature(value: f64) -> u16 {
+    ((value * 10.0).round() as i16) as u16
+}
+
+/// Response to the protocol data unit of a request, a function code and its data.
+fn respond(request: &[u8], registers: &[u16]) -> Vec<u8> {
+    let exception = |function: u8, code: u8| vec![function | 0x80, code];
+
+    let Some(&function) = request.first() else {
+        return exception(0, ILLEGAL_FUNCTION);
+    };
+    if function != READ_HOLDING_REGISTERS && function != READ_INPUT_REGISTERS {
+        return exception(function, ILLEGAL_FUNCTION);
+    }
+
+    let &[_, start_high, start_low, quantity_high, quantity_low] = request else {
+        return exception(function, ILLEGAL_DATA_VALUE);
+    };
+    let start = usize::from(u16::from_be_bytes([start_high, start_low]));
+    let quantity = usize::from(u16::from_be_bytes([quantity_high, quantity_low]));
+
+    if !(1..=MAX_QUANTITY).contains(&quantity) {
+        return exception(function, ILLEGAL_DATA_VALUE);
+    }
+    let Some(values) = registers.get(start..start + quantity) else {
+        return exception(function, ILLEGAL_DATA_ADDRESS);
+    };
+
+    let mut response = vec![function, (quantity * 2) as u8];
+    for value in values {
+        response.extend_from_slice(&value.to_be_bytes());
+    }
+
+    response
+}
+
+fn serve(mut stream: TcpStream, registers: &Registers, stopped: &AtomicBool) -> io::Result<()> {
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    stream.set_nodelay(true)?;
+
+    let mut header = [0; HEADER_SIZE];
+
+    while !stopped.load(Ordering::Relaxed) {
+        // waits for a request without consuming it, a timeout in the middle of one would lose
+        // its start
+        match stream.peek(&mut [0]) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e),
+        }
+
+        stream.read_exact(&mut header)?;
+        // the length counts the unit identifier, already part of the header
+        let length = usize::from(u16::from_be_bytes([header[4], header[5]]));
+        let mut request = vec![0; length.saturating_sub(1)];
+        stream.read_exact(&mut request)?;
+
+        let response = {
+            let registers = registers.lock().unwrap_or_else(|e| e.into_inner());
+            respond(&request, registers.as_slice())
+        };
+
+        let mut packet = Vec::with_capacity(HEADER_SIZE + response.len());
+        packet.extend_from_slice(&header[..4]);
+        packet.extend_from_slice(&(response.len() as u16 + 1).to_be_bytes());
+        packet.push(header[6]);
+        packet.extend_from_slice(&response);
+        stream.write_all(&packet)?;
+    }
+
+    Ok(())
+}
+
+pub struct ModbusServer {
+    registers: Registers,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ModbusServer {
+    /// Listens on `port` of every interface, a client is served by a thread of its own.
+    pub fn start(port: u16) -> anyhow::Result<Self> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .with_context(|| format!("Failed to listen on port {port}"))?;
+        listener.set_nonblocking(true)?;
+
+        let mut initial = [0; REGISTER_COUNT];
+        initial[MAX] = NO_VALUE;
+        initial[MIN] = NO_VALUE;
+        initial[MARKERS..].fill(NO_VALUE);
+
+        let registers = Arc::new(Mutex::new(initial));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let registers = Arc::clone(&registers);
+            let stopped = Arc::clone(&stopped);
+
+            thread::Builder::new()
+                .name("modbus".to_owned())
+                .spawn(move || {
+                    let client_stopped = Arc::clone(&stopped);
+                    tcp_server::serve_nonblocking("Modbus", &listener, &stopped, move |stream| {
+                        serve(stream, &registers, &client_stopped)
+                    });
+                })?
+        };
+
+        Ok(Self {
+            registers,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+
+    /// Changes the registers, clients read them all changed or none.
+    pub fn update(&self, update: impl FnOnce(&mut [u16])) {
+        let mut registers = self.registers.lock().unwrap_or_else(|e| e.into_inner());
+        update(registers.as_mut_slice());
+    }
+}
+
+impl Drop for ModbusServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        // the clients notice it within a poll interval too, without being waited for
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REGISTERS: [u16; 4] = [0x0102, 0x0304, 0x0506, 0x0708];
+
+    #[test]
+    fn reads_registers() {
+        assert_eq!(
+            respond(&[READ_HOLDING_REGISTERS, 0, 1, 0, 2], &REGISTERS),
+            [READ_HOLDING_REGISTERS, 4, 0x03, 0x04, 0x05, 0x06]
+        );
+        assert_eq!(
+            respond(&[READ_INPUT_REGISTERS, 0, 3, 0, 1], &REGISTERS),
+            [READ_INPUT_REGISTERS, 2, 0x07, 0x08]
+        );
+    }
+
+    #[test]
+    fn rejects_other_functions() {
+        // write single register
+        assert_eq!(
+            respond(&[0x06, 0, 0, 0, 1], &REGISTERS),
+            [0x86, ILLEGAL_FUNCTION]
+        );
+    }
+
+    #[test]
+    fn rejects_reads_past_the_registers() {
+        assert_eq!(
+            respond(&[READ_HOLDING_REGISTERS, 0, 3, 0, 2], &REGISTERS),
+            [0x83, ILLEGAL_DATA_ADDRESS]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_quantities() {
+        assert_eq!(
+            respond(&[READ_HOLDING_REGISTERS, 0, 0, 0, 0], &REGISTERS),
+            [0x83, ILLEGAL_DATA_VALUE]
+        );
+        assert_eq!(
+            respond(&[READ_HOLDING_REGISTERS, 0, 0, 0, 126], &REGISTERS),
+            [0x83, ILLEGAL_DATA_VALUE]
+        );
+        assert_eq!(
+            respond(&[READ_HOLDING_REGISTERS, 0, 0], &REGISTERS),
+            [0x83, ILLEGAL_DATA_VALUE]
+        );
+    }
+
+    #[test]
+    fn encodes_negative_temperatures() {
+        assert_eq!(temperature(-12.3), 0xff85);
+        assert_eq!(temperature(64.8), 648);
+    }
+}
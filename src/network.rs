@@ -0,0 +1,77 @@
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::thermal::{PortError, PortErrorKind};
+#[cfg(target_os = "ios")]
+use crate::thermal::{PortInfo, PortOpener, Settings};
+
+use anyhow::Context;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Connects to a network source which streams the camera's serial data as is, e.g. ser2net or
+/// `socat TCP-LISTEN:4001,fork,reuseaddr /dev/ttyACM0,raw,b921600` running on the machine the
+/// camera is plugged into. Commands are forwarded to the camera the same way.
+pub fn connect(address: &str) -> anyhow::Result<TcpStream> {
+    if address.is_empty() {
+        return Err(
+            PortError::new(PortErrorKind::NotFound, "No network address configured").into(),
+        );
+    }
+
+    let socket_address = address
+        .to_socket_addrs()
+        .with_context(|| format!("Invalid network address {address}"))?
+        .next()
+        .ok_or_else(|| {
+            PortError::new(
+                PortErrorKind::NotFound,
+                format!("Failed to resolve {address}"),
+            )
+        })?;
+
+    let stream = TcpStream::connect_timeout(&socket_address, CONNECT_TIMEOUT)
+        .with_context(|| format!("Failed to connect to {address}"))?;
+
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    stream.set_nodelay(true)?;
+
+    Ok(stream)
+}
+
+/// iOS doesn't give apps access to USB serial devices, a network source is the only option
+/// there.
+#[cfg(target_os = "ios")]
+#[derive(Default)]
+pub struct NetworkOpener {
+    address: String,
+}
+
+#[cfg(target_os = "ios")]
+impl<'a> PortOpener<'a> for NetworkOpener {
+    type RW = TcpStream;
+
+    fn open(&mut self) -> anyhow::Result<Self::RW> {
+        connect(&self.address)
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: self.address.clone(),
+            baud_rate: None,
+            serial_number: None,
+        }
+    }
+
+    fn configure(&mut self, settings: &Settings) {
+        self.address.clone_from(&settings.network_address);
+    }
+
+    fn writer(&self, rw: &Self::RW) -> Option<Box<dyn std::io::Write + Send>> {
+        rw.try_clone()
+            .inspect_err(|e| log::warn!("Failed to split the connection: {e}"))
+            .ok()
+            .map(|stream| Box::new(stream) as _)
+    }
+}
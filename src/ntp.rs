@@ -0,0 +1,121 @@
+//! Offset of the system clock from an NTP server, measured with a single SNTP query, so the
+//! timestamps of sessions recorded on several machines can be correlated afterwards.
+
+use anyhow::{anyhow, Context};
+use std::net::UdpSocket;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_SERVER: &str = "pool.ntp.org:123";
+
+const PACKET_SIZE: usize = 48;
+/// Leap indicator 0, version 4, client mode
+const CLIENT_REQUEST: u8 = 0x23;
+const SERVER_MODE: u8 = 4;
+/// Seconds from the NTP era (1900) to the Unix epoch
+const UNIX_EPOCH_IN_NTP: f64 = 2_208_988_800.0;
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Seconds since the Unix epoch.
+fn unix_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64())
+}
+
+/// NTP timestamp, 32 bits of seconds and 32 of a fraction of a second.
+fn to_ntp(seconds: f64) -> u64 {
+    ((seconds + UNIX_EPOCH_IN_NTP) * f64::from(1u32 << 16) * f64::from(1u32 << 16)) as u64
+}
+
+fn from_ntp(timestamp: u64) -> f64 {
+    timestamp as f64 / f64::from(1u32 << 16) / f64::from(1u32 << 16) - UNIX_EPOCH_IN_NTP
+}
+
+fn timestamp(packet: &[u8; PACKET_SIZE], offset: usize) -> u64 {
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&packet[offset..offset + 8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Offset of the server's clock from ours given the time the request was sent (`sent`), received
+/// by the server (`received`), answered by it (`answered`) and the answer received (`returned`),
+/// assuming the way there takes as long as the way back.
+fn clock_offset(sent: f64, received: f64, answered: f64, returned: f64) -> f64 {
+    ((received - sent) + (answered - returned)) / 2.0
+}
+
+/// Offset of the system clock from a server's, with when it has been measured.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockOffset {
+    /// Seconds to add to the system clock
+    pub offset: f64,
+    pub measured_at: SystemTime,
+}
+
+impl ClockOffset {
+    pub fn measure(server: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            offset: measure_offset(server)?,
+            measured_at: SystemTime::now(),
+        })
+    }
+}
+
+/// Seconds to add to the system clock to get the time of `server`, a host and a port.
+pub fn measure_offset(server: &str) -> anyhow::Result<f64> {
+    let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+    socket.set_read_timeout(Some(TIMEOUT))?;
+    socket
+        .connect(server)
+        .with_context(|| format!("Failed to resolve {server}"))?;
+
+    let mut request = [0; PACKET_SIZE];
+    request[0] = CLIENT_REQUEST;
+    let sent = unix_seconds(SystemTime::now());
+    // echoed by the server as the originate timestamp, pairing the answer with the request
+    let transmit = to_ntp(sent);
+    request[40..].copy_from_slice(&transmit.to_be_bytes());
+    socket.send(&request)?;
+
+    let mut response = [0; PACKET_SIZE];
+    let len = socket
+        .recv(&mut response)
+        .with_context(|| format!("No answer from {server}"))?;
+    let returned = unix_seconds(SystemTime::now());
+
+    // stratum 0 is a "kiss-o'-death", the server refusing to answer
+    if len != PACKET_SIZE
+        || response[0] & 0x07 != SERVER_MODE
+        || response[1] == 0
+        || timestamp(&response, 24) != transmit
+    {
+        return Err(anyhow!("Invalid answer from {server}"));
+    }
+
+    Ok(clock_offset(
+        sent,
+        from_ntp(timestamp(&response, 32)),
+        from_ntp(timestamp(&response, 40)),
+        returned,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_timestamps() {
+        let seconds = 1_760_000_000.25;
+
+        assert_eq!(to_ntp(0.0) >> 32, 2_208_988_800);
+        assert!((from_ntp(to_ntp(seconds)) - seconds).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cancels_out_the_delay() {
+        // the server is 5 s ahead, the request and the answer take 0.1 s each
+        let offset = clock_offset(100.0, 105.1, 105.2, 100.3);
+
+        assert!((offset - 5.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,186 @@
+//! Frame statistics and a coarse grid of the temperatures sent over OSC (Open Sound Control), so
+//! TouchDesigner, Max or Pure Data can drive an installation from the camera. Every frame is a
+//! bundle of `/tiop01/min`, `/tiop01/max` and `/tiop01/mean` with a float each, and of
+//! `/tiop01/grid` with the mean of every cell of the grid, row by row. Temperatures are in °C.
+
+use crate::thermal::{self, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use anyhow::{anyhow, Context};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+const ADDRESS_PREFIX: &str = "/tiop01";
+/// Time tag of a bundle to be processed as soon as it arrives
+const IMMEDIATELY: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscSettings {
+    pub enabled: bool,
+    /// Host and port of the receiver
+    pub address: String,
+    /// Cells per side of the grid, the size of the image sends every pixel
+    pub grid_size: usize,
+}
+
+impl Default for OscSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:9000".to_owned(),
+            grid_size: 8,
+        }
+    }
+}
+
+/// Appends `string` null terminated and padded to a multiple of 4 bytes.
+fn write_string(packet: &mut Vec<u8>, string: &str) {
+    packet.extend_from_slice(string.as_bytes());
+    packet.resize((packet.len() / 4 + 1) * 4, 0);
+}
+
+/// Message of `address` with float arguments.
+fn message(address: &str, arguments: &[f32]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    write_string(&mut packet, address);
+    write_string(&mut packet, &format!(",{}", "f".repeat(arguments.len())));
+    for argument in arguments {
+        packet.extend_from_slice(&argument.to_be_bytes());
+    }
+
+    packet
+}
+
+fn bundle(messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    write_string(&mut packet, "#bundle");
+    packet.extend_from_slice(&IMMEDIATELY.to_be_bytes());
+    for message in messages {
+        packet.extend_from_slice(&(message.len() as u32).to_be_bytes());
+        packet.extend_from_slice(message);
+    }
+
+    packet
+}
+
+/// Mean temperature of each of the `size` × `size` cells, row by row.
+fn grid(values: &[u16], size: usize) -> Vec<f32> {
+    let size = size.clamp(1, THERMAL_IMAGE_WIDTH.min(THERMAL_IMAGE_HEIGHT));
+    let bounds = |index: usize, length: usize| index * length / size..(index + 1) * length / size;
+
+    (0..size)
+        .flat_map(|row| (0..size).map(move |column| (row, column)))
+        .map(|(row, column)| {
+            let cell: Vec<u16> = bounds(row, THERMAL_IMAGE_HEIGHT)
+                .flat_map(|y| {
+                    bounds(column, THERMAL_IMAGE_WIDTH)
+                        .map(move |x| values[y * THERMAL_IMAGE_WIDTH + x])
+                })
+                .collect();
+
+            mean(&cell)
+        })
+        .collect()
+}
+
+fn mean(values: &[u16]) -> f32 {
+    let sum: u64 = values.iter().map(|&value| u64::from(value)).sum();
+
+    sum as f32 / values.len().max(1) as f32 / 10.0
+}
+
+pub struct OscSender {
+    socket: UdpSocket,
+    target: SocketAddr,
+}
+
+impl OscSender {
+    /// Resolves `address` once, sending the frames won't wait for a lookup.
+    pub fn new(address: &str) -> anyhow::Result<Self> {
+        let target = address
+            .to_socket_addrs()
+            .with_context(|| format!("Invalid OSC address {address}"))?
+            .next()
+            .ok_or_else(|| anyhow!("Failed to resolve {address}"))?;
+
+        let local: SocketAddr = if target.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local).context("Failed to open the OSC socket")?;
+
+        Ok(Self { socket, target })
+    }
+
+    /// Sends the statistics of a frame and its `grid_size` × `grid_size` grid.
+    pub fn send(
+        &self,
+        values: &thermal::GrayImage,
+        min: f64,
+        max: f64,
+        grid_size: usize,
+    ) -> io::Result<()> {
+        let values = values.data();
+        let packet = bundle(&[
+            message(&format!("{ADDRESS_PREFIX}/min"), &[min as f32]),
+            message(&format!("{ADDRESS_PREFIX}/max"), &[max as f32]),
+            message(&format!("{ADDRESS_PREFIX}/mean"), &[mean(values)]),
+            message(&format!("{ADDRESS_PREFIX}/grid"), &grid(values, grid_size)),
+        ]);
+
+        self.socket.send_to(&packet, self.target).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pads_strings() {
+        let mut packet = Vec::new();
+        write_string(&mut packet, "/abc");
+        assert_eq!(packet, b"/abc\0\0\0\0");
+
+        packet.clear();
+        write_string(&mut packet, "/ab");
+        assert_eq!(packet, b"/ab\0");
+    }
+
+    #[test]
+    fn encodes_float_messages() {
+        assert_eq!(
+            message("/max", &[1.5]),
+            [b"/max\0\0\0\0,f\0\0".as_slice(), &1.5f32.to_be_bytes()].concat()
+        );
+    }
+
+    #[test]
+    fn prefixes_bundle_elements_with_their_size() {
+        let element = message("/a", &[]);
+        let packet = bundle(std::slice::from_ref(&element));
+
+        assert_eq!(&packet[..8], b"#bundle\0");
+        assert_eq!(&packet[8..16], &IMMEDIATELY.to_be_bytes());
+        assert_eq!(&packet[16..20], &(element.len() as u32).to_be_bytes());
+        assert_eq!(&packet[20..], element.as_slice());
+    }
+
+    #[test]
+    fn averages_grid_cells() {
+        // left half at 20 °C, right half at 30 °C
+        let values: Vec<u16> = (0..THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT)
+            .map(|index| {
+                if index % THERMAL_IMAGE_WIDTH < THERMAL_IMAGE_WIDTH / 2 {
+                    200
+                } else {
+                    300
+                }
+            })
+            .collect();
+
+        assert_eq!(grid(&values, 2), [20.0, 30.0, 20.0, 30.0]);
+        assert_eq!(grid(&values, 1), [25.0]);
+        assert_eq!(grid(&values, 32).len(), 32 * 32);
+    }
+}
@@ -0,0 +1,420 @@
+//! Markers, pixel grid, sensor coordinates, a physical scale bar and a watermark drawn over the
+//! thermal image.
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::thermal;
+use crate::thermal::THERMAL_IMAGE_WIDTH;
+
+use eframe::egui;
+use strum_macros::{Display, EnumIter};
+
+/// Snapshots are upscaled, 32x32 images are hard to look at.
+#[cfg(not(target_arch = "wasm32"))]
+pub const SNAPSHOT_SCALE: usize = 10;
+/// Sensor pixels between two coordinate labels.
+pub const LABEL_STEP: usize = 4;
+/// Height of the watermark logo relative to the image.
+pub const LOGO_SIZE: f32 = 0.15;
+
+/// Colors given to new markers in turn.
+pub const MARKER_COLORS: [egui::Color32; 6] = [
+    egui::Color32::WHITE,
+    egui::Color32::YELLOW,
+    egui::Color32::LIGHT_GREEN,
+    egui::Color32::from_rgb(0x5a, 0xc8, 0xfa),
+    egui::Color32::from_rgb(0xff, 0x95, 0x00),
+    egui::Color32::from_rgb(0xff, 0x6f, 0xcf),
+];
+
+/// Marker of a pixel whose temperature is shown on the image, optionally named ("Q3", "Inlet").
+#[derive(Debug, Clone, PartialEq)]
+pub struct Marker {
+    pub pixel: [usize; 2],
+    pub name: String,
+    pub color: egui::Color32,
+}
+
+impl Marker {
+    /// Name of the marker, or its number (M1, M2, ...) given its `index` when it has none. Used
+    /// in reports and as a column name of exports.
+    pub fn label(&self, index: usize) -> String {
+        if self.name.is_empty() {
+            format!("M{}", index + 1)
+        } else {
+            self.name.clone()
+        }
+    }
+}
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum WatermarkPosition {
+    #[strum(to_string = "Top left")]
+    TopLeft,
+    #[strum(to_string = "Top right")]
+    TopRight,
+    #[strum(to_string = "Bottom left")]
+    BottomLeft,
+    #[default]
+    #[strum(to_string = "Bottom right")]
+    BottomRight,
+}
+
+impl WatermarkPosition {
+    pub fn align(self) -> egui::Align2 {
+        match self {
+            Self::TopLeft => egui::Align2::LEFT_TOP,
+            Self::TopRight => egui::Align2::RIGHT_TOP,
+            Self::BottomLeft => egui::Align2::LEFT_BOTTOM,
+            Self::BottomRight => egui::Align2::RIGHT_BOTTOM,
+        }
+    }
+}
+
+/// Logo and a line of text (e.g. company and operator) put on shown and saved images.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Watermark {
+    pub enabled: bool,
+    pub text: String,
+    /// Path of the PNG logo, loaded on request
+    #[cfg(not(target_arch = "wasm32"))]
+    pub logo_path: String,
+    pub position: WatermarkPosition,
+    pub opacity: f32,
+}
+
+impl Default for Watermark {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            text: String::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            logo_path: String::new(),
+            position: WatermarkPosition::default(),
+            opacity: 0.8,
+        }
+    }
+}
+
+/// Decodes a PNG logo of any color type and bit depth.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_logo(path: &str) -> anyhow::Result<egui::ColorImage> {
+    let mut decoder = png::Decoder::new(std::fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder.read_info()?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer)?;
+    let pixels = &buffer[..info.buffer_size()];
+
+    let rgba: Vec<u8> = match info.color_type {
+        png::ColorType::Rgba => pixels.to_vec(),
+        png::ColorType::Rgb => pixels
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks_exact(2)
+            .flat_map(|gray| [gray[0], gray[0], gray[0], gray[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels
+            .iter()
+            .flat_map(|&gray| [gray, gray, gray, 255])
+            .collect(),
+        png::ColorType::Indexed => anyhow::bail!("Indexed PNG hasn't been expanded"),
+    };
+
+    Ok(egui::ColorImage::from_rgba_unmultiplied(
+        [info.width as usize, info.height as usize],
+        &rgba,
+    ))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlaySettings {
+    pub grid: bool,
+    pub coordinates: bool,
+    pub scale_bar: bool,
+    /// Distance (m) to the imaged surface
+    pub distance: f32,
+    /// Horizontal field of view (°) of the sensor
+    pub field_of_view: f32,
+    /// Draw the enabled overlays into saved snapshots too, the watermark is always drawn
+    #[cfg(not(target_arch = "wasm32"))]
+    pub in_snapshots: bool,
+    pub watermark: Watermark,
+    /// Fading line through the recent positions of the hottest pixel, shown on screen only
+    pub hot_spot_trail: bool,
+    /// Seconds the trail reaches back
+    pub trail_length: f32,
+    /// Marks the hottest and the coldest pixel with their temperatures, shown on screen only
+    pub min_max_markers: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            grid: false,
+            coordinates: false,
+            scale_bar: false,
+            distance: 1.0,
+            field_of_view: 90.0,
+            #[cfg(not(target_arch = "wasm32"))]
+            in_snapshots: false,
+            watermark: Watermark::default(),
+            hot_spot_trail: false,
+            trail_length: 5.0,
+            min_max_markers: false,
+        }
+    }
+}
+
+impl OverlaySettings {
+    /// Length of the scale bar in sensor pixels and its label, `None` when it's disabled.
+    /// The bar is a round length close to a quarter of the image width.
+    pub fn scale_bar(&self) -> Option<(f32, String)> {
+        if !self.scale_bar {
+            return None;
+        }
+
+        let scene_width = 2.0 * self.distance * (self.field_of_view.to_radians() / 2.0).tan();
+        let pixel_size = scene_width / THERMAL_IMAGE_WIDTH as f32;
+        let target = scene_width / 4.0;
+
+        let magnitude = 10_f32.powf(target.log10().floor());
+        let length = [5.0, 2.0, 1.0]
+            .into_iter()
+            .map(|step| step * magnitude)
+            .find(|&length| length <= target)
+            .unwrap_or(magnitude);
+
+        let label = if length >= 1.0 {
+            format!("{length} m")
+        } else if length >= 0.01 {
+            format!("{} cm", (length * 100.0).round())
+        } else {
+            format!("{} mm", (length * 1000.0).round())
+        };
+
+        Some((length / pixel_size, label))
+    }
+}
+
+/// Rows of a 3x5 glyph, the top row in the highest bits. Lowercase letters are drawn as uppercase
+/// ones.
+#[cfg(not(target_arch = "wasm32"))]
+fn glyph(c: char) -> u16 {
+    match c {
+        '0' => 0b111_101_101_101_111,
+        '1' => 0b010_110_010_010_111,
+        '2' => 0b111_001_111_100_111,
+        '3' => 0b111_001_111_001_111,
+        '4' => 0b101_101_111_001_001,
+        '5' => 0b111_100_111_001_111,
+        '6' => 0b111_100_111_101_111,
+        '7' => 0b111_001_001_001_001,
+        '8' => 0b111_101_111_101_111,
+        '9' => 0b111_101_111_001_111,
+        '.' => 0b000_000_000_000_010,
+        'A' => 0b010_101_111_101_101,
+        'B' => 0b110_101_110_101_110,
+        'C' => 0b011_100_100_100_011,
+        'D' => 0b110_101_101_101_110,
+        'E' => 0b111_100_110_100_111,
+        'F' => 0b111_100_110_100_100,
+        'G' => 0b011_100_101_101_011,
+        'H' => 0b101_101_111_101_101,
+        'I' => 0b111_010_010_010_111,
+        'J' => 0b001_001_001_101_010,
+        'K' => 0b101_101_110_101_101,
+        'L' => 0b100_100_100_100_111,
+        'M' => 0b101_111_111_101_101,
+        'N' => 0b110_101_101_101_101,
+        'O' => 0b010_101_101_101_010,
+        'P' => 0b110_101_110_100_100,
+        'Q' => 0b010_101_101_110_011,
+        'R' => 0b110_101_110_101_101,
+        'S' => 0b011_100_010_001_110,
+        'T' => 0b111_010_010_010_010,
+        'U' => 0b101_101_101_101_111,
+        'V' => 0b101_101_101_101_010,
+        'W' => 0b101_101_111_111_101,
+        'X' => 0b101_101_010_101_101,
+        'Y' => 0b101_101_010_010_010,
+        'Z' => 0b111_001_010_100_111,
+        '-' => 0b000_000_111_000_000,
+        ':' => 0b000_010_000_010_000,
+        ',' => 0b000_000_000_010_100,
+        '/' => 0b001_001_010_100_100,
+        '(' => 0b010_100_100_100_010,
+        ')' => 0b010_001_001_001_010,
+        c if c.is_ascii_lowercase() => glyph(c.to_ascii_uppercase()),
+        _ => 0,
+    }
+}
+
+/// Whether the pixel at `x`, `y` is covered by `text` drawn at `origin` with glyph pixels of
+/// `size`.
+#[cfg(not(target_arch = "wasm32"))]
+fn text_covers(text: &str, origin: [usize; 2], size: usize, x: usize, y: usize) -> bool {
+    let (Some(x), Some(y)) = (x.checked_sub(origin[0]), y.checked_sub(origin[1])) else {
+        return false;
+    };
+    let (column, row) = (x / size, y / size);
+
+    // glyphs are 3 pixels wide with a space of 1 pixel between them
+    if row >= 5 || column % 4 == 3 {
+        return false;
+    }
+
+    text.chars()
+        .nth(column / 4)
+        .is_some_and(|c| (glyph(c) >> (14 - (row * 3 + column % 4))) & 1 == 1)
+}
+
+/// Width of `text` drawn with glyph pixels of `size`.
+#[cfg(not(target_arch = "wasm32"))]
+fn text_width(text: &str, size: usize) -> usize {
+    (text.chars().count() * 4).saturating_sub(1) * size
+}
+
+/// Mixes `top` over `color`, `top` being premultiplied by `alpha`.
+#[cfg(not(target_arch = "wasm32"))]
+fn blend(color: [u8; 3], top: [f32; 3], alpha: f32) -> [u8; 3] {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    [0, 1, 2].map(|i| (top[i] + f32::from(color[i]) * (1.0 - alpha)).round() as u8)
+}
+
+/// Upscales `image` by `scale` for a snapshot and draws the enabled overlays and the watermark
+/// with its `logo` into it.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn snapshot(
+    image: &thermal::RgbImage,
+    settings: &OverlaySettings,
+    logo: Option<&egui::ColorImage>,
+    scale: usize,
+) -> thermal::RgbImage {
+    let (width, height) = (image.width() * scale, image.height() * scale);
+    let text_size = (scale / 5).max(1);
+    let margin = scale / 2;
+
+    let mut labels = Vec::new();
+    if settings.coordinates {
+        for column in (0..image.width()).step_by(LABEL_STEP) {
+            labels.push((column.to_string(), [column * scale + 2, 2]));
+        }
+        for row in (LABEL_STEP..image.height()).step_by(LABEL_STEP) {
+            labels.push((row.to_string(), [2, row * scale + 2]));
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let bar = settings.scale_bar().map(|(length, label)| {
+        let length = (length * scale as f32).round() as usize;
+        let top = height - margin - 2 * text_size;
+        labels.push((label, [margin, top - 7 * text_size]));
+
+        [margin, top, margin + length, top + 2 * text_size]
+    });
+
+    // the logo and the text next to it form a block aligned to a corner
+    let watermark = &settings.watermark;
+    let mut watermark_logo = None;
+    let mut watermark_text = None;
+
+    if watermark.enabled {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let logo_size = logo.map_or([0, 0], |logo| {
+            let logo_height = (height as f32 * LOGO_SIZE) as usize;
+            [
+                logo_height * logo.width() / logo.height().max(1),
+                logo_height,
+            ]
+        });
+        let gap = if logo.is_some() { margin } else { 0 };
+        let block = egui::vec2(
+            (logo_size[0] + gap + text_width(&watermark.text, text_size)) as f32,
+            logo_size[1].max(5 * text_size) as f32,
+        );
+        let area =
+            egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(width as f32, height as f32))
+                .shrink(margin as f32);
+        let block = watermark
+            .position
+            .align()
+            .align_size_within_rect(block, area);
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let [left, top, block_height] =
+            [block.left(), block.top(), block.height()].map(|value| value.max(0.0) as usize);
+
+        watermark_logo = logo.map(|logo| {
+            let origin = [left, top + (block_height - logo_size[1]) / 2];
+            (logo, origin, logo_size)
+        });
+        watermark_text = Some([
+            left + logo_size[0] + gap,
+            top + (block_height - 5 * text_size) / 2,
+        ]);
+    }
+
+    let mut snapshot = thermal::RgbImage::new([width, height]);
+
+    snapshot.each_pixel_mut(|pt, pixel| {
+        let (x, y) = (pt.x, pt.y);
+        let mut color = [0; 3];
+        color.copy_from_slice(image.get([x / scale, y / scale]).as_slice());
+
+        if settings.grid && (x % scale == 0 || y % scale == 0) {
+            color = color.map(|channel| channel / 2);
+        }
+
+        let covered = |x: usize, y: usize| {
+            bar.is_some_and(|[left, top, right, bottom]| {
+                (left..right).contains(&x) && (top..bottom).contains(&y)
+            }) || labels
+                .iter()
+                .any(|(text, origin)| text_covers(text, *origin, text_size, x, y))
+        };
+
+        // white with a black shadow, readable on any color map
+        if covered(x, y) {
+            color = [255; 3];
+        } else if x > 0 && y > 0 && covered(x - 1, y - 1) {
+            color = [0; 3];
+        }
+
+        let opacity = watermark.opacity;
+
+        if let Some((logo, origin, size)) = watermark_logo {
+            let (logo_x, logo_y) = (x.wrapping_sub(origin[0]), y.wrapping_sub(origin[1]));
+
+            if logo_x < size[0] && logo_y < size[1] {
+                let source = (
+                    logo_x * logo.width() / size[0],
+                    logo_y * logo.height() / size[1],
+                );
+                let [r, g, b, a] = logo[source].to_array();
+
+                color = blend(
+                    color,
+                    [r, g, b].map(|channel| f32::from(channel) * opacity),
+                    f32::from(a) / 255.0 * opacity,
+                );
+            }
+        }
+
+        if let Some(origin) = watermark_text {
+            let text = &watermark.text;
+
+            if text_covers(text, origin, text_size, x, y) {
+                color = blend(color, [255.0 * opacity; 3], opacity);
+            } else if x > 0 && y > 0 && text_covers(text, origin, text_size, x - 1, y - 1) {
+                color = blend(color, [0.0; 3], opacity);
+            }
+        }
+
+        pixel.copy_from_slice(color);
+    });
+
+    snapshot
+}
@@ -0,0 +1,162 @@
+use crate::image_utils;
+use crate::stabilization::MotionTracker;
+use crate::thermal::{self, ColorRange, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use eframe::egui;
+
+// Size of the mosaic in frames per side, the sweep starts in its middle
+const CANVAS_FRAMES: usize = 8;
+const CANVAS_WIDTH: usize = THERMAL_IMAGE_WIDTH * CANVAS_FRAMES;
+const CANVAS_HEIGHT: usize = THERMAL_IMAGE_HEIGHT * CANVAS_FRAMES;
+
+/// Weight of a pixel `index` pixels into a frame of `size`, falling off towards the edges so
+/// the seams between frames are blended.
+fn feather(index: usize, size: usize) -> f32 {
+    let edge = index.min(size - 1 - index) + 1;
+    (edge as f32 / (size / 2) as f32).min(1.0)
+}
+
+/// Mosaic of frames captured while sweeping the camera over a scene larger than its view.
+pub struct Panorama {
+    tracker: MotionTracker,
+    /// Position of the latest frame's top left corner in the canvas
+    position: egui::Vec2,
+    /// Weighted sums of the temperatures and the sums of their weights
+    sums: Vec<f32>,
+    weights: Vec<f32>,
+    /// Part of the canvas covered by frames, `None` until the first one
+    covered: Option<[usize; 4]>,
+    pub capturing: bool,
+}
+
+impl Default for Panorama {
+    fn default() -> Self {
+        Self {
+            tracker: MotionTracker::default(),
+            position: egui::vec2(
+                ((CANVAS_WIDTH - THERMAL_IMAGE_WIDTH) / 2) as f32,
+                ((CANVAS_HEIGHT - THERMAL_IMAGE_HEIGHT) / 2) as f32,
+            ),
+            sums: vec![0.0; CANVAS_WIDTH * CANVAS_HEIGHT],
+            weights: vec![0.0; CANVAS_WIDTH * CANVAS_HEIGHT],
+            covered: None,
+            capturing: true,
+        }
+    }
+}
+
+impl Panorama {
+    pub fn clear(&mut self) {
+        *self = Self {
+            capturing: self.capturing,
+            ..Self::default()
+        };
+    }
+
+    /// Size of the covered part in pixels.
+    pub fn size(&self) -> [usize; 2] {
+        self.covered.map_or([0, 0], |[min_x, min_y, max_x, max_y]| {
+            [max_x - min_x, max_y - min_y]
+        })
+    }
+
+    /// Places a frame (temperatures in tenths of a degree) next to the previous one, returns
+    /// whether it has been added. Frames which can't be registered are skipped.
+    pub fn add(&mut self, values: &thermal::GrayImage) -> bool {
+        if !self.capturing {
+            return false;
+        }
+
+        // the content moving by `shift` means the camera has moved the other way
+        match self.tracker.track(values) {
+            Some(shift) => self.position -= shift,
+            None if self.covered.is_some() => return false,
+            None => {}
+        }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let (left, top) = (
+            self.position.x.round() as isize,
+            self.position.y.round() as isize,
+        );
+
+        for (i, &value) in values.data().iter().enumerate() {
+            let (x, y) = (i % THERMAL_IMAGE_WIDTH, i / THERMAL_IMAGE_WIDTH);
+            let (Ok(canvas_x), Ok(canvas_y)) = (
+                usize::try_from(left + x as isize),
+                usize::try_from(top + y as isize),
+            ) else {
+                continue;
+            };
+
+            if canvas_x >= CANVAS_WIDTH || canvas_y >= CANVAS_HEIGHT {
+                continue;
+            }
+
+            let weight = feather(x, THERMAL_IMAGE_WIDTH) * feather(y, THERMAL_IMAGE_HEIGHT);
+            let index = canvas_y * CANVAS_WIDTH + canvas_x;
+
+            self.sums[index] += f32::from(value) * weight;
+            self.weights[index] += weight;
+        }
+
+        let clamp = |coordinate: isize, max: usize| coordinate.clamp(0, max as isize) as usize;
+        let frame = [
+            clamp(left, CANVAS_WIDTH),
+            clamp(top, CANVAS_HEIGHT),
+            clamp(left + THERMAL_IMAGE_WIDTH as isize, CANVAS_WIDTH),
+            clamp(top + THERMAL_IMAGE_HEIGHT as isize, CANVAS_HEIGHT),
+        ];
+
+        self.covered = Some(match self.covered {
+            Some([min_x, min_y, max_x, max_y]) => [
+                min_x.min(frame[0]),
+                min_y.min(frame[1]),
+                max_x.max(frame[2]),
+                max_y.max(frame[3]),
+            ],
+            None => frame,
+        });
+
+        true
+    }
+
+    /// Colorized covered part, parts the sweep has missed are black.
+    pub fn image(
+        &self,
+        cmap: &(dyn scarlet::colormap::ColorMap<scarlet::color::RGBColor> + Sync),
+        color_range: ColorRange,
+    ) -> Option<thermal::RgbImage> {
+        let [min_x, min_y, ..] = self.covered?;
+        let [width, height] = self.size();
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let value = |x: usize, y: usize| {
+            let index = (min_y + y) * CANVAS_WIDTH + min_x + x;
+            (self.weights[index] > 0.0).then(|| (self.sums[index] / self.weights[index]) as u16)
+        };
+
+        let values: Vec<_> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter_map(|(x, y)| value(x, y))
+            .collect();
+        let min = values.iter().copied().min()?;
+        // a uniform scene would otherwise divide by zero
+        let max = values.iter().copied().max()?.max(min + 1);
+
+        let mut imgbuf = thermal::RgbImage::new([width, height]);
+
+        imgbuf.each_pixel_mut(|pt, pixel| {
+            if let Some(value) = value(pt.x, pt.y) {
+                let scaled_value = image_utils::map_to_scaled_value(value, min, max, color_range);
+                let color = cmap.transform_single(scaled_value);
+
+                pixel.copy_from_slice([color.int_r(), color.int_g(), color.int_b()]);
+            } else {
+                pixel.copy_from_slice([0, 0, 0]);
+            }
+        });
+
+        Some(imgbuf)
+    }
+}
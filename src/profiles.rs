@@ -0,0 +1,297 @@
+//! Per-device profiles: calibration and preferred settings of a camera, stored in a file named
+//! after its USB serial number and loaded whenever that camera connects. Sources of frames have
+//! profiles of the same format, remembered when switching away from a source and loaded when
+//! switching back to it.
+
+use crate::thermal::{
+    ColorMap, CountFormat, EdgeStrategy, FilteringMethod, LensDistortion, Settings,
+    MAX_FRAMES_TO_AVERAGE, MAX_RANGE_OUTLIERS, MAX_RANGE_SMOOTHING,
+};
+
+use anyhow::anyhow;
+use eframe::emath::Numeric;
+use std::fmt::{Display, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use strum::IntoEnumIterator;
+
+/// Directory of the app's configuration files, `None` when the platform doesn't have one.
+pub fn config_directory() -> Option<PathBuf> {
+    #[cfg(target_os = "android")]
+    let base = crate::ANDROID_APP.get()?.internal_data_path();
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(not(any(target_os = "android", target_os = "windows", target_os = "macos")))]
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+
+    Some(base?.join("tiop01-gui"))
+}
+
+/// Path of the profile `name` in `directory` of the configuration directory.
+fn path(directory: &str, name: &str) -> anyhow::Result<PathBuf> {
+    // serial numbers are chosen by the manufacturer and sources named after addresses, keep them
+    // from escaping the directory
+    let file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    Ok(config_directory()
+        .ok_or_else(|| anyhow!("No directory for profiles"))?
+        .join(directory)
+        .join(format!("{file_name}.conf")))
+}
+
+/// Parses the value of one of the enums picked from a combo box by its name.
+fn parse_variant<T: IntoEnumIterator + Display>(value: &str) -> anyhow::Result<T> {
+    T::iter()
+        .find(|variant| variant.to_string() == value)
+        .ok_or_else(|| anyhow!("Unknown value {value}"))
+}
+
+fn parse<T: FromStr>(value: &str) -> anyhow::Result<T>
+where
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    Ok(value.parse()?)
+}
+
+/// Parses the value of a slider, clamped to its range like the slider does.
+fn parse_numeric<T: Numeric>(value: &str) -> anyhow::Result<T> {
+    let value: f64 = parse(value)?;
+
+    Ok(T::from_f64(value.clamp(T::MIN.to_f64(), T::MAX.to_f64())))
+}
+
+/// Bad pixels are listed as `x,y` pairs separated by spaces.
+fn parse_pixels(value: &str) -> anyhow::Result<Vec<[usize; 2]>> {
+    value
+        .split_whitespace()
+        .map(|pixel| {
+            let (x, y) = pixel
+                .split_once(',')
+                .ok_or_else(|| anyhow!("Invalid pixel {pixel}"))?;
+            Ok([parse(x)?, parse(y)?])
+        })
+        .collect()
+}
+
+/// Invalid counts are listed in hexadecimal (`0xffff`) separated by spaces.
+fn parse_counts(value: &str) -> anyhow::Result<Vec<u16>> {
+    value
+        .split_whitespace()
+        .map(|count| {
+            let digits = count
+                .strip_prefix("0x")
+                .ok_or_else(|| anyhow!("Invalid count {count}"))?;
+            Ok(u16::from_str_radix(digits, 16)?)
+        })
+        .collect()
+}
+
+/// Applies the profile of the camera with `serial_number` to `settings`, returns whether it has
+/// one.
+pub fn load(serial_number: &str, settings: &mut Settings) -> anyhow::Result<bool> {
+    load_file(&path("devices", serial_number)?, settings)
+}
+
+/// Applies the profile of `source` to `settings`, returns whether it has one.
+pub fn load_source(source: &str, settings: &mut Settings) -> anyhow::Result<bool> {
+    load_file(&path("sources", source)?, settings)
+}
+
+fn load_file(path: &Path, settings: &mut Settings) -> anyhow::Result<bool> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e.into()),
+    };
+
+    apply(&contents, &path.display().to_string(), settings)?;
+
+    Ok(true)
+}
+
+/// Applies the `key = value` lines of a profile to `settings`, `origin` names the profile in the
+/// errors. Nothing is applied when any of the lines is invalid.
+pub fn apply(contents: &str, origin: &str, settings: &mut Settings) -> anyhow::Result<()> {
+    let mut profile = settings.clone();
+
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| anyhow!("{}:{}: expected key = value", origin, number + 1))?;
+
+        let result = match key {
+            "emissivity" => parse_numeric(value).map(|value| profile.emissivity = value),
+            "colormap" => parse_variant::<ColorMap>(value).map(|value| profile.colormap = value),
+            "color_range" => parse_numeric(value).map(|value| profile.color_range = value),
+            "range_smoothing" => parse(value).map(|value: f32| {
+                profile.range_smoothing = value.clamp(0.0, MAX_RANGE_SMOOTHING);
+            }),
+            "range_outliers" => parse(value).map(|value: usize| {
+                profile.range_outliers = value.min(MAX_RANGE_OUTLIERS);
+            }),
+            "centered_span" => parse(value).map(|value| profile.centered_span = value),
+            "span_center" => parse(value).map(|value| profile.span_center = value),
+            "span_half_width" => parse(value).map(|value: f32| {
+                profile.span_half_width = value.clamp(0.5, 50.0);
+            }),
+            "filtering_method" => parse_variant::<FilteringMethod>(value)
+                .map(|value| profile.filtering_method = value),
+            "edge_strategy" => {
+                parse_variant::<EdgeStrategy>(value).map(|value| profile.edge_strategy = value)
+            }
+            "measure_filtered" => parse(value).map(|value| profile.measure_filtered = value),
+            "frames_to_average" => parse(value).map(|value: usize| {
+                profile.frames_to_average = value.clamp(1, MAX_FRAMES_TO_AVERAGE);
+            }),
+            "flip_horizontally" => parse(value).map(|value| profile.flip_horizontally = value),
+            "flip_vertically" => parse(value).map(|value| profile.flip_vertically = value),
+            "distortion_k1" => parse(value).map(|value| profile.distortion.k1 = value),
+            "distortion_k2" => parse(value).map(|value| profile.distortion.k2 = value),
+            "temperature_offset" => parse(value).map(|value| profile.temperature_offset = value),
+            "count_format" => {
+                parse_variant::<CountFormat>(value).map(|value| profile.count_format = value)
+            }
+            "count_scale" => parse(value).map(|value| profile.count_scale = value),
+            "count_offset" => parse(value).map(|value| profile.count_offset = value),
+            "bad_pixels" => parse_pixels(value).map(|value| profile.bad_pixels = value),
+            "invalid_counts" => parse_counts(value).map(|value| profile.invalid_counts = value),
+            _ => {
+                log::warn!("{}:{}: unknown key {key}", origin, number + 1);
+                Ok(())
+            }
+        };
+
+        result.map_err(|e| anyhow!("{}:{}: {e}", origin, number + 1))?;
+    }
+
+    if !LensDistortion::RANGE.contains(&profile.distortion.k1)
+        || !LensDistortion::RANGE.contains(&profile.distortion.k2)
+    {
+        return Err(anyhow!("{}: distortion out of range", origin));
+    }
+
+    *settings = profile;
+
+    Ok(())
+}
+
+/// Stores the calibration and preferred settings of the camera with `serial_number`, returns
+/// the path of the profile.
+pub fn save(serial_number: &str, settings: &Settings) -> anyhow::Result<PathBuf> {
+    save_file(
+        path("devices", serial_number)?,
+        &format!("Tiop01 camera {serial_number}"),
+        settings,
+    )
+}
+
+/// Stores the settings used with `source`, returns the path of the profile.
+pub fn save_source(source: &str, settings: &Settings) -> anyhow::Result<PathBuf> {
+    save_file(
+        path("sources", source)?,
+        &format!("Tiop01 source {source}"),
+        settings,
+    )
+}
+
+fn save_file(path: PathBuf, title: &str, settings: &Settings) -> anyhow::Result<PathBuf> {
+    let contents = format(title, settings)?;
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(&path, contents)?;
+
+    Ok(path)
+}
+
+/// Profile of `settings`, headed by a comment with `title`.
+pub fn format(title: &str, settings: &Settings) -> anyhow::Result<String> {
+    let mut contents = format!("# {title}\n");
+    let bad_pixels: Vec<_> = settings
+        .bad_pixels
+        .iter()
+        .map(|[x, y]| format!("{x},{y}"))
+        .collect();
+    let invalid_counts: Vec<_> = settings
+        .invalid_counts
+        .iter()
+        .map(|count| format!("{count:#06x}"))
+        .collect();
+
+    writeln!(contents, "emissivity = {}", settings.emissivity.to_f64())?;
+    writeln!(contents, "colormap = {}", settings.colormap)?;
+    writeln!(contents, "color_range = {}", settings.color_range.to_f64())?;
+    writeln!(contents, "range_smoothing = {}", settings.range_smoothing)?;
+    writeln!(contents, "range_outliers = {}", settings.range_outliers)?;
+    writeln!(contents, "centered_span = {}", settings.centered_span)?;
+    writeln!(contents, "span_center = {}", settings.span_center)?;
+    writeln!(contents, "span_half_width = {}", settings.span_half_width)?;
+    writeln!(contents, "filtering_method = {}", settings.filtering_method)?;
+    writeln!(contents, "edge_strategy = {}", settings.edge_strategy)?;
+    writeln!(contents, "measure_filtered = {}", settings.measure_filtered)?;
+    writeln!(
+        contents,
+        "frames_to_average = {}",
+        settings.frames_to_average
+    )?;
+    writeln!(
+        contents,
+        "flip_horizontally = {}",
+        settings.flip_horizontally
+    )?;
+    writeln!(contents, "flip_vertically = {}", settings.flip_vertically)?;
+    writeln!(contents, "distortion_k1 = {}", settings.distortion.k1)?;
+    writeln!(contents, "distortion_k2 = {}", settings.distortion.k2)?;
+    writeln!(
+        contents,
+        "temperature_offset = {}",
+        settings.temperature_offset
+    )?;
+    writeln!(contents, "count_format = {}", settings.count_format)?;
+    writeln!(contents, "count_scale = {}", settings.count_scale)?;
+    writeln!(contents, "count_offset = {}", settings.count_offset)?;
+    writeln!(contents, "bad_pixels = {}", bad_pixels.join(" "))?;
+    writeln!(contents, "invalid_counts = {}", invalid_counts.join(" "))?;
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thermal::{ColorRange, Emissivity};
+
+    #[test]
+    fn clamps_the_slider_values() {
+        let mut settings = Settings::default();
+        apply("emissivity = 500\ncolor_range = -20", "test", &mut settings).unwrap();
+        assert_eq!(settings.emissivity, Emissivity::MAX);
+        assert_eq!(settings.color_range, ColorRange::MIN);
+
+        apply("emissivity = 0\ncolor_range = 250", "test", &mut settings).unwrap();
+        assert_eq!(settings.emissivity, Emissivity::MIN);
+        assert_eq!(settings.color_range, ColorRange::MAX);
+    }
+}
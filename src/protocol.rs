@@ -0,0 +1,207 @@
+//! Commands to the camera. A command is a packet of a start byte, the command code, a payload
+//! byte and a checksum, the wrapping sum of the three bytes before it.
+
+use std::fmt;
+
+const START: u8 = 0x55;
+pub const PACKET_SIZE: usize = 4;
+
+const SET_EMISSIVITY: u8 = 0x01;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Emissivity in hundredths, 1 to 100
+    SetEmissivity(u8),
+}
+
+// the camera doesn't answer commands, only the tests read packets so far
+#[cfg_attr(not(test), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    /// Number of bytes of the packet
+    Length(usize),
+    StartByte(u8),
+    Checksum {
+        expected: u8,
+        actual: u8,
+    },
+    UnknownCommand(u8),
+    /// Payload out of the range of the command
+    Payload {
+        code: u8,
+        payload: u8,
+    },
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::Length(length) => {
+                write!(f, "Expected a packet of {PACKET_SIZE} bytes, got {length}")
+            }
+            DecodeError::StartByte(byte) => write!(f, "Invalid start byte {byte:#04x}"),
+            DecodeError::Checksum { expected, actual } => {
+                write!(f, "Checksum {actual:#04x} doesn't match {expected:#04x}")
+            }
+            DecodeError::UnknownCommand(code) => write!(f, "Unknown command {code:#04x}"),
+            DecodeError::Payload { code, payload } => {
+                write!(f, "Invalid payload {payload} of command {code:#04x}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |sum, &byte| sum.wrapping_add(byte))
+}
+
+impl Command {
+    fn code(self) -> u8 {
+        match self {
+            Command::SetEmissivity(_) => SET_EMISSIVITY,
+        }
+    }
+
+    fn payload(self) -> u8 {
+        match self {
+            Command::SetEmissivity(emissivity) => emissivity,
+        }
+    }
+
+    pub fn encode(self) -> [u8; PACKET_SIZE] {
+        let header = [START, self.code(), self.payload()];
+
+        [header[0], header[1], header[2], checksum(&header)]
+    }
+
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn decode(packet: &[u8]) -> Result<Self, DecodeError> {
+        let &[start, code, payload, actual] = packet else {
+            return Err(DecodeError::Length(packet.len()));
+        };
+
+        if start != START {
+            return Err(DecodeError::StartByte(start));
+        }
+
+        let expected = checksum(&[start, code, payload]);
+        if actual != expected {
+            return Err(DecodeError::Checksum { expected, actual });
+        }
+
+        match code {
+            SET_EMISSIVITY if (1..=100).contains(&payload) => Ok(Command::SetEmissivity(payload)),
+            SET_EMISSIVITY => Err(DecodeError::Payload { code, payload }),
+            _ => Err(DecodeError::UnknownCommand(code)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_emissivity() {
+        assert_eq!(
+            Command::SetEmissivity(95).encode(),
+            [0x55, 0x01, 95, 0x56 + 95]
+        );
+    }
+
+    #[test]
+    fn checksum_wraps() {
+        assert_eq!(Command::SetEmissivity(0xff).encode()[3], 0x55);
+    }
+
+    #[test]
+    fn round_trips_every_emissivity() {
+        for emissivity in 1..=100 {
+            let command = Command::SetEmissivity(emissivity);
+
+            assert_eq!(Command::decode(&command.encode()), Ok(command));
+        }
+    }
+
+    #[test]
+    fn rejects_emissivity_out_of_range() {
+        for payload in (0..=u8::MAX).filter(|payload| !(1..=100).contains(payload)) {
+            let packet = [START, SET_EMISSIVITY, payload];
+            let packet = [packet[0], packet[1], packet[2], checksum(&packet)];
+
+            assert_eq!(
+                Command::decode(&packet),
+                Err(DecodeError::Payload {
+                    code: SET_EMISSIVITY,
+                    payload
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_every_corrupted_byte() {
+        let packet = Command::SetEmissivity(95).encode();
+
+        for index in 0..PACKET_SIZE {
+            for flipped in 0..8 {
+                let mut corrupted = packet;
+                corrupted[index] ^= 1 << flipped;
+
+                assert!(
+                    Command::decode(&corrupted).is_err(),
+                    "bit {flipped} of byte {index}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_start_byte() {
+        let mut packet = Command::SetEmissivity(95).encode();
+        packet[0] = 0xaa;
+
+        assert_eq!(Command::decode(&packet), Err(DecodeError::StartByte(0xaa)));
+    }
+
+    #[test]
+    fn rejects_wrong_checksum() {
+        let mut packet = Command::SetEmissivity(95).encode();
+        packet[3] = 0;
+
+        assert_eq!(
+            Command::decode(&packet),
+            Err(DecodeError::Checksum {
+                expected: 0x56 + 95,
+                actual: 0
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_commands() {
+        for code in (0..=u8::MAX).filter(|&code| code != SET_EMISSIVITY) {
+            let packet = [START, code, 95];
+            let packet = [packet[0], packet[1], packet[2], checksum(&packet)];
+
+            assert_eq!(
+                Command::decode(&packet),
+                Err(DecodeError::UnknownCommand(code))
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_wrong_lengths() {
+        let packet = Command::SetEmissivity(95).encode();
+
+        assert_eq!(Command::decode(&packet[..3]), Err(DecodeError::Length(3)));
+        assert_eq!(Command::decode(&[]), Err(DecodeError::Length(0)));
+        assert_eq!(
+            Command::decode(&[packet.as_slice(), &[0]].concat()),
+            Err(DecodeError::Length(5))
+        );
+    }
+}
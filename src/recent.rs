@@ -0,0 +1,189 @@
+//! Recently played recordings and debug bundles, and what to resume at startup: the last source
+//! and measurement layout. Stored in `recent.conf` of the configuration directory.
+
+use crate::debug_bundle::Replay;
+use crate::image_utils;
+use crate::profiles;
+use crate::session::{self, SessionReader};
+use crate::thermal::{THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use anyhow::anyhow;
+use eframe::egui;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// Older files are forgotten beyond this
+pub const MAX_FILES: usize = 8;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recent {
+    /// Resumes the source and the measurement layout at startup
+    pub resume: bool,
+    /// Name of the last source, a playback plays the most recent file
+    pub source: String,
+    /// Name of the last measurement layout applied
+    pub layout: String,
+    /// Most recent first
+    pub files: Vec<PathBuf>,
+}
+
+fn path() -> anyhow::Result<PathBuf> {
+    Ok(profiles::config_directory()
+        .ok_or_else(|| anyhow!("No directory for the recent files"))?
+        .join("recent.conf"))
+}
+
+impl Recent {
+    /// The stored list, an empty one before the first file is played.
+    pub fn load() -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path()?) {
+            Ok(contents) => Ok(Self::parse(&contents)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = path()?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory)?;
+        }
+
+        Ok(std::fs::write(path, self.format()?)?)
+    }
+
+    /// Parses the `key = value` lines, unknown keys are skipped.
+    fn parse(contents: &str) -> Self {
+        let mut recent = Self::default();
+
+        for (key, value) in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('='))
+        {
+            let value = value.trim();
+            match key.trim() {
+                "resume" => recent.resume = value == "true",
+                "source" => recent.source = value.to_owned(),
+                "layout" => recent.layout = value.to_owned(),
+                "file" if recent.files.len() < MAX_FILES => recent.files.push(value.into()),
+                _ => {}
+            }
+        }
+
+        recent
+    }
+
+    fn format(&self) -> anyhow::Result<String> {
+        let mut contents = String::from("# Tiop01 recent files\n");
+        writeln!(contents, "resume = {}", self.resume)?;
+        writeln!(contents, "source = {}", self.source)?;
+        writeln!(contents, "layout = {}", self.layout)?;
+        for file in &self.files {
+            writeln!(contents, "file = {}", file.display())?;
+        }
+
+        Ok(contents)
+    }
+
+    /// Moves `path` to the top of the list.
+    pub fn add_file(&mut self, path: &Path) {
+        // the same file may be played by relative and absolute paths
+        let path = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+
+        self.files.retain(|file| *file != path);
+        self.files.insert(0, path);
+        self.files.truncate(MAX_FILES);
+    }
+}
+
+/// Temperatures of the first frame of the recording or debug bundle at `path`. A recording is
+/// only read up to that frame.
+pub fn first_frame(path: &Path) -> anyhow::Result<Vec<u16>> {
+    let mut file = std::fs::File::open(path)?;
+    let mut magic = [0; 8];
+    let is_session = std::io::Read::read_exact(&mut file, &mut magic)
+        .is_ok_and(|()| session::is_session(&magic));
+
+    if !is_session {
+        return Replay::load(path)?
+            .first_frame()
+            .ok_or_else(|| anyhow!("{} has no frames", path.display()));
+    }
+
+    let mut reader = SessionReader::new(std::io::BufReader::new(std::fs::File::open(path)?))?;
+    while let Some(chunk) = reader.next_chunk()? {
+        if chunk.kind == session::FRAME {
+            return Ok(session::SessionFrame::decode(&chunk.payload)?.values);
+        }
+    }
+
+    Err(anyhow!("{} has no frames", path.display()))
+}
+
+/// `values` of a frame colored with `cmap` over their span.
+pub fn thumbnail(
+    values: &[u16],
+    cmap: &(dyn scarlet::colormap::ColorMap<scarlet::color::RGBColor> + Sync),
+) -> anyhow::Result<egui::ColorImage> {
+    if values.len() != THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT {
+        return Err(anyhow!("Frame of {} values", values.len()));
+    }
+
+    let min = values.iter().copied().min().unwrap_or_default();
+    // a uniform frame is at the start of the color map
+    let max = values
+        .iter()
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(min.saturating_add(1));
+
+    let rgb: Vec<u8> = values
+        .iter()
+        .flat_map(|&value| {
+            let color = cmap.transform_single(image_utils::position(value, min, max));
+            [color.int_r(), color.int_g(), color.int_b()]
+        })
+        .collect();
+
+    Ok(egui::ColorImage::from_rgb(
+        [THERMAL_IMAGE_WIDTH, THERMAL_IMAGE_HEIGHT],
+        &rgb,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_list() {
+        let recent = Recent {
+            resume: true,
+            source: "Playback file".to_owned(),
+            layout: "Power stage".to_owned(),
+            files: vec!["/data/a.tiop01".into(), "/data/debug-bundle.zip".into()],
+        };
+
+        assert_eq!(Recent::parse(&recent.format().unwrap()), recent);
+    }
+
+    #[test]
+    fn keeps_the_most_recent_files() {
+        let mut recent = Recent::default();
+        for index in 0..=MAX_FILES {
+            recent.add_file(Path::new(&format!("/missing/{index}.tiop01")));
+        }
+        recent.add_file(Path::new("/missing/3.tiop01"));
+
+        assert_eq!(recent.files.len(), MAX_FILES);
+        assert_eq!(recent.files[0], Path::new("/missing/3.tiop01"));
+        assert_eq!(
+            recent.files[1],
+            Path::new(&format!("/missing/{MAX_FILES}.tiop01"))
+        );
+        assert!(!recent.files.contains(&PathBuf::from("/missing/0.tiop01")));
+    }
+}
@@ -0,0 +1,294 @@
+//! Inspection reports: snapshots with their measurements and notes, saved as a self-contained
+//! HTML page which any browser can print to PDF.
+
+use crate::i18n::{format_number, tr};
+use crate::thermal;
+
+use std::fmt::Write;
+use std::time::SystemTime;
+
+/// Snapshot taken into a report.
+pub struct ReportEntry {
+    /// Name of the inspected point, empty for snapshots taken outside of a survey
+    pub label: String,
+    pub taken_at: SystemTime,
+    pub image: thermal::RgbImage,
+    /// Temperatures (°C) of the whole image
+    pub min: f64,
+    pub max: f64,
+    /// Labels of the markers, their pixels and temperatures (°C)
+    pub markers: Vec<(String, [usize; 2], f64)>,
+    pub note: String,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub title: String,
+    pub notes: String,
+    pub entries: Vec<ReportEntry>,
+}
+
+/// Checklist of inspection points walked through one by one, each captured into the report.
+pub struct Survey {
+    pub points: Vec<String>,
+    /// Index of the point to capture next, `points.len()` once all of them are done
+    pub current: usize,
+}
+
+impl Survey {
+    /// Survey of the non-empty lines of `points`, `None` when there are none.
+    pub fn new(points: &str) -> Option<Self> {
+        let points: Vec<_> = points
+            .lines()
+            .map(str::trim)
+            .filter(|point| !point.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        (!points.is_empty()).then_some(Self { points, current: 0 })
+    }
+
+    pub fn current_point(&self) -> Option<&str> {
+        self.points.get(self.current).map(String::as_str)
+    }
+
+    pub fn next(&mut self) {
+        self.current = (self.current + 1).min(self.points.len());
+    }
+
+    pub fn previous(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+pub fn encode_png(image: &thermal::RgbImage) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(
+        &mut png,
+        u32::try_from(image.width())?,
+        u32::try_from(image.height())?,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(image.data())?;
+
+    Ok(png)
+}
+
+/// Grayscale PNG of the temperatures in tenths of °C, 16 bits a pixel, for analysis in other
+/// tools.
+pub fn encode_gray16_png(values: &thermal::GrayImage) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+
+    let mut encoder = png::Encoder::new(
+        &mut png,
+        u32::try_from(values.width())?,
+        u32::try_from(values.height())?,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    encoder.add_text_chunk(
+        "Description".to_owned(),
+        "Temperatures in tenths of a degree Celsius".to_owned(),
+    )?;
+
+    let data: Vec<u8> = values
+        .data()
+        .iter()
+        .flat_map(|value| value.to_be_bytes())
+        .collect();
+    encoder.write_header()?.write_image_data(&data)?;
+
+    Ok(png)
+}
+
+/// Temperatures (°C) as a CSV table, a row of the image per line, for spreadsheets.
+pub fn temperature_csv(values: &thermal::GrayImage) -> String {
+    let mut csv = String::new();
+
+    for row in values.data().chunks(values.width()) {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|&value| format!("{:.1}", f64::from(value) / 10.0))
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// UTC date and time as year, month, day, hour, minute and second.
+pub fn civil_time(time: SystemTime) -> [u64; 6] {
+    let seconds = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    [
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+    ]
+}
+
+/// Formats a time as UTC, there's no time zone database to do better.
+pub fn format_time(time: SystemTime) -> String {
+    let [year, month, day, hour, minute, second] = civil_time(time);
+
+    format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}:{second:02} UTC")
+}
+
+impl Report {
+    /// Renders the report, `metadata` being pairs of a name and a value describing the session.
+    pub fn to_html(&self, metadata: &[(&str, String)], precision: usize) -> anyhow::Result<String> {
+        let temperature = |value: f64| format!("{} °C", format_number(value, precision));
+        let title = if self.title.is_empty() {
+            tr("Inspection report")
+        } else {
+            &self.title
+        };
+
+        let mut html = String::new();
+
+        writeln!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n\
+             <style>\n\
+             body {{ font-family: sans-serif; margin: 2em; }}\n\
+             table {{ border-collapse: collapse; margin: 0.5em 0; }}\n\
+             th, td {{ border: 1px solid #999; padding: 0.2em 0.6em; text-align: left; }}\n\
+             section {{ page-break-inside: avoid; margin-top: 2em; }}\n\
+             img {{ image-rendering: pixelated; width: 320px; }}\n\
+             </style>\n</head>\n<body>\n<h1>{0}</h1>",
+            escape(title)
+        )?;
+
+        html.push_str("<table>\n");
+        for (name, value) in metadata {
+            writeln!(
+                html,
+                "<tr><th>{}</th><td>{}</td></tr>",
+                escape(tr(name)),
+                escape(value)
+            )?;
+        }
+        html.push_str("</table>\n");
+
+        if !self.notes.is_empty() {
+            writeln!(html, "<p>{}</p>", escape(&self.notes).replace('\n', "<br>"))?;
+        }
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            let heading = if entry.label.is_empty() {
+                format!("{} {}", tr("Snapshot"), index + 1)
+            } else {
+                format!("{}. {}", index + 1, escape(&entry.label))
+            };
+
+            writeln!(
+                html,
+                "<section>\n<h2>{heading}</h2>\n<p>{}</p>\n<img src=\"data:image/png;base64,{}\">",
+                format_time(entry.taken_at),
+                base64(&encode_png(&entry.image)?)
+            )?;
+
+            if !entry.note.is_empty() {
+                writeln!(html, "<p>{}</p>", escape(&entry.note))?;
+            }
+
+            writeln!(
+                html,
+                "<table>\n<tr><th>{}</th><td>{}</td></tr>\n<tr><th>{}</th><td>{}</td></tr>\n</table>",
+                tr("Min"),
+                temperature(entry.min),
+                tr("Max"),
+                temperature(entry.max)
+            )?;
+
+            if !entry.markers.is_empty() {
+                writeln!(
+                    html,
+                    "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th></tr>",
+                    tr("Marker"),
+                    tr("Pixel"),
+                    tr("Temperature")
+                )?;
+                for (label, [x, y], value) in &entry.markers {
+                    writeln!(
+                        html,
+                        "<tr><td>{}</td><td>{x}, {y}</td><td>{}</td></tr>",
+                        escape(label),
+                        temperature(*value)
+                    )?;
+                }
+                html.push_str("</table>\n");
+            }
+
+            html.push_str("</section>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+
+        Ok(html)
+    }
+}
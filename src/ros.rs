@@ -0,0 +1,160 @@
+//! ROS 2 node publishing the frames, so the camera can be mounted on a robot and consumed by the
+//! existing tooling. Runs instead of the window with `--ros2`, building it needs a sourced ROS 2
+//! environment, see the README.
+
+use crate::app::{self, ConnectionStatus, ProducerMessage, Source, UiMessage};
+use crate::debug_bundle::Replay;
+use crate::image_utils::FramePool;
+use crate::thermal::{Frame, Settings};
+
+use anyhow::anyhow;
+use eframe::egui;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use builtin_interfaces::msg::Time;
+use sensor_msgs::msg::Image;
+use std_msgs::msg::{Float32MultiArray, Header, MultiArrayDimension, MultiArrayLayout};
+
+const NODE_NAME: &str = "tiop01";
+/// Frame of reference of the messages, for the transforms of the robot
+const FRAME_ID: &str = "thermal_camera";
+/// How often the node checks for a shutdown while no frames arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+fn header() -> Header {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Header {
+        stamp: Time {
+            sec: since_epoch.as_secs() as i32,
+            nanosec: since_epoch.subsec_nanos(),
+        },
+        frame_id: FRAME_ID.to_owned(),
+    }
+}
+
+/// The image as shown in the app, color mapped.
+fn image_message(header: Header, frame: &Frame) -> Image {
+    let (width, height) = (frame.image.width() as u32, frame.image.height() as u32);
+
+    Image {
+        header,
+        height,
+        width,
+        encoding: "rgb8".to_owned(),
+        is_bigendian: 0,
+        step: width * 3,
+        data: frame.image.data().to_vec(),
+    }
+}
+
+/// Temperatures in °C, row by row.
+fn temperature_message(frame: &Frame) -> Float32MultiArray {
+    let (width, height) = (frame.values.width() as u32, frame.values.height() as u32);
+    let dimension = |label: &str, size, stride| MultiArrayDimension {
+        label: label.to_owned(),
+        size,
+        stride,
+    };
+
+    Float32MultiArray {
+        layout: MultiArrayLayout {
+            dim: vec![
+                dimension("height", height, height * width),
+                dimension("width", width, width),
+            ],
+            data_offset: 0,
+        },
+        data: frame
+            .values
+            .data()
+            .iter()
+            .map(|&value| f32::from(value) / 10.0)
+            .collect(),
+    }
+}
+
+/// Publishes the frames of the camera, or of `replay` when there's one, until the node is shut
+/// down. `~/image` gets the color mapped image and `~/temperature` the temperatures.
+pub fn run(replay: Option<Arc<Replay>>) -> anyhow::Result<()> {
+    let context = rclrs::Context::new(std::env::args())?;
+    let node = rclrs::create_node(&context, NODE_NAME)?;
+    let image_publisher =
+        node.create_publisher::<Image>("~/image", rclrs::QOS_PROFILE_SENSOR_DATA)?;
+    let temperature_publisher = node
+        .create_publisher::<Float32MultiArray>("~/temperature", rclrs::QOS_PROFILE_SENSOR_DATA)?;
+
+    let mut settings = Settings::default();
+    let source = match replay {
+        Some(ref replay) => {
+            replay.apply_settings(&mut settings)?;
+            Source::Playback
+        }
+        None => Source::Camera,
+    };
+
+    let (sender, producer_receiver) = mpsc::channel();
+    let (producer_sender, receiver) = mpsc::channel();
+    let frame_pool = FramePool::default();
+
+    let _ = sender.send(UiMessage::ChangeSettings(settings));
+
+    let producer_thread = {
+        let frame_pool = frame_pool.clone();
+        thread::Builder::new()
+            .name("thermal".to_owned())
+            .spawn(move || {
+                // nothing is drawn, the context only takes the repaint requests
+                app::producer_main(
+                    egui::Context::default(),
+                    producer_sender,
+                    producer_receiver,
+                    frame_pool,
+                    source,
+                    replay,
+                );
+            })?
+    };
+
+    let mut result = Ok(());
+
+    while context.ok() {
+        let message = match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                result = Err(anyhow!("The producer has stopped"));
+                break;
+            }
+        };
+
+        match message {
+            ProducerMessage::Frame(frame) => {
+                let published = image_publisher
+                    .publish(image_message(header(), &frame))
+                    .and_then(|()| temperature_publisher.publish(temperature_message(&frame)));
+                frame_pool.recycle(frame);
+
+                if let Err(e) = published {
+                    result = Err(e.into());
+                    break;
+                }
+            }
+            ProducerMessage::ConnectionStatusChange(ConnectionStatus::Connected(port)) => {
+                log::info!("Publishing the frames of {}", port.name);
+            }
+            ProducerMessage::ConnectionError(error) => log::warn!("{error}"),
+            _ => {}
+        }
+    }
+
+    let _ = sender.send(UiMessage::Stop);
+    let _ = producer_thread.join();
+
+    result
+}
@@ -0,0 +1,176 @@
+//! Scene presets: a span of the color map, a color map and an alarm threshold for a kind of scene,
+//! applied at once. Stored in `presets.conf` of the configuration directory, which starts with a
+//! few defaults.
+
+use crate::profiles;
+use crate::thermal::ColorMap;
+
+use anyhow::anyhow;
+use std::fmt::Write;
+use std::path::PathBuf;
+use strum::IntoEnumIterator;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenePreset {
+    pub name: String,
+    /// Span of the color map (°C)
+    pub low: f32,
+    pub high: f32,
+    pub colormap: ColorMap,
+    /// Threshold (°C) of the alarm, `None` disables the alarm
+    pub alarm: Option<f64>,
+}
+
+impl ScenePreset {
+    fn new(name: &str, low: f32, high: f32, colormap: ColorMap, alarm: Option<f64>) -> Self {
+        Self {
+            name: name.to_owned(),
+            low,
+            high,
+            colormap,
+            alarm,
+        }
+    }
+
+    /// Center and half width of the span.
+    pub fn span(&self) -> (f32, f32) {
+        ((self.low + self.high) / 2.0, (self.high - self.low) / 2.0)
+    }
+}
+
+/// Presets until the user saves their own.
+pub fn defaults() -> Vec<ScenePreset> {
+    vec![
+        ScenePreset::new(
+            "Electronics 20–100 °C",
+            20.0,
+            100.0,
+            ColorMap::Turbo,
+            Some(85.0),
+        ),
+        ScenePreset::new("Body 30–40 °C", 30.0, 40.0, ColorMap::Magma, Some(38.0)),
+        ScenePreset::new("Building 0–30 °C", 0.0, 30.0, ColorMap::Bluered, None),
+    ]
+}
+
+fn path() -> anyhow::Result<PathBuf> {
+    Ok(profiles::config_directory()
+        .ok_or_else(|| anyhow!("No directory for the scene presets"))?
+        .join("presets.conf"))
+}
+
+/// The stored presets, the defaults before any is saved.
+pub fn load() -> anyhow::Result<Vec<ScenePreset>> {
+    let path = path()?;
+
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => parse(&contents).map_err(|e| anyhow!("{}:{e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(defaults()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Stores `presets`, returns the path of the file.
+pub fn save(presets: &[ScenePreset]) -> anyhow::Result<PathBuf> {
+    let path = path()?;
+
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)?;
+    }
+    std::fs::write(&path, format(presets)?)?;
+
+    Ok(path)
+}
+
+/// Parses a preset stored as `name = low, high, color map, alarm`, the alarm being `off` or a
+/// threshold.
+fn parse_preset(line: &str) -> anyhow::Result<ScenePreset> {
+    let (name, value) = line
+        .split_once('=')
+        .ok_or_else(|| anyhow!("expected name = low, high, color map, alarm"))?;
+    let name = name.trim();
+    let fields: Vec<&str> = value.split(',').map(str::trim).collect();
+
+    let [low, high, colormap, alarm] = fields[..] else {
+        return Err(anyhow!("expected low, high, color map, alarm"));
+    };
+    let (low, high): (f32, f32) = (low.parse()?, high.parse()?);
+    if name.is_empty() || low >= high {
+        return Err(anyhow!("invalid preset {name}"));
+    }
+
+    let colormap = ColorMap::iter()
+        .find(|variant| variant.to_string() == colormap)
+        .ok_or_else(|| anyhow!("Unknown color map {colormap}"))?;
+    let alarm = match alarm {
+        "off" => None,
+        threshold => Some(threshold.parse()?),
+    };
+
+    Ok(ScenePreset {
+        name: name.to_owned(),
+        low,
+        high,
+        colormap,
+        alarm,
+    })
+}
+
+fn parse(contents: &str) -> anyhow::Result<Vec<ScenePreset>> {
+    contents
+        .lines()
+        .map(str::trim)
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(number, line)| parse_preset(line).map_err(|e| anyhow!("{}: {e}", number + 1)))
+        .collect()
+}
+
+fn format(presets: &[ScenePreset]) -> anyhow::Result<String> {
+    let mut contents = String::from(
+        "# Tiop01 scene presets: name = low °C, high °C, color map, alarm °C or off\n",
+    );
+
+    for preset in presets {
+        let alarm = preset
+            .alarm
+            .map_or_else(|| "off".to_owned(), |threshold| threshold.to_string());
+        writeln!(
+            contents,
+            "{} = {}, {}, {}, {alarm}",
+            preset.name.trim(),
+            preset.low,
+            preset.high,
+            preset.colormap
+        )?;
+    }
+
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_defaults() {
+        assert_eq!(parse(&format(&defaults()).unwrap()).unwrap(), defaults());
+    }
+
+    #[test]
+    fn parses_only_valid_presets() {
+        assert!(parse("Oven = 100, 20, Turbo, off").is_err());
+        assert!(parse("Oven = 20, 100, Sepia, off").is_err());
+        assert!(parse("Oven = 20, 100, Turbo").is_err());
+        assert_eq!(
+            parse("# comment\n\nOven = 20, 250.5, Blue Red (linear), 230").unwrap(),
+            vec![ScenePreset::new(
+                "Oven",
+                20.0,
+                250.5,
+                ColorMap::LinearBlueRed,
+                Some(230.0)
+            )]
+        );
+    }
+}
@@ -0,0 +1,690 @@
+//! Container of recorded sessions: the frames as the camera sent them with their timestamps, the
+//! settings and a description of the recording. Files written today have to stay readable by
+//! future releases, so the format is versioned and extensible:
+//!
+//! - The header is the magic `TIOP01S\n` and the major and minor version, 16 bit each.
+//! - Chunks follow up to the end of the file, each a 4 letter type, the length of its payload
+//!   (32 bit), the payload and the CRC-32 of the type and the payload. Integers are little
+//!   endian.
+//! - A newer minor version only adds chunk types. Readers skip the types they don't know whose
+//!   first letter is lower case (ancillary) and refuse the files with unknown upper case ones
+//!   (critical), which change how the rest has to be read.
+//! - A newer major version changes the layout, readers refuse the files of newer major versions.
+//!   `tiop01-gui migrate` rewrites older files in the current version.
+//!
+//! Chunks of version 1.0:
+//!
+//! | Type   | Payload                                                                         |
+//! |--------|---------------------------------------------------------------------------------|
+//! | `META` | Description of the recording, a `name: value` line each, UTF-8                  |
+//! | `PROF` | Settings in the profile format                                                  |
+//! | `FRAM` | Microseconds since the first frame on a monotonic clock and since the Unix epoch on the system clock (64 bit each), width and height (16 bit each), the values in tenths of °C (16 bit each) row by row |
+//! | `DONE` | Number of frames (32 bit), missing when the recording has been interrupted    |
+//!
+//! Chunks added in version 1.1:
+//!
+//! | Type   | Payload                                                                         |
+//! |--------|---------------------------------------------------------------------------------|
+//! | `visi` | Image of the visible light camera taken during the recording: microseconds since the first frame on the same monotonic clock as `FRAM` and since the Unix epoch (64 bit each), then the image as an RGB PNG |
+
+use crate::zip::crc32;
+
+use anyhow::anyhow;
+use eframe::egui;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MAGIC: &[u8; 8] = b"TIOP01S\n";
+pub const MAJOR_VERSION: u16 = 1;
+pub const MINOR_VERSION: u16 = 1;
+
+pub const META: [u8; 4] = *b"META";
+pub const PROFILE: [u8; 4] = *b"PROF";
+pub const FRAME: [u8; 4] = *b"FRAM";
+pub const DONE: [u8; 4] = *b"DONE";
+pub const VISIBLE: [u8; 4] = *b"visi";
+
+/// Guards against allocating whatever a corrupted length says, a frame is a few kilobytes
+const MAX_CHUNK_SIZE: usize = 64 << 20;
+const FRAME_HEADER_SIZE: usize = 20;
+const VISIBLE_HEADER_SIZE: usize = 16;
+
+fn is_critical(kind: [u8; 4]) -> bool {
+    kind[0].is_ascii_uppercase()
+}
+
+fn kind_name(kind: [u8; 4]) -> String {
+    String::from_utf8_lossy(&kind).into_owned()
+}
+
+pub struct Chunk {
+    pub kind: [u8; 4],
+    pub payload: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionFrame {
+    /// Since the first frame of the session
+    pub offset: Duration,
+    pub wall_clock: SystemTime,
+    pub width: usize,
+    pub height: usize,
+    /// Tenths of °C as the camera sent them, row by row
+    pub values: Vec<u16>,
+}
+
+impl SessionFrame {
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let unix = self
+            .wall_clock
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut payload = Vec::with_capacity(FRAME_HEADER_SIZE + self.values.len() * 2);
+        payload.extend_from_slice(&u64::try_from(self.offset.as_micros())?.to_le_bytes());
+        payload.extend_from_slice(&u64::try_from(unix.as_micros())?.to_le_bytes());
+        payload.extend_from_slice(&u16::try_from(self.width)?.to_le_bytes());
+        payload.extend_from_slice(&u16::try_from(self.height)?.to_le_bytes());
+        for value in &self.values {
+            payload.extend_from_slice(&value.to_le_bytes());
+        }
+
+        Ok(payload)
+    }
+
+    pub fn decode(payload: &[u8]) -> anyhow::Result<Self> {
+        let header = payload
+            .get(..FRAME_HEADER_SIZE)
+            .ok_or_else(|| anyhow!("Frame of {} bytes", payload.len()))?;
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(header[offset..offset + 8].try_into().unwrap());
+        let u16_at =
+            |offset: usize| usize::from(u16::from_le_bytes([header[offset], header[offset + 1]]));
+
+        let (width, height) = (u16_at(16), u16_at(18));
+        let data = &payload[FRAME_HEADER_SIZE..];
+        if data.len() != width * height * 2 {
+            return Err(anyhow!(
+                "Frame of {width}×{height} pixels with {} bytes of values",
+                data.len()
+            ));
+        }
+
+        Ok(Self {
+            offset: Duration::from_micros(u64_at(0)),
+            wall_clock: UNIX_EPOCH + Duration::from_micros(u64_at(8)),
+            width,
+            height,
+            values: data
+                .chunks_exact(2)
+                .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+                .collect(),
+        })
+    }
+}
+
+/// Image of the visible light camera, timed like the frames so that both can be played back
+/// together.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisibleFrame {
+    /// Since the first frame of the session
+    pub offset: Duration,
+    pub wall_clock: SystemTime,
+    pub png: Vec<u8>,
+}
+
+impl VisibleFrame {
+    pub fn new(
+        offset: Duration,
+        wall_clock: SystemTime,
+        image: &egui::ColorImage,
+    ) -> anyhow::Result<Self> {
+        let [width, height] = image.size;
+        let rgb: Vec<u8> = image
+            .pixels
+            .iter()
+            .flat_map(|pixel| [pixel.r(), pixel.g(), pixel.b()])
+            .collect();
+
+        let mut png = Vec::new();
+        let mut encoder =
+            png::Encoder::new(&mut png, u32::try_from(width)?, u32::try_from(height)?);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_compression(png::Compression::Fast);
+        encoder.write_header()?.write_image_data(&rgb)?;
+
+        Ok(Self {
+            offset,
+            wall_clock,
+            png,
+        })
+    }
+
+    pub fn image(&self) -> anyhow::Result<egui::ColorImage> {
+        let decoder = png::Decoder::new(self.png.as_slice());
+        let mut reader = decoder.read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer)?;
+
+        if (info.color_type, info.bit_depth) != (png::ColorType::Rgb, png::BitDepth::Eight) {
+            return Err(anyhow!("Visible image of {:?} pixels", info.color_type));
+        }
+
+        Ok(egui::ColorImage::from_rgb(
+            [info.width as usize, info.height as usize],
+            &buffer[..info.buffer_size()],
+        ))
+    }
+
+    fn encode(&self) -> anyhow::Result<Vec<u8>> {
+        let unix = self
+            .wall_clock
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut payload = Vec::with_capacity(VISIBLE_HEADER_SIZE + self.png.len());
+        payload.extend_from_slice(&u64::try_from(self.offset.as_micros())?.to_le_bytes());
+        payload.extend_from_slice(&u64::try_from(unix.as_micros())?.to_le_bytes());
+        payload.extend_from_slice(&self.png);
+
+        Ok(payload)
+    }
+
+    pub fn decode(payload: &[u8]) -> anyhow::Result<Self> {
+        if payload.len() <= VISIBLE_HEADER_SIZE {
+            return Err(anyhow!("Visible image of {} bytes", payload.len()));
+        }
+
+        let u64_at =
+            |offset: usize| u64::from_le_bytes(payload[offset..offset + 8].try_into().unwrap());
+
+        Ok(Self {
+            offset: Duration::from_micros(u64_at(0)),
+            wall_clock: UNIX_EPOCH + Duration::from_micros(u64_at(8)),
+            png: payload[VISIBLE_HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// Everything of a session needed to replay it.
+#[derive(Debug, Default)]
+pub struct Session {
+    /// Settings in the profile format, missing in sessions of older releases
+    pub profile: Option<String>,
+    pub frames: Vec<SessionFrame>,
+    /// By their offsets, empty when no visible light camera was recording
+    pub visible_frames: Vec<VisibleFrame>,
+}
+
+/// Writes a session in the current version, the chunks as they come.
+pub struct SessionWriter<W: Write> {
+    writer: W,
+    frames: u32,
+}
+
+impl<W: Write> SessionWriter<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&MAJOR_VERSION.to_le_bytes())?;
+        writer.write_all(&MINOR_VERSION.to_le_bytes())?;
+
+        Ok(Self { writer, frames: 0 })
+    }
+
+    pub fn write_chunk(&mut self, kind: [u8; 4], payload: &[u8]) -> anyhow::Result<()> {
+        let length = u32::try_from(payload.len())?;
+        let crc = crc32(&[kind.as_slice(), payload].concat());
+
+        self.writer.write_all(&kind)?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(payload)?;
+        self.writer.write_all(&crc.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Pairs of a name and a value describing the session.
+    pub fn write_meta(&mut self, meta: &[(String, String)]) -> anyhow::Result<()> {
+        let text: String = meta
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\n"))
+            .collect();
+        self.write_chunk(META, text.as_bytes())
+    }
+
+    pub fn write_frame(&mut self, frame: &SessionFrame) -> anyhow::Result<()> {
+        self.write_chunk(FRAME, &frame.encode()?)?;
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    pub fn write_visible_frame(&mut self, frame: &VisibleFrame) -> anyhow::Result<()> {
+        self.write_chunk(VISIBLE, &frame.encode()?)
+    }
+
+    /// Marks the session complete, a session without the mark has been interrupted.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        let frames = self.frames.to_le_bytes();
+        self.write_chunk(DONE, &frames)?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Reads the chunks of a session of any supported version.
+pub struct SessionReader<R: Read> {
+    reader: R,
+    pub version: (u16, u16),
+}
+
+impl<R: Read> SessionReader<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut header = [0; 12];
+        reader
+            .read_exact(&mut header)
+            .map_err(|_| anyhow!("Not a session file"))?;
+
+        if &header[..8] != MAGIC {
+            return Err(anyhow!("Not a session file"));
+        }
+
+        let version = (
+            u16::from_le_bytes([header[8], header[9]]),
+            u16::from_le_bytes([header[10], header[11]]),
+        );
+        if version.0 > MAJOR_VERSION {
+            return Err(anyhow!(
+                "Session of version {}.{}, this release reads up to {MAJOR_VERSION}.x",
+                version.0,
+                version.1
+            ));
+        }
+
+        Ok(Self { reader, version })
+    }
+
+    /// The next chunk, `None` at the end of the file. A corrupted chunk is an error, the ones
+    /// after it can still be read.
+    pub fn next_chunk(&mut self) -> anyhow::Result<Option<Chunk>> {
+        let mut head = [0; 8];
+        match self.reader.read(&mut head[..1])? {
+            0 => return Ok(None),
+            _ => self
+                .reader
+                .read_exact(&mut head[1..])
+                .map_err(|_| Unreadable(TRUNCATED))?,
+        }
+
+        let kind = [head[0], head[1], head[2], head[3]];
+        let length = u32::from_le_bytes([head[4], head[5], head[6], head[7]]) as usize;
+        if length > MAX_CHUNK_SIZE {
+            return Err(Unreadable("A chunk length is corrupted").into());
+        }
+
+        let mut payload = vec![0; length];
+        let mut crc = [0; 4];
+        self.reader
+            .read_exact(&mut payload)
+            .and_then(|()| self.reader.read_exact(&mut crc))
+            .map_err(|_| Unreadable(TRUNCATED))?;
+
+        if crc32(&[kind.as_slice(), &payload].concat()) != u32::from_le_bytes(crc) {
+            return Err(anyhow!("Chunk {} is corrupted", kind_name(kind)));
+        }
+
+        Ok(Some(Chunk { kind, payload }))
+    }
+}
+
+const TRUNCATED: &str = "The file ends in the middle of a chunk";
+
+/// The chunks after the error can't be found.
+#[derive(Debug)]
+struct Unreadable(&'static str);
+
+impl fmt::Display for Unreadable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Unreadable {}
+
+/// Outcome of checking a session file.
+#[derive(Debug, Default)]
+pub struct Verification {
+    pub version: (u16, u16),
+    /// Number of the valid chunks of each type
+    pub chunks: BTreeMap<String, usize>,
+    pub frames: usize,
+    pub visible_frames: usize,
+    pub duration: Duration,
+    /// Whether the session has been finished
+    pub complete: bool,
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    /// Whether the file can be read without losing anything.
+    pub fn is_valid(&self) -> bool {
+        self.complete && self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Version: {}.{}", self.version.0, self.version.1)?;
+        writeln!(
+            f,
+            "Frames: {} over {:.1} s",
+            self.frames,
+            self.duration.as_secs_f64()
+        )?;
+        if self.visible_frames > 0 {
+            writeln!(f, "Visible frames: {}", self.visible_frames)?;
+        }
+        for (kind, count) in &self.chunks {
+            writeln!(f, "Chunks {kind}: {count}")?;
+        }
+        if !self.complete {
+            writeln!(f, "Incomplete, the recording has been interrupted")?;
+        }
+        for problem in &self.problems {
+            writeln!(f, "Problem: {problem}")?;
+        }
+
+        write!(f, "{}", if self.is_valid() { "Valid" } else { "Invalid" })
+    }
+}
+
+/// Reads a whole session, checking every chunk, and hands the valid ones to `keep`.
+fn check<R: Read>(
+    reader: R,
+    mut keep: impl FnMut(&Chunk) -> anyhow::Result<()>,
+) -> anyhow::Result<Verification> {
+    let mut reader = SessionReader::new(reader)?;
+    let mut verification = Verification {
+        version: reader.version,
+        ..Verification::default()
+    };
+
+    loop {
+        let chunk = match reader.next_chunk() {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                verification.problems.push(e.to_string());
+                if e.is::<Unreadable>() {
+                    break;
+                }
+                continue;
+            }
+        };
+
+        if verification.complete {
+            verification
+                .problems
+                .push(format!("Chunk {} after the end", kind_name(chunk.kind)));
+        }
+
+        match chunk.kind {
+            FRAME => match SessionFrame::decode(&chunk.payload) {
+                Ok(frame) => {
+                    verification.frames += 1;
+                    verification.duration = verification.duration.max(frame.offset);
+                }
+                Err(e) => {
+                    verification.problems.push(e.to_string());
+                    continue;
+                }
+            },
+            VISIBLE => {
+                if let Err(e) = VisibleFrame::decode(&chunk.payload) {
+                    verification.problems.push(e.to_string());
+                    continue;
+                }
+                verification.visible_frames += 1;
+            }
+            DONE => {
+                let frames = chunk
+                    .payload
+                    .get(..4)
+                    .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize);
+                if frames != Some(verification.frames) {
+                    verification.problems.push(format!(
+                        "{} frames instead of the recorded {}",
+                        verification.frames,
+                        frames.map_or_else(|| "?".to_owned(), |frames| frames.to_string())
+                    ));
+                }
+                verification.complete = true;
+            }
+            META | PROFILE => {}
+            kind if is_critical(kind) => {
+                return Err(anyhow!(
+                    "Unknown critical chunk {}, the session needs a newer release",
+                    kind_name(kind)
+                ));
+            }
+            // ancillary chunks of a newer minor version
+            _ => {}
+        }
+
+        *verification
+            .chunks
+            .entry(kind_name(chunk.kind))
+            .or_default() += 1;
+        keep(&chunk)?;
+    }
+
+    Ok(verification)
+}
+
+/// Checks every chunk of the session at `path`.
+pub fn verify(path: &Path) -> anyhow::Result<Verification> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    check(file, |_| Ok(()))
+}
+
+/// Whether `bytes` are the start of a session file.
+pub fn is_session(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// The session at `path` for replaying it, whatever can still be read of a damaged or
+/// interrupted one.
+pub fn read(path: &Path) -> anyhow::Result<Session> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    let mut session = Session::default();
+
+    let verification = check(file, |chunk| {
+        match chunk.kind {
+            PROFILE => {
+                session.profile = Some(String::from_utf8_lossy(&chunk.payload).into_owned());
+            }
+            FRAME => session.frames.push(SessionFrame::decode(&chunk.payload)?),
+            VISIBLE => session
+                .visible_frames
+                .push(VisibleFrame::decode(&chunk.payload)?),
+            _ => {}
+        }
+        Ok(())
+    })?;
+    if !verification.is_valid() {
+        log::warn!("{}: {verification}", path.display());
+    }
+
+    Ok(session)
+}
+
+/// Rewrites the session at `path` to `output` in the current version, keeping what can still be
+/// read of a damaged file and finishing an interrupted one. Returns the verification of `path`.
+pub fn migrate(path: &Path, output: &Path) -> anyhow::Result<Verification> {
+    let file = io::BufReader::new(std::fs::File::open(path)?);
+    let mut writer = SessionWriter::new(io::BufWriter::new(std::fs::File::create(output)?))?;
+
+    let verification = check(file, |chunk| match chunk.kind {
+        // rewritten with the frames actually kept
+        DONE => Ok(()),
+        FRAME => writer.write_frame(&SessionFrame::decode(&chunk.payload)?),
+        kind => writer.write_chunk(kind, &chunk.payload),
+    })?;
+    writer.write_meta(&[(
+        "Migrated from".to_owned(),
+        format!("{}.{}", verification.version.0, verification.version.1),
+    )])?;
+    writer.finish()?;
+
+    Ok(verification)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(index: u64) -> SessionFrame {
+        SessionFrame {
+            offset: Duration::from_millis(40 * index),
+            wall_clock: UNIX_EPOCH + Duration::from_secs(1_760_000_000),
+            width: 2,
+            height: 2,
+            values: vec![200, 210, 220, 230],
+        }
+    }
+
+    fn session(frames: u64) -> Vec<u8> {
+        let mut writer = SessionWriter::new(Vec::new()).unwrap();
+        writer
+            .write_meta(&[("Application".to_owned(), "tiop01-gui".to_owned())])
+            .unwrap();
+        writer.write_chunk(PROFILE, b"emissivity = 0.95\n").unwrap();
+        for index in 0..frames {
+            writer.write_frame(&frame(index)).unwrap();
+        }
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trips_frames() {
+        assert_eq!(
+            SessionFrame::decode(&frame(3).encode().unwrap()).unwrap(),
+            frame(3)
+        );
+    }
+
+    #[test]
+    fn verifies_a_complete_session() {
+        let verification = check(session(3).as_slice(), |_| Ok(())).unwrap();
+
+        assert!(verification.is_valid(), "{verification}");
+        assert_eq!(verification.version, (MAJOR_VERSION, MINOR_VERSION));
+        assert_eq!(verification.frames, 3);
+        assert_eq!(verification.duration, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn detects_an_interrupted_session() {
+        let mut data = session(3);
+        // the DONE chunk: type, length, payload and CRC
+        data.truncate(data.len() - 16);
+
+        let verification = check(data.as_slice(), |_| Ok(())).unwrap();
+        assert!(!verification.complete);
+        assert!(verification.problems.is_empty());
+        assert_eq!(verification.frames, 3);
+
+        data.truncate(data.len() - 5);
+        let verification = check(data.as_slice(), |_| Ok(())).unwrap();
+        assert_eq!(verification.frames, 2);
+        assert_eq!(verification.problems.len(), 1);
+    }
+
+    #[test]
+    fn skips_corrupted_chunks() {
+        let mut data = session(3);
+        // a value of the last frame, before its CRC and the DONE chunk
+        let index = data.len() - 16 - 4 - 1;
+        data[index] ^= 0xff;
+
+        let verification = check(data.as_slice(), |_| Ok(())).unwrap();
+        assert_eq!(verification.frames, 2);
+        assert!(verification.complete);
+        assert!(!verification.is_valid());
+    }
+
+    #[test]
+    fn skips_unknown_ancillary_chunks_only() {
+        let mut writer = SessionWriter::new(Vec::new()).unwrap();
+        writer
+            .write_chunk(*b"note", b"from a newer release")
+            .unwrap();
+        let data = writer.finish().unwrap();
+        assert!(check(data.as_slice(), |_| Ok(())).unwrap().is_valid());
+
+        let mut writer = SessionWriter::new(Vec::new()).unwrap();
+        writer.write_chunk(*b"ZSTD", b"compressed frames").unwrap();
+        let data = writer.finish().unwrap();
+        assert!(check(data.as_slice(), |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn reads_what_is_left_of_an_interrupted_session() {
+        let mut data = session(3);
+        data.truncate(data.len() - 16 - 5);
+        assert!(is_session(&data));
+
+        let path = std::env::temp_dir().join(format!("tiop01-read-{}.tiop01", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+        let read = read(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let session = read.unwrap();
+        assert_eq!(session.profile.as_deref(), Some("emissivity = 0.95\n"));
+        assert_eq!(session.frames, [frame(0), frame(1)]);
+    }
+
+    #[test]
+    fn records_visible_frames_alongside() {
+        let image = egui::ColorImage::from_rgb([2, 1], &[255, 0, 0, 0, 128, 255]);
+        let visible = VisibleFrame::new(
+            Duration::from_millis(50),
+            UNIX_EPOCH + Duration::from_secs(1_760_000_000),
+            &image,
+        )
+        .unwrap();
+        assert_eq!(visible.image().unwrap(), image);
+
+        let mut writer = SessionWriter::new(Vec::new()).unwrap();
+        writer.write_frame(&frame(0)).unwrap();
+        writer.write_visible_frame(&visible).unwrap();
+        writer.write_frame(&frame(1)).unwrap();
+        let data = writer.finish().unwrap();
+
+        let mut visible_frames = Vec::new();
+        let verification = check(data.as_slice(), |chunk| {
+            if chunk.kind == VISIBLE {
+                visible_frames.push(VisibleFrame::decode(&chunk.payload)?);
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert!(verification.is_valid(), "{verification}");
+        assert_eq!((verification.frames, verification.visible_frames), (2, 1));
+        assert_eq!(visible_frames, [visible]);
+    }
+
+    #[test]
+    fn refuses_newer_major_versions() {
+        let mut data = session(0);
+        data[8..10].copy_from_slice(&(MAJOR_VERSION + 1).to_le_bytes());
+
+        assert!(SessionReader::new(data.as_slice()).is_err());
+        assert!(SessionReader::new(b"PK\x03\x04".as_slice()).is_err());
+    }
+}
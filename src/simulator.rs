@@ -0,0 +1,110 @@
+//! Simulated camera, for trying the app out without the hardware: a warm spot circling over a
+//! gradient at room temperature, streamed the way the camera sends its frames.
+
+use crate::thermal::{PortInfo, PortOpener, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use std::io;
+use web_time::{Duration, Instant};
+
+// 25 fps
+const FRAME_INTERVAL: Duration = Duration::from_millis(40);
+/// Time the spot takes to circle once, in seconds
+const PERIOD: f64 = 10.0;
+
+/// Values of frame `index`, in tenths of °C.
+fn render(index: u64) -> Vec<u16> {
+    let angle = std::f64::consts::TAU * (index as f64 * FRAME_INTERVAL.as_secs_f64()) / PERIOD;
+    let (width, height) = (THERMAL_IMAGE_WIDTH as f64, THERMAL_IMAGE_HEIGHT as f64);
+    let spot = [
+        width / 2.0 + width / 4.0 * angle.cos(),
+        height / 2.0 + height / 4.0 * angle.sin(),
+    ];
+    // the same sequence for every frame would look frozen, a cheap hash of the pixel and the
+    // frame index is noise enough
+    let noise = |x: usize, y: usize| {
+        let hash =
+            (x as u64 * 73_856_093) ^ (y as u64 * 19_349_663) ^ index.wrapping_mul(83_492_791);
+        (hash % 5) as f64 - 2.0
+    };
+
+    (0..THERMAL_IMAGE_HEIGHT)
+        .flat_map(|y| (0..THERMAL_IMAGE_WIDTH).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let background = 200.0 + 40.0 * y as f64 / height;
+            let distance_squared = (x as f64 - spot[0]).powi(2) + (y as f64 - spot[1]).powi(2);
+            let warmth = 250.0 * (-distance_squared / 18.0).exp();
+
+            (background + warmth + noise(x, y)).round() as u16
+        })
+        .collect()
+}
+
+pub struct SimulatedPort {
+    started_at: Instant,
+    /// Index of the next frame
+    index: u64,
+    /// Bytes of the current frame
+    frame: Vec<u8>,
+    /// Bytes of the current frame already read
+    position: usize,
+}
+
+impl io::Read for SimulatedPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.position == self.frame.len() {
+            let due = self.started_at + FRAME_INTERVAL * self.index as u32;
+            let now = Instant::now();
+            if due > now {
+                std::thread::sleep(due - now);
+            }
+
+            self.frame = render(self.index)
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            self.index += 1;
+            self.position = 0;
+        }
+
+        let len = buf.len().min(self.frame.len() - self.position);
+        buf[..len].copy_from_slice(&self.frame[self.position..self.position + len]);
+        self.position += len;
+
+        Ok(len)
+    }
+}
+
+// nothing to configure
+impl io::Write for SimulatedPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct SimulatedOpener;
+
+impl PortOpener<'static> for SimulatedOpener {
+    type RW = SimulatedPort;
+
+    fn open(&mut self) -> anyhow::Result<Self::RW> {
+        Ok(SimulatedPort {
+            started_at: Instant::now(),
+            index: 0,
+            frame: Vec::new(),
+            position: 0,
+        })
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: "Simulated camera".to_owned(),
+            baud_rate: None,
+            serial_number: None,
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Sonification of a temperature, so a board can be scanned by ear while watching the probed
+//! spot instead of the screen. Clicks come faster (like a Geiger counter) or a tone gets higher
+//! as the temperature rises.
+
+use eframe::egui;
+use strum_macros::{Display, EnumIter};
+use web_time::Duration;
+#[cfg(target_os = "android")]
+use web_time::Instant;
+
+#[cfg(not(target_os = "android"))]
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+#[cfg(not(target_os = "android"))]
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+/// Clicks per second at the top of the range, the bottom of it clicks once a second.
+const MAX_CLICK_RATE: f32 = 40.0;
+/// Tone at the bottom of the range, it rises by `OCTAVES` towards the top
+#[cfg(not(target_os = "android"))]
+const MIN_FREQUENCY: f32 = 220.0;
+#[cfg(not(target_os = "android"))]
+const OCTAVES: f32 = 3.0;
+#[cfg(not(target_os = "android"))]
+const CLICK_LENGTH: Duration = Duration::from_millis(3);
+#[cfg(not(target_os = "android"))]
+const TONE_AMPLITUDE: f32 = 0.2;
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum SonificationSource {
+    #[default]
+    Max,
+    #[strum(to_string = "Center spot")]
+    Center,
+}
+
+#[cfg(not(target_os = "android"))]
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum SonificationMode {
+    #[default]
+    Clicks,
+    Pitch,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SonificationSettings {
+    pub enabled: bool,
+    pub source: SonificationSource,
+    /// Android only clicks
+    #[cfg(not(target_os = "android"))]
+    pub mode: SonificationMode,
+    /// Temperatures (°C) mapped onto the slowest and the fastest clicks (lowest and highest
+    /// tone), it's silent below `low`
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Default for SonificationSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            source: SonificationSource::default(),
+            #[cfg(not(target_os = "android"))]
+            mode: SonificationMode::default(),
+            low: 30.0,
+            high: 80.0,
+        }
+    }
+}
+
+impl SonificationSettings {
+    /// Position (0 to 1) of `temperature` in the range, `None` below it.
+    pub fn level(&self, temperature: f64) -> Option<f32> {
+        if temperature < self.low {
+            return None;
+        }
+
+        let level = if self.high > self.low {
+            ((temperature - self.low) / (self.high - self.low)).min(1.0)
+        } else {
+            1.0
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        Some(level as f32)
+    }
+}
+
+fn click_rate(level: f32) -> f32 {
+    1.0 + level * (MAX_CLICK_RATE - 1.0)
+}
+
+/// Xorshift generator, the clicks of a Geiger counter come at random.
+struct Random(u32);
+
+impl Random {
+    /// Uniformly distributed in (0, 1].
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+
+        #[allow(clippy::cast_precision_loss)]
+        let value = self.0 as f32 / u32::MAX as f32;
+        value.max(f32::EPSILON)
+    }
+
+    /// Time until the next click of a Poisson process with `rate` clicks per second.
+    fn interval(&mut self, rate: f32) -> Duration {
+        Duration::from_secs_f32(-self.next().ln() / rate)
+    }
+}
+
+/// What the audio thread plays, f32 bits as there are no atomic floats.
+#[cfg(not(target_os = "android"))]
+#[derive(Default)]
+struct Voice {
+    /// Clicks per second, 0 is silent
+    click_rate: AtomicU32,
+    /// Tone (Hz), 0 is silent
+    frequency: AtomicU32,
+}
+
+#[cfg(not(target_os = "android"))]
+struct Synth {
+    sample_rate: f32,
+    random: Random,
+    /// Seconds until the next click
+    until_click: f32,
+    /// Samples left of the current click
+    click: usize,
+    click_samples: usize,
+    phase: f32,
+    /// Tone amplitude, faded so switching the tone on and off doesn't pop
+    amplitude: f32,
+    frequency: f32,
+}
+
+#[cfg(not(target_os = "android"))]
+impl Synth {
+    fn new(sample_rate: f32) -> Self {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let click_samples = (CLICK_LENGTH.as_secs_f32() * sample_rate) as usize;
+
+        Self {
+            sample_rate,
+            random: Random(0x2545_f491),
+            until_click: 0.0,
+            click: 0,
+            click_samples: click_samples.max(1),
+            phase: 0.0,
+            amplitude: 0.0,
+            frequency: 0.0,
+        }
+    }
+
+    fn next(&mut self, voice: &Voice) -> f32 {
+        let click_rate = f32::from_bits(voice.click_rate.load(Ordering::Relaxed));
+        let frequency = f32::from_bits(voice.frequency.load(Ordering::Relaxed));
+        let mut sample = 0.0;
+
+        if click_rate > 0.0 {
+            // a long wait drawn at a low rate would delay reacting to a hot spot
+            self.until_click = self.until_click.min(3.0 / click_rate) - 1.0 / self.sample_rate;
+
+            if self.until_click <= 0.0 {
+                self.until_click += self.random.interval(click_rate).as_secs_f32();
+                self.click = self.click_samples;
+            }
+        }
+
+        // a burst of decaying noise sounds like a Geiger counter's click
+        if self.click > 0 {
+            #[allow(clippy::cast_precision_loss)]
+            let envelope = self.click as f32 / self.click_samples as f32;
+            sample += (2.0 * self.random.next() - 1.0) * envelope * envelope;
+            self.click -= 1;
+        }
+
+        let target = if frequency > 0.0 {
+            self.frequency = frequency;
+            TONE_AMPLITUDE
+        } else {
+            0.0
+        };
+        self.amplitude += (target - self.amplitude) * 0.001;
+
+        if self.amplitude > f32::EPSILON {
+            self.phase = (self.phase + self.frequency / self.sample_rate).fract();
+            sample += self.amplitude * (std::f32::consts::TAU * self.phase).sin();
+        }
+
+        sample
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+fn build_stream<T, F>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut next_sample: F,
+) -> anyhow::Result<cpal::Stream>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+    F: FnMut() -> f32 + Send + 'static,
+{
+    let channels = usize::from(config.channels);
+
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(channels) {
+                frame.fill(T::from_sample(next_sample()));
+            }
+        },
+        |e| log::error!("Audio output failed: {e}"),
+        None,
+    )?;
+
+    Ok(stream)
+}
+
+/// Plays the mono samples of `source`, made for the sample rate (Hz) of the default output
+/// device, while the returned stream exists.
+#[cfg(not(target_os = "android"))]
+pub fn play<F>(source: impl FnOnce(f32) -> F) -> anyhow::Result<cpal::Stream>
+where
+    F: FnMut() -> f32 + Send + 'static,
+{
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| anyhow::anyhow!("No audio output device"))?;
+    let supported_config = device.default_output_config()?;
+    let config = supported_config.config();
+    #[allow(clippy::cast_precision_loss)]
+    let next_sample = source(config.sample_rate.0 as f32);
+
+    let stream = match supported_config.sample_format() {
+        cpal::SampleFormat::I16 => build_stream::<i16, _>(&device, &config, next_sample),
+        cpal::SampleFormat::U16 => build_stream::<u16, _>(&device, &config, next_sample),
+        _ => build_stream::<f32, _>(&device, &config, next_sample),
+    }?;
+    stream.play()?;
+
+    Ok(stream)
+}
+
+/// Plays the sonification while it exists.
+pub struct Sonifier {
+    #[cfg(not(target_os = "android"))]
+    voice: Arc<Voice>,
+    #[cfg(not(target_os = "android"))]
+    _stream: cpal::Stream,
+    /// The clicks are played from the UI thread, `None` while silent
+    #[cfg(target_os = "android")]
+    next_click_at: Option<Instant>,
+    #[cfg(target_os = "android")]
+    random: Random,
+}
+
+impl Sonifier {
+    #[cfg(not(target_os = "android"))]
+    pub fn new() -> anyhow::Result<Self> {
+        let voice = Arc::new(Voice::default());
+        let stream = play(|sample_rate| {
+            let voice = Arc::clone(&voice);
+            let mut synth = Synth::new(sample_rate);
+            move || synth.next(&voice)
+        })?;
+
+        Ok(Self {
+            voice,
+            _stream: stream,
+        })
+    }
+
+    #[cfg(target_os = "android")]
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            next_click_at: None,
+            random: Random(0x2545_f491),
+        })
+    }
+
+    /// Follows the `level` of the temperature (see [`SonificationSettings::level`]), `None`
+    /// silences it.
+    #[cfg(not(target_os = "android"))]
+    pub fn update(
+        &mut self,
+        _ctx: &egui::Context,
+        settings: &SonificationSettings,
+        level: Option<f32>,
+    ) {
+        let (click_rate, frequency) = match (level, settings.mode) {
+            (None, _) => (0.0, 0.0),
+            (Some(level), SonificationMode::Clicks) => (click_rate(level), 0.0),
+            (Some(level), SonificationMode::Pitch) => {
+                (0.0, MIN_FREQUENCY * (level * OCTAVES).exp2())
+            }
+        };
+
+        self.voice
+            .click_rate
+            .store(click_rate.to_bits(), Ordering::Relaxed);
+        self.voice
+            .frequency
+            .store(frequency.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Follows the `level` of the temperature (see [`SonificationSettings::level`]), `None`
+    /// silences it.
+    #[cfg(target_os = "android")]
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        _settings: &SonificationSettings,
+        level: Option<f32>,
+    ) {
+        let Some(level) = level else {
+            self.next_click_at = None;
+            return;
+        };
+
+        let now = Instant::now();
+        let next_click_at = match self.next_click_at {
+            Some(at) if now < at => at,
+            _ => {
+                if let Err(e) = crate::android::play_click() {
+                    log::error!("Failed to play a click: {e}");
+                }
+
+                now + self.random.interval(click_rate(level))
+            }
+        };
+
+        self.next_click_at = Some(next_click_at);
+        ctx.request_repaint_after(next_click_at - now);
+    }
+}
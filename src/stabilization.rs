@@ -0,0 +1,252 @@
+use crate::thermal::{self, THERMAL_IMAGE_HEIGHT, THERMAL_IMAGE_WIDTH};
+
+use eframe::egui;
+use std::f32::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+// Frames are upscaled before registering them, so motion is found with sub-pixel precision
+const UPSCALE: usize = 4;
+const SIZE: usize = THERMAL_IMAGE_WIDTH * UPSCALE;
+// Share of the compensation kept for the next frame, so panning on purpose is followed
+const DECAY: f32 = 0.9;
+// Correlation peaks below this are noise rather than motion, e.g. in front of a uniform wall
+const MIN_PEAK: f32 = 0.05;
+// Motion beyond this (in pixels) is the camera being moved on purpose
+const MAX_SHIFT: f32 = 4.0;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Complex {
+    re: f32,
+    im: f32,
+}
+
+impl Complex {
+    fn from_angle(angle: f32) -> Self {
+        Self {
+            re: angle.cos(),
+            im: angle.sin(),
+        }
+    }
+
+    fn conj(self) -> Self {
+        Self {
+            re: self.re,
+            im: -self.im,
+        }
+    }
+
+    fn norm(self) -> f32 {
+        self.re.hypot(self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            re: self.re + other.re,
+            im: self.im + other.im,
+        }
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            re: self.re - other.re,
+            im: self.im - other.im,
+        }
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+/// In-place radix-2 FFT of `SIZE` values, `stride` apart starting at `start`.
+fn fft(data: &mut [Complex], start: usize, stride: usize, inverse: bool) {
+    let index = |i: usize| start + i * stride;
+
+    let bits = SIZE.trailing_zeros();
+    for i in 0..SIZE {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            data.swap(index(i), index(j));
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut length = 2;
+
+    while length <= SIZE {
+        let step = Complex::from_angle(sign * 2.0 * PI / length as f32);
+
+        for chunk in (0..SIZE).step_by(length) {
+            let mut twiddle = Complex { re: 1.0, im: 0.0 };
+
+            for i in 0..length / 2 {
+                let even = data[index(chunk + i)];
+                let odd = data[index(chunk + i + length / 2)] * twiddle;
+
+                data[index(chunk + i)] = even + odd;
+                data[index(chunk + i + length / 2)] = even - odd;
+                twiddle = twiddle * step;
+            }
+        }
+
+        length *= 2;
+    }
+}
+
+fn fft_2d(data: &mut [Complex], inverse: bool) {
+    for row in 0..SIZE {
+        fft(data, row * SIZE, 1, inverse);
+    }
+    for column in 0..SIZE {
+        fft(data, column, SIZE, inverse);
+    }
+}
+
+/// Bilinearly upscaled and windowed spectrum of a frame.
+fn spectrum(image: &thermal::GrayImage) -> Vec<Complex> {
+    let value = |x: usize, y: usize| {
+        let x = x.min(THERMAL_IMAGE_WIDTH - 1);
+        let y = y.min(THERMAL_IMAGE_HEIGHT - 1);
+        f32::from(image.get([x, y]).as_slice()[0])
+    };
+    let window = |i: usize| 0.5 - 0.5 * (2.0 * PI * i as f32 / SIZE as f32).cos();
+    // the mean would otherwise dominate the spectrum
+    let mean = image
+        .data()
+        .iter()
+        .map(|&value| f32::from(value))
+        .sum::<f32>()
+        / image.data().len() as f32;
+
+    let mut data = vec![Complex::default(); SIZE * SIZE];
+
+    for (i, sample) in data.iter_mut().enumerate() {
+        let (x, y) = (i % SIZE, i / SIZE);
+        let source_x = x as f32 / UPSCALE as f32;
+        let source_y = y as f32 / UPSCALE as f32;
+        let (x0, y0) = (source_x as usize, source_y as usize);
+        let (fx, fy) = (source_x.fract(), source_y.fract());
+
+        let top = value(x0, y0) * (1.0 - fx) + value(x0 + 1, y0) * fx;
+        let bottom = value(x0, y0 + 1) * (1.0 - fx) + value(x0 + 1, y0 + 1) * fx;
+
+        sample.re = (top * (1.0 - fy) + bottom * fy - mean) * window(x) * window(y);
+    }
+
+    fft_2d(&mut data, false);
+    data
+}
+
+/// Shift (in pixels) of `current` against `previous` by phase correlation, `None` when the
+/// frames have too little in common.
+fn register(previous: &[Complex], current: &[Complex]) -> Option<egui::Vec2> {
+    let mut cross: Vec<Complex> = current
+        .iter()
+        .zip(previous)
+        .map(|(&current, &previous)| {
+            let product = current * previous.conj();
+            let norm = product.norm();
+
+            if norm > f32::EPSILON {
+                Complex {
+                    re: product.re / norm,
+                    im: product.im / norm,
+                }
+            } else {
+                Complex::default()
+            }
+        })
+        .collect();
+
+    fft_2d(&mut cross, true);
+
+    let (peak, value) = cross
+        .iter()
+        .enumerate()
+        .map(|(i, value)| (i, value.re / (SIZE * SIZE) as f32))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    if value < MIN_PEAK {
+        return None;
+    }
+
+    // the correlation wraps around, shifts past the middle are negative
+    let signed = |coordinate: usize| {
+        let coordinate = coordinate as f32;
+        let size = SIZE as f32;
+        let coordinate = if coordinate > size / 2.0 {
+            coordinate - size
+        } else {
+            coordinate
+        };
+
+        coordinate / UPSCALE as f32
+    };
+
+    Some(egui::vec2(signed(peak % SIZE), signed(peak / SIZE)))
+}
+
+/// Measures the motion of the image content between consecutive frames.
+#[derive(Default)]
+pub struct MotionTracker {
+    previous: Option<Vec<Complex>>,
+}
+
+impl MotionTracker {
+    /// Shift (in pixels) of the content since the previous frame, `None` for the first frame or
+    /// when the frames have too little in common.
+    pub fn track(&mut self, image: &thermal::GrayImage) -> Option<egui::Vec2> {
+        let current = spectrum(image);
+        let shift = self
+            .previous
+            .as_deref()
+            .and_then(|previous| register(previous, &current));
+
+        self.previous = Some(current);
+        shift
+    }
+}
+
+/// Follows small motion between consecutive frames to keep the shown image steady.
+#[derive(Default)]
+pub struct Stabilizer {
+    tracker: MotionTracker,
+    /// Motion (in pixels) of the image content which is compensated
+    offset: egui::Vec2,
+}
+
+impl Stabilizer {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Registers a new frame, returns the shift (in texture coordinates) which undoes the motion.
+    pub fn update(&mut self, image: &thermal::GrayImage) -> egui::Vec2 {
+        if let Some(shift) = self
+            .tracker
+            .track(image)
+            .filter(|shift| shift.length() <= MAX_SHIFT)
+        {
+            self.offset += shift;
+        }
+
+        self.offset *= DECAY;
+
+        self.offset / egui::vec2(THERMAL_IMAGE_WIDTH as f32, THERMAL_IMAGE_HEIGHT as f32)
+    }
+}
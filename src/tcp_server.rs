@@ -0,0 +1,47 @@
+//! Accept loop shared by the servers other tools poll, see [`crate::http`] and
+//! [`crate::modbus`].
+
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// How often the servers check whether they have been stopped
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Accepts the clients of the non-blocking `listener` until `stopped` is set, each served by
+/// `handler` on a thread of its own. `name` is the protocol, for the thread names and the log.
+pub fn serve_nonblocking(
+    name: &str,
+    listener: &TcpListener,
+    stopped: &AtomicBool,
+    handler: impl Fn(TcpStream) -> io::Result<()> + Send + Sync + 'static,
+) {
+    let handler = Arc::new(handler);
+
+    while !stopped.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, address)) => {
+                let handler = Arc::clone(&handler);
+                let name = name.to_owned();
+
+                let _ = thread::Builder::new()
+                    .name(format!("{}-client", name.to_lowercase()))
+                    .spawn(move || {
+                        // the accepted stream inherits non-blocking on some platforms
+                        let result = stream.set_nonblocking(false).and_then(|()| handler(stream));
+                        if let Err(e) = result {
+                            log::warn!("{name} client {address}: {e}");
+                        }
+                    });
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(e) => {
+                log::error!("{name} server failed to accept a client: {e}");
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
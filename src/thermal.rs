@@ -1,5 +1,8 @@
 use crate::app::{ConnectionStatus, ProducerMessage, UiMessage};
-use crate::image_utils::{self, map_to_scaled_value};
+use crate::homography::Homography;
+use crate::image_utils;
+use crate::protocol::Command;
+use crate::stabilization::Stabilizer;
 use crate::thermal;
 
 use byteorder::{LittleEndian, ReadBytesExt};
@@ -7,11 +10,15 @@ use eframe::egui;
 use eframe::emath::Numeric;
 use image2::Kernel;
 use scarlet::colormap::{GradientColorMap, ListedColorMap};
+use std::collections::VecDeque;
+use std::io;
 use std::io::Write;
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
-use std::time::Duration;
-use std::{io, thread};
+use std::sync::{Arc, Condvar, Mutex};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
 use strum_macros::{Display, EnumIter};
+use web_time::{Duration, Instant};
 
 pub type GrayImage = image2::Image<u16, image2::Gray>;
 pub type RgbImage = image2::Image<u8, image2::Rgb>;
@@ -19,6 +26,28 @@ pub type RgbImage = image2::Image<u8, image2::Rgb>;
 pub const THERMAL_IMAGE_WIDTH: usize = 32;
 pub const THERMAL_IMAGE_HEIGHT: usize = 32;
 pub const THERMAL_IMAGE_SIZE: [usize; 2] = [THERMAL_IMAGE_WIDTH, THERMAL_IMAGE_HEIGHT];
+pub const MAX_FRAMES_TO_AVERAGE: usize = 16;
+/// Longest time constant (s) of the auto-range smoothing
+pub const MAX_RANGE_SMOOTHING: f32 = 10.0;
+/// Most extreme pixels ignored at each end of the auto-range
+pub const MAX_RANGE_OUTLIERS: usize = 32;
+
+const DATA_RATE_INTERVAL: Duration = Duration::from_secs(1);
+const REOPEN_INTERVAL: Duration = Duration::from_secs(1);
+// 5 fps while saving power
+const POWER_SAVING_FRAME_INTERVAL: Duration = Duration::from_millis(200);
+// Frames processed while the window is hidden, enough for the alarm
+const SUSPENDED_FRAME_INTERVAL: Duration = Duration::from_secs(1);
+// Some firmware states stall the stream without a read error, the port is reopened after this
+const STALL_TIMEOUT: Duration = Duration::from_secs(5);
+// Shortest time between two commands, the firmware may drop a command arriving right after another
+const COMMAND_INTERVAL: Duration = Duration::from_millis(20);
+// Frames straight from the camera kept for debug bundles, about half a minute of them
+#[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+const MAX_RAW_FRAMES: usize = 300;
+// Deviations (tenths of a degree) smaller than this don't reach the ends of the color map, so
+// noise doesn't look like heating
+const MIN_DEVIATION_SPAN: f32 = 10.0;
 
 #[derive(Debug, Display, Clone, PartialEq, EnumIter)]
 pub enum FilteringMethod {
@@ -37,8 +66,9 @@ pub enum EdgeStrategy {
     Mirror,
 }
 
-#[derive(Debug, Display, Clone, PartialEq, EnumIter)]
+#[derive(Debug, Display, Clone, Default, PartialEq, EnumIter)]
 pub enum ColorMap {
+    #[default]
     Turbo,
     Magma,
     #[strum(to_string = "Blue Red")]
@@ -100,6 +130,93 @@ impl ColorMap {
     }
 }
 
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum ControlLine {
+    #[default]
+    #[strum(to_string = "Platform default")]
+    Default,
+    Asserted,
+    Deasserted,
+}
+
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum Transport {
+    #[default]
+    #[strum(to_string = "Serial port")]
+    Serial,
+    /// Talks to the camera over USB directly, bypassing the kernel's serial driver
+    #[strum(to_string = "Direct USB")]
+    Usb,
+    /// Reads the frames from a server forwarding the camera's serial port
+    Network,
+}
+
+/// What the values a firmware sends count, converted to the tenths of °C the rest of the app
+/// works with.
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum CountFormat {
+    /// The stock firmware
+    #[default]
+    #[strum(to_string = "Tenths of °C")]
+    DeciCelsius,
+    #[strum(to_string = "Hundredths of °C")]
+    CentiCelsius,
+    #[strum(to_string = "Tenths of K")]
+    DeciKelvin,
+    /// [`Settings::count_scale`] and [`Settings::count_offset`]
+    Custom,
+}
+
+impl CountFormat {
+    /// °C per count and °C at a count of 0, `None` for [`CountFormat::Custom`].
+    fn conversion(self) -> Option<(f32, f32)> {
+        match self {
+            CountFormat::DeciCelsius => Some((0.1, 0.0)),
+            CountFormat::CentiCelsius => Some((0.01, 0.0)),
+            CountFormat::DeciKelvin => Some((0.1, -273.15)),
+            CountFormat::Custom => None,
+        }
+    }
+}
+
+/// Bytes asked for by each read from the port. Some USB serial adapters and hubs cope better
+/// with a packet at a time than with a whole frame.
+#[derive(Debug, Display, Clone, Copy, Default, PartialEq, EnumIter)]
+pub enum ReadChunkSize {
+    #[default]
+    #[strum(to_string = "Whole frame")]
+    WholeFrame,
+    /// A full speed USB packet
+    #[strum(to_string = "64 bytes")]
+    Bytes64,
+    /// A high speed USB packet
+    #[strum(to_string = "512 bytes")]
+    Bytes512,
+}
+
+impl ReadChunkSize {
+    fn bytes(self) -> Option<usize> {
+        match self {
+            ReadChunkSize::WholeFrame => None,
+            ReadChunkSize::Bytes64 => Some(64),
+            ReadChunkSize::Bytes512 => Some(512),
+        }
+    }
+}
+
+/// Reads at most `size` bytes at a time from `inner`.
+struct ChunkedReader<'r, R> {
+    inner: &'r mut R,
+    size: usize,
+}
+
+impl<R: io::Read> io::Read for ChunkedReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.size);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub struct Emissivity(u8);
 
@@ -123,6 +240,29 @@ impl From<Emissivity> for u8 {
     }
 }
 
+/// Radial distortion of the sensor optics. A point at distance `r` from the image center (1 at
+/// the middle of the edges) shows up at `r * (1 + k1 * r^2 + k2 * r^4)`; the barrel distortion of
+/// the camera needs a negative `k1`.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct LensDistortion {
+    pub k1: f32,
+    pub k2: f32,
+}
+
+impl LensDistortion {
+    pub const RANGE: std::ops::RangeInclusive<f32> = -0.5..=0.5;
+
+    fn is_none(self) -> bool {
+        self.k1 == 0.0 && self.k2 == 0.0
+    }
+
+    /// Where the point at `pos` (-1 to 1 from the center) is found in the distorted image.
+    pub fn distort(self, pos: egui::Vec2) -> egui::Vec2 {
+        let r2 = pos.length_sq();
+        pos * (1.0 + self.k1 * r2 + self.k2 * r2 * r2)
+    }
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub struct ColorRange(u8);
 
@@ -144,11 +284,67 @@ impl Numeric for ColorRange {
 pub struct Settings {
     pub flip_horizontally: bool,
     pub flip_vertically: bool,
+    /// Corners of the area shown straightened (clockwise from the top left one), in texture
+    /// coordinates of the flipped image. `None` shows the image as it is.
+    pub keystone: Option<[egui::Pos2; 4]>,
+    pub distortion: LensDistortion,
+    /// Compensates small hand motion between frames
+    pub stabilization: bool,
+    /// Frames averaged to reduce noise in static scenes, 1 shows every frame as it is
+    pub frames_to_average: usize,
+    /// Colors the deviation from a slowly following baseline instead of the temperature, so
+    /// transient heating stands out from the static background
+    pub baseline_difference: bool,
+    /// Time constant (s) of the baseline
+    pub baseline_period: f32,
     pub filtering_method: FilteringMethod,
     pub edge_strategy: EdgeStrategy,
+    /// Takes the temperatures (min, max, markers) from the filtered image too, the filter only
+    /// smooths the displayed image otherwise
+    pub measure_filtered: bool,
     pub colormap: ColorMap,
     pub emissivity: Emissivity,
     pub color_range: ColorRange,
+    /// Time constant (s) with which the span of the color map follows the minimum and the
+    /// maximum of the frames, 0 spans each frame on its own
+    pub range_smoothing: f32,
+    /// Hottest and coldest pixels left out of the span of the color map, so a glint doesn't
+    /// compress the rest of the scene into a few colors. They get the colors of its ends.
+    pub range_outliers: usize,
+    /// Keeps the span of the last frame before the lock, so the colors stay put while the camera
+    /// moves across a scene. Not part of profiles.
+    pub span_locked: bool,
+    /// Spreads the color map over `span_center` ± `span_half_width` (°C) instead of between
+    /// the minimum and the maximum of the frame
+    pub centered_span: bool,
+    pub span_center: f32,
+    pub span_half_width: f32,
+    /// Serial port to use, `None` selects the camera automatically
+    pub port: Option<String>,
+    pub dtr: ControlLine,
+    pub rts: ControlLine,
+    pub transport: Transport,
+    /// `host:port` of the network source
+    pub network_address: String,
+    /// How long (s) a read from the serial port or the direct USB device waits for data before the
+    /// camera is considered disconnected
+    pub serial_timeout: f32,
+    pub read_chunk_size: ReadChunkSize,
+    /// Skips frames to save battery, the camera is still read at its full rate
+    pub power_saving: bool,
+    /// Dead or stuck sensor pixels, replaced by their neighbours. Sensor coordinates, before
+    /// any flip.
+    pub bad_pixels: Vec<[usize; 2]>,
+    /// Correction (°C) added to every temperature the camera reports
+    pub temperature_offset: f32,
+    pub count_format: CountFormat,
+    /// °C per count of a [`CountFormat::Custom`] firmware
+    pub count_scale: f32,
+    /// °C at a count of 0 of a [`CountFormat::Custom`] firmware
+    pub count_offset: f32,
+    /// Counts the sensor sends for pixels it couldn't measure or which are saturated. They're
+    /// replaced by their neighbours like bad pixels and shown in a color of their own.
+    pub invalid_counts: Vec<u16>,
 }
 
 impl Default for Settings {
@@ -156,17 +352,153 @@ impl Default for Settings {
         Self {
             flip_horizontally: false,
             flip_vertically: false,
+            keystone: None,
+            distortion: LensDistortion::default(),
+            stabilization: false,
+            frames_to_average: 1,
+            baseline_difference: false,
+            baseline_period: 60.0,
             filtering_method: FilteringMethod::Box3x3,
             edge_strategy: EdgeStrategy::Extend,
+            measure_filtered: false,
             colormap: ColorMap::Turbo,
             emissivity: Emissivity(95),
             color_range: ColorRange(100),
+            range_smoothing: 0.0,
+            range_outliers: 0,
+            span_locked: false,
+            centered_span: false,
+            span_center: 37.0,
+            span_half_width: 3.0,
+            port: None,
+            dtr: ControlLine::default(),
+            rts: ControlLine::default(),
+            transport: Transport::default(),
+            network_address: String::new(),
+            serial_timeout: 1.0,
+            read_chunk_size: ReadChunkSize::default(),
+            power_saving: false,
+            bad_pixels: Vec::new(),
+            temperature_offset: 0.0,
+            count_format: CountFormat::default(),
+            count_scale: 0.1,
+            count_offset: 0.0,
+            invalid_counts: vec![0x0000, 0xffff],
         }
     }
 }
 
 impl Settings {
-    fn get_kernel(&self) -> Option<image2::Kernel> {
+    /// Whether the port has to be reopened to apply the new settings.
+    fn port_changed(&self, other: &Settings) -> bool {
+        self.port != other.port
+            || self.dtr != other.dtr
+            || self.rts != other.rts
+            || self.transport != other.transport
+            || self.network_address != other.network_address
+            || self.serial_timeout != other.serial_timeout
+    }
+
+    /// Commands configuring the camera for these settings, written whenever the port is opened.
+    fn device_config(&self) -> Vec<Command> {
+        vec![Command::SetEmissivity(self.emissivity.into())]
+    }
+
+    fn get_keystone(&self) -> Option<Homography> {
+        self.keystone.and_then(Homography::from_quad)
+    }
+
+    /// °C per count and °C at a count of 0 of the firmware, before the temperature offset.
+    pub fn count_conversion(&self) -> (f32, f32) {
+        self.count_format
+            .conversion()
+            .unwrap_or((self.count_scale, self.count_offset))
+    }
+
+    /// Count of the firmware a temperature (°C) has been converted from.
+    pub fn count(&self, temperature: f64) -> f64 {
+        let (scale, offset) = self.count_conversion();
+
+        (temperature - f64::from(offset) - f64::from(self.temperature_offset)) / f64::from(scale)
+    }
+
+    /// Applies the device calibration to a frame straight from the camera, returns the mask of
+    /// the pixels of invalid counts. The frames hold tenths of °C unsigned, so temperatures below
+    /// 0 °C or above 6553.5 °C can't be represented and their pixels are masked too. Masked and bad
+    /// pixels are replaced by their neighbours, so the filters don't spread them.
+    fn calibrate(&self, image: &mut thermal::GrayImage) -> Vec<bool> {
+        let mut invalid: Vec<bool> = image
+            .data()
+            .iter()
+            .map(|value| self.invalid_counts.contains(value))
+            .collect();
+
+        let (scale, offset) = self.count_conversion();
+        let offset = offset + self.temperature_offset;
+
+        // the counts of the stock firmware are the tenths of °C already
+        if (scale, offset) != (0.1, 0.0) {
+            for (value, invalid) in image.data_mut().iter_mut().zip(&mut invalid) {
+                let converted = ((f32::from(*value) * scale + offset) * 10.0).round();
+                *invalid |= !(0.0..=f32::from(u16::MAX)).contains(&converted);
+
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                {
+                    *value = converted.clamp(0.0, f32::from(u16::MAX)) as u16;
+                }
+            }
+        }
+
+        let mut missing = invalid.clone();
+        for &[x, y] in &self.bad_pixels {
+            if x < THERMAL_IMAGE_WIDTH && y < THERMAL_IMAGE_HEIGHT {
+                missing[y * THERMAL_IMAGE_WIDTH + x] = true;
+            }
+        }
+
+        // a ring at a time, so a larger block is filled from its edges inwards
+        loop {
+            let fills: Vec<(usize, u16)> = (0..missing.len())
+                .filter(|&index| missing[index])
+                .filter_map(|index| {
+                    let (x, y) = (index % THERMAL_IMAGE_WIDTH, index / THERMAL_IMAGE_WIDTH);
+                    let neighbours: Vec<u16> = (y.saturating_sub(1)
+                        ..=(y + 1).min(THERMAL_IMAGE_HEIGHT - 1))
+                        .flat_map(|y| {
+                            (x.saturating_sub(1)..=(x + 1).min(THERMAL_IMAGE_WIDTH - 1))
+                                .map(move |x| y * THERMAL_IMAGE_WIDTH + x)
+                        })
+                        .filter(|&neighbour| !missing[neighbour])
+                        .map(|neighbour| image.data()[neighbour])
+                        .collect();
+
+                    if neighbours.is_empty() {
+                        return None;
+                    }
+
+                    let sum: usize = neighbours.iter().map(|&value| usize::from(value)).sum();
+                    // an average of u16 values, it always fits
+                    u16::try_from(sum / neighbours.len())
+                        .ok()
+                        .map(|value| (index, value))
+                })
+                .collect();
+
+            // none left, or a frame without any valid pixel
+            if fills.is_empty() {
+                break;
+            }
+
+            for (index, value) in fills {
+                image.data_mut()[index] = value;
+                missing[index] = false;
+            }
+        }
+
+        invalid
+    }
+
+    pub fn get_kernel(&self) -> Option<image2::Kernel> {
         let mut kernel = self.filtering_method.get_kernel();
 
         if let Some(ref mut kernel) = kernel {
@@ -177,16 +509,510 @@ impl Settings {
     }
 }
 
+/// Errors of [`PortOpener::open`] which are shown as connection states instead of failures.
+// only Android has to ask the user for access to USB devices at runtime
+#[cfg_attr(not(target_os = "android"), allow(dead_code))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessError {
+    WaitingForPermission,
+    PermissionDenied,
+}
+
+impl std::fmt::Display for AccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AccessError::WaitingForPermission => write!(f, "Waiting for permission"),
+            AccessError::PermissionDenied => write!(f, "Permission denied"),
+        }
+    }
+}
+
+impl std::error::Error for AccessError {}
+
+/// Category of a connection failure, each calling for a different fix from the user.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PortErrorKind {
+    /// No camera is connected, or nothing answers at the configured port or address
+    NotFound,
+    PermissionDenied,
+    /// Another program has the port open
+    Busy,
+    /// The device doesn't talk like the camera
+    ProtocolError,
+    /// The camera has stopped sending frames
+    Timeout,
+    Other,
+}
+
+impl PortErrorKind {
+    /// Short description shown in the status bar.
+    pub fn title(self) -> &'static str {
+        match self {
+            PortErrorKind::NotFound => "Camera not found",
+            PortErrorKind::PermissionDenied => "Access denied",
+            PortErrorKind::Busy => "Port busy",
+            PortErrorKind::ProtocolError => "Unexpected data",
+            PortErrorKind::Timeout => "Camera not responding",
+            PortErrorKind::Other => "Connection failed",
+        }
+    }
+
+    /// What the user can do about it.
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            PortErrorKind::NotFound => {
+                Some("Check that the camera is plugged in, or pick its port")
+            }
+            PortErrorKind::PermissionDenied => {
+                Some("Allow access to the port, on Linux by joining the dialout group")
+            }
+            PortErrorKind::Busy => Some("Close the other program using the camera"),
+            PortErrorKind::ProtocolError => Some("Make sure the port belongs to the camera"),
+            PortErrorKind::Timeout => Some("Replug the camera if it doesn't recover"),
+            PortErrorKind::Other => None,
+        }
+    }
+}
+
+/// Failure to open or read the port, shown to the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortError {
+    pub kind: PortErrorKind,
+    pub message: String,
+}
+
+impl PortError {
+    pub fn new(kind: PortErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Categorizes an error of a transport, by the I/O error behind it unless it's a
+    /// [`PortError`] already.
+    pub fn classify(error: &anyhow::Error) -> Self {
+        if let Some(error) = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<PortError>())
+        {
+            return error.clone();
+        }
+
+        let io_kind = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<io::Error>())
+            .map(io::Error::kind);
+
+        let kind = match io_kind {
+            Some(
+                io::ErrorKind::NotFound
+                | io::ErrorKind::ConnectionRefused
+                | io::ErrorKind::AddrNotAvailable,
+            ) => PortErrorKind::NotFound,
+            Some(io::ErrorKind::PermissionDenied) => PortErrorKind::PermissionDenied,
+            Some(io::ErrorKind::ResourceBusy | io::ErrorKind::AddrInUse) => PortErrorKind::Busy,
+            Some(io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof) => {
+                PortErrorKind::ProtocolError
+            }
+            Some(io::ErrorKind::TimedOut) => PortErrorKind::Timeout,
+            _ => PortErrorKind::Other,
+        };
+
+        Self::new(kind, format!("{error:#}"))
+    }
+}
+
+impl std::fmt::Display for PortError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PortError {}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortInfo {
+    pub name: String,
+    pub baud_rate: Option<u32>,
+    /// USB serial number of the camera, when the transport can tell it
+    pub serial_number: Option<String>,
+}
+
 pub trait PortOpener<'a> {
     type RW: io::Read + io::Write + 'a;
 
     fn open(&mut self) -> anyhow::Result<Self::RW>;
+
+    /// Describes the most recently opened port.
+    fn port_info(&self) -> PortInfo;
+
+    /// Called whenever the settings change, before the port is (re)opened.
+    fn configure(&mut self, _settings: &Settings) {}
+
+    /// Forgets a denied access request, so the next open asks the user again.
+    fn retry(&mut self) {}
+
+    /// Writer half of `rw`, writing while a read blocks on the other half. Without one the
+    /// commands are written between the reads, up to a frame or a read timeout late.
+    fn writer(&self, _rw: &Self::RW) -> Option<Box<dyn io::Write + Send>> {
+        None
+    }
+}
+
+#[derive(Default)]
+struct CommandQueueState {
+    /// Incremented whenever the port is opened or closed, the writer thread of a connection
+    /// ends with it
+    connection: u64,
+    /// Whether a thread writes through the writer half of the current connection
+    threaded: bool,
+    /// Commands to write in order, a command of each kind at most
+    pending: VecDeque<Command>,
+    /// Last command of each kind written to the current connection
+    written: Vec<Command>,
+    written_at: Option<Instant>,
+    /// Error of a failed write, the pending commands wait for the port to be reopened
+    error: Option<io::Error>,
+}
+
+impl CommandQueueState {
+    /// The command to write now, or else how long to wait for it, `None` until something changes.
+    fn next(&self) -> Result<Command, Option<Duration>> {
+        let command = match self.pending.front() {
+            Some(&command) if self.error.is_none() => command,
+            _ => return Err(None),
+        };
+
+        match self.written_at.map(|written_at| written_at.elapsed()) {
+            Some(elapsed) if elapsed < COMMAND_INTERVAL => Err(Some(COMMAND_INTERVAL - elapsed)),
+            _ => Ok(command),
+        }
+    }
+
+    /// Records the result of writing `command`, returns whether it has been written.
+    fn complete(&mut self, command: Command, result: io::Result<()>) -> bool {
+        if let Err(e) = result {
+            log::error!("Failed to write {command:?}, retrying after reconnecting: {e}");
+            self.error = Some(e);
+            return false;
+        }
+
+        // a newer command of the same kind may have replaced it during the write
+        if let Some(index) = self.pending.iter().position(|pending| *pending == command) {
+            self.pending.remove(index);
+        }
+        self.written_at = Some(Instant::now());
+        let kind = std::mem::discriminant(&command);
+        self.written
+            .retain(|written| std::mem::discriminant(written) != kind);
+        self.written.push(command);
+        true
+    }
+}
+
+#[derive(Default)]
+struct CommandQueueShared {
+    state: Mutex<CommandQueueState>,
+    /// Wakes the writer thread up when a command is queued or the connection changes
+    changed: Condvar,
+}
+
+/// Commands to the camera, written one at a time and at most one every [`COMMAND_INTERVAL`].
+/// Shared by the producer with the UI, which only queues a changed setting. A thread of its
+/// own writes it right away through the writer half of the port instead of after the read in
+/// progress.
+#[derive(Clone, Default)]
+pub struct CommandQueue(Arc<CommandQueueShared>);
+
+impl CommandQueue {
+    fn state(&self) -> std::sync::MutexGuard<'_, CommandQueueState> {
+        self.0.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Starts writing to a newly opened port, its writer half `None` when the transport can't
+    /// be split. `config` is the whole configuration of the camera, written before the commands
+    /// that failed on the previous connection.
+    fn attach(&self, writer: Option<Box<dyn io::Write + Send>>, config: Vec<Command>) {
+        let mut state = self.state();
+        state.connection += 1;
+        state.written.clear();
+        state.error = None;
+
+        let kinds: Vec<_> = config.iter().map(std::mem::discriminant).collect();
+        state
+            .pending
+            .retain(|command| !kinds.contains(&std::mem::discriminant(command)));
+        for command in config.into_iter().rev() {
+            state.pending.push_front(command);
+        }
+
+        state.threaded = match writer {
+            Some(writer) => self.spawn_writer(writer, state.connection),
+            None => false,
+        };
+    }
+
+    /// Stops writing to a closed port, the pending commands are kept for the next one.
+    fn detach(&self) {
+        let mut state = self.state();
+        state.connection += 1;
+        state.threaded = false;
+        self.0.changed.notify_all();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn_writer(&self, writer: Box<dyn io::Write + Send>, connection: u64) -> bool {
+        let commands = self.clone();
+        let spawned = thread::Builder::new()
+            .name("commands".into())
+            .spawn(move || commands.write_connection(writer, connection));
+
+        if let Err(ref e) = spawned {
+            log::error!("Failed to spawn the command writer, writing between the reads: {e}");
+        }
+        spawned.is_ok()
+    }
+
+    // the browser has no threads to block in a write, the producer writes between the reads
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_writer(&self, _writer: Box<dyn io::Write + Send>, _connection: u64) -> bool {
+        false
+    }
+
+    /// Writes the pending commands through the writer half of `connection` until it's closed or
+    /// a write fails.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_connection(&self, mut writer: Box<dyn io::Write + Send>, connection: u64) {
+        let mut state = self.state();
+
+        while state.connection == connection {
+            match state.next() {
+                Ok(command) => {
+                    // without the lock, so queueing a command doesn't wait for a slow write
+                    drop(state);
+                    let result = writer.write_all(&command.encode());
+                    state = self.state();
+
+                    if state.connection != connection || !state.complete(command, result) {
+                        return;
+                    }
+                }
+                Err(Some(timeout)) => {
+                    state = self
+                        .0
+                        .changed
+                        .wait_timeout(state, timeout)
+                        .unwrap_or_else(|e| e.into_inner())
+                        .0;
+                }
+                Err(None) => {
+                    state = self
+                        .0
+                        .changed
+                        .wait(state)
+                        .unwrap_or_else(|e| e.into_inner());
+                }
+            }
+        }
+    }
+
+    /// Queues `command` unless it's already in effect, replacing a pending one of the same kind.
+    fn push(&self, command: Command) {
+        let mut state = self.state();
+        let state = &mut *state;
+        let kind = std::mem::discriminant(&command);
+
+        match state
+            .pending
+            .iter_mut()
+            .find(|pending| std::mem::discriminant(*pending) == kind)
+        {
+            Some(pending) => *pending = command,
+            None if !state.written.contains(&command) => state.pending.push_back(command),
+            None => {}
+        }
+        self.0.changed.notify_all();
+    }
+
+    /// Queues `command` without waiting for it to be written, which the writer thread does
+    /// right away if the port has a writer half and the producer otherwise after the next read.
+    pub fn send(&self, command: Command) {
+        self.push(command);
+    }
+
+    /// Writes the pending commands the pacing allows through `port`, unless the writer thread
+    /// writes them.
+    fn flush(&self, port: &mut dyn io::Write) {
+        let mut state = self.state();
+        if state.threaded {
+            return;
+        }
+
+        while let Ok(command) = state.next() {
+            let result = port.write_all(&command.encode());
+            if !state.complete(command, result) {
+                return;
+            }
+        }
+    }
+
+    /// Takes the error of a failed write, after which the port is to be reopened.
+    fn take_error(&self) -> Option<io::Error> {
+        self.state().error.take()
+    }
 }
 
 pub struct Frame {
     pub image: thermal::RgbImage,
+    /// `image` converted for the texture by the producer, so the UI thread only uploads it
+    pub color_image: egui::ColorImage,
+    /// Temperatures in tenths of a degree, oriented the same way as `image`
+    pub values: thermal::GrayImage,
+    /// Where every pixel of `image` is in the span of the color map, see
+    /// [`image_utils::encode_position`] or [`image_utils::INVALID_POSITION`], for recoloring it
+    /// without the producer
+    pub positions: thermal::GrayImage,
+    /// Temperatures before filtering and the corrections which interpolate them, only flipped
+    /// like `image`, once the UI has asked for them
+    pub unfiltered: Option<thermal::GrayImage>,
     pub min: f64,
     pub max: f64,
+    /// Pixels of `values` with the lowest and the highest temperature
+    pub coldest: [usize; 2],
+    pub hottest: [usize; 2],
+}
+
+/// Frame as the camera has sent it, before any calibration.
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+#[derive(Clone)]
+pub struct RawFrame {
+    pub received_at: Instant,
+    /// `received_at` on the system clock, for correlating sessions of several machines
+    pub wall_clock: std::time::SystemTime,
+    pub values: Vec<u16>,
+}
+
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingStatus {
+    Started(std::path::PathBuf),
+    Finished {
+        path: std::path::PathBuf,
+        frames: usize,
+    },
+    /// The recording has stopped, what has been written before stays readable
+    Failed(String),
+}
+
+/// Writes the frames as the camera sent them with their timestamps to a session file, see
+/// [`crate::session`], for replaying them later with other settings.
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+struct Recorder {
+    path: std::path::PathBuf,
+    writer: crate::session::SessionWriter<io::BufWriter<std::fs::File>>,
+    /// When the first frame has been received, the offsets of the frames count from it
+    started_at: Option<Instant>,
+    frames: usize,
+}
+
+#[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+impl Recorder {
+    /// Starts the `file` created for `path` with the description of the recording and the
+    /// settings it starts with.
+    fn start(
+        path: std::path::PathBuf,
+        file: std::fs::File,
+        settings: &Settings,
+        port_info: &PortInfo,
+        ntp_offset: &anyhow::Result<crate::ntp::ClockOffset>,
+    ) -> anyhow::Result<Self> {
+        let mut writer = crate::session::SessionWriter::new(io::BufWriter::new(file))?;
+
+        let mut meta = vec![
+            (
+                "Application".to_owned(),
+                format!("tiop01-gui {}", env!("CARGO_PKG_VERSION")),
+            ),
+            (
+                "Started".to_owned(),
+                crate::report::format_time(std::time::SystemTime::now()),
+            ),
+            ("Port".to_owned(), port_info.name.clone()),
+        ];
+        // adding the offset to the wall clock times of the frames lines up recordings of
+        // several machines
+        match ntp_offset {
+            Ok(ntp_offset) => {
+                meta.push((
+                    "NTP offset".to_owned(),
+                    format!(
+                        "{:+.3} s from {}",
+                        ntp_offset.offset,
+                        crate::ntp::DEFAULT_SERVER
+                    ),
+                ));
+                meta.push((
+                    "NTP measured".to_owned(),
+                    crate::report::format_time(ntp_offset.measured_at),
+                ));
+            }
+            Err(e) => meta.push(("NTP offset".to_owned(), format!("Unavailable: {e:#}"))),
+        }
+        writer.write_meta(&meta)?;
+        writer.write_chunk(
+            crate::session::PROFILE,
+            crate::profiles::format("Tiop01 recording", settings)?.as_bytes(),
+        )?;
+
+        Ok(Self {
+            path,
+            writer,
+            started_at: None,
+            frames: 0,
+        })
+    }
+
+    fn write(&mut self, frame: &RawFrame) -> anyhow::Result<()> {
+        let started_at = *self.started_at.get_or_insert(frame.received_at);
+
+        self.writer.write_frame(&crate::session::SessionFrame {
+            offset: frame.received_at.duration_since(started_at),
+            wall_clock: frame.wall_clock,
+            width: THERMAL_IMAGE_WIDTH,
+            height: THERMAL_IMAGE_HEIGHT,
+            values: frame.values.clone(),
+        })?;
+        self.frames += 1;
+
+        Ok(())
+    }
+
+    /// Writes an image of the visible light camera timed like the frames, the ones taken before
+    /// the first frame are dropped.
+    fn write_visible(&mut self, taken_at: Instant, image: &egui::ColorImage) -> anyhow::Result<()> {
+        let Some(offset) = self
+            .started_at
+            .and_then(|started_at| taken_at.checked_duration_since(started_at))
+        else {
+            return Ok(());
+        };
+
+        let wall_clock = std::time::SystemTime::now() - taken_at.elapsed();
+        self.writer
+            .write_visible_frame(&crate::session::VisibleFrame::new(
+                offset, wall_clock, image,
+            )?)
+    }
+
+    fn finish(self) -> anyhow::Result<RecordingStatus> {
+        self.writer.finish()?;
+
+        Ok(RecordingStatus::Finished {
+            path: self.path,
+            frames: self.frames,
+        })
+    }
 }
 
 pub struct ImageProducer<'a, T>
@@ -195,12 +1021,49 @@ where
 {
     opener: T,
     rw: Option<T::RW>,
+    commands: CommandQueue,
     settings: Settings,
     kernel: Option<Kernel>,
+    keystone: Option<Homography>,
+    stabilizer: Stabilizer,
+    averager: image_utils::FrameAverager,
+    baseline: image_utils::RollingBaseline,
+    range: image_utils::SmoothedRange,
+    /// Span (raw values) of the color map of the last frame, kept while the span is locked
+    last_span: Option<(f64, f64)>,
+    frame_pool: image_utils::FramePool,
     colormap: Box<dyn scarlet::colormap::ColorMap<scarlet::color::RGBColor> + Sync>,
     sender: Sender<ProducerMessage>,
     receiver: Receiver<UiMessage>,
     egui_ctx: egui::Context,
+    bytes_read: usize,
+    data_rate_since: Instant,
+    reopen_at: Instant,
+    produced_at: Instant,
+    /// When the last new frame has been read, a repeated one doesn't count
+    frame_received_at: Instant,
+    /// Values of the last frame read, to tell a repeated one
+    last_values: Vec<u16>,
+    /// Set once the port has been reopened for a stalled stream, until a frame arrives again
+    recovering: bool,
+    /// How long without a new frame the stream is considered stalled, [`STALL_TIMEOUT`] but for
+    /// the tests
+    stall_timeout: Duration,
+    paused: bool,
+    suspended: bool,
+    /// Shortest time between the repaints new frames ask for
+    repaint_interval: Duration,
+    /// When the last repaint asked for is due
+    repaint_due: Instant,
+    /// Cleared once the UI asks to stop or is gone
+    running: bool,
+    /// Set while the UI waits for a frame with the unfiltered temperatures
+    unfiltered_requested: bool,
+    /// Most recent frames read, oldest first
+    #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+    raw_frames: VecDeque<RawFrame>,
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    recorder: Option<Recorder>,
 }
 
 impl<'a, T> ImageProducer<'a, T>
@@ -211,72 +1074,168 @@ where
         egui_ctx: egui::Context,
         sender: Sender<ProducerMessage>,
         receiver: Receiver<UiMessage>,
-        opener: T,
+        mut opener: T,
     ) -> Self {
         let settings = Settings::default();
         let kernel = settings.get_kernel();
+        let keystone = settings.get_keystone();
         let colormap = settings.colormap.get_colormap();
         let rw = None;
+        opener.configure(&settings);
+
+        let commands = CommandQueue::default();
+        let _ = sender.send(ProducerMessage::Commands(commands.clone()));
 
         Self {
             opener,
             rw,
+            commands,
             settings,
             kernel,
+            keystone,
+            stabilizer: Stabilizer::default(),
+            averager: image_utils::FrameAverager::default(),
+            baseline: image_utils::RollingBaseline::default(),
+            range: image_utils::SmoothedRange::default(),
+            last_span: None,
+            frame_pool: image_utils::FramePool::default(),
             colormap,
             sender,
             receiver,
             egui_ctx,
+            bytes_read: 0,
+            data_rate_since: Instant::now(),
+            reopen_at: Instant::now(),
+            produced_at: Instant::now(),
+            frame_received_at: Instant::now(),
+            last_values: Vec::new(),
+            recovering: false,
+            stall_timeout: STALL_TIMEOUT,
+            paused: false,
+            suspended: false,
+            repaint_interval: Duration::ZERO,
+            repaint_due: Instant::now(),
+            running: true,
+            unfiltered_requested: false,
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            raw_frames: VecDeque::with_capacity(MAX_RAW_FRAMES),
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            recorder: None,
         }
     }
 
+    /// Shares the frame buffers with the UI, which hands them back once it's done with them.
+    pub fn with_frame_pool(mut self, frame_pool: image_utils::FramePool) -> Self {
+        self.frame_pool = frame_pool;
+        self
+    }
+
     #[profiling::function]
     fn ensure_port_opened(&mut self) {
-        if self.rw.is_some() {
+        if self.rw.is_some() || Instant::now() < self.reopen_at {
             return;
         }
 
         match self.opener.open() {
             Ok(rw) => {
+                self.commands
+                    .attach(self.opener.writer(&rw), self.settings.device_config());
                 self.rw = Some(rw);
-                self.write_emissivity();
+                // frames of the previous connection may show a different camera
+                self.averager.reset();
+                self.baseline.reset();
+                self.range.reset();
+                self.frame_received_at = Instant::now();
+                self.last_values.clear();
+                self.flush_commands();
                 self.send_message_to_ui(ProducerMessage::ConnectionStatusChange(
-                    ConnectionStatus::Connected,
+                    ConnectionStatus::Connected(self.opener.port_info()),
                 ));
             }
             Err(e) => {
-                log::warn!("Failed to create rw: {e}. Sleeping for 1 sec");
-                thread::sleep(Duration::from_secs(1));
+                log::warn!("Failed to create rw: {e}. Retrying in 1 sec");
+
+                let message = match e.downcast_ref::<AccessError>() {
+                    Some(AccessError::WaitingForPermission) => {
+                        ProducerMessage::ConnectionStatusChange(
+                            ConnectionStatus::WaitingForPermission,
+                        )
+                    }
+                    Some(AccessError::PermissionDenied) => {
+                        ProducerMessage::ConnectionStatusChange(ConnectionStatus::PermissionDenied)
+                    }
+                    None => ProducerMessage::ConnectionError(PortError::classify(&e)),
+                };
+
+                self.send_message_to_ui(message);
+                self.wait_before_reopening();
             }
         }
     }
 
-    #[profiling::function]
-    fn read_image(&mut self) -> Option<thermal::GrayImage> {
-        let mut imgbuf = thermal::GrayImage::new(THERMAL_IMAGE_SIZE);
+    #[cfg(not(target_arch = "wasm32"))]
+    fn wait_before_reopening(&mut self) {
+        thread::sleep(REOPEN_INTERVAL);
+    }
 
-        let r = self
-            .rw
-            .as_mut()?
-            .read_u16_into::<LittleEndian>(imgbuf.data_mut());
+    // the browser's main thread can't sleep, so just skip the attempts until the interval passes
+    #[cfg(target_arch = "wasm32")]
+    fn wait_before_reopening(&mut self) {
+        self.reopen_at = Instant::now() + REOPEN_INTERVAL;
+        self.egui_ctx.request_repaint_after(REOPEN_INTERVAL);
+    }
 
-        match r {
-            Ok(()) => Some(imgbuf),
-            Err(e) => {
-                log::error!("Failed to read from serial port: {e}");
+    #[profiling::function]
+    fn read_image(&mut self) -> Option<thermal::GrayImage> {
+        let rw = self.rw.as_mut()?;
+        let mut imgbuf = self.frame_pool.gray_image();
 
-                self.rw = None;
-                self.send_message_to_ui(ProducerMessage::ConnectionStatusChange(
-                    ConnectionStatus::Disconnected,
-                ));
+        let r = match self.settings.read_chunk_size.bytes() {
+            Some(size) => {
+                ChunkedReader { inner: rw, size }.read_u16_into::<LittleEndian>(imgbuf.data_mut())
+            }
+            None => rw.read_u16_into::<LittleEndian>(imgbuf.data_mut()),
+        };
 
-                None
+        let e = match r {
+            Ok(()) => {
+                self.bytes_read += std::mem::size_of_val(imgbuf.data());
+                return Some(imgbuf);
             }
+            Err(e) => e,
+        };
+        self.frame_pool.recycle_gray_image(imgbuf);
+
+        // non-blocking transports report an incomplete frame this way
+        if e.kind() == io::ErrorKind::WouldBlock {
+            return None;
         }
+
+        log::error!("Failed to read from serial port: {e}");
+        self.port_failed(e, "Failed to read from the port");
+
+        None
+    }
+
+    /// Closes the port after a failed read or write, to be reopened on the next step.
+    fn port_failed(&mut self, e: io::Error, context: &'static str) {
+        self.close_port();
+        self.send_message_to_ui(ProducerMessage::ConnectionStatusChange(
+            ConnectionStatus::Disconnected,
+        ));
+        // after the status change, which clears the previous error
+        self.send_message_to_ui(ProducerMessage::ConnectionError(PortError::classify(
+            &anyhow::Error::new(e).context(context),
+        )));
     }
 
     #[profiling::function]
-    fn produce_thermal_frame(&self, gray_image: &thermal::GrayImage) {
+    fn produce_thermal_frame(
+        &mut self,
+        gray_image: &thermal::GrayImage,
+        stabilization_shift: Option<egui::Vec2>,
+        invalid: &[bool],
+    ) {
         let filtered = {
             profiling::scope!("filter");
             self.kernel
@@ -286,99 +1245,525 @@ where
 
         let filtered = filtered.as_ref().unwrap_or(gray_image);
         let color_range = self.settings.color_range;
+        // in raw values, tenths of °C
+        let centered_span = self.settings.centered_span.then(|| {
+            let (center, half_width) = (
+                f64::from(self.settings.span_center),
+                f64::from(self.settings.span_half_width),
+            );
+            ((center - half_width) * 10.0, (center + half_width) * 10.0)
+        });
 
-        if let Some((min, max)) = {
-            profiling::scope!("minmax");
-            let min = filtered.iter().map(|(_pt, data)| data.as_slice()[0]).min();
-            let max = filtered.iter().map(|(_pt, data)| data.as_slice()[0]).max();
+        let deviations = if self.settings.baseline_difference {
+            let period = Duration::from_secs_f32(self.settings.baseline_period);
+            Some(self.baseline.update(filtered, period))
+        } else {
+            self.baseline.reset();
+            None
+        };
+        let span = deviations.as_ref().map(|deviations| {
+            deviations
+                .iter()
+                .zip(invalid)
+                .filter(|(_, &invalid)| !invalid)
+                .map(|(deviation, _)| deviation.abs())
+                .fold(MIN_DEVIATION_SPAN, f32::max)
+        });
+
+        // smoothing lowers hot spots, so the temperatures are measured on the raw image
+        let measured = if self.settings.measure_filtered {
+            filtered
+        } else {
+            gray_image
+        };
+        // the masked pixels hold their neighbours' values, or the invalid counts if they have none
+        let min_max = |image: &thermal::GrayImage| {
+            let min = image_utils::unmasked(image.data(), invalid).min();
+            let max = image_utils::unmasked(image.data(), invalid).max();
             min.zip(max)
+        };
+
+        if let (Some((min, max)), Some((measured_min, measured_max))) = {
+            profiling::scope!("minmax");
+            (min_max(filtered), min_max(measured))
         } {
-            let mut imgbuf = thermal::RgbImage::new(THERMAL_IMAGE_SIZE);
+            // all of them are overwritten as a whole
+            let mut imgbuf = self.frame_pool.rgb_image();
+            let mut values = self.frame_pool.gray_image();
+            let mut positions = self.frame_pool.gray_image();
+            values.data_mut().copy_from_slice(measured.data());
+
+            let unmasked: Vec<u16> = image_utils::unmasked(filtered.data(), invalid).collect();
+            let (span_min, span_max) =
+                image_utils::trimmed_range(&unmasked, self.settings.range_outliers)
+                    .unwrap_or((min, max));
+            // noise moves the extremes a little every frame, which would shift all the colors
+            let auto_span = if self.settings.range_smoothing > 0.0 {
+                let period = Duration::from_secs_f32(self.settings.range_smoothing);
+                Some(self.range.update(span_min, span_max, period))
+            } else {
+                self.range.reset();
+                ((span_min, span_max) != (min, max)).then(|| {
+                    let low = f64::from(span_min);
+                    (low, f64::from(span_max).max(low + 1.0))
+                })
+            };
+            let auto_span = match self.last_span {
+                Some(span) if self.settings.span_locked => Some(span),
+                _ => {
+                    self.last_span = Some(auto_span.unwrap_or_else(|| {
+                        let low = f64::from(min);
+                        (low, f64::from(max).max(low + 1.0))
+                    }));
+                    auto_span
+                }
+            };
+
+            // where a pixel is in the span of the color map, before the color range
+            let position_at = |index: usize| match (&deviations, span) {
+                (Some(deviations), Some(span)) => {
+                    image_utils::deviation_position(deviations[index], span)
+                }
+                _ => {
+                    let current_pixel = filtered.data()[index];
+
+                    match centered_span.or(auto_span) {
+                        Some((low, high)) => image_utils::span_position(current_pixel, low, high),
+                        None => image_utils::position(current_pixel, min, max),
+                    }
+                }
+            };
 
             {
                 profiling::scope!("colorize");
-                imgbuf.each_pixel_mut(|pt, pixel| {
-                    let current_pixel = filtered.get([pt.x, pt.y]).as_slice()[0];
-                    let scaled_value = map_to_scaled_value(current_pixel, min, max, color_range);
+                let pixels = imgbuf
+                    .data_mut()
+                    .chunks_exact_mut(3)
+                    .zip(positions.data_mut());
+
+                for (index, (pixel, encoded)) in pixels.enumerate() {
+                    if invalid[index] {
+                        pixel.copy_from_slice(&image_utils::INVALID_COLOR);
+                        *encoded = image_utils::INVALID_POSITION;
+                        continue;
+                    }
 
+                    let position = position_at(index);
+                    let scaled_value = image_utils::apply_color_range(position, color_range);
                     let color = self.colormap.transform_single(scaled_value);
-                    pixel.copy_from_slice([color.int_r(), color.int_g(), color.int_b()]);
-                });
+                    pixel.copy_from_slice(&[color.int_r(), color.int_g(), color.int_b()]);
+                    *encoded = image_utils::encode_position(position);
+                }
             }
 
+            if !self.settings.distortion.is_none() {
+                profiling::scope!("undistort");
+                let undistort = image_utils::Undistort(self.settings.distortion);
+                imgbuf = imgbuf.run(undistort, None);
+                values = values.run(undistort, None);
+                positions = positions.run(undistort, None);
+            }
+            if let Some(shift) = stabilization_shift {
+                profiling::scope!("stabilize");
+                let stabilize = image_utils::Warp(Homography::from_rect(
+                    egui::Rect::from_min_size(shift.to_pos2(), egui::Vec2::splat(1.0)),
+                ));
+                imgbuf = imgbuf.run(stabilize, None);
+                values = values.run(stabilize, None);
+                positions = positions.run(stabilize, None);
+            }
             if self.settings.flip_horizontally {
                 profiling::scope!("horizontal flip");
                 imgbuf.run_in_place(image_utils::Flip::Horizontal);
+                values.run_in_place(image_utils::Flip::Horizontal);
+                positions.run_in_place(image_utils::Flip::Horizontal);
             }
             if self.settings.flip_vertically {
                 profiling::scope!("vertical flip");
                 imgbuf.run_in_place(image_utils::Flip::Vertical);
+                values.run_in_place(image_utils::Flip::Vertical);
+                positions.run_in_place(image_utils::Flip::Vertical);
+            }
+            if let Some(keystone) = self.keystone {
+                profiling::scope!("keystone");
+                imgbuf = imgbuf.run(image_utils::Warp(keystone), None);
+                values = values.run(image_utils::Warp(keystone), None);
+                positions = positions.run(image_utils::Warp(keystone), None);
             }
 
+            let unfiltered = std::mem::take(&mut self.unfiltered_requested).then(|| {
+                let mut unfiltered = self.frame_pool.gray_image();
+                unfiltered.data_mut().copy_from_slice(gray_image.data());
+                if self.settings.flip_horizontally {
+                    unfiltered.run_in_place(image_utils::Flip::Horizontal);
+                }
+                if self.settings.flip_vertically {
+                    unfiltered.run_in_place(image_utils::Flip::Vertical);
+                }
+                unfiltered
+            });
+
+            let color_image = {
+                profiling::scope!("color image");
+                image_utils::to_color_image(&imgbuf)
+            };
+
+            // after the corrections, so they're on the pixels as shown
+            let coldest = image_utils::coldest_pixel(&values, &positions).unwrap_or_default();
+            let hottest = image_utils::hottest_pixel(&values, &positions).unwrap_or_default();
+
             self.send_message_to_ui(ProducerMessage::Frame(Frame {
                 image: imgbuf,
-                min: f64::from(min) / 10.0,
-                max: f64::from(max) / 10.0,
+                color_image,
+                values,
+                positions,
+                unfiltered,
+                min: f64::from(measured_min) / 10.0,
+                max: f64::from(measured_max) / 10.0,
+                coldest,
+                hottest,
             }));
         }
     }
 
+    /// Tracks the new frames and reopens the port once none has arrived for a while, as if the
+    /// camera had been disconnected.
+    #[profiling::function]
+    fn watch_for_stall(&mut self, frame: Option<&thermal::GrayImage>) {
+        if let Some(image) = frame.filter(|image| image.data() != self.last_values.as_slice()) {
+            self.frame_received_at = Instant::now();
+            self.last_values.clear();
+            self.last_values.extend_from_slice(image.data());
+
+            if self.recovering {
+                log::info!("Frames resumed after reopening the stalled port");
+                self.recovering = false;
+            }
+        }
+
+        if self.rw.is_none() || self.frame_received_at.elapsed() < self.stall_timeout {
+            return;
+        }
+
+        log::warn!(
+            "No new frame for {:.1} s, reopening the port",
+            self.stall_timeout.as_secs_f32()
+        );
+
+        self.close_port();
+        self.recovering = true;
+        self.send_message_to_ui(ProducerMessage::ConnectionStatusChange(
+            ConnectionStatus::Disconnected,
+        ));
+        self.send_message_to_ui(ProducerMessage::ConnectionError(PortError::new(
+            PortErrorKind::Timeout,
+            "The camera has stopped sending frames",
+        )));
+    }
+
+    /// Closes both halves of the port, returns whether it has been open.
+    fn close_port(&mut self) -> bool {
+        self.commands.detach();
+        self.rw.take().is_some()
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn start_recording(
+        &mut self,
+        path: std::path::PathBuf,
+        file: std::fs::File,
+        ntp_offset: &anyhow::Result<crate::ntp::ClockOffset>,
+    ) {
+        self.stop_recording();
+
+        let port_info = self.opener.port_info();
+        let recorder = Recorder::start(path.clone(), file, &self.settings, &port_info, ntp_offset);
+        let status = match recorder {
+            Ok(recorder) => {
+                self.recorder = Some(recorder);
+                RecordingStatus::Started(path)
+            }
+            Err(e) => {
+                log::error!("Failed to start recording to {}: {e}", path.display());
+                RecordingStatus::Failed(e.to_string())
+            }
+        };
+        self.send_message_to_ui(ProducerMessage::Recording(status));
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+
+        let status = recorder.finish().unwrap_or_else(|e| {
+            log::error!("Failed to finish the recording: {e}");
+            RecordingStatus::Failed(e.to_string())
+        });
+        self.send_message_to_ui(ProducerMessage::Recording(status));
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn record(&mut self, frame: &RawFrame) {
+        let Some(ref mut recorder) = self.recorder else {
+            return;
+        };
+
+        if let Err(e) = recorder.write(frame) {
+            log::error!("Failed to record a frame: {e}");
+            self.recorder = None;
+            self.send_message_to_ui(ProducerMessage::Recording(RecordingStatus::Failed(
+                e.to_string(),
+            )));
+        }
+    }
+
+    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+    fn record_visible(&mut self, taken_at: Instant, image: &egui::ColorImage) {
+        let Some(ref mut recorder) = self.recorder else {
+            return;
+        };
+
+        if let Err(e) = recorder.write_visible(taken_at, image) {
+            log::error!("Failed to record a visible image: {e}");
+            self.recorder = None;
+            self.send_message_to_ui(ProducerMessage::Recording(RecordingStatus::Failed(
+                e.to_string(),
+            )));
+        }
+    }
+
+    #[profiling::function]
+    fn flush_commands(&mut self) {
+        if let Some(rw) = self.rw.as_mut() {
+            self.commands.flush(rw);
+        }
+
+        if let Some(e) = self.commands.take_error() {
+            self.port_failed(e, "Failed to write to the port");
+        }
+    }
+
     #[profiling::function]
-    fn write_emissivity(&mut self) {
-        if let Some(ref mut rw) = self.rw {
-            let command: [u8; 4] = [
-                0x55,
-                0x01,
-                self.settings.emissivity.into(),
-                0x56 + u8::from(self.settings.emissivity),
-            ];
+    fn update_data_rate(&mut self) {
+        let elapsed = self.data_rate_since.elapsed();
 
-            let _ = rw
-                .write_all(&command)
-                .inspect_err(|e| log::error!("Failed to write emissivity {e}"));
+        if elapsed >= DATA_RATE_INTERVAL {
+            #[allow(clippy::cast_precision_loss)]
+            let rate = self.bytes_read as f64 / elapsed.as_secs_f64();
+
+            self.send_message_to_ui(ProducerMessage::DataRate(rate));
+            self.bytes_read = 0;
+            self.data_rate_since = Instant::now();
         }
     }
 
     #[profiling::function]
-    fn send_message_to_ui(&self, message: ProducerMessage) {
-        if self.sender.send(message).is_ok() {
-            self.egui_ctx.request_repaint();
+    fn send_message_to_ui(&mut self, message: ProducerMessage) {
+        let repaint_after = match message {
+            // the paused or hidden UI polls for the frames on its own, the rate is shown with the
+            // next one
+            ProducerMessage::Frame(_) if !self.paused && !self.suspended => {
+                Some(self.frame_repaint_delay())
+            }
+            ProducerMessage::Frame(_)
+            | ProducerMessage::DataRate(_)
+            | ProducerMessage::Commands(_) => None,
+            ProducerMessage::ConnectionStatusChange(_) | ProducerMessage::ConnectionError(_) => {
+                Some(Duration::ZERO)
+            }
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            ProducerMessage::RawFrames(_) => Some(Duration::ZERO),
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            ProducerMessage::Recording(_) => Some(Duration::ZERO),
+        };
+
+        if let (Ok(()), Some(delay)) = (self.sender.send(message), repaint_after) {
+            self.egui_ctx.request_repaint_after(delay);
         }
     }
 
+    /// How long the repaint for a new frame has to wait for the frame rate cap. Frames arriving
+    /// in the meantime are shown together by that repaint, the UI keeps the last one.
+    fn frame_repaint_delay(&mut self) -> Duration {
+        let now = Instant::now();
+        // a repaint still pending shows this frame too
+        if self.repaint_due < now {
+            self.repaint_due = now.max(self.repaint_due + self.repaint_interval);
+        }
+        let due = self.repaint_due;
+
+        due - now
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
     pub fn main_loop(&mut self) {
-        loop {
-            self.ensure_port_opened();
-
-            let new_settings: Option<Settings> = {
-                profiling::scope!("receive settings");
-                let mut received_settings: Option<Settings> = None;
-
-                loop {
-                    match self.receiver.try_recv() {
-                        Ok(UiMessage::ChangeSettings(settings)) => {
-                            received_settings = Some(settings);
-                        }
-                        Err(TryRecvError::Disconnected | TryRecvError::Empty) => {
-                            break received_settings
-                        }
+        while self.running {
+            self.step();
+        }
+    }
+
+    /// Runs a single iteration of the producer. Blocks while waiting for a frame unless the
+    /// transport is non-blocking.
+    pub fn step(&mut self) {
+        self.ensure_port_opened();
+
+        let new_settings: Option<Settings> = {
+            profiling::scope!("receive settings");
+            let mut received_settings: Option<Settings> = None;
+
+            loop {
+                match self.receiver.try_recv() {
+                    Ok(UiMessage::ChangeSettings(settings)) => {
+                        received_settings = Some(settings);
+                    }
+                    Ok(UiMessage::RetryConnection) => {
+                        self.opener.retry();
+                        self.reopen_at = Instant::now();
+                    }
+                    Ok(UiMessage::Pause(paused)) => self.paused = paused,
+                    Ok(UiMessage::Suspend(suspended)) => self.suspended = suspended,
+                    Ok(UiMessage::MaxFrameRate(rate)) => {
+                        self.repaint_interval = Duration::from_secs(1) / rate.max(1);
                     }
+                    Ok(UiMessage::Stop) => self.running = false,
+                    #[cfg(not(any(
+                        target_os = "android",
+                        target_os = "ios",
+                        target_arch = "wasm32"
+                    )))]
+                    Ok(UiMessage::CollectRawFrames) => {
+                        let frames = self.raw_frames.iter().cloned().collect();
+                        self.send_message_to_ui(ProducerMessage::RawFrames(frames));
+                    }
+                    #[cfg(not(any(
+                        target_os = "android",
+                        target_os = "ios",
+                        target_arch = "wasm32"
+                    )))]
+                    Ok(UiMessage::CollectUnfiltered) => self.unfiltered_requested = true,
+                    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+                    Ok(UiMessage::StartRecording(path, file, ntp_offset)) => {
+                        self.start_recording(path, file, &ntp_offset);
+                    }
+                    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+                    Ok(UiMessage::StopRecording) => self.stop_recording(),
+                    #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+                    Ok(UiMessage::RecordVisible(taken_at, image)) => {
+                        self.record_visible(taken_at, &image);
+                    }
+                    Err(TryRecvError::Disconnected) => {
+                        self.running = false;
+                        break received_settings;
+                    }
+                    Err(TryRecvError::Empty) => break received_settings,
                 }
+            }
+        };
+
+        if !self.running {
+            // an unfinished recording would look interrupted
+            #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+            self.stop_recording();
+            // dropping the port closes it
+            self.close_port();
+            return;
+        }
+
+        if let Some(ref new_settings) = new_settings {
+            profiling::scope!("apply settings");
+            let port_changed = self.settings.port_changed(new_settings);
+
+            self.settings = new_settings.clone();
+            self.opener.configure(&self.settings);
+
+            if port_changed && self.close_port() {
+                self.send_message_to_ui(ProducerMessage::ConnectionStatusChange(
+                    ConnectionStatus::Disconnected,
+                ));
+            }
+
+            self.kernel = self.settings.get_kernel();
+            self.keystone = self.settings.get_keystone();
+            if !self.settings.stabilization {
+                self.stabilizer.reset();
+            }
+            self.colormap = self.settings.colormap.get_colormap();
+            for command in self.settings.device_config() {
+                self.commands.push(command);
+            }
+        }
+
+        self.flush_commands();
+
+        // the camera streams frames without any header to resynchronize on, so they're read even
+        // while suspended, just not processed
+        let frame = self.read_image();
+        self.watch_for_stall(frame.as_ref());
+
+        #[cfg(not(any(target_os = "ios", target_arch = "wasm32")))]
+        if let Some(ref image) = frame {
+            let raw_frame = RawFrame {
+                received_at: Instant::now(),
+                wall_clock: std::time::SystemTime::now(),
+                values: image.data().to_vec(),
             };
+            self.record(&raw_frame);
+
+            #[cfg(not(any(target_os = "android", target_os = "ios", target_arch = "wasm32")))]
+            {
+                if self.raw_frames.len() == MAX_RAW_FRAMES {
+                    self.raw_frames.pop_front();
+                }
+                self.raw_frames.push_back(raw_frame);
+            }
+        }
 
-            if let Some(ref new_settings) = new_settings {
-                profiling::scope!("apply settings");
-                self.settings = new_settings.clone();
-                self.kernel = self.settings.get_kernel();
-                self.colormap = self.settings.colormap.get_colormap();
-                self.write_emissivity();
+        let frame = match frame {
+            Some(image)
+                if self.suspended && self.produced_at.elapsed() < SUSPENDED_FRAME_INTERVAL =>
+            {
+                self.frame_pool.recycle_gray_image(image);
+                None
             }
+            frame => frame,
+        };
+
+        if let Some(mut gray_image) = frame {
+            let invalid = self.settings.calibrate(&mut gray_image);
 
-            if let Some(ref gray_image) = self.read_image() {
-                self.produce_thermal_frame(gray_image);
+            if self.settings.frames_to_average > 1 {
+                profiling::scope!("average frames");
+                let mean = self
+                    .averager
+                    .push(&gray_image, self.settings.frames_to_average);
+                self.frame_pool
+                    .recycle_gray_image(std::mem::replace(&mut gray_image, mean));
+            } else {
+                self.averager.reset();
             }
+            // every frame is registered, motion between skipped ones would be missed otherwise
+            let stabilization_shift = self.settings.stabilization.then(|| {
+                profiling::scope!("register frame");
+                self.stabilizer.update(&gray_image)
+            });
 
-            profiling::finish_frame!();
+            if !self.settings.power_saving
+                || self.produced_at.elapsed() >= POWER_SAVING_FRAME_INTERVAL
+            {
+                self.produced_at = Instant::now();
+                self.produce_thermal_frame(&gray_image, stabilization_shift, &invalid);
+            }
+
+            self.frame_pool.recycle_gray_image(gray_image);
         }
+
+        self.update_data_rate();
+
+        profiling::finish_frame!();
     }
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,666 @@
+use super::*;
+use crate::mock::MockOpener;
+
+use std::path::PathBuf;
+use std::sync::mpsc;
+use strum::IntoEnumIterator;
+
+/// 20 °C in the top left corner rising to 51 °C in the bottom right one.
+fn gradient() -> Vec<u16> {
+    (0..THERMAL_IMAGE_HEIGHT)
+        .flat_map(|y| (0..THERMAL_IMAGE_WIDTH).map(move |x| 200 + 5 * (x + y) as u16))
+        .collect()
+}
+
+/// 30 °C with a single pixel at 60 °C.
+fn hot_spot() -> Vec<u16> {
+    let mut values = vec![300; THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT];
+    values[10 * THERMAL_IMAGE_WIDTH + 20] = 600;
+    values
+}
+
+fn unfiltered() -> Settings {
+    Settings {
+        filtering_method: FilteringMethod::None,
+        ..Settings::default()
+    }
+}
+
+struct Harness {
+    producer: ImageProducer<'static, MockOpener>,
+    written: std::rc::Rc<std::cell::RefCell<Vec<u8>>>,
+    receiver: Receiver<ProducerMessage>,
+    // keeps the producer running
+    sender: Sender<UiMessage>,
+}
+
+impl Harness {
+    fn new(opener: MockOpener, settings: Settings) -> Self {
+        let (ui_sender, producer_receiver) = mpsc::channel();
+        let (producer_sender, ui_receiver) = mpsc::channel();
+        let written = std::rc::Rc::clone(&opener.written);
+
+        ui_sender.send(UiMessage::ChangeSettings(settings)).unwrap();
+
+        Self {
+            producer: ImageProducer::new(
+                egui::Context::default(),
+                producer_sender,
+                producer_receiver,
+                opener,
+            ),
+            written,
+            receiver: ui_receiver,
+            sender: ui_sender,
+        }
+    }
+
+    fn step(&mut self) -> Vec<ProducerMessage> {
+        self.producer.step();
+        self.receiver.try_iter().collect()
+    }
+
+    /// Steps once and returns the frame produced.
+    fn frame(&mut self) -> Frame {
+        self.step()
+            .into_iter()
+            .find_map(|message| match message {
+                ProducerMessage::Frame(frame) => Some(frame),
+                _ => None,
+            })
+            .expect("No frame produced")
+    }
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.png"))
+}
+
+fn decode_png(path: &PathBuf) -> (u32, u32, Vec<u8>) {
+    let decoder = png::Decoder::new(std::fs::File::open(path).unwrap());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    data.truncate(info.buffer_size());
+
+    (info.width, info.height, data)
+}
+
+/// Compares `image` with its golden PNG in `tests/golden`. With `UPDATE_GOLDEN=1` the golden
+/// image is written instead, after an intended change of the output.
+fn assert_golden(name: &str, image: &RgbImage) {
+    let path = golden_path(name);
+
+    if std::env::var("UPDATE_GOLDEN").is_ok_and(|update| update == "1") {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, crate::report::encode_png(image).unwrap()).unwrap();
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "{name}: {} is missing, run the tests with UPDATE_GOLDEN=1 to create it",
+        path.display()
+    );
+
+    let (width, height, data) = decode_png(&path);
+    assert_eq!(
+        (width as usize, height as usize),
+        (image.width(), image.height()),
+        "{name}: size differs from {}",
+        path.display()
+    );
+
+    let differing = data
+        .iter()
+        .zip(image.data())
+        .filter(|(golden, actual)| golden != actual)
+        .count();
+    assert_eq!(differing, 0, "{name}: differs from {}", path.display());
+}
+
+#[test]
+fn decodes_little_endian_frames() {
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), unfiltered());
+    let frame = harness.frame();
+
+    assert_eq!(frame.values.data(), gradient().as_slice());
+    assert!((frame.min - 20.0).abs() < f64::EPSILON);
+    assert!((frame.max - 51.0).abs() < f64::EPSILON);
+}
+
+#[test]
+fn reports_connection() {
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), unfiltered());
+
+    assert!(harness.step().iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionStatusChange(ConnectionStatus::Connected(port))
+            if port.name == "Mock"
+    )));
+}
+
+#[test]
+fn writes_emissivity() {
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), unfiltered());
+    harness.step();
+
+    let written = harness.written.borrow();
+    let packet = &written[written.len() - crate::protocol::PACKET_SIZE..];
+    assert_eq!(
+        crate::protocol::Command::decode(packet),
+        Ok(crate::protocol::Command::SetEmissivity(95))
+    );
+}
+
+/// Writer half of a port, keeping what's written.
+struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+impl io::Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writer failing every write, like the port of an unplugged camera.
+struct FailingWriter;
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Polls `condition` until the writer thread has caught up, failing after a second.
+fn wait_for(mut condition: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !condition() {
+        assert!(
+            Instant::now() < deadline,
+            "Timed out waiting for the writer thread"
+        );
+        thread::sleep(Duration::from_millis(1));
+    }
+}
+
+fn packets(commands: &[Command]) -> Vec<u8> {
+    commands
+        .iter()
+        .flat_map(|command| command.encode())
+        .collect()
+}
+
+#[test]
+fn writes_changed_commands_through_the_writer_half() {
+    let commands = CommandQueue::default();
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    commands.attach(
+        Some(Box::new(SharedWriter(Arc::clone(&written)))),
+        vec![Command::SetEmissivity(95)],
+    );
+    wait_for(|| *written.lock().unwrap() == packets(&[Command::SetEmissivity(95)]));
+    commands.send(Command::SetEmissivity(95));
+
+    // paced, the latest of the same kind replaces the pending one
+    commands.state().written_at = Some(Instant::now() + Duration::from_secs(60));
+    commands.send(Command::SetEmissivity(80));
+    commands.send(Command::SetEmissivity(70));
+    thread::sleep(COMMAND_INTERVAL * 2);
+    assert_eq!(
+        *written.lock().unwrap(),
+        packets(&[Command::SetEmissivity(95)])
+    );
+
+    commands.state().written_at = None;
+    wait_for(|| {
+        *written.lock().unwrap()
+            == packets(&[Command::SetEmissivity(95), Command::SetEmissivity(70)])
+    });
+    assert!(commands.state().pending.is_empty());
+}
+
+#[test]
+fn retries_failed_commands_after_reconnecting() {
+    let commands = CommandQueue::default();
+    let written = Arc::new(Mutex::new(Vec::new()));
+
+    commands.attach(
+        Some(Box::new(FailingWriter)),
+        vec![Command::SetEmissivity(95)],
+    );
+    // reported to the producer, which reopens the port
+    let mut error = None;
+    wait_for(|| {
+        error = commands.take_error();
+        error.is_some()
+    });
+    assert_eq!(error.unwrap().kind(), io::ErrorKind::BrokenPipe);
+    commands.detach();
+    commands.send(Command::SetEmissivity(80));
+    assert_eq!(commands.state().pending.len(), 1);
+
+    // the configuration of the new connection is up to date
+    commands.attach(None, vec![Command::SetEmissivity(80)]);
+    let mut port = Vec::new();
+    commands.flush(&mut port);
+    assert_eq!(port, packets(&[Command::SetEmissivity(80)]));
+    assert!(commands.state().pending.is_empty());
+
+    commands.state().written_at = None;
+    commands.attach(
+        Some(Box::new(SharedWriter(Arc::clone(&written)))),
+        vec![Command::SetEmissivity(80)],
+    );
+    wait_for(|| *written.lock().unwrap() == packets(&[Command::SetEmissivity(80)]));
+    assert!(commands.take_error().is_none());
+}
+
+#[test]
+fn measures_unfiltered_values() {
+    let settings = Settings {
+        filtering_method: FilteringMethod::Gaussian3x3,
+        ..Settings::default()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[hot_spot()]), settings);
+    let frame = harness.frame();
+
+    assert!((frame.max - 60.0).abs() < f64::EPSILON);
+    assert_eq!(frame.values.data(), hot_spot().as_slice());
+}
+
+#[test]
+fn applies_temperature_offset() {
+    let settings = Settings {
+        temperature_offset: -1.5,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), settings);
+    let frame = harness.frame();
+
+    assert!((frame.min - 18.5).abs() < 1e-9);
+    assert!((frame.max - 49.5).abs() < 1e-9);
+}
+
+#[test]
+fn converts_the_counts_of_other_firmware() {
+    // 20 °C to 51 °C in tenths of K
+    let kelvin: Vec<u16> = gradient().iter().map(|value| value + 2732).collect();
+    let settings = Settings {
+        count_format: CountFormat::DeciKelvin,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[kelvin]), settings.clone());
+    let frame = harness.frame();
+
+    assert!((frame.min - 20.0).abs() < 0.1);
+    assert!((frame.max - 51.0).abs() < 0.1);
+    assert!((settings.count(frame.min) - 2932.0).abs() < 1.0);
+}
+
+#[test]
+fn flips_values_with_the_image() {
+    let settings = Settings {
+        flip_horizontally: true,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[hot_spot()]), settings);
+    let frame = harness.frame();
+
+    let hottest = frame.values.data().iter().position(|&value| value == 600);
+    assert_eq!(
+        hottest,
+        Some(10 * THERMAL_IMAGE_WIDTH + THERMAL_IMAGE_WIDTH - 1 - 20)
+    );
+}
+
+#[test]
+fn disconnects_once_the_camera_goes_silent() {
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), unfiltered());
+    harness.frame();
+
+    let messages = harness.step();
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionStatusChange(ConnectionStatus::Disconnected)
+    )));
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionError(PortError {
+            kind: PortErrorKind::Timeout,
+            ..
+        })
+    )));
+}
+
+#[test]
+fn reopens_the_port_of_a_stalled_stream() {
+    let mut harness = Harness::new(
+        MockOpener::default()
+            .connect(&vec![gradient(); 3])
+            .connect(&[hot_spot()]),
+        unfiltered(),
+    );
+    harness.producer.stall_timeout = Duration::from_millis(50);
+    harness.frame();
+
+    // the same frame again, after the timeout
+    thread::sleep(Duration::from_millis(100));
+    let messages = harness.step();
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionStatusChange(ConnectionStatus::Disconnected)
+    )));
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionError(PortError {
+            kind: PortErrorKind::Timeout,
+            ..
+        })
+    )));
+    assert!(harness.producer.rw.is_none());
+
+    let messages = harness.step();
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionStatusChange(ConnectionStatus::Connected(_))
+    )));
+    let frame = messages
+        .into_iter()
+        .find_map(|message| match message {
+            ProducerMessage::Frame(frame) => Some(frame),
+            _ => None,
+        })
+        .expect("No frame after reopening");
+    assert!((frame.max - 60.0).abs() < 1e-9);
+}
+
+#[test]
+fn drops_truncated_frames() {
+    let mut bytes = crate::mock::encode_frame(&gradient());
+    bytes.truncate(bytes.len() / 2);
+    let mut harness = Harness::new(MockOpener::default().connect_raw(bytes), unfiltered());
+
+    let messages = harness.step();
+    assert!(!messages
+        .iter()
+        .any(|message| matches!(message, ProducerMessage::Frame(_))));
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionStatusChange(ConnectionStatus::Disconnected)
+    )));
+}
+
+#[test]
+fn categorizes_open_failures() {
+    let error = io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied");
+    let mut harness = Harness::new(
+        MockOpener::default().fail(anyhow::Error::new(error).context("Failed to open port")),
+        unfiltered(),
+    );
+
+    let messages = harness.step();
+    assert!(messages.iter().any(|message| matches!(
+        message,
+        ProducerMessage::ConnectionError(PortError {
+            kind: PortErrorKind::PermissionDenied,
+            message,
+        }) if message == "Failed to open port: Permission denied"
+    )));
+}
+
+#[test]
+fn colormaps_match_golden_images() {
+    for colormap in ColorMap::iter() {
+        let settings = Settings {
+            colormap: colormap.clone(),
+            ..unfiltered()
+        };
+        let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), settings);
+        let name = colormap
+            .to_string()
+            .to_lowercase()
+            .replace([' ', '(', ')'], "");
+
+        assert_golden(&format!("colormap-{name}"), &harness.frame().image);
+    }
+}
+
+#[test]
+fn recolors_frames_from_their_positions() {
+    let frame = Harness::new(MockOpener::default().connect(&[gradient()]), unfiltered()).frame();
+    let settings = Settings {
+        colormap: ColorMap::Magma,
+        color_range: ColorRange(80),
+        ..unfiltered()
+    };
+    let expected = Harness::new(
+        MockOpener::default().connect(&[gradient()]),
+        settings.clone(),
+    )
+    .frame()
+    .image;
+
+    let mut recolored = RgbImage::new(frame.positions.size());
+    image_utils::ColorLut::new(&*settings.colormap.get_colormap(), settings.color_range)
+        .colorize(&frame.positions, &mut recolored);
+
+    // the table is sampled, colors in between its entries may be a shade off
+    let max_difference = recolored
+        .data()
+        .iter()
+        .zip(expected.data())
+        .map(|(recolored, expected)| recolored.abs_diff(*expected))
+        .max();
+    assert!(max_difference <= Some(4), "{max_difference:?}");
+}
+
+#[test]
+fn filters_match_golden_images() {
+    for filtering_method in FilteringMethod::iter() {
+        let settings = Settings {
+            filtering_method: filtering_method.clone(),
+            ..Settings::default()
+        };
+        let mut harness = Harness::new(MockOpener::default().connect(&[hot_spot()]), settings);
+        let name = filtering_method
+            .to_string()
+            .to_lowercase()
+            .replace(' ', "-");
+
+        assert_golden(&format!("filter-{name}"), &harness.frame().image);
+    }
+}
+
+#[test]
+fn centered_span_matches_golden_image() {
+    let settings = Settings {
+        centered_span: true,
+        span_center: 35.0,
+        span_half_width: 5.0,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[gradient()]), settings);
+
+    assert_golden("centered-span", &harness.frame().image);
+}
+
+#[test]
+fn smooths_the_auto_range() {
+    // 5 °C warmer, as if the scene had warmed up
+    let warmer: Vec<u16> = gradient().iter().map(|value| value + 50).collect();
+    let settings = Settings {
+        range_smoothing: MAX_RANGE_SMOOTHING,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(
+        MockOpener::default().connect(&[gradient(), warmer.clone()]),
+        settings,
+    );
+    harness.frame();
+    let smoothed = harness.frame();
+    let unsmoothed = Harness::new(MockOpener::default().connect(&[warmer]), unfiltered()).frame();
+
+    // the span still starts at the coldest pixel of the first frame
+    assert_eq!(unsmoothed.positions.data()[0], 0);
+    assert!(smoothed.positions.data()[0] > 0);
+    assert_eq!(
+        smoothed.positions.data().last(),
+        Some(&image_utils::encode_position(1.0))
+    );
+}
+
+#[test]
+fn ignores_extreme_pixels_in_the_auto_range() {
+    let mut values = gradient();
+    // a glint in the middle of the scene
+    values[10 * THERMAL_IMAGE_WIDTH + 20] = 900;
+    let settings = Settings {
+        range_outliers: 1,
+        ..unfiltered()
+    };
+    let trimmed = Harness::new(MockOpener::default().connect(&[values.clone()]), settings).frame();
+    let full = Harness::new(MockOpener::default().connect(&[values]), unfiltered()).frame();
+
+    // the hottest pixel of the gradient is at the end of the span once the glint is ignored
+    assert_eq!(
+        trimmed.positions.data().last(),
+        Some(&image_utils::encode_position(1.0))
+    );
+    assert!(full.positions.data().last() < Some(&(u16::MAX / 2)));
+    assert!((trimmed.max - 90.0).abs() < 1e-9);
+}
+
+#[test]
+fn keeps_the_locked_span() {
+    let warmer: Vec<u16> = gradient().iter().map(|value| value + 50).collect();
+    let mut harness = Harness::new(
+        MockOpener::default().connect(&[gradient(), warmer]),
+        unfiltered(),
+    );
+    harness.frame();
+
+    let locked = Settings {
+        span_locked: true,
+        ..unfiltered()
+    };
+    harness
+        .sender
+        .send(UiMessage::ChangeSettings(locked))
+        .unwrap();
+    let frame = harness.frame();
+
+    // the coldest pixel is 5 °C above the start of the span of the first frame
+    assert_eq!(
+        frame.positions.data()[0],
+        image_utils::encode_position(50.0 / 310.0)
+    );
+    assert_eq!(
+        frame.positions.data().last(),
+        Some(&image_utils::encode_position(1.0))
+    );
+}
+
+#[test]
+fn masks_invalid_counts() {
+    let mut values = hot_spot();
+    values[5 * THERMAL_IMAGE_WIDTH + 5] = 0xffff;
+    let mut harness = Harness::new(MockOpener::default().connect(&[values]), unfiltered());
+    let frame = harness.frame();
+
+    // replaced by its neighbours, it doesn't stretch the span
+    assert!((frame.max - 60.0).abs() < 1e-9);
+    assert_eq!(frame.values.data()[5 * THERMAL_IMAGE_WIDTH + 5], 300);
+    assert_eq!(
+        frame.image.data()[(5 * THERMAL_IMAGE_WIDTH + 5) * 3..][..3],
+        image_utils::INVALID_COLOR
+    );
+}
+
+#[test]
+fn leaves_invalid_blocks_out_of_the_statistics() {
+    let mut values = hot_spot();
+    for y in 4..7 {
+        for x in 4..7 {
+            values[y * THERMAL_IMAGE_WIDTH + x] = 0xffff;
+        }
+    }
+    let dropped = vec![0xffff; THERMAL_IMAGE_WIDTH * THERMAL_IMAGE_HEIGHT];
+    let mut harness = Harness::new(
+        MockOpener::default().connect(&[values, dropped]),
+        unfiltered(),
+    );
+    let frame = harness.frame();
+
+    assert!((frame.min - 30.0).abs() < 1e-9);
+    assert!((frame.max - 60.0).abs() < 1e-9);
+    assert_eq!(frame.hottest, [20, 10]);
+    // the center of the block has no valid neighbour, it's filled from the block's edges
+    assert_eq!(frame.values.data()[5 * THERMAL_IMAGE_WIDTH + 5], 300);
+
+    // a frame without any valid pixel isn't shown
+    let messages = harness.step();
+    assert!(!messages
+        .iter()
+        .any(|message| matches!(message, ProducerMessage::Frame(_))));
+}
+
+#[test]
+fn recolors_invalid_pixels() {
+    let mut values = hot_spot();
+    values[5 * THERMAL_IMAGE_WIDTH + 5] = 0xffff;
+    let frame = Harness::new(MockOpener::default().connect(&[values]), unfiltered()).frame();
+
+    let mut recolored = RgbImage::new(frame.positions.size());
+    image_utils::ColorLut::new(&*ColorMap::Magma.get_colormap(), ColorRange(100))
+        .colorize(&frame.positions, &mut recolored);
+
+    assert_eq!(
+        recolored.data()[(5 * THERMAL_IMAGE_WIDTH + 5) * 3..][..3],
+        image_utils::INVALID_COLOR
+    );
+    assert_ne!(recolored.data()[..3], image_utils::INVALID_COLOR);
+}
+
+#[test]
+fn masks_temperatures_below_zero() {
+    let mut values = hot_spot();
+    values[5 * THERMAL_IMAGE_WIDTH + 5] = 20;
+    let settings = Settings {
+        temperature_offset: -5.0,
+        ..unfiltered()
+    };
+    let mut harness = Harness::new(MockOpener::default().connect(&[values]), settings);
+    let frame = harness.frame();
+
+    // -3 °C isn't clamped to 0 °C, it's replaced by its neighbours and shown as invalid
+    assert!((frame.min - 25.0).abs() < 1e-9);
+    assert_eq!(frame.values.data()[5 * THERMAL_IMAGE_WIDTH + 5], 250);
+    assert_eq!(
+        frame.image.data()[(5 * THERMAL_IMAGE_WIDTH + 5) * 3..][..3],
+        image_utils::INVALID_COLOR
+    );
+}
+
+#[test]
+fn locates_the_extreme_pixels() {
+    let mut values = hot_spot();
+    values[30 * THERMAL_IMAGE_WIDTH + 5] = 100;
+    let mut harness = Harness::new(MockOpener::default().connect(&[values]), unfiltered());
+
+    let frame = harness.frame();
+
+    assert_eq!(frame.hottest, [20, 10]);
+    assert_eq!(frame.coldest, [5, 30]);
+}
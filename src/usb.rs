@@ -0,0 +1,324 @@
+use std::{
+    collections::VecDeque,
+    future::Future,
+    io,
+    sync::Arc,
+    task::{self, Poll, Waker},
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+use crate::thermal::{PortError, PortErrorKind};
+
+use anyhow::Context;
+use nusb::transfer::{
+    Control, ControlType, Direction, EndpointType, Queue, Recipient, RequestBuffer,
+};
+
+// USB class codes of the CDC-ACM interfaces
+const CDC_CONTROL_CLASS: u8 = 0x02;
+const CDC_DATA_CLASS: u8 = 0x0a;
+
+const SET_LINE_CODING: u8 = 0x20;
+const SET_CONTROL_LINE_STATE: u8 = 0x22;
+const DTR: u16 = 0x01;
+const RTS: u16 = 0x02;
+
+const CONTROL_TIMEOUT: Duration = Duration::from_secs(1);
+// how long a bulk transfer waits by default before the device is considered gone silent
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(1);
+const TRANSFER_SIZE: usize = 4096;
+// keeps the device busy while the previous transfer is being processed
+const QUEUED_TRANSFERS: usize = 4;
+
+/// A CDC-ACM device driven directly over USB, without the kernel tty driver. That keeps
+/// ModemManager from grabbing the port and doesn't require the user to be in the serial group
+/// (only access to the USB device itself).
+pub struct CdcAcm {
+    name: String,
+    serial_number: Option<String>,
+    interface: nusb::Interface,
+    endpoint_out: u8,
+    reads: Queue<RequestBuffer>,
+    pending: VecDeque<u8>,
+    timeout: Duration,
+}
+
+impl CdcAcm {
+    /// Opens the first device with the given ids. DTR and RTS are asserted if requested, or
+    /// DTR only by default, which is what the kernel driver does on open.
+    #[cfg(not(target_os = "android"))]
+    pub fn open(
+        vid: u16,
+        pid: u16,
+        baud_rate: u32,
+        dtr: Option<bool>,
+        rts: Option<bool>,
+    ) -> anyhow::Result<Self> {
+        let device_info = nusb::list_devices()?
+            .find(|device| device.vendor_id() == vid && device.product_id() == pid)
+            .ok_or_else(|| {
+                PortError::new(
+                    PortErrorKind::NotFound,
+                    format!("Failed to find USB device {vid:04x}:{pid:04x}"),
+                )
+            })?;
+
+        let name = format!(
+            "USB {vid:04x}:{pid:04x} (bus {}, address {})",
+            device_info.bus_number(),
+            device_info.device_address()
+        );
+
+        let device = device_info.open().context("Failed to open USB device")?;
+
+        let mut cdc = Self::from_device(&device, name, baud_rate, dtr, rts)?;
+        cdc.serial_number = device_info.serial_number().map(str::to_owned);
+
+        Ok(cdc)
+    }
+
+    /// Sets up an already opened device.
+    pub fn from_device(
+        device: &nusb::Device,
+        name: String,
+        baud_rate: u32,
+        dtr: Option<bool>,
+        rts: Option<bool>,
+    ) -> anyhow::Result<Self> {
+        let configuration = device.active_configuration()?;
+
+        let mut control_interface = None;
+        let mut data_interface = None;
+
+        for interface in configuration.interfaces() {
+            let Some(alt_setting) = interface.alt_settings().next() else {
+                continue;
+            };
+
+            match alt_setting.class() {
+                CDC_CONTROL_CLASS => control_interface = Some(interface.interface_number()),
+                CDC_DATA_CLASS => {
+                    let endpoint = |direction| {
+                        alt_setting
+                            .endpoints()
+                            .find(|endpoint| {
+                                endpoint.transfer_type() == EndpointType::Bulk
+                                    && endpoint.direction() == direction
+                            })
+                            .map(|endpoint| endpoint.address())
+                    };
+
+                    data_interface = endpoint(Direction::In)
+                        .zip(endpoint(Direction::Out))
+                        .map(|endpoints| (interface.interface_number(), endpoints));
+                }
+                _ => {}
+            }
+        }
+
+        let (data_interface, (endpoint_in, endpoint_out)) = data_interface.ok_or_else(|| {
+            PortError::new(
+                PortErrorKind::ProtocolError,
+                "USB device has no CDC data interface with bulk endpoints",
+            )
+        })?;
+
+        // detaching unbinds the kernel's cdc_acm driver on Linux, the tty disappears until the
+        // device is replugged
+        let interface = device
+            .detach_and_claim_interface(data_interface)
+            .context("Failed to claim USB interface")?;
+
+        if let Some(control_interface) = control_interface {
+            let control = device
+                .detach_and_claim_interface(control_interface)
+                .context("Failed to claim USB interface")?;
+
+            // 8 data bits, no parity, 1 stop bit
+            let mut line_coding = baud_rate.to_le_bytes().to_vec();
+            line_coding.extend_from_slice(&[0, 0, 8]);
+
+            let mut line_state = 0;
+
+            if dtr.unwrap_or(true) {
+                line_state |= DTR;
+            }
+            if rts.unwrap_or(false) {
+                line_state |= RTS;
+            }
+
+            for (request, value, data) in [
+                (SET_LINE_CODING, 0, line_coding.as_slice()),
+                (SET_CONTROL_LINE_STATE, line_state, &[][..]),
+            ] {
+                let request = Control {
+                    control_type: ControlType::Class,
+                    recipient: Recipient::Interface,
+                    request,
+                    value,
+                    index: u16::from(control_interface),
+                };
+
+                control.control_out_blocking(request, data, CONTROL_TIMEOUT)?;
+            }
+        }
+
+        let mut reads = interface.bulk_in_queue(endpoint_in);
+
+        for _ in 0..QUEUED_TRANSFERS {
+            reads.submit(RequestBuffer::new(TRANSFER_SIZE));
+        }
+
+        Ok(Self {
+            name,
+            serial_number: None,
+            interface,
+            endpoint_out,
+            reads,
+            pending: VecDeque::new(),
+            timeout: TRANSFER_TIMEOUT,
+        })
+    }
+
+    /// How long reads and writes wait before failing with [`io::ErrorKind::TimedOut`], taken
+    /// over by writers created afterwards.
+    #[cfg(not(target_os = "android"))]
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn writer(&self) -> CdcAcmWriter {
+        CdcAcmWriter {
+            interface: self.interface.clone(),
+            endpoint_out: self.endpoint_out,
+            timeout: self.timeout,
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+}
+
+impl io::Read for CdcAcm {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.is_empty() {
+            // the transfers stay queued, a later read picks up where this one gave up
+            let completion = block_on_timeout(self.reads.next_complete(), self.timeout)?;
+            completion.status.map_err(io::Error::other)?;
+
+            self.pending.extend(&completion.data);
+            self.reads
+                .submit(RequestBuffer::reuse(completion.data, TRANSFER_SIZE));
+        }
+
+        let len = buf.len().min(self.pending.len());
+
+        for (dst, src) in buf.iter_mut().zip(self.pending.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
+    }
+}
+
+/// Wakes the thread blocked in [`block_on_timeout`].
+struct ThreadWaker(Thread);
+
+impl task::Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` to completion on the current thread, or fails with
+/// [`io::ErrorKind::TimedOut`] once `timeout` has passed. A transfer dropped unfinished is
+/// cancelled.
+fn block_on_timeout<F: Future>(future: F, timeout: Duration) -> io::Result<F::Output> {
+    let deadline = Instant::now() + timeout;
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = task::Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut context) {
+            return Ok(output);
+        }
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "USB device not responding",
+            ));
+        }
+        // spurious wake-ups just poll again
+        thread::park_timeout(remaining);
+    }
+}
+
+fn bulk_out(
+    interface: &nusb::Interface,
+    endpoint: u8,
+    buf: &[u8],
+    timeout: Duration,
+) -> io::Result<usize> {
+    block_on_timeout(interface.bulk_out(endpoint, buf.to_vec()), timeout)?
+        .into_result()
+        .map_err(io::Error::other)?;
+
+    Ok(buf.len())
+}
+
+impl io::Write for CdcAcm {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        bulk_out(&self.interface, self.endpoint_out, buf, self.timeout)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes to the device of a [`CdcAcm`] while it's being read, transfers of both directions
+/// run independently.
+pub struct CdcAcmWriter {
+    interface: nusb::Interface,
+    endpoint_out: u8,
+    timeout: Duration,
+}
+
+impl io::Write for CdcAcmWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        bulk_out(&self.interface, self.endpoint_out, buf, self.timeout)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gives_up_on_a_silent_transfer() {
+        let timeout = Duration::from_millis(20);
+
+        assert_eq!(block_on_timeout(async { 7 }, timeout).unwrap(), 7);
+        assert_eq!(
+            block_on_timeout(std::future::pending::<()>(), timeout)
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::TimedOut
+        );
+    }
+}
@@ -0,0 +1,675 @@
+//! Video export of the rendered frames as Motion JPEG in an AVI file, which common players and
+//! editors open and `ffmpeg -i input.avi output.mp4` converts. The frames are encoded by a
+//! baseline JPEG encoder of its own with the example tables of the JPEG standard, without
+//! subsampling the chroma as the images are small and the colors of the maps matter.
+
+use crate::thermal;
+
+use anyhow::anyhow;
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use web_time::{Duration, Instant};
+
+pub const MIN_FRAME_RATE: u32 = 1;
+pub const MAX_FRAME_RATE: u32 = 30;
+pub const MAX_SCALE: usize = 16;
+const QUALITY: u32 = 90;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoSettings {
+    /// Frames per second of the video, the shown image is repeated or skipped to keep the pace
+    pub frame_rate: u32,
+    /// Pixels of the video per pixel of the sensor
+    pub scale: usize,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            frame_rate: 10,
+            scale: 8,
+        }
+    }
+}
+
+/// Coefficient of a block in the zigzag order, the order of the quantization tables and of the
+/// entropy coding.
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20,
+    13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59,
+    52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+const LUMINANCE_QUANTIZATION: [u32; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+const CHROMINANCE_QUANTIZATION: [u32; 64] = [
+    17, 18, 24, 47, 99, 99, 99, 99, 18, 21, 26, 66, 99, 99, 99, 99, 24, 26, 56, 99, 99, 99, 99, 99,
+    47, 66, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+    99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99, 99,
+];
+
+/// Number of the codes of each length from 1 to 16 bits, and the symbols they code in order
+struct HuffmanSpec {
+    counts: [u8; 16],
+    symbols: &'static [u8],
+}
+
+const LUMINANCE_DC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+    symbols: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+};
+
+const CHROMINANCE_DC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+    symbols: &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+};
+
+const LUMINANCE_AC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+    symbols: &[
+        0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61,
+        0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1, 0x15, 0x52,
+        0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x25,
+        0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44, 0x45,
+        0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63, 0x64,
+        0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x83,
+        0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99,
+        0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6,
+        0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3,
+        0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8,
+        0xe9, 0xea, 0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+};
+
+const CHROMINANCE_AC: HuffmanSpec = HuffmanSpec {
+    counts: [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+    symbols: &[
+        0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07, 0x61,
+        0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09, 0x23, 0x33,
+        0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25, 0xf1, 0x17, 0x18,
+        0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38, 0x39, 0x3a, 0x43, 0x44,
+        0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x63,
+        0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7a,
+        0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97,
+        0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4,
+        0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca,
+        0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7,
+        0xe8, 0xe9, 0xea, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+    ],
+};
+
+/// Code and its length in bits of every symbol
+struct HuffmanTable([(u16, u8); 256]);
+
+impl HuffmanTable {
+    fn new(spec: &HuffmanSpec) -> Self {
+        let mut table = [(0, 0); 256];
+        let mut symbols = spec.symbols.iter();
+        let mut code = 0u16;
+
+        for (length, &count) in (1u8..).zip(&spec.counts) {
+            for &symbol in symbols.by_ref().take(usize::from(count)) {
+                table[usize::from(symbol)] = (code, length);
+                code += 1;
+            }
+            code <<= 1;
+        }
+
+        Self(table)
+    }
+}
+
+/// Quantization table of `QUALITY`, scaled the way of the IJG library.
+fn quantization(table: &[u32; 64]) -> [u8; 64] {
+    let scale = if QUALITY < 50 {
+        5000 / QUALITY
+    } else {
+        200 - 2 * QUALITY
+    };
+
+    table.map(|value| ((value * scale + 50) / 100).clamp(1, 255) as u8)
+}
+
+/// Entropy coded data, a 0xff byte is followed by a zero so it isn't taken for a marker.
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bits: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u16, length: u8) {
+        self.bits = (self.bits << length) | (u32::from(bits) & ((1 << length) - 1));
+        self.count += length;
+
+        while self.count >= 8 {
+            let byte = (self.bits >> (self.count - 8)) as u8;
+            self.data.push(byte);
+            if byte == 0xff {
+                self.data.push(0);
+            }
+            self.count -= 8;
+        }
+    }
+
+    /// Pads the last byte with ones.
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 {
+            self.write(0x7f, 8 - self.count);
+        }
+        self.data
+    }
+}
+
+/// Number of bits of `value` without the sign, and those bits of it, one less for a negative
+/// value.
+fn magnitude(value: i32) -> (u8, u16) {
+    let size = (32 - value.unsigned_abs().leading_zeros()) as u8;
+    let bits = if value < 0 { value - 1 } else { value };
+
+    (size, bits as u16)
+}
+
+/// Discrete cosine transform of an 8×8 block.
+fn forward_dct(block: &[f32; 64]) -> [f32; 64] {
+    let cosines: [[f32; 8]; 8] = std::array::from_fn(|u| {
+        std::array::from_fn(|x| ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0).cos())
+    });
+    let c = |u: usize| {
+        if u == 0 {
+            std::f32::consts::FRAC_1_SQRT_2
+        } else {
+            1.0
+        }
+    };
+
+    // rows first, then the columns of the result
+    let mut rows = [0.0; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            let sum: f32 = (0..8).map(|x| block[y * 8 + x] * cosines[u][x]).sum();
+            rows[y * 8 + u] = sum * c(u) / 2.0;
+        }
+    }
+
+    let mut coefficients = [0.0; 64];
+    for u in 0..8 {
+        for v in 0..8 {
+            let sum: f32 = (0..8).map(|y| rows[y * 8 + u] * cosines[v][y]).sum();
+            coefficients[v * 8 + u] = sum * c(v) / 2.0;
+        }
+    }
+
+    coefficients
+}
+
+struct Component {
+    quantization: [u8; 64],
+    dc: HuffmanTable,
+    ac: HuffmanTable,
+    /// DC coefficient of the previous block, the DC of a block is coded as the difference
+    previous_dc: i32,
+}
+
+impl Component {
+    fn encode_block(&mut self, block: &[f32; 64], writer: &mut BitWriter) {
+        let coefficients = forward_dct(block);
+        let quantized: [i32; 64] = std::array::from_fn(|index| {
+            let natural = ZIGZAG[index];
+            (coefficients[natural] / f32::from(self.quantization[natural])).round() as i32
+        });
+
+        let (size, bits) = magnitude(quantized[0] - self.previous_dc);
+        self.previous_dc = quantized[0];
+        let (code, length) = self.dc.0[usize::from(size)];
+        writer.write(code, length);
+        writer.write(bits, size);
+
+        let mut zeros = 0;
+        for &coefficient in &quantized[1..] {
+            if coefficient == 0 {
+                zeros += 1;
+                continue;
+            }
+
+            // runs of 16 zeros
+            while zeros > 15 {
+                let (code, length) = self.ac.0[0xf0];
+                writer.write(code, length);
+                zeros -= 16;
+            }
+
+            let (size, bits) = magnitude(coefficient);
+            let (code, length) = self.ac.0[(zeros << 4) | usize::from(size)];
+            writer.write(code, length);
+            writer.write(bits, size);
+            zeros = 0;
+        }
+
+        // end of block
+        if zeros > 0 {
+            let (code, length) = self.ac.0[0x00];
+            writer.write(code, length);
+        }
+    }
+}
+
+fn write_segment(jpeg: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    jpeg.extend_from_slice(&[0xff, marker]);
+    jpeg.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    jpeg.extend_from_slice(payload);
+}
+
+/// Baseline JPEG of an image of `width` × `height` pixels, `rgb` holding 3 bytes a pixel row by
+/// row.
+pub fn encode_jpeg(width: usize, height: usize, rgb: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if width == 0 || height == 0 || rgb.len() != width * height * 3 {
+        return Err(anyhow!("Invalid image of {width}×{height} pixels"));
+    }
+
+    let luminance = quantization(&LUMINANCE_QUANTIZATION);
+    let chrominance = quantization(&CHROMINANCE_QUANTIZATION);
+
+    let mut jpeg = vec![0xff, 0xd8];
+
+    for (id, table) in [(0, &luminance), (1, &chrominance)] {
+        let mut payload = vec![id];
+        payload.extend(ZIGZAG.iter().map(|&natural| table[natural]));
+        write_segment(&mut jpeg, 0xdb, &payload);
+    }
+
+    let mut frame = vec![8];
+    frame.extend_from_slice(&u16::try_from(height)?.to_be_bytes());
+    frame.extend_from_slice(&u16::try_from(width)?.to_be_bytes());
+    // components Y, Cb and Cr, none subsampled
+    frame.extend_from_slice(&[3, 1, 0x11, 0, 2, 0x11, 1, 3, 0x11, 1]);
+    write_segment(&mut jpeg, 0xc0, &frame);
+
+    for (class_and_id, spec) in [
+        (0x00, &LUMINANCE_DC),
+        (0x10, &LUMINANCE_AC),
+        (0x01, &CHROMINANCE_DC),
+        (0x11, &CHROMINANCE_AC),
+    ] {
+        let mut payload = vec![class_and_id];
+        payload.extend_from_slice(&spec.counts);
+        payload.extend_from_slice(spec.symbols);
+        write_segment(&mut jpeg, 0xc4, &payload);
+    }
+
+    write_segment(&mut jpeg, 0xda, &[3, 1, 0x00, 2, 0x11, 3, 0x11, 0, 63, 0]);
+
+    let mut components = [
+        (luminance, &LUMINANCE_DC, &LUMINANCE_AC),
+        (chrominance, &CHROMINANCE_DC, &CHROMINANCE_AC),
+        (chrominance, &CHROMINANCE_DC, &CHROMINANCE_AC),
+    ]
+    .map(|(quantization, dc, ac)| Component {
+        quantization,
+        dc: HuffmanTable::new(dc),
+        ac: HuffmanTable::new(ac),
+        previous_dc: 0,
+    });
+
+    let mut writer = BitWriter::default();
+    for block_y in (0..height).step_by(8) {
+        for block_x in (0..width).step_by(8) {
+            let pixels: [[f32; 3]; 64] = std::array::from_fn(|index| {
+                // the edge pixels are repeated to fill the blocks past the image
+                let x = (block_x + index % 8).min(width - 1);
+                let y = (block_y + index / 8).min(height - 1);
+                let pixel = &rgb[(y * width + x) * 3..][..3];
+                let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+
+                // level shifted by 128, as the transform expects
+                [
+                    0.299 * r + 0.587 * g + 0.114 * b - 128.0,
+                    -0.168_736 * r - 0.331_264 * g + 0.5 * b,
+                    0.5 * r - 0.418_688 * g - 0.081_312 * b,
+                ]
+            });
+            let blocks: [[f32; 64]; 3] =
+                std::array::from_fn(|component| pixels.map(|pixel| pixel[component]));
+
+            for (component, block) in components.iter_mut().zip(&blocks) {
+                component.encode_block(block, &mut writer);
+            }
+        }
+    }
+
+    jpeg.extend_from_slice(&writer.finish());
+    jpeg.extend_from_slice(&[0xff, 0xd9]);
+
+    Ok(jpeg)
+}
+
+// offsets of the fields completed once the video is finished
+const RIFF_SIZE: u64 = 4;
+const TOTAL_FRAMES: u64 = 48;
+const MAIN_BUFFER_SIZE: u64 = 60;
+const STREAM_LENGTH: u64 = 140;
+const STREAM_BUFFER_SIZE: u64 = 144;
+const MOVI_SIZE: u64 = 216;
+/// Size of the headers up to the `movi` list type, the frames follow
+const HEADERS_SIZE: usize = 224;
+
+/// Writes an AVI of a single Motion JPEG stream, the frames as they come.
+pub struct VideoWriter<W: Write + Seek> {
+    writer: W,
+    width: usize,
+    height: usize,
+    /// Offset from the `movi` list type and size of every frame, for the index
+    index: Vec<(u32, u32)>,
+    /// Bytes of the `movi` list after its type
+    movi_size: u32,
+    largest_frame: u32,
+}
+
+impl<W: Write + Seek> VideoWriter<W> {
+    pub fn new(
+        mut writer: W,
+        width: usize,
+        height: usize,
+        frame_rate: u32,
+    ) -> anyhow::Result<Self> {
+        let (width_u32, height_u32) = (u32::try_from(width)?, u32::try_from(height)?);
+        let frame_rate = frame_rate.max(1);
+
+        let mut headers = Vec::with_capacity(HEADERS_SIZE);
+        let mut put = |bytes: &[u8]| headers.extend_from_slice(bytes);
+
+        put(b"RIFF");
+        put(&0u32.to_le_bytes());
+        put(b"AVI LIST");
+        put(&192u32.to_le_bytes());
+        put(b"hdrlavih");
+        put(&56u32.to_le_bytes());
+        // microseconds per frame, maximum data rate, padding and flags (has an index)
+        put(&(1_000_000 / frame_rate).to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&0x10u32.to_le_bytes());
+        // total and initial frames, streams and suggested buffer size
+        put(&0u32.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&1u32.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&width_u32.to_le_bytes());
+        put(&height_u32.to_le_bytes());
+        put(&[0; 16]);
+
+        put(b"LIST");
+        put(&116u32.to_le_bytes());
+        put(b"strlstrh");
+        put(&56u32.to_le_bytes());
+        put(b"vidsMJPG");
+        // flags, priority and language, initial frames
+        put(&[0; 12]);
+        // the rate over the scale is the frame rate
+        put(&1u32.to_le_bytes());
+        put(&frame_rate.to_le_bytes());
+        // start, length, suggested buffer size, quality (default) and sample size (varying)
+        put(&0u32.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&u32::MAX.to_le_bytes());
+        put(&0u32.to_le_bytes());
+        put(&0u16.to_le_bytes());
+        put(&0u16.to_le_bytes());
+        put(&u16::try_from(width)?.to_le_bytes());
+        put(&u16::try_from(height)?.to_le_bytes());
+
+        put(b"strf");
+        put(&40u32.to_le_bytes());
+        // the bitmap info header of the decoded frames
+        put(&40u32.to_le_bytes());
+        put(&width_u32.to_le_bytes());
+        put(&height_u32.to_le_bytes());
+        put(&1u16.to_le_bytes());
+        put(&24u16.to_le_bytes());
+        put(b"MJPG");
+        put(&(width_u32 * height_u32 * 3).to_le_bytes());
+        put(&[0; 16]);
+
+        put(b"LIST");
+        put(&0u32.to_le_bytes());
+        put(b"movi");
+        debug_assert_eq!(headers.len(), HEADERS_SIZE);
+
+        writer.write_all(&headers)?;
+
+        Ok(Self {
+            writer,
+            width,
+            height,
+            index: Vec::new(),
+            movi_size: 4,
+            largest_frame: 0,
+        })
+    }
+
+    pub fn write_frame(&mut self, image: &thermal::RgbImage) -> anyhow::Result<()> {
+        if (image.width(), image.height()) != (self.width, self.height) {
+            return Err(anyhow!(
+                "Frame of {}×{} pixels in a video of {}×{}",
+                image.width(),
+                image.height(),
+                self.width,
+                self.height
+            ));
+        }
+
+        let jpeg = encode_jpeg(self.width, self.height, image.data())?;
+        let size = u32::try_from(jpeg.len())?;
+
+        self.writer.write_all(b"00dc")?;
+        self.writer.write_all(&size.to_le_bytes())?;
+        self.writer.write_all(&jpeg)?;
+        // chunks are aligned to 2 bytes
+        if size % 2 == 1 {
+            self.writer.write_all(&[0])?;
+        }
+
+        self.index.push((self.movi_size, size));
+        self.movi_size += 8 + size + size % 2;
+        self.largest_frame = self.largest_frame.max(size);
+
+        Ok(())
+    }
+
+    pub fn frames(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Writes the index and completes the headers, a video which isn't finished can't be played.
+    pub fn finish(mut self) -> anyhow::Result<W> {
+        let frames = u32::try_from(self.index.len())?;
+
+        self.writer.write_all(b"idx1")?;
+        self.writer.write_all(&(frames * 16).to_le_bytes())?;
+        for &(offset, size) in &self.index {
+            self.writer.write_all(b"00dc")?;
+            // a key frame, every JPEG is one
+            self.writer.write_all(&0x10u32.to_le_bytes())?;
+            self.writer.write_all(&offset.to_le_bytes())?;
+            self.writer.write_all(&size.to_le_bytes())?;
+        }
+
+        let end = self.writer.stream_position()?;
+        for (offset, value) in [
+            (RIFF_SIZE, u32::try_from(end - 8)?),
+            (TOTAL_FRAMES, frames),
+            (MAIN_BUFFER_SIZE, self.largest_frame),
+            (STREAM_LENGTH, frames),
+            (STREAM_BUFFER_SIZE, self.largest_frame),
+            (MOVI_SIZE, self.movi_size),
+        ] {
+            self.writer.seek(SeekFrom::Start(offset))?;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Video being written to a file at a steady frame rate.
+pub struct VideoRecording {
+    path: PathBuf,
+    writer: VideoWriter<io::BufWriter<std::fs::File>>,
+    started_at: Instant,
+    frame_interval: Duration,
+}
+
+impl VideoRecording {
+    /// Creates the video at `path` with `image` as its first frame, the later ones have to be
+    /// of the same size.
+    pub fn start(
+        path: PathBuf,
+        settings: &VideoSettings,
+        image: &thermal::RgbImage,
+    ) -> anyhow::Result<Self> {
+        let frame_rate = settings.frame_rate.clamp(MIN_FRAME_RATE, MAX_FRAME_RATE);
+        let file = io::BufWriter::new(std::fs::File::create(&path)?);
+        let mut writer = VideoWriter::new(file, image.width(), image.height(), frame_rate)?;
+        writer.write_frame(image)?;
+
+        Ok(Self {
+            path,
+            writer,
+            started_at: Instant::now(),
+            frame_interval: Duration::from_secs(1) / frame_rate,
+        })
+    }
+
+    /// Number of the frames due by `now` and not written yet. The shown image fills them, so the
+    /// video plays at the pace it has been recorded whatever the frame rate of the camera.
+    pub fn due_frames(&self, now: Instant) -> usize {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        let due = (elapsed.as_secs_f64() / self.frame_interval.as_secs_f64()) as usize + 1;
+
+        due.saturating_sub(self.writer.frames())
+    }
+
+    pub fn write_frames(&mut self, image: &thermal::RgbImage, count: usize) -> anyhow::Result<()> {
+        for _ in 0..count {
+            self.writer.write_frame(image)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Completes the file, returns its path and the number of frames.
+    pub fn finish(self) -> anyhow::Result<(PathBuf, usize)> {
+        let frames = self.writer.frames();
+        self.writer.finish()?;
+
+        Ok((self.path, frames))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(jpeg: &[u8]) -> Vec<u8> {
+        let mut markers = Vec::new();
+        let mut position = 2;
+
+        while let [0xff, marker, high, low, ..] = jpeg[position..] {
+            markers.push(marker);
+            if marker == 0xda {
+                break;
+            }
+            position += 2 + usize::from(u16::from_be_bytes([high, low]));
+        }
+
+        markers
+    }
+
+    #[test]
+    fn transforms_a_flat_block_to_its_dc() {
+        let coefficients = forward_dct(&[10.0; 64]);
+
+        assert!((coefficients[0] - 80.0).abs() < 1e-3);
+        assert!(coefficients[1..].iter().all(|value| value.abs() < 1e-3));
+    }
+
+    #[test]
+    fn codes_magnitudes() {
+        assert_eq!(magnitude(0), (0, 0));
+        assert_eq!(magnitude(5), (3, 0b101));
+        // one's complement of the magnitude
+        assert_eq!(magnitude(-5).0, 3);
+        assert_eq!(magnitude(-5).1 & 0b111, 0b010);
+    }
+
+    #[test]
+    fn builds_canonical_codes() {
+        let table = HuffmanTable::new(&LUMINANCE_DC);
+
+        assert_eq!(table.0[0], (0b00, 2));
+        assert_eq!(table.0[1], (0b010, 3));
+        assert_eq!(table.0[11], (0b1_1111_1110, 9));
+    }
+
+    #[test]
+    fn stuffs_ff_bytes() {
+        let mut writer = BitWriter::default();
+        writer.write(0xff, 8);
+        writer.write(0b1, 1);
+
+        assert_eq!(writer.finish(), [0xff, 0x00, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn encodes_baseline_jpegs() {
+        // not a multiple of the block size
+        let (width, height) = (20, 12);
+        let rgb: Vec<u8> = (0..width * height * 3).map(|index| index as u8).collect();
+        let jpeg = encode_jpeg(width, height, &rgb).unwrap();
+
+        assert_eq!(&jpeg[..2], [0xff, 0xd8]);
+        assert_eq!(&jpeg[jpeg.len() - 2..], [0xff, 0xd9]);
+        assert_eq!(
+            segments(&jpeg),
+            [0xdb, 0xdb, 0xc0, 0xc4, 0xc4, 0xc4, 0xc4, 0xda]
+        );
+        assert!(encode_jpeg(width, height, &rgb[1..]).is_err());
+    }
+
+    #[test]
+    fn completes_the_headers_of_finished_videos() {
+        let image = thermal::RgbImage::new([16, 8]);
+        let mut writer = VideoWriter::new(io::Cursor::new(Vec::new()), 16, 8, 10).unwrap();
+        for _ in 0..3 {
+            writer.write_frame(&image).unwrap();
+        }
+        assert!(writer.write_frame(&thermal::RgbImage::new([8, 8])).is_err());
+        let avi = writer.finish().unwrap().into_inner();
+
+        let u32_at = |offset: u64| {
+            let offset = offset as usize;
+            u32::from_le_bytes(avi[offset..offset + 4].try_into().unwrap())
+        };
+        assert_eq!(&avi[..4], b"RIFF");
+        assert_eq!(u32_at(RIFF_SIZE) as usize, avi.len() - 8);
+        assert_eq!(u32_at(TOTAL_FRAMES), 3);
+        assert_eq!(u32_at(STREAM_LENGTH), 3);
+        assert_eq!(&avi[HEADERS_SIZE - 4..HEADERS_SIZE], b"movi");
+        // the index follows the frames
+        let index = HEADERS_SIZE - 4 + u32_at(MOVI_SIZE) as usize;
+        assert_eq!(&avi[index..index + 4], b"idx1");
+        assert_eq!(u32_at(index as u64 + 4), 3 * 16);
+    }
+}
@@ -0,0 +1,405 @@
+use std::{cell::RefCell, collections::VecDeque, io, rc::Rc};
+
+use crate::thermal::{PortError, PortErrorKind, PortInfo, PortOpener};
+use crate::web_usb::{self, CdcAcm};
+
+use anyhow::anyhow;
+use eframe::egui;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+
+const BAUD_RATE: u32 = 921_600;
+const VID: u16 = 0x303a;
+const PID: u16 = 0x4001;
+
+/// Device selected by the user. WebUSB is used only where Web Serial isn't available.
+enum Device {
+    Serial(web_sys::SerialPort),
+    Usb(web_sys::UsbDevice),
+}
+
+thread_local! {
+    // Device picked by the user, waiting to be opened by the producer
+    static REQUESTED_DEVICE: RefCell<Option<Device>> = const { RefCell::new(None) };
+}
+
+/// Converts an exception, categorizing the ones of opening a device by their name.
+pub fn js_error(error: &JsValue) -> anyhow::Error {
+    let Some(error) = error.dyn_ref::<js_sys::Error>() else {
+        return anyhow!("{error:?}");
+    };
+
+    let kind = match String::from(error.name()).as_str() {
+        "NotFoundError" => PortErrorKind::NotFound,
+        "SecurityError" | "NotAllowedError" => PortErrorKind::PermissionDenied,
+        // Chrome fails to open a port another program has open with a NetworkError
+        "InvalidStateError" | "NetworkError" => PortErrorKind::Busy,
+        _ => PortErrorKind::Other,
+    };
+
+    PortError::new(kind, String::from(error.message())).into()
+}
+
+fn serial() -> Option<web_sys::Serial> {
+    let navigator = web_sys::window()?.navigator();
+
+    js_sys::Reflect::has(&navigator, &"serial".into())
+        .unwrap_or(false)
+        .then(|| navigator.serial())
+}
+
+/// Whether the browser can reach the camera at all.
+pub fn is_supported() -> bool {
+    serial().is_some() || web_usb::is_supported()
+}
+
+async fn request_device() -> anyhow::Result<Device> {
+    let Some(serial) = serial() else {
+        return Ok(Device::Usb(web_usb::request_device(VID, PID).await?));
+    };
+
+    let filter = web_sys::SerialPortFilter::new();
+    filter.set_usb_vendor_id(VID);
+    filter.set_usb_product_id(PID);
+
+    let options = web_sys::SerialPortRequestOptions::new();
+    options.set_filters(&js_sys::Array::of1(&filter));
+
+    let port = JsFuture::from(serial.request_port_with_options(&options))
+        .await
+        .map_err(|e| js_error(&e))?;
+
+    Ok(Device::Serial(port.unchecked_into()))
+}
+
+/// Shows the browser's port chooser. Browsers only allow this in response to a user action,
+/// so it has to be called from a click handler.
+pub fn request_port(egui_ctx: egui::Context) {
+    spawn_local(async move {
+        match request_device().await {
+            Ok(device) => {
+                REQUESTED_DEVICE.with_borrow_mut(|requested| *requested = Some(device));
+                egui_ctx.request_repaint();
+            }
+            Err(e) => log::warn!("No serial port selected: {e}"),
+        }
+    });
+}
+
+// devices selected in the previous sessions can be opened without asking again
+async fn granted_device() -> anyhow::Result<Device> {
+    let device = match serial() {
+        Some(serial) => {
+            let ports: js_sys::Array = JsFuture::from(serial.get_ports())
+                .await
+                .map_err(|e| js_error(&e))?
+                .unchecked_into();
+
+            ports
+                .iter()
+                .next()
+                .map(|port| Device::Serial(port.unchecked_into()))
+        }
+        None => web_usb::granted_device().await?.map(Device::Usb),
+    };
+
+    device.ok_or_else(|| PortError::new(PortErrorKind::NotFound, "No serial port selected").into())
+}
+
+enum Transport {
+    Serial(web_sys::SerialPort),
+    Usb(CdcAcm),
+}
+
+impl Transport {
+    fn name(&self) -> &'static str {
+        match self {
+            Transport::Serial(_) => "Web Serial",
+            Transport::Usb(_) => "WebUSB",
+        }
+    }
+}
+
+async fn open_device() -> anyhow::Result<Transport> {
+    let device = match REQUESTED_DEVICE.with_borrow_mut(Option::take) {
+        Some(device) => device,
+        None => granted_device().await?,
+    };
+
+    match device {
+        Device::Serial(port) => {
+            JsFuture::from(port.open(&web_sys::SerialOptions::new(BAUD_RATE)))
+                .await
+                .map_err(|e| js_error(&e))?;
+
+            Ok(Transport::Serial(port))
+        }
+        Device::Usb(device) => Ok(Transport::Usb(CdcAcm::open(device, BAUD_RATE).await?)),
+    }
+}
+
+#[derive(Default)]
+enum OpenState {
+    #[default]
+    Idle,
+    Opening,
+    Opened(Transport),
+    Failed(anyhow::Error),
+}
+
+pub struct SerialPortOpener {
+    egui_ctx: egui::Context,
+    state: Rc<RefCell<OpenState>>,
+    transport_name: &'static str,
+}
+
+impl SerialPortOpener {
+    pub fn new(egui_ctx: egui::Context) -> Self {
+        Self {
+            egui_ctx,
+            state: Rc::default(),
+            transport_name: "Web Serial",
+        }
+    }
+
+    fn start_opening(&self) {
+        let state = Rc::clone(&self.state);
+        let egui_ctx = self.egui_ctx.clone();
+
+        *state.borrow_mut() = OpenState::Opening;
+
+        spawn_local(async move {
+            *state.borrow_mut() = match open_device().await {
+                Ok(transport) => OpenState::Opened(transport),
+                Err(e) => OpenState::Failed(e),
+            };
+
+            egui_ctx.request_repaint();
+        });
+    }
+}
+
+impl PortOpener<'static> for SerialPortOpener {
+    type RW = SerialPortReadWrite;
+
+    fn open(&mut self) -> anyhow::Result<Self::RW> {
+        let state = std::mem::take(&mut *self.state.borrow_mut());
+
+        match state {
+            OpenState::Idle => {
+                self.start_opening();
+                Err(anyhow!("Opening serial port"))
+            }
+            OpenState::Opening => {
+                *self.state.borrow_mut() = OpenState::Opening;
+                Err(anyhow!("Opening serial port"))
+            }
+            OpenState::Opened(transport) => {
+                self.transport_name = transport.name();
+                SerialPortReadWrite::new(transport, self.egui_ctx.clone())
+            }
+            OpenState::Failed(e) => Err(e),
+        }
+    }
+
+    fn port_info(&self) -> PortInfo {
+        PortInfo {
+            name: self.transport_name.into(),
+            baud_rate: Some(BAUD_RATE),
+            serial_number: None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ReadBuffer {
+    data: VecDeque<u8>,
+    error: Option<String>,
+}
+
+#[derive(Clone)]
+enum Stream {
+    Serial {
+        port: web_sys::SerialPort,
+        reader: web_sys::ReadableStreamDefaultReader,
+        writer: web_sys::WritableStreamDefaultWriter,
+    },
+    Usb(CdcAcm),
+}
+
+pub struct SerialPortReadWrite {
+    stream: Stream,
+    buffer: Rc<RefCell<ReadBuffer>>,
+}
+
+impl SerialPortReadWrite {
+    fn new(transport: Transport, egui_ctx: egui::Context) -> anyhow::Result<Self> {
+        let buffer = Rc::new(RefCell::new(ReadBuffer::default()));
+
+        let stream = match transport {
+            Transport::Serial(port) => {
+                let reader: web_sys::ReadableStreamDefaultReader =
+                    port.readable().get_reader().unchecked_into();
+                let writer = port.writable().get_writer().map_err(|e| js_error(&e))?;
+
+                spawn_local(Self::read_loop(
+                    Self::read_serial(reader.clone(), Rc::clone(&buffer), egui_ctx.clone()),
+                    Rc::clone(&buffer),
+                    egui_ctx,
+                ));
+
+                Stream::Serial {
+                    port,
+                    reader,
+                    writer,
+                }
+            }
+            Transport::Usb(cdc) => {
+                spawn_local(Self::read_loop(
+                    Self::read_usb(cdc.clone(), Rc::clone(&buffer), egui_ctx.clone()),
+                    Rc::clone(&buffer),
+                    egui_ctx,
+                ));
+
+                Stream::Usb(cdc)
+            }
+        };
+
+        Ok(Self { stream, buffer })
+    }
+
+    fn push(buffer: &RefCell<ReadBuffer>, data: Vec<u8>, egui_ctx: &egui::Context) {
+        buffer.borrow_mut().data.extend(data);
+        egui_ctx.request_repaint();
+    }
+
+    /// Runs until the stream ends, then stores the reason so the next read reports it.
+    async fn read_loop(
+        reader: impl std::future::Future<Output = String>,
+        buffer: Rc<RefCell<ReadBuffer>>,
+        egui_ctx: egui::Context,
+    ) {
+        let error = reader.await;
+
+        buffer.borrow_mut().error = Some(error);
+        egui_ctx.request_repaint();
+    }
+
+    async fn read_serial(
+        reader: web_sys::ReadableStreamDefaultReader,
+        buffer: Rc<RefCell<ReadBuffer>>,
+        egui_ctx: egui::Context,
+    ) -> String {
+        loop {
+            let chunk = match JsFuture::from(reader.read()).await {
+                Ok(chunk) => chunk,
+                Err(e) => return js_error(&e).to_string(),
+            };
+
+            let done = js_sys::Reflect::get(&chunk, &"done".into())
+                .ok()
+                .and_then(|done| done.as_bool())
+                .unwrap_or(true);
+
+            if done {
+                return "Serial port has been closed".to_owned();
+            }
+
+            if let Ok(value) = js_sys::Reflect::get(&chunk, &"value".into()) {
+                let value: js_sys::Uint8Array = value.unchecked_into();
+                Self::push(&buffer, value.to_vec(), &egui_ctx);
+            }
+        }
+    }
+
+    async fn read_usb(
+        cdc: CdcAcm,
+        buffer: Rc<RefCell<ReadBuffer>>,
+        egui_ctx: egui::Context,
+    ) -> String {
+        loop {
+            match cdc.read().await {
+                Ok(data) => Self::push(&buffer, data, &egui_ctx),
+                Err(e) => return e.to_string(),
+            }
+        }
+    }
+}
+
+impl io::Read for SerialPortReadWrite {
+    // reads are all or nothing, so read_exact never consumes an incomplete frame
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if REQUESTED_DEVICE.with_borrow(Option::is_some) {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "Another serial port has been selected",
+            ));
+        }
+
+        let mut buffer = self.buffer.borrow_mut();
+
+        if buffer.data.len() < buf.len() {
+            return Err(match buffer.error.take() {
+                Some(error) => io::Error::new(io::ErrorKind::BrokenPipe, error),
+                None => io::ErrorKind::WouldBlock.into(),
+            });
+        }
+
+        for (dst, src) in buf.iter_mut().zip(buffer.data.drain(..buf.len())) {
+            *dst = src;
+        }
+
+        Ok(buf.len())
+    }
+}
+
+impl io::Write for SerialPortReadWrite {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let promise = match self.stream {
+            Stream::Serial { ref writer, .. } => {
+                writer.write_with_chunk(&js_sys::Uint8Array::from(buf))
+            }
+            Stream::Usb(ref cdc) => cdc.write(buf),
+        };
+
+        spawn_local(async move {
+            if let Err(e) = JsFuture::from(promise).await {
+                log::error!("Failed to write to serial port: {}", js_error(&e));
+            }
+        });
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for SerialPortReadWrite {
+    fn drop(&mut self) {
+        let stream = self.stream.clone();
+
+        spawn_local(async move {
+            let result = match stream {
+                Stream::Serial {
+                    port,
+                    reader,
+                    writer,
+                } => {
+                    // the port can be closed only after its streams have been released
+                    let _ = JsFuture::from(reader.cancel()).await;
+                    reader.release_lock();
+                    writer.release_lock();
+
+                    JsFuture::from(port.close()).await
+                }
+                Stream::Usb(cdc) => JsFuture::from(cdc.close()).await,
+            };
+
+            if let Err(e) = result {
+                log::warn!("Failed to close serial port: {}", js_error(&e));
+            }
+        });
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::anyhow;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use crate::web::js_error;
+
+// USB class codes of the CDC-ACM interfaces
+const CDC_CONTROL_CLASS: u8 = 0x02;
+const CDC_DATA_CLASS: u8 = 0x0a;
+
+const SET_LINE_CODING: u8 = 0x20;
+const SET_CONTROL_LINE_STATE: u8 = 0x22;
+const DTR: u16 = 0x01;
+
+const TRANSFER_SIZE: u32 = 4096;
+
+fn usb() -> anyhow::Result<web_sys::Usb> {
+    let navigator = web_sys::window()
+        .ok_or_else(|| anyhow!("No window"))?
+        .navigator();
+
+    if js_sys::Reflect::has(&navigator, &"usb".into()).unwrap_or(false) {
+        Ok(navigator.usb())
+    } else {
+        Err(anyhow!(
+            "Neither Web Serial nor WebUSB is supported by this browser"
+        ))
+    }
+}
+
+pub fn is_supported() -> bool {
+    usb().is_ok()
+}
+
+/// Shows the browser's device chooser. Same as with Web Serial, it requires a user action.
+pub async fn request_device(vid: u16, pid: u16) -> anyhow::Result<web_sys::UsbDevice> {
+    let filter = web_sys::UsbDeviceFilter::new();
+    filter.set_vendor_id(vid);
+    filter.set_product_id(pid);
+
+    let options = web_sys::UsbDeviceRequestOptions::new(&js_sys::Array::of1(&filter));
+
+    Ok(JsFuture::from(usb()?.request_device(&options))
+        .await
+        .map_err(|e| js_error(&e))?
+        .unchecked_into())
+}
+
+/// Returns a device the user has granted access to in a previous session.
+pub async fn granted_device() -> anyhow::Result<Option<web_sys::UsbDevice>> {
+    let devices: js_sys::Array = JsFuture::from(usb()?.get_devices())
+        .await
+        .map_err(|e| js_error(&e))?
+        .unchecked_into();
+
+    Ok(devices.iter().next().map(JsCast::unchecked_into))
+}
+
+/// A CDC-ACM device driven directly over USB, for browsers without Web Serial.
+#[derive(Clone)]
+pub struct CdcAcm {
+    device: web_sys::UsbDevice,
+    endpoint_in: u8,
+    endpoint_out: u8,
+}
+
+impl CdcAcm {
+    pub async fn open(device: web_sys::UsbDevice, baud_rate: u32) -> anyhow::Result<Self> {
+        JsFuture::from(device.open())
+            .await
+            .map_err(|e| js_error(&e))?;
+
+        if device.configuration().is_none() {
+            JsFuture::from(device.select_configuration(1))
+                .await
+                .map_err(|e| js_error(&e))?;
+        }
+
+        let configuration = device
+            .configuration()
+            .ok_or_else(|| anyhow!("USB device has no configuration"))?;
+
+        let mut control_interface = None;
+        let mut data_interface = None;
+
+        for interface in configuration.interfaces().iter() {
+            let interface: web_sys::UsbInterface = interface.unchecked_into();
+            let alternate = interface.alternate();
+
+            match alternate.interface_class() {
+                CDC_CONTROL_CLASS => control_interface = Some(interface.interface_number()),
+                CDC_DATA_CLASS => data_interface = Some((interface.interface_number(), alternate)),
+                _ => {}
+            }
+        }
+
+        let (data_interface, alternate) =
+            data_interface.ok_or_else(|| anyhow!("USB device has no CDC data interface"))?;
+
+        let mut endpoint_in = None;
+        let mut endpoint_out = None;
+
+        for endpoint in alternate.endpoints().iter() {
+            let endpoint: web_sys::UsbEndpoint = endpoint.unchecked_into();
+
+            if endpoint.type_() != web_sys::UsbEndpointType::Bulk {
+                continue;
+            }
+
+            match endpoint.direction() {
+                web_sys::UsbDirection::In => endpoint_in = Some(endpoint.endpoint_number()),
+                web_sys::UsbDirection::Out => endpoint_out = Some(endpoint.endpoint_number()),
+                _ => {}
+            }
+        }
+
+        let (endpoint_in, endpoint_out) = endpoint_in
+            .zip(endpoint_out)
+            .ok_or_else(|| anyhow!("CDC data interface has no bulk endpoints"))?;
+
+        JsFuture::from(device.claim_interface(data_interface))
+            .await
+            .map_err(|e| js_error(&e))?;
+
+        if let Some(control_interface) = control_interface {
+            // the interface is claimed only so the requests below can be sent to it
+            JsFuture::from(device.claim_interface(control_interface))
+                .await
+                .map_err(|e| js_error(&e))?;
+
+            // 8 data bits, no parity, 1 stop bit
+            let mut line_coding = baud_rate.to_le_bytes().to_vec();
+            line_coding.extend_from_slice(&[0, 0, 8]);
+
+            let setup = Self::class_request(control_interface, SET_LINE_CODING, 0);
+            JsFuture::from(device.control_transfer_out_with_buffer_source(
+                &setup,
+                &js_sys::Uint8Array::from(line_coding.as_slice()),
+            ))
+            .await
+            .map_err(|e| js_error(&e))?;
+
+            // the kernel driver asserts DTR on open as well, some firmwares don't send
+            // anything without it
+            let setup = Self::class_request(control_interface, SET_CONTROL_LINE_STATE, DTR);
+            JsFuture::from(device.control_transfer_out(&setup))
+                .await
+                .map_err(|e| js_error(&e))?;
+        }
+
+        Ok(Self {
+            device,
+            endpoint_in,
+            endpoint_out,
+        })
+    }
+
+    fn class_request(
+        interface: u8,
+        request: u8,
+        value: u16,
+    ) -> web_sys::UsbControlTransferParameters {
+        web_sys::UsbControlTransferParameters::new(
+            u16::from(interface),
+            web_sys::UsbRecipient::Interface,
+            request,
+            web_sys::UsbRequestType::Class,
+            value,
+        )
+    }
+
+    pub async fn read(&self) -> anyhow::Result<Vec<u8>> {
+        let result: web_sys::UsbInTransferResult =
+            JsFuture::from(self.device.transfer_in(self.endpoint_in, TRANSFER_SIZE))
+                .await
+                .map_err(|e| js_error(&e))?
+                .unchecked_into();
+
+        if result.status() != web_sys::UsbTransferStatus::Ok {
+            return Err(anyhow!("USB transfer failed: {:?}", result.status()));
+        }
+
+        Ok(result.data().map_or_else(Vec::new, |data| {
+            js_sys::Uint8Array::new_with_byte_offset_and_length(
+                &data.buffer(),
+                u32::try_from(data.byte_offset()).unwrap_or(0),
+                u32::try_from(data.byte_length()).unwrap_or(0),
+            )
+            .to_vec()
+        }))
+    }
+
+    pub fn write(&self, buf: &[u8]) -> js_sys::Promise {
+        self.device
+            .transfer_out_with_buffer_source(self.endpoint_out, &js_sys::Uint8Array::from(buf))
+    }
+
+    pub fn close(&self) -> js_sys::Promise {
+        self.device.close()
+    }
+}
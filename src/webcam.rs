@@ -0,0 +1,126 @@
+//! Webcam frames for the visible-light overlay. `Camera::frame` blocks until the next frame, so
+//! the webcam is read on a thread of its own.
+
+use std::sync::mpsc::{self, Receiver, TryRecvError, TrySendError};
+use std::thread;
+
+use eframe::egui;
+use nokhwa::{
+    pixel_format::RgbFormat,
+    utils::{ApiBackend, CameraIndex, RequestedFormat, RequestedFormatType},
+    Camera,
+};
+
+// Frames waiting for the UI, older ones are dropped when it falls behind
+const QUEUED_FRAMES: usize = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebcamDescription {
+    pub index: u32,
+    pub name: String,
+}
+
+pub fn available_webcams() -> Vec<WebcamDescription> {
+    let cameras = nokhwa::query(ApiBackend::Auto).unwrap_or_else(|e| {
+        log::error!("Failed to list webcams: {e}");
+        Vec::new()
+    });
+
+    cameras
+        .into_iter()
+        .filter_map(|info| match *info.index() {
+            CameraIndex::Index(index) => Some(WebcamDescription {
+                index,
+                name: info.human_name(),
+            }),
+            CameraIndex::String(_) => None,
+        })
+        .collect()
+}
+
+fn open_camera(index: u32) -> anyhow::Result<Camera> {
+    let format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+    let mut camera = Camera::new(CameraIndex::Index(index), format)?;
+    camera.open_stream()?;
+
+    Ok(camera)
+}
+
+fn read_frames(mut camera: Camera, sender: &mpsc::SyncSender<egui::ColorImage>) {
+    loop {
+        let image = match camera
+            .frame()
+            .and_then(|frame| frame.decode_image::<RgbFormat>())
+        {
+            Ok(image) => image,
+            Err(e) => {
+                log::error!("Failed to read the webcam: {e}");
+                return;
+            }
+        };
+
+        let size = [image.width() as usize, image.height() as usize];
+
+        match sender.try_send(egui::ColorImage::from_rgb(size, image.as_raw())) {
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            // the webcam has been closed
+            Err(TrySendError::Disconnected(_)) => return,
+        }
+    }
+}
+
+/// Streams a webcam until dropped.
+pub struct Webcam {
+    receiver: Receiver<egui::ColorImage>,
+    disconnected: bool,
+}
+
+impl Webcam {
+    pub fn open(index: u32) -> anyhow::Result<Self> {
+        let (sender, receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+        let (result_sender, result_receiver) = mpsc::channel();
+
+        // some backends can't move the camera between threads, so it's opened by the reader
+        thread::Builder::new()
+            .name("webcam".into())
+            .spawn(move || match open_camera(index) {
+                Ok(camera) => {
+                    let _ = result_sender.send(Ok(()));
+                    read_frames(camera, &sender);
+                }
+                Err(e) => {
+                    let _ = result_sender.send(Err(e));
+                }
+            })?;
+
+        result_receiver.recv()??;
+
+        Ok(Self {
+            receiver,
+            disconnected: false,
+        })
+    }
+
+    /// The webcam has stopped streaming (e.g. it's been unplugged) and has to be opened again.
+    pub fn is_disconnected(&self) -> bool {
+        self.disconnected
+    }
+
+    /// Newest image captured since the previous call, if any.
+    pub fn latest_frame(&mut self) -> Option<egui::ColorImage> {
+        let mut latest = None;
+
+        loop {
+            match self.receiver.try_recv() {
+                Ok(image) => latest = Some(image),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnected = true;
+                    break;
+                }
+            }
+        }
+
+        latest
+    }
+}
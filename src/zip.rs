@@ -0,0 +1,166 @@
+//! Uncompressed ZIP archives, the exports bundling several files.
+
+use crate::report;
+
+use anyhow::anyhow;
+use std::time::SystemTime;
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+
+    for &byte in data {
+        crc ^= u32::from(byte);
+
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+/// MS-DOS time and date of the ZIP headers, in UTC.
+fn dos_time(time: SystemTime) -> (u16, u16) {
+    let [year, month, day, hour, minute, second] = report::civil_time(time);
+    // the format starts in 1980
+    let year = year.saturating_sub(1980).min(127);
+
+    #[allow(clippy::cast_possible_truncation)]
+    (
+        ((hour << 11) | (minute << 5) | (second / 2)) as u16,
+        ((year << 9) | (month << 5) | day) as u16,
+    )
+}
+
+/// Writes an uncompressed ZIP archive, PNGs wouldn't get any smaller compressed.
+#[derive(Default)]
+pub struct ZipWriter {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipWriter {
+    pub fn add(&mut self, name: &str, modified: SystemTime, contents: &[u8]) -> anyhow::Result<()> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(contents.len())?;
+        let name_length = u16::try_from(name.len())?;
+        let crc = crc32(contents);
+        let (time, date) = dos_time(modified);
+
+        // version needed, flags (UTF-8 names), method (stored), time, date, crc, sizes and the
+        // name length are shared by both headers
+        let mut common = Vec::new();
+        for value in [20_u16, 1 << 11, 0, time, date] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        for value in [crc, size, size] {
+            common.extend_from_slice(&value.to_le_bytes());
+        }
+        common.extend_from_slice(&name_length.to_le_bytes());
+
+        self.data.extend_from_slice(&0x0403_4b50_u32.to_le_bytes());
+        self.data.extend_from_slice(&common);
+        self.data.extend_from_slice(&0_u16.to_le_bytes());
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(contents);
+
+        let directory = &mut self.central_directory;
+        directory.extend_from_slice(&0x0201_4b50_u32.to_le_bytes());
+        // version made by
+        directory.extend_from_slice(&20_u16.to_le_bytes());
+        directory.extend_from_slice(&common);
+        // extra field and comment lengths, disk number and internal attributes
+        for value in [0_u16; 4] {
+            directory.extend_from_slice(&value.to_le_bytes());
+        }
+        // external attributes
+        directory.extend_from_slice(&0_u32.to_le_bytes());
+        directory.extend_from_slice(&offset.to_le_bytes());
+        directory.extend_from_slice(name.as_bytes());
+
+        self.entries = self
+            .entries
+            .checked_add(1)
+            .ok_or_else(|| anyhow!("Too many files for a ZIP archive"))?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> anyhow::Result<Vec<u8>> {
+        let offset = u32::try_from(self.data.len())?;
+        let size = u32::try_from(self.central_directory.len())?;
+
+        self.data.extend_from_slice(&self.central_directory);
+        self.data.extend_from_slice(&0x0605_4b50_u32.to_le_bytes());
+        // this disk and the one the directory starts on
+        self.data.extend_from_slice(&[0; 4]);
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&self.entries.to_le_bytes());
+        self.data.extend_from_slice(&size.to_le_bytes());
+        self.data.extend_from_slice(&offset.to_le_bytes());
+        // comment length
+        self.data.extend_from_slice(&0_u16.to_le_bytes());
+
+        Ok(self.data)
+    }
+}
+
+/// Reads the files of an archive written by [`ZipWriter`], as their names and contents. Only
+/// stored files are supported, with their sizes in the local headers.
+#[cfg(not(any(target_os = "android", target_os = "ios")))]
+pub fn read(data: &[u8]) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let u16_at = |offset: usize| -> anyhow::Result<usize> {
+        let bytes = data
+            .get(offset..offset + 2)
+            .ok_or_else(|| anyhow!("Truncated ZIP archive"))?;
+        Ok(usize::from(u16::from_le_bytes([bytes[0], bytes[1]])))
+    };
+    let u32_at = |offset: usize| -> anyhow::Result<u32> {
+        let bytes = data
+            .get(offset..offset + 4)
+            .ok_or_else(|| anyhow!("Truncated ZIP archive"))?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    let mut files = Vec::new();
+    let mut offset = 0;
+
+    // the local headers follow each other up to the central directory
+    while u32_at(offset)? == 0x0403_4b50 {
+        let flags = u16_at(offset + 6)?;
+        let method = u16_at(offset + 8)?;
+        let crc = u32_at(offset + 14)?;
+        let size = usize::try_from(u32_at(offset + 18)?)?;
+        let name_length = u16_at(offset + 26)?;
+        let extra_length = u16_at(offset + 28)?;
+
+        // bit 3 moves the sizes behind the contents
+        if method != 0 || flags & (1 << 3) != 0 {
+            return Err(anyhow!("Only uncompressed ZIP archives are supported"));
+        }
+
+        let name_start = offset + 30;
+        let contents_start = name_start + name_length + extra_length;
+        let name = data
+            .get(name_start..name_start + name_length)
+            .ok_or_else(|| anyhow!("Truncated ZIP archive"))?;
+        let contents = data
+            .get(contents_start..contents_start + size)
+            .ok_or_else(|| anyhow!("Truncated ZIP archive"))?;
+        let name = String::from_utf8_lossy(name).into_owned();
+
+        if crc32(contents) != crc {
+            return Err(anyhow!("{name} is corrupted"));
+        }
+
+        files.push((name, contents.to_vec()));
+        offset = contents_start + size;
+    }
+
+    Ok(files)
+}